@@ -0,0 +1,23 @@
+//! Records the git commit the explorer is built from as `EXPLORER_GIT_HASH`, reported by
+//! `/admin/diagnostics`. Left empty when building outside a git checkout.
+
+use std::process::Command;
+
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=EXPLORER_GIT_HASH={}", hash.trim());
+
+    // Rebuild when HEAD moves: it changes on checkout, the branch ref on commit.
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+        if let Some(reference) = head.trim().strip_prefix("ref: ") {
+            println!("cargo:rerun-if-changed=.git/{}", reference);
+        }
+    }
+}
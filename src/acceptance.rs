@@ -0,0 +1,287 @@
+//! Transaction acceptance: which selected-chain block accepted a transaction.
+//!
+//! In the DAG a transaction is "confirmed" once a chain block merges the block that included
+//! it and accepts the transaction into the UTXO set; being in a block is not enough, since a
+//! conflicting transaction may win. Confirmations count the blue score the sink has gained
+//! since the accepting block.
+//!
+//! The index keeps an acceptance table (transaction id to accepting chain block) fed by the
+//! acceptance data of virtual-chain-changed notifications: chain blocks removed by a reorg
+//! drop their acceptances before the added ones record theirs. A lookup is then a single
+//! row. Transactions accepted while the explorer was not listening fall back to walking the
+//! virtual chain from the earliest including block, and the answer is recorded for next time.
+//! kaspad returns the whole chain from that block to the sink in one response, so the walk is
+//! only made for blocks within `MAX_WALK_BLUE_SCORE` of the sink; older transactions without
+//! a recorded acceptance get a 404.
+
+use crate::chain::{ChainSource, Client};
+use crate::index::{Acceptance, IndexHandle};
+use crate::{error_response, not_connected, require_index, rpc, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::VirtualChainChangedNotification;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// About ten minutes of testnet-12 chain; deeper walks answer "not recorded" instead.
+const MAX_WALK_BLUE_SCORE: u64 = 6_000;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionAcceptance {
+    transaction_id: String,
+    // Indexed blocks that included the transaction.
+    block_hashes: Vec<String>,
+    // False while no chain block has accepted it (not merged yet, or lost to a double spend).
+    accepted: bool,
+    accepting_block_hash: Option<String>,
+    accepting_daa_score: Option<u64>,
+    accepting_blue_score: Option<u64>,
+    confirmations: Option<u64>,
+}
+
+/// The chain block that accepted a transaction.
+pub struct Accepted {
+    pub block_hash: Hash,
+    pub daa_score: u64,
+    pub blue_score: u64,
+}
+
+/// Records the acceptance data of a virtual-chain-changed notification in the index.
+pub async fn on_chain_changed(state: &AppState, notification: &VirtualChainChangedNotification) {
+    let Some(index) = &state.index else {
+        return;
+    };
+    if index.current_epoch_id().is_none() || index.network_mismatch().is_some() {
+        return;
+    }
+    let removed = notification
+        .removed_chain_block_hashes
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let accepted = notification
+        .accepted_transaction_ids
+        .iter()
+        .map(|accepted| {
+            (
+                accepted.accepting_block_hash.to_string(),
+                accepted
+                    .accepted_transaction_ids
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            )
+        })
+        .collect();
+    if let Err(e) = index.apply_acceptance(removed, accepted).await {
+        tracing::warn!("Failed to record accepted transactions: {:?}", e);
+    }
+}
+
+fn usable_index(state: &AppState) -> Option<&IndexHandle> {
+    state
+        .index
+        .as_ref()
+        .filter(|index| index.network_mismatch().is_none())
+}
+
+/// The acceptance of transaction `id` as recorded in the index, without asking kaspad; None
+/// without a usable index.
+pub async fn recorded(state: &AppState, id: Hash) -> Result<Option<Acceptance>, Response> {
+    let Some(index) = usable_index(state) else {
+        return Ok(None);
+    };
+    index.acceptance(id.to_string()).await.map_err(|e| {
+        tracing::error!("Acceptance lookup of transaction {} failed: {:?}", id, e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    })
+}
+
+/// The chain block that accepted transaction `id`, included by the indexed `block_hashes`
+/// (oldest first). None while it is not accepted.
+pub async fn accepting_block(
+    state: &AppState,
+    client: &Client,
+    id: Hash,
+    block_hashes: &[String],
+) -> Result<Option<Accepted>, Response> {
+    let Some(index) = usable_index(state) else {
+        return Ok(None);
+    };
+    if let Some(acceptance) = recorded(state, id).await? {
+        let block_hash = acceptance.block_hash.parse::<Hash>().map_err(|_| {
+            tracing::error!(
+                "Index holds an invalid block hash {:?}",
+                acceptance.block_hash
+            );
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+        })?;
+        if let (Some(daa_score), Some(blue_score)) = (acceptance.daa_score, acceptance.blue_score) {
+            return Ok(Some(Accepted {
+                block_hash,
+                daa_score,
+                blue_score,
+            }));
+        }
+        // The indexer has not reached the accepting block yet; read its header instead.
+        return header_of(state, client, block_hash).await.map(Some);
+    }
+
+    // The earliest including block; whichever chain block merges it accepts the transaction.
+    let Some(start) = block_hashes
+        .first()
+        .and_then(|hash| hash.parse::<Hash>().ok())
+    else {
+        return Ok(None);
+    };
+    let start_blue_score = index
+        .block_blue_score(start.to_string())
+        .await
+        .map_err(|e| {
+            tracing::error!("Index lookup of block {} failed: {:?}", start, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+        })?;
+    let sink_blue_score = rpc::retried("get_sink_blue_score", || client.get_sink_blue_score())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get sink blue score: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get sink blue score",
+            )
+        })?;
+    if start_blue_score.is_none_or(|b| sink_blue_score.saturating_sub(b) > MAX_WALK_BLUE_SCORE) {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "Acceptance not recorded, and the transaction is too deep to look up",
+        ));
+    }
+    let chain = rpc::retried("get_virtual_chain_from_block", || {
+        client.get_virtual_chain_from_block(start, true, None)
+    })
+    .await
+    .map_err(|e| {
+        tracing::warn!(
+            "get_virtual_chain_from_block from {} failed: {:?}",
+            start,
+            e
+        );
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to read the selected chain",
+        )
+    })?;
+    let Some(block_hash) = chain
+        .accepted_transaction_ids
+        .iter()
+        .find(|accepted| accepted.accepted_transaction_ids.contains(&id))
+        .map(|accepted| accepted.accepting_block_hash)
+    else {
+        return Ok(None);
+    };
+    if let Err(e) = index
+        .record_acceptance(id.to_string(), block_hash.to_string())
+        .await
+    {
+        tracing::warn!("Failed to record acceptance of {}: {:?}", id, e);
+    }
+    header_of(state, client, block_hash).await.map(Some)
+}
+
+async fn header_of(
+    state: &AppState,
+    client: &Client,
+    block_hash: Hash,
+) -> Result<Accepted, Response> {
+    let block = rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(block_hash, false).await },
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch accepting block {}: {:?}", block_hash, e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch accepting block",
+        )
+    })?;
+    Ok(Accepted {
+        block_hash,
+        daa_score: block.header.daa_score,
+        blue_score: block.header.blue_score,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tx/{id}/acceptance",
+    tag = "transactions",
+    params(("id" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "Accepting chain block and confirmations", body = TransactionAcceptance),
+        (status = 400, description = "Invalid transaction id", body = ErrorResponse),
+        (status = 404, description = "Transaction not indexed, or its acceptance not recorded and too deep to look up", body = ErrorResponse),
+        (status = 503, description = "Index disabled or not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_acceptance(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionAcceptance>, Response> {
+    let id = id
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid transaction id"))?;
+    let index = require_index(&state)?;
+    let block_hashes = index
+        .transaction_blocks(id.to_string())
+        .await
+        .map_err(|e| {
+            tracing::error!("Index lookup of transaction {} failed: {:?}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+        })?;
+    if block_hashes.is_empty() {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "Transaction not indexed",
+        ));
+    }
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let Some(accepted) = accepting_block(&state, client, id, &block_hashes).await? else {
+        return Ok(Json(TransactionAcceptance {
+            transaction_id: id.to_string(),
+            block_hashes,
+            accepted: false,
+            accepting_block_hash: None,
+            accepting_daa_score: None,
+            accepting_blue_score: None,
+            confirmations: None,
+        }));
+    };
+    let sink_blue_score = rpc::retried("get_sink_blue_score", || client.get_sink_blue_score())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get sink blue score: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get sink blue score",
+            )
+        })?;
+
+    Ok(Json(TransactionAcceptance {
+        transaction_id: id.to_string(),
+        block_hashes,
+        accepted: true,
+        accepting_block_hash: Some(accepted.block_hash.to_string()),
+        accepting_daa_score: Some(accepted.daa_score),
+        accepting_blue_score: Some(accepted.blue_score),
+        confirmations: Some(sink_blue_score.saturating_sub(accepted.blue_score)),
+    }))
+}
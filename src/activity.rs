@@ -0,0 +1,128 @@
+//! Address activity heat score.
+//!
+//! Derived from the UTXO set returned for an address: how many UTXOs were created in the
+//! last hour/day/week (by DAA score), and how much the UTXO set changed since the address
+//! was last queried. Lets the UI show "active" badges without clients crunching history.
+//!
+//! The previous lookup's outpoints are kept in a bounded cache: at most `MAX_CACHED_OUTPOINTS`
+//! outpoints over all addresses, each address dropped a day after its last lookup, so
+//! clients querying many addresses cannot grow it without limit.
+
+use kaspa_rpc_core::RpcUtxosByAddressesEntry;
+use moka::future::Cache;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+// Addresses with more UTXOs than this are scored without churn to bound memory.
+const MAX_TRACKED_UTXOS: usize = 10_000;
+// Outpoints kept over all addresses' snapshots.
+const MAX_CACHED_OUTPOINTS: u64 = 1_000_000;
+const SNAPSHOT_IDLE: Duration = Duration::from_secs(86_400);
+
+const HOUR_SECONDS: f64 = 3_600.0;
+const DAY_SECONDS: f64 = 86_400.0;
+const WEEK_SECONDS: f64 = 604_800.0;
+
+pub type ActivityCache = Cache<String, Arc<UtxoSnapshot>>;
+
+pub fn new_cache() -> ActivityCache {
+    Cache::builder()
+        .max_capacity(MAX_CACHED_OUTPOINTS)
+        .weigher(|_: &String, snapshot: &Arc<UtxoSnapshot>| {
+            u32::try_from(snapshot.outpoints.len() + 1).unwrap_or(u32::MAX)
+        })
+        .time_to_idle(SNAPSHOT_IDLE)
+        .build()
+}
+
+/// Outpoints seen for an address at its previous lookup.
+#[derive(Debug)]
+pub struct UtxoSnapshot {
+    taken_at: Instant,
+    outpoints: HashSet<(kaspa_hashes::Hash, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AddressActivity {
+    score: f64, // 0 (dormant) to 100 (very active)
+    active_last_hour: bool,
+    active_last_day: bool,
+    active_last_week: bool,
+    utxos_last_hour: usize,
+    utxos_last_day: usize,
+    utxos_last_week: usize,
+    last_activity_daa_score: Option<u64>,
+    // UTXOs added plus removed per hour since the previous lookup, if there was one.
+    utxo_churn_per_hour: Option<f64>,
+}
+
+/// Scores `utxos` against the virtual DAA score, updating the stored snapshot for `address`.
+pub async fn score(
+    cache: &ActivityCache,
+    address: &str,
+    utxos: &[RpcUtxosByAddressesEntry],
+    virtual_daa_score: u64,
+    daa_per_second: f64,
+) -> AddressActivity {
+    let age_seconds = |daa_score: u64| virtual_daa_score.saturating_sub(daa_score) as f64 / daa_per_second;
+
+    let (mut hour, mut day, mut week) = (0usize, 0usize, 0usize);
+    for utxo in utxos {
+        let age = age_seconds(utxo.utxo_entry.block_daa_score);
+        if age <= HOUR_SECONDS {
+            hour += 1;
+        }
+        if age <= DAY_SECONDS {
+            day += 1;
+        }
+        if age <= WEEK_SECONDS {
+            week += 1;
+        }
+    }
+    let last_activity_daa_score = utxos.iter().map(|u| u.utxo_entry.block_daa_score).max();
+
+    let utxo_churn_per_hour = if utxos.len() <= MAX_TRACKED_UTXOS {
+        let outpoints: HashSet<_> = utxos
+            .iter()
+            .map(|u| (u.outpoint.transaction_id, u.outpoint.index))
+            .collect();
+        let churn = cache.get(address).await.map(|previous| {
+            // Floor at one minute so back-to-back lookups do not extrapolate into huge rates.
+            let hours = (previous.taken_at.elapsed().as_secs_f64() / HOUR_SECONDS).max(1.0 / 60.0);
+            outpoints.symmetric_difference(&previous.outpoints).count() as f64 / hours
+        });
+        cache
+            .insert(
+                address.to_string(),
+                Arc::new(UtxoSnapshot {
+                    taken_at: Instant::now(),
+                    outpoints,
+                }),
+            )
+            .await;
+        churn
+    } else {
+        cache.invalidate(address).await;
+        None
+    };
+
+    // Weighted recent UTXO creation plus churn, squashed into 0..100 so a handful of recent
+    // transactions already registers while heavy users saturate near the top.
+    let weighted = 3.0 * hour as f64 + day as f64 + 0.25 * week as f64 + utxo_churn_per_hour.unwrap_or(0.0);
+    let score = (100.0 * (1.0 - (-weighted / 10.0).exp())).round();
+
+    AddressActivity {
+        score,
+        active_last_hour: hour > 0,
+        active_last_day: day > 0,
+        active_last_week: week > 0,
+        utxos_last_hour: hour,
+        utxos_last_day: day,
+        utxos_last_week: week,
+        last_activity_daa_score,
+        utxo_churn_per_hour,
+    }
+}
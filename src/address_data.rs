@@ -0,0 +1,309 @@
+//! Full UTXO list and transaction history of an address.
+//!
+//! As JSON, the UTXO list is capped at `limits.max_utxos` like the balance view, and the
+//! history is paged. With `?format=csv` or `?format=ndjson` (see `export.rs`) both are
+//! streamed in full, for pulling thousands of records into spreadsheets or scripts.
+//!
+//! UTXOs come from kaspad's UTXO index. The history comes from the local index: every
+//! transaction that paid the address or spent one of its outputs, with both amounts, newest
+//! first.
+
+use crate::blocks_range::parse_cursor;
+use crate::chain::ChainSource;
+use crate::export::{self, Format, Row};
+use crate::{error_response, network, not_connected, require_index, rpc, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_rpc_core::RpcUtxosByAddressesEntry;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1_000;
+// History rows read from the index per query while exporting.
+const EXPORT_PAGE: usize = 1_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UtxosQuery {
+    /// json (default), csv or ndjson
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// json (default, paged), csv or ndjson (complete history)
+    format: Option<String>,
+    /// Transactions per JSON page (default 100, max 1000)
+    limit: Option<usize>,
+    /// next_cursor of the previous JSON page
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressUtxo {
+    transaction_id: String,
+    index: u32,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64,
+    block_daa_score: u64,
+    is_coinbase: bool,
+}
+
+impl AddressUtxo {
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+}
+
+impl From<RpcUtxosByAddressesEntry> for AddressUtxo {
+    fn from(entry: RpcUtxosByAddressesEntry) -> Self {
+        AddressUtxo {
+            transaction_id: entry.outpoint.transaction_id.to_string(),
+            index: entry.outpoint.index,
+            amount: entry.utxo_entry.amount,
+            block_daa_score: entry.utxo_entry.block_daa_score,
+            is_coinbase: entry.utxo_entry.is_coinbase,
+        }
+    }
+}
+
+impl Row for AddressUtxo {
+    const CSV_HEADER: &'static str = "transaction_id,index,amount,block_daa_score,is_coinbase";
+
+    fn csv_line(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.transaction_id, self.index, self.amount, self.block_daa_score, self.is_coinbase
+        )
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressUtxos {
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    utxo_count: usize,
+    truncated: bool, // More UTXOs exist than listed; export them as CSV or NDJSON
+    utxos: Vec<AddressUtxo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressHistoryEntry {
+    transaction_id: String,
+    daa_score: u64,
+    timestamp: Option<u64>, // Unix ms of the earliest indexed block including it
+    #[serde(serialize_with = "crate::units::sompi")]
+    received: u64, // Paid to the address
+    #[serde(serialize_with = "crate::units::sompi")]
+    sent: u64, // Spent from the address's outputs
+    #[serde(serialize_with = "crate::units::sompi_signed")]
+    net: i64, // received - sent
+}
+
+impl Row for AddressHistoryEntry {
+    const CSV_HEADER: &'static str = "transaction_id,daa_score,timestamp,received,sent,net";
+
+    fn csv_line(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.transaction_id,
+            self.daa_score,
+            self.timestamp.map(|t| t.to_string()).unwrap_or_default(),
+            self.received,
+            self.sent,
+            self.net
+        )
+    }
+}
+
+impl From<crate::index::AddressTransaction> for AddressHistoryEntry {
+    fn from(tx: crate::index::AddressTransaction) -> Self {
+        AddressHistoryEntry {
+            net: tx.received as i64 - tx.sent as i64,
+            transaction_id: tx.tx_id,
+            daa_score: tx.daa_score,
+            timestamp: tx.timestamp,
+            received: tx.received,
+            sent: tx.sent,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressHistory {
+    address: String,
+    transactions: Vec<AddressHistoryEntry>,
+    // Pass as cursor to continue; None on the last page.
+    next_cursor: Option<String>,
+    // Older activity was moved to the index's cold tier and is not listed.
+    truncated: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/utxos",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address"), UtxosQuery),
+    responses(
+        (status = 200, description = "UTXOs of the address, newest first; CSV or NDJSON with format", body = AddressUtxos),
+        (status = 400, description = "Invalid address or format", body = ErrorResponse),
+        (status = 404, description = "Exports disabled", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_address_utxos(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(query): Query<UtxosQuery>,
+) -> Result<Response, Response> {
+    let parsed = network::parse_address(&state, &address)?;
+    let format = export::parse_format(&state, query.format.as_deref())?;
+
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
+    let mut entries = rpc::retried("get_utxos_by_addresses", || {
+        client.get_utxos_by_addresses(vec![parsed.clone()])
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get UTXOs for address {}: {:?}", address, e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch UTXOs (is --utxoindex enabled?)",
+        )
+    })?;
+    drop(client);
+    entries.sort_by(|a, b| {
+        b.utxo_entry
+            .block_daa_score
+            .cmp(&a.utxo_entry.block_daa_score)
+    });
+    let utxos: Vec<AddressUtxo> = entries.into_iter().map(AddressUtxo::from).collect();
+
+    if format != Format::Json {
+        let (sender, response) =
+            export::stream::<AddressUtxo>(format, &format!("utxos-{}", address.replace(':', "-")));
+        state.shutdown.clone().spawn(async move {
+            for utxo in utxos {
+                if sender.send(Ok(utxo)).await.is_err() {
+                    break; // Client went away
+                }
+            }
+        });
+        return Ok(response);
+    }
+
+    let max = state.config.limits.max_utxos;
+    Ok(Json(AddressUtxos {
+        balance: utxos.iter().map(|u| u.amount).sum(),
+        utxo_count: utxos.len(),
+        truncated: utxos.len() > max,
+        utxos: utxos.into_iter().take(max).collect(),
+        address,
+    })
+    .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/transactions",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address"), HistoryQuery),
+    responses(
+        (status = 200, description = "Transactions that paid or spent from the address, newest first; CSV or NDJSON with format", body = AddressHistory),
+        (status = 400, description = "Invalid address, format, limit or cursor", body = ErrorResponse),
+        (status = 404, description = "Exports disabled", body = ErrorResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_address_transactions(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Response, Response> {
+    let address = network::parse_address(&state, &address)?.to_string();
+    let format = export::parse_format(&state, query.format.as_deref())?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let before = match query.cursor.as_deref() {
+        Some(cursor) => Some(
+            parse_cursor(cursor)
+                .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Invalid cursor"))?,
+        ),
+        None => None,
+    };
+    let index = require_index(&state)?;
+
+    if format != Format::Json {
+        let (sender, response) = export::stream::<AddressHistoryEntry>(
+            format,
+            &format!("transactions-{}", address.replace(':', "-")),
+        );
+        state.shutdown.clone().spawn(async move {
+            let mut before = None;
+            loop {
+                let page = match index
+                    .address_transactions(address.clone(), before.take(), EXPORT_PAGE)
+                    .await
+                {
+                    Ok(page) => page,
+                    Err(e) => {
+                        let _ = sender.send(Err(e)).await;
+                        return;
+                    }
+                };
+                let done = page.len() < EXPORT_PAGE;
+                before = page.last().map(|tx| (tx.daa_score, tx.tx_id.clone()));
+                for tx in page {
+                    if sender.send(Ok(tx.into())).await.is_err() {
+                        return; // Client went away
+                    }
+                }
+                if done {
+                    return;
+                }
+            }
+        });
+        return Ok(response);
+    }
+
+    // One extra transaction tells whether another page follows.
+    let mut transactions = index
+        .address_transactions(address.clone(), before, limit + 1)
+        .await
+        .map_err(|e| {
+            tracing::error!("Index lookup of history for {} failed: {:?}", address, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+        })?;
+    let truncated = index
+        .address_history_truncated(address.clone())
+        .await
+        .map_err(|e| {
+            tracing::error!("Index lookup of history for {} failed: {:?}", address, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+        })?;
+    let more = transactions.len() > limit;
+    transactions.truncate(limit);
+    let next_cursor = transactions
+        .last()
+        .filter(|_| more)
+        .map(|last| format!("{}:{}", last.daa_score, last.tx_id));
+
+    Ok(Json(AddressHistory {
+        address,
+        transactions: transactions.into_iter().map(Into::into).collect(),
+        next_cursor,
+        truncated,
+    })
+    .into_response())
+}
@@ -0,0 +1,138 @@
+//! Pending transactions of an address, from kaspad's mempool address index.
+//!
+//! Amounts follow the address summary: an outgoing transaction counts its outputs to other
+//! addresses plus the fee, so change coming back is netted out instead of listed as
+//! incoming, and an incoming one counts the outputs paying the address. The net pending
+//! delta is what the confirmed balance will become once everything listed is accepted.
+
+use crate::address_summary::paid_to;
+use crate::chain::ChainSource;
+use crate::{error_response, network, not_connected, rpc, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_rpc_core::RpcMempoolEntry;
+use serde::Serialize;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingTransaction {
+    transaction_id: String,
+    // incoming or outgoing; a send paying change back to the address is outgoing.
+    direction: &'static str,
+    #[serde(serialize_with = "crate::units::sompi")]
+    received: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    sent: u64,
+    #[serde(serialize_with = "crate::units::sompi_signed")]
+    net: i64, // received - sent
+    #[serde(serialize_with = "crate::units::sompi")]
+    fee: u64,
+    mass: u64,
+    is_orphan: bool, // Waiting for a parent; not accepted until it is
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressMempool {
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_incoming: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_outgoing: u64,
+    #[serde(serialize_with = "crate::units::sompi_signed")]
+    net_pending: i64,
+    transactions: Vec<PendingTransaction>, // Outgoing first
+}
+
+fn pending(
+    entry: &RpcMempoolEntry,
+    direction: &'static str,
+    received: u64,
+    sent: u64,
+) -> PendingTransaction {
+    let tx = &entry.transaction;
+    PendingTransaction {
+        transaction_id: tx
+            .verbose_data
+            .as_ref()
+            .map(|v| v.transaction_id.to_string())
+            .unwrap_or_default(),
+        direction,
+        received,
+        sent,
+        net: received as i64 - sent as i64,
+        fee: entry.fee,
+        mass: tx.verbose_data.as_ref().map_or(tx.mass, |v| v.compute_mass),
+        is_orphan: entry.is_orphan,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/mempool",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address")),
+    responses(
+        (status = 200, description = "Pending transactions sending to or spending from the address, with the net pending delta", body = AddressMempool),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_address_mempool(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<Json<AddressMempool>, Response> {
+    let parsed = network::parse_address(&state, &address)?;
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    // (include_orphan_pool=true, filter_transaction_pool=false) => orphans and transaction pool
+    let entries = rpc::retried("get_mempool_entries_by_addresses", || {
+        client.get_mempool_entries_by_addresses(vec![parsed.clone()], true, false)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch mempool entries of {}: {:?}", address, e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch mempool entries",
+        )
+    })?;
+    drop(client_guard);
+
+    let mut transactions = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.address == parsed) {
+        let mut sending = HashSet::new();
+        for sent in &entry.sending {
+            if let Some(v) = sent.transaction.verbose_data.as_ref() {
+                sending.insert(v.transaction_id);
+            }
+            let amount = paid_to(&sent.transaction, &parsed, false) + sent.fee;
+            transactions.push(pending(sent, "outgoing", 0, amount));
+        }
+        for received in &entry.receiving {
+            let own_send = received
+                .transaction
+                .verbose_data
+                .as_ref()
+                .is_some_and(|v| sending.contains(&v.transaction_id));
+            if !own_send {
+                let amount = paid_to(&received.transaction, &parsed, true);
+                transactions.push(pending(received, "incoming", amount, 0));
+            }
+        }
+    }
+    let pending_incoming: u64 = transactions.iter().map(|tx| tx.received).sum();
+    let pending_outgoing: u64 = transactions.iter().map(|tx| tx.sent).sum();
+
+    Ok(Json(AddressMempool {
+        address,
+        pending_incoming,
+        pending_outgoing,
+        net_pending: pending_incoming as i64 - pending_outgoing as i64,
+        transactions,
+    }))
+}
@@ -0,0 +1,121 @@
+//! Address summary: confirmed balance plus what the mempool is about to change.
+//!
+//! Pending amounts are derived from the mempool transactions touching the address. A send
+//! counts its outputs to other addresses plus the fee, so change coming back is not shown
+//! as incoming; a receive counts the outputs paying the address. First and last activity
+//! come from the index, which records every DAA score at which the address received or spent.
+
+use crate::chain::{ChainSource, Client};
+use crate::{rpc, AppState};
+use kaspa_addresses::Address;
+use kaspa_rpc_core::{RpcMempoolEntryByAddress, RpcTransaction};
+use serde::Serialize;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AddressSummary {
+    #[serde(serialize_with = "crate::units::sompi")]
+    confirmed_balance: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_incoming: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_outgoing: u64,
+    utxo_count: Option<usize>,
+    // None without the index or before the address was first seen by it.
+    first_activity_daa_score: Option<u64>,
+    last_activity_daa_score: Option<u64>,
+}
+
+/// Sum of the outputs of `tx` paying `address`, or with `to_address` false, paying others.
+pub fn paid_to(tx: &RpcTransaction, address: &Address, to_address: bool) -> u64 {
+    tx.outputs
+        .iter()
+        .filter(|output| {
+            output
+                .verbose_data
+                .as_ref()
+                .is_some_and(|v| v.script_public_key_address == *address)
+                == to_address
+        })
+        .map(|output| output.value)
+        .sum()
+}
+
+/// Pending (incoming, outgoing) amounts of one address's mempool entries.
+pub fn pending_amounts(entry: &RpcMempoolEntryByAddress) -> (u64, u64) {
+    let paid_to_address =
+        |tx: &RpcTransaction, to_self: bool| -> u64 { paid_to(tx, &entry.address, to_self) };
+    let mut sending = HashSet::new();
+    let mut outgoing = 0;
+    for sent in &entry.sending {
+        if let Some(v) = sent.transaction.verbose_data.as_ref() {
+            sending.insert(v.transaction_id);
+        }
+        outgoing += paid_to_address(&sent.transaction, false) + sent.fee;
+    }
+    // Change in the address's own sends is already netted out above.
+    let incoming = entry
+        .receiving
+        .iter()
+        .filter(|received| {
+            !received
+                .transaction
+                .verbose_data
+                .as_ref()
+                .is_some_and(|v| sending.contains(&v.transaction_id))
+        })
+        .map(|received| paid_to_address(&received.transaction, true))
+        .sum();
+    (incoming, outgoing)
+}
+
+/// Summary of `address`; None if the mempool could not be read.
+pub async fn summarize(
+    state: &AppState,
+    client: &Client,
+    address: &Address,
+    confirmed_balance: u64,
+    utxo_count: Option<usize>,
+) -> Option<AddressSummary> {
+    let entries = match rpc::retried("get_mempool_entries_by_addresses", || {
+        client.get_mempool_entries_by_addresses(vec![address.clone()], true, false)
+    })
+    .await
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Failed to fetch mempool entries of {}: {:?}", address, e);
+            return None;
+        }
+    };
+    let (pending_incoming, pending_outgoing) = entries
+        .iter()
+        .filter(|entry| entry.address == *address)
+        .map(pending_amounts)
+        .fold((0, 0), |(i, o), (di, dout)| (i + di, o + dout));
+
+    let activity = match state
+        .index
+        .as_ref()
+        .filter(|index| index.network_mismatch().is_none())
+    {
+        Some(index) => index
+            .address_activity(address.to_string())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Index lookup of activity of {} failed: {:?}", address, e);
+                None
+            }),
+        None => None,
+    };
+
+    Some(AddressSummary {
+        confirmed_balance,
+        pending_incoming,
+        pending_outgoing,
+        utxo_count,
+        first_activity_daa_score: activity.map(|(first, _)| first),
+        last_activity_daa_score: activity.map(|(_, last)| last),
+    })
+}
@@ -0,0 +1,76 @@
+//! Address validation and decoding, for wallet developers checking what they generate.
+//!
+//! `/validate/:address` always answers 200: an address that does not decode is reported with
+//! `valid: false` and the decoder's reason. A decodable address of another network is valid
+//! but has `matches_network: false`, since it would be rejected by this explorer's address
+//! endpoints.
+
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use kaspa_addresses::{Address, Version};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressValidation {
+    address: String,
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>, // Why the address does not decode
+    prefix: Option<String>,            // e.g. "kaspatest"
+    matches_network: Option<bool>,     // Whether the prefix is the served network's
+    version: Option<String>,           // PubKey, PubKeyECDSA or ScriptHash
+    payload: Option<String>,           // Hex of the public key or script hash
+    script_public_key: Option<String>, // Hex of the script paying to the address
+}
+
+fn version_name(version: Version) -> &'static str {
+    match version {
+        Version::PubKey => "PubKey",
+        Version::PubKeyECDSA => "PubKeyECDSA",
+        Version::ScriptHash => "ScriptHash",
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/validate/{address}",
+    tag = "tools",
+    params(("address" = String, Path, description = "Address to validate")),
+    responses(
+        (status = 200, description = "Whether the address is valid, and its decoded parts", body = AddressValidation)
+    )
+)]
+pub async fn validate_address(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Json<AddressValidation> {
+    let validation = match Address::try_from(address.as_str()) {
+        Ok(parsed) => AddressValidation {
+            valid: true,
+            error: None,
+            prefix: Some(parsed.prefix.to_string()),
+            matches_network: Some(parsed.prefix == state.network.prefix()),
+            version: Some(version_name(parsed.version).to_string()),
+            payload: Some(hex::encode(parsed.payload.as_slice())),
+            script_public_key: Some(hex::encode(
+                kaspa_txscript::pay_to_address_script(&parsed).script(),
+            )),
+            address,
+        },
+        Err(e) => AddressValidation {
+            address,
+            valid: false,
+            error: Some(e.to_string()),
+            prefix: None,
+            matches_network: None,
+            version: None,
+            payload: None,
+            script_public_key: None,
+        },
+    };
+    Json(validation)
+}
@@ -0,0 +1,506 @@
+//! Operator API under `/admin`.
+//!
+//! Mounted only when `admin` is enabled in `[features]`, and then only with a bearer token
+//! from `--admin-token` (or `EXPLORER_ADMIN_TOKEN`). It covers what otherwise needs a restart
+//! on a long-running explorer: reconnecting to kaspad or switching to another node, dropping
+//! cached balances and API responses, changing the log filter, and inspecting cache sizes.
+//! `/admin/diagnostics` bundles what a bug report needs into one JSON document, and
+//! `/admin/index/snapshot` downloads the index for bootstrapping another instance, and
+//! `/admin/index/wipe` deletes it so it is rebuilt from kaspad (see `epochs.rs`).
+//! `/admin/labels/{address}` names well-known addresses (see `labels.rs`), and
+//! `/admin/debug/stats` returns the runtime counters of `debug_stats.rs`. Additional networks
+//! serve only their own debug stats, under `/<prefix>/admin`.
+
+use crate::auth::{self, Scope};
+use crate::config::Features;
+use crate::debug_stats;
+use crate::index::IndexStats;
+use crate::index_snapshot::{self, ChannelWriter};
+use crate::labels;
+use crate::sampler::now_ms;
+use crate::telemetry::LogRecord;
+use crate::{connect_to_kaspad, error_response, rate_limit, AppState, NetworkInfo, API_VERSION};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+    routing::{get, post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+// Compressed chunks buffered between the snapshot writer and a slow client.
+const SNAPSHOT_CHUNKS: usize = 64;
+
+/// Validates the admin token against the feature flag: the admin API needs one, and a token
+/// without the admin API is ignored.
+pub fn token(features: &Features, token: Option<String>) -> anyhow::Result<Option<Arc<String>>> {
+    match token {
+        Some(token) if features.admin => {
+            if token.len() < auth::MIN_TOKEN_LENGTH {
+                anyhow::bail!(
+                    "The admin token must be at least {} characters",
+                    auth::MIN_TOKEN_LENGTH
+                );
+            }
+            Ok(Some(Arc::new(token)))
+        }
+        None if features.admin => {
+            anyhow::bail!("The admin feature requires --admin-token or EXPLORER_ADMIN_TOKEN")
+        }
+        Some(_) => {
+            tracing::warn!("Ignoring the admin token, the admin feature is disabled");
+            Ok(None)
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn routes(state: &AppState) -> Router<AppState> {
+    let router = Router::new()
+        .route("/reconnect", post(reconnect))
+        .route("/node", post(switch_node))
+        .route("/caches", get(get_cache_stats))
+        .route("/caches/flush", post(flush_caches))
+        .route("/log-level", get(get_log_level).put(set_log_level))
+        .route("/diagnostics", get(get_diagnostics))
+        .route("/index/snapshot", get(download_index_snapshot))
+        .route("/index/wipe", post(wipe_index))
+        .route(
+            "/labels/:address",
+            put(labels::set_label).delete(labels::delete_label),
+        )
+        .route("/debug/stats", get(debug_stats::get_debug_stats));
+    guarded(router, state)
+}
+
+/// The admin routes an additional network serves for itself.
+pub fn network_routes(state: &AppState) -> Router<AppState> {
+    let router = Router::new().route("/debug/stats", get(debug_stats::get_debug_stats));
+    guarded(router, state)
+}
+
+fn guarded(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            debug_stats::count_calls,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            authorize,
+        ))
+        // Outside the token check, so failed attempts count against the client too.
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit_default,
+        ))
+}
+
+async fn authorize(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let presented = auth::bearer(request.headers());
+    let admin_token = presented
+        .zip(state.admin_token.as_deref())
+        .is_some_and(|(presented, token)| auth::tokens_match(presented, token));
+    if admin_token {
+        return next.run(request).await;
+    }
+    // API tokens with the admin scope work as well, and are logged by name.
+    if let Some(tokens) = &state.api_tokens {
+        if presented.is_some() {
+            return match tokens.check(request.headers(), Scope::Admin) {
+                Ok(name) => {
+                    tracing::info!(
+                        "{} {} authorized for token {}",
+                        request.method(),
+                        request.uri().path(),
+                        name
+                    );
+                    next.run(request).await
+                }
+                Err(response) => response,
+            };
+        }
+    }
+    auth::unauthorized("Invalid admin token")
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SwitchNodeRequest {
+    url: String, // kaspad gRPC address, like --kaspad-url
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NodeResponse {
+    server_url: String,
+    server_version: String,
+    is_synced: bool,
+    is_utxo_indexed: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/reconnect",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Reconnected to the active node", body = NodeResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 502, description = "kaspad is unreachable; the previous client is kept", body = ErrorResponse)
+    )
+)]
+pub async fn reconnect(State(state): State<AppState>) -> Result<Json<NodeResponse>, Response> {
+    let url = state.network_info.read().await.server_url.clone();
+    connect(&state, url).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/node",
+    tag = "admin",
+    request_body = SwitchNodeRequest,
+    responses(
+        (status = 200, description = "Switched to the given node", body = NodeResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 502, description = "The node is unreachable; the previous one stays active", body = ErrorResponse)
+    )
+)]
+pub async fn switch_node(
+    State(state): State<AppState>,
+    Json(request): Json<SwitchNodeRequest>,
+) -> Result<Json<NodeResponse>, Response> {
+    let url = request.url.trim().to_string();
+    if url.is_empty() {
+        return Err(error_response(StatusCode::BAD_REQUEST, "Missing node URL"));
+    }
+    connect(&state, url).await
+}
+
+async fn connect(state: &AppState, url: String) -> Result<Json<NodeResponse>, Response> {
+    tracing::info!("Admin request to connect to kaspad at {}", url);
+    let info = connect_to_kaspad(state, &url).await.map_err(|e| {
+        tracing::error!("Admin reconnect to {} failed: {}", url, e);
+        error_response(
+            StatusCode::BAD_GATEWAY,
+            &format!("Failed to connect to {}: {}", url, e),
+        )
+    })?;
+    // Cached answers came from the previous connection, possibly another node.
+    flush(state).await;
+    Ok(Json(NodeResponse {
+        server_url: url,
+        server_version: info.server_version,
+        is_synced: info.is_synced,
+        is_utxo_indexed: info.is_utxo_indexed,
+    }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheStats {
+    balance_entries: usize,
+    activity_entries: usize,
+    block_transaction_entries: usize, // Blocks with cached transaction summaries
+    peer_entries: usize,
+    network_samples: usize,
+    network_rollups: usize,
+    response_entries: u64,    // Approximate; see cache.rs
+    index_epoch: Option<i64>, // None when the index is disabled or has no epoch yet
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/caches",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Sizes of the in-memory caches", body = CacheStats),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse)
+    )
+)]
+pub async fn get_cache_stats(State(state): State<AppState>) -> Json<CacheStats> {
+    let sampler = state.sampler.read().await;
+    Json(CacheStats {
+        balance_entries: state.balance_cache.read().await.len(),
+        activity_entries: state.activity_cache.entry_count() as usize,
+        block_transaction_entries: state.block_transactions.read().await.len(),
+        peer_entries: state.peer_info.read().await.len(),
+        network_samples: sampler.samples().len(),
+        network_rollups: sampler.rollups().len(),
+        response_entries: state.response_cache.entry_count(),
+        index_epoch: state
+            .index
+            .as_ref()
+            .and_then(|index| index.current_epoch_id()),
+    })
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FlushResponse {
+    balance_entries: usize,
+    response_entries: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/caches/flush",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cached balances and API responses dropped", body = FlushResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse)
+    )
+)]
+pub async fn flush_caches(State(state): State<AppState>) -> Json<FlushResponse> {
+    let flushed = flush(&state).await;
+    tracing::info!(
+        "Admin flushed {} cached balance(s) and {} response(s)",
+        flushed.balance_entries,
+        flushed.response_entries
+    );
+    Json(flushed)
+}
+
+async fn flush(state: &AppState) -> FlushResponse {
+    let balance_entries = std::mem::take(&mut *state.balance_cache.write().await).len();
+    FlushResponse {
+        balance_entries,
+        response_entries: state.response_cache.clear(),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LogLevel {
+    filter: String, // `RUST_LOG` syntax, e.g. "info,kaspa_testnet12_explorer=debug"
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/log-level",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Active log filter", body = LogLevel),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse)
+    )
+)]
+pub async fn get_log_level(State(state): State<AppState>) -> Result<Json<LogLevel>, Response> {
+    current_filter(&state).map(Json)
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/log-level",
+    tag = "admin",
+    request_body = LogLevel,
+    responses(
+        (status = 200, description = "Log filter replaced; returns the new one", body = LogLevel),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse)
+    )
+)]
+pub async fn set_log_level(
+    State(state): State<AppState>,
+    Json(request): Json<LogLevel>,
+) -> Result<Json<LogLevel>, Response> {
+    let filter = EnvFilter::try_new(&request.filter).map_err(|e| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid log filter: {}", e),
+        )
+    })?;
+    state.log_filter.reload(filter).map_err(|e| {
+        tracing::error!("Failed to replace the log filter: {}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to replace the log filter",
+        )
+    })?;
+    // Logged at warn so the change is visible under any filter.
+    tracing::warn!("Admin set the log filter to {}", request.filter);
+    current_filter(&state).map(Json)
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn current_filter(state: &AppState) -> Result<LogLevel, Response> {
+    state
+        .log_filter
+        .with_current(|filter| LogLevel {
+            filter: filter.to_string(),
+        })
+        .map_err(|e| {
+            tracing::error!("Failed to read the log filter: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read the log filter",
+            )
+        })
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Diagnostics {
+    version: &'static str,
+    git_hash: Option<&'static str>, // None when built outside a git checkout
+    api_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    generated_at: u64, // Unix ms
+    uptime_seconds: u64,
+    // The loaded configuration, with secrets redacted.
+    #[schema(value_type = Object)]
+    config: serde_json::Value,
+    node: NetworkInfo,
+    index: Option<IndexStats>, // None when the index is disabled
+    index_error: Option<String>,
+    recent_errors: Vec<LogRecord>, // Latest warnings and errors, oldest first
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/diagnostics",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Version, configuration, node, index and recent errors in one document", body = Diagnostics),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse)
+    )
+)]
+pub async fn get_diagnostics(State(state): State<AppState>) -> Json<Diagnostics> {
+    let mut node = state.network_info.read().await.clone();
+    node.explorer_uptime_seconds = state.started_at.elapsed().as_secs();
+    let (index, index_error) = match &state.index {
+        Some(index) => match index.stats().await {
+            Ok(stats) => (Some(stats), None),
+            Err(e) => (None, Some(e.to_string())),
+        },
+        None => (None, None),
+    };
+    let recent_errors = state
+        .recent_errors
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .cloned()
+        .collect();
+    Json(Diagnostics {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: Some(env!("EXPLORER_GIT_HASH")).filter(|hash| !hash.is_empty()),
+        api_version: API_VERSION,
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        generated_at: now_ms(),
+        uptime_seconds: node.explorer_uptime_seconds,
+        config: serde_json::to_value(&*state.config).unwrap_or_default(),
+        node,
+        index,
+        index_error,
+        recent_errors,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/index/snapshot",
+    tag = "admin",
+    responses(
+        (status = 200, description = "zstd-compressed copy of the index database, for import-snapshot", content_type = "application/zstd"),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 503, description = "Index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn download_index_snapshot(State(state): State<AppState>) -> Result<Response, Response> {
+    if state.index.is_none() {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Index disabled",
+        ));
+    }
+    let path = state.config.index.path.clone();
+    let (sender, receiver) = mpsc::channel(SNAPSHOT_CHUNKS);
+    tracing::info!("Admin requested a snapshot of index {}", path.display());
+    tokio::task::spawn_blocking(move || {
+        let errors = sender.clone();
+        match index_snapshot::write_archive(&path, ChannelWriter(sender)) {
+            Ok(size) => tracing::info!("Index snapshot sent ({} bytes uncompressed)", size),
+            Err(e) => {
+                tracing::warn!("Index snapshot aborted: {:?}", e);
+                // Cut the transfer short rather than end it like a complete archive.
+                let _ = errors.blocking_send(Err(std::io::Error::other(e.to_string())));
+            }
+        }
+    });
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zstd".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"index-snapshot-{}.sqlite.zst\"",
+                    now_ms()
+                ),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(receiver)),
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WipeIndexRequest {
+    confirm: bool, // Must be true
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WipeIndexResponse {
+    epochs_deleted: usize,
+    // Why the index was not served before the wipe, if it was not.
+    network_mismatch: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/index/wipe",
+    tag = "admin",
+    request_body = WipeIndexRequest,
+    responses(
+        (status = 200, description = "Index wiped; the indexer starts a new epoch from kaspad's pruning point", body = WipeIndexResponse),
+        (status = 400, description = "confirm is not set", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 503, description = "Index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn wipe_index(
+    State(state): State<AppState>,
+    Json(request): Json<WipeIndexRequest>,
+) -> Result<Json<WipeIndexResponse>, Response> {
+    let Some(index) = &state.index else {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Index disabled",
+        ));
+    };
+    if !request.confirm {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Wiping deletes every indexed block and transaction; send confirm: true",
+        ));
+    }
+    let network_mismatch = index.network_mismatch();
+    let failed = |e: anyhow::Error| {
+        tracing::error!("Admin index wipe failed: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index wipe failed")
+    };
+    let epochs_deleted = index.epochs().await.map_err(failed)?.len();
+    index.wipe().await.map_err(failed)?;
+    tracing::warn!(
+        "Admin wiped the index ({} epoch(s)); rebuilding from kaspad",
+        epochs_deleted
+    );
+    // Cached answers came from the wiped index.
+    flush(&state).await;
+    Ok(Json(WipeIndexResponse {
+        epochs_deleted,
+        network_mismatch,
+    }))
+}
@@ -0,0 +1,201 @@
+//! Bearer tokens for privileged routes.
+//!
+//! Reads stay public. With `[auth]` enabled, the routes that act rather than read need
+//! `Authorization: Bearer <token>` with a token holding the route's scope:
+//!
+//! - `transactions`: `POST /transaction/validate`, the endpoint that takes client
+//!   transactions (the explorer does not broadcast them)
+//! - `faucet`: `POST /faucet`
+//! - `watch`: `POST /watch`, and `GET`/`DELETE /watch/:id`, which also keep asking for the
+//!   watch's secret in `x-api-key`
+//! - `admin`: everything under `/admin`, accepted next to `--admin-token`
+//!
+//! Only scopes listed in `protected` are enforced, so a public faucet can stay open while
+//! watches need a token. Each token has a name, and its value inline or in an environment
+//! variable; requests it authorizes are logged under that name, which is also recorded as the
+//! `client` field of the request span. A missing or unknown token gets 401, a token without
+//! the route's scope 403.
+
+use crate::config::AuthConfig;
+use crate::{error_response, timeouts, AppState};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Shared with --admin-token.
+pub const MIN_TOKEN_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Transactions,
+    Faucet,
+    Watch,
+    Admin,
+}
+
+impl Scope {
+    fn name(self) -> &'static str {
+        match self {
+            Scope::Transactions => "transactions",
+            Scope::Faucet => "faucet",
+            Scope::Watch => "watch",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+// API routes by method and path pattern under /api, as `timeouts::route_key` gives them.
+const SCOPED_ROUTES: [(Method, &str, Scope); 5] = [
+    (Method::POST, "/transaction/validate", Scope::Transactions),
+    (Method::POST, "/faucet", Scope::Faucet),
+    (Method::POST, "/watch", Scope::Watch),
+    (Method::GET, "/watch/:id", Scope::Watch),
+    (Method::DELETE, "/watch/:id", Scope::Watch),
+];
+
+struct ApiToken {
+    name: String,
+    token: String,
+    scopes: HashSet<Scope>,
+}
+
+pub struct ApiTokens {
+    tokens: Vec<ApiToken>,
+    protected: HashSet<Scope>,
+}
+
+impl ApiTokens {
+    /// The configured tokens, or None when `[auth]` is disabled.
+    pub fn load(config: &AuthConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let mut names = HashSet::new();
+        let mut tokens = Vec::with_capacity(config.tokens.len());
+        for entry in &config.tokens {
+            if !names.insert(entry.name.as_str()) {
+                anyhow::bail!("[auth] token name {:?} is used twice", entry.name);
+            }
+            let token = match (&entry.token, &entry.token_env) {
+                (Some(token), None) => token.clone(),
+                (None, Some(var)) => std::env::var(var).map_err(|_| {
+                    anyhow::anyhow!(
+                        "[auth] token {:?} reads environment variable {}, which is not set",
+                        entry.name,
+                        var
+                    )
+                })?,
+                _ => anyhow::bail!(
+                    "[auth] token {:?} needs exactly one of token and token_env",
+                    entry.name
+                ),
+            };
+            if token.len() < MIN_TOKEN_LENGTH {
+                anyhow::bail!(
+                    "[auth] token {:?} must be at least {} characters",
+                    entry.name,
+                    MIN_TOKEN_LENGTH
+                );
+            }
+            tokens.push(ApiToken {
+                name: entry.name.clone(),
+                token,
+                scopes: entry.scopes.iter().copied().collect(),
+            });
+        }
+        if tokens.is_empty() {
+            tracing::warn!(
+                "[auth] is enabled without tokens; its protected routes refuse everyone"
+            );
+        }
+        Ok(Some(Arc::new(ApiTokens {
+            tokens,
+            protected: config.protected.iter().copied().collect(),
+        })))
+    }
+
+    /// The name of the presented token if it holds `scope`, or the 401/403 to answer.
+    #[allow(clippy::result_large_err)] // Handlers return the error response as-is
+    pub fn check(&self, headers: &HeaderMap, scope: Scope) -> Result<&str, Response> {
+        let token = bearer(headers).and_then(|presented| {
+            self.tokens
+                .iter()
+                .find(|token| tokens_match(presented, &token.token))
+        });
+        let Some(token) = token else {
+            return Err(unauthorized("Missing or invalid API token"));
+        };
+        if !token.scopes.contains(&scope) {
+            return Err(error_response(
+                StatusCode::FORBIDDEN,
+                &format!("Token {} lacks the {} scope", token.name, scope.name()),
+            ));
+        }
+        tracing::Span::current().record("client", token.name.as_str());
+        Ok(&token.name)
+    }
+}
+
+/// The token of an `Authorization: Bearer` header.
+pub fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Compares digests in constant time, so response timing does not leak the token.
+pub fn tokens_match(presented: &str, token: &str) -> bool {
+    let presented = Sha256::digest(presented.as_bytes());
+    let token = Sha256::digest(token.as_bytes());
+    presented
+        .iter()
+        .zip(token.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// A 401 asking for a bearer token.
+pub fn unauthorized(detail: &str) -> Response {
+    let mut response = error_response(StatusCode::UNAUTHORIZED, detail);
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+pub async fn authorize(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(tokens) = &state.api_tokens else {
+        return next.run(request).await;
+    };
+    let scope = timeouts::route_key(&request).and_then(|route| {
+        SCOPED_ROUTES
+            .iter()
+            .find(|(method, pattern, _)| method == request.method() && *pattern == route)
+            .map(|(_, _, scope)| *scope)
+    });
+    let Some(scope) = scope.filter(|scope| tokens.protected.contains(scope)) else {
+        return next.run(request).await;
+    };
+    match tokens.check(request.headers(), scope) {
+        Ok(name) => {
+            tracing::info!(
+                "{} {} authorized for token {}",
+                request.method(),
+                request.uri().path(),
+                name
+            );
+            next.run(request).await
+        }
+        Err(response) => response,
+    }
+}
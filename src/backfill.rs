@@ -0,0 +1,132 @@
+//! `backfill` subcommand: indexes existing history without serving.
+//!
+//! Walks the DAG forward from the index cursor (the pruning point on a fresh index, or
+//! `--from`) to the sink with the same batches as the background indexer, so a new explorer
+//! can be filled before it takes traffic. Every batch stores its cursor along with its
+//! blocks, so an interrupted backfill (Ctrl-C, lost connection) resumes where it stopped
+//! when run again without `--from`. The explorer must not be serving from the same index
+//! file meanwhile.
+
+use crate::chain::ChainSource;
+use crate::config::Config;
+use crate::index::Index;
+use crate::network::Network;
+use crate::{epochs, indexer, rpc};
+use kaspa_hashes::Hash;
+use tokio::time::{Duration, Instant};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, clap::Args)]
+pub struct BackfillArgs {
+    /// Block hash to start from instead of the stored cursor or the pruning point
+    #[arg(long)]
+    from: Option<String>,
+}
+
+pub async fn run(
+    args: BackfillArgs,
+    kaspad_url: &str,
+    network: Network,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let mut from = args
+        .from
+        .as_deref()
+        .map(|hash| {
+            hash.parse::<Hash>()
+                .map_err(|e| anyhow::anyhow!("Invalid --from hash {}: {:?}", hash, e))
+        })
+        .transpose()?;
+    if !config.index.enabled {
+        tracing::warn!("[index] is disabled; the backfilled index is only served once enabled");
+    }
+    let index = Index::open(&config.index.path)?;
+    let client = rpc::connect(kaspad_url).await?;
+
+    epochs::check(&client, &index, config.index.auto_reset).await?;
+    if let Some(mismatch) = index.network_mismatch() {
+        anyhow::bail!("{} (run with --auto-reset-index to rebuild)", mismatch);
+    }
+    if index.current_epoch_id().is_none() {
+        anyhow::bail!("kaspad is not synced yet; backfill once it is");
+    }
+    match (&from, index.cursor().await?) {
+        (Some(from), _) => tracing::info!("Backfilling from {}", from),
+        (None, Some(cursor)) => tracing::info!("Resuming backfill from cursor {}", cursor),
+        (None, None) => {}
+    }
+
+    let started = Instant::now();
+    let mut next_report = started + PROGRESS_INTERVAL;
+    let mut start_daa_score = None;
+    let mut daa_score = 0;
+    let mut blocks = 0;
+    let result = loop {
+        let batch = tokio::select! {
+            batch = indexer::next_batch(&client, &index, from, network, config.index.tokens) => batch,
+            _ = tokio::signal::ctrl_c() => break Ok(false),
+        };
+        let batch = match batch {
+            Ok(Some(batch)) => batch,
+            Ok(None) => break Ok(true),
+            Err(e) => break Err(e),
+        };
+        // Later batches continue from the stored cursor.
+        from = None;
+        blocks += batch.blocks;
+        daa_score = daa_score.max(batch.max_daa_score);
+        let start = *start_daa_score.get_or_insert(daa_score);
+        if batch.caught_up {
+            break Ok(true);
+        }
+        if Instant::now() >= next_report {
+            let target = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+                .await
+                .map_or(daa_score, |dag_info| dag_info.virtual_daa_score);
+            let progress = if target > start {
+                daa_score.saturating_sub(start) as f64 * 100.0 / (target - start) as f64
+            } else {
+                100.0
+            };
+            tracing::info!(
+                "Backfilled {} blocks ({:.0}/s), DAA score {} of {} ({:.1}%), cursor {}",
+                blocks,
+                blocks as f64 / started.elapsed().as_secs_f64(),
+                daa_score,
+                target,
+                progress.min(100.0),
+                batch.cursor
+            );
+            next_report = Instant::now() + PROGRESS_INTERVAL;
+        }
+    };
+
+    index.checkpoint().await?;
+    if let Err(e) = client.disconnect().await {
+        tracing::warn!("Failed to disconnect from kaspad: {}", e);
+    }
+    match result {
+        Ok(true) => {
+            tracing::info!(
+                "Backfill reached the sink: {} blocks in {:.0}s, up to DAA score {}",
+                blocks,
+                started.elapsed().as_secs_f64(),
+                daa_score
+            );
+            Ok(())
+        }
+        Ok(false) => {
+            tracing::info!(
+                "Interrupted after {} blocks; run backfill again without --from to resume",
+                blocks
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow::anyhow!(
+            "{:?} ({} block(s) indexed; run backfill again without --from to resume)",
+            e,
+            blocks
+        )),
+    }
+}
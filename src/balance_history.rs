@@ -0,0 +1,161 @@
+//! Balance of an address over time, for charting how a faucet or pool wallet drained or
+//! filled during a test.
+//!
+//! The index records every output paying the address and every input spending one, so the
+//! flows of a window are known per transaction. Only transactions a chain block accepted
+//! count, so a double spend that lost, or a transaction not merged yet, moves no balance.
+//! Acceptances come from the notifications the explorer received; transactions accepted while
+//! it was down are left out. The series is anchored at the current balance
+//! from kaspad's UTXO index and walked back through them: each point is the balance at the
+//! end of its bucket. Spends of outputs created before the index started are not seen, so for
+//! addresses older than the index the earlier points can be off by those amounts. Flows
+//! already moved to the index's cold tier are not read either; `truncated` says when the
+//! window reaches that far back.
+
+use crate::chain::ChainSource;
+use crate::charts::parse_window;
+use crate::sampler::now_ms;
+use crate::{error_response, network, not_connected, require_index, rpc, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+// Default number of points returned when no resolution is requested.
+const DEFAULT_POINTS: u64 = 120;
+// Finer resolutions are coarsened to stay below this many points.
+const MAX_POINTS: u64 = 1_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BalanceHistoryQuery {
+    /// 1h, 24h or 7d (default 24h)
+    window: Option<String>,
+    resolution: Option<u64>, // Bucket size in seconds
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BalancePoint {
+    timestamp: u64, // Bucket start, Unix time in milliseconds
+    // At the end of the bucket.
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    received: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    sent: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BalanceHistory {
+    address: String,
+    window: String,
+    resolution_seconds: u64,
+    points: Vec<BalancePoint>,
+    // Part of the window is in the cold tier, so earlier points miss those flows.
+    truncated: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/history",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address"), BalanceHistoryQuery),
+    responses(
+        (status = 200, description = "Balance at the end of each bucket, with the amounts received and sent in it", body = BalanceHistory),
+        (status = 400, description = "Invalid address or window", body = ErrorResponse),
+        (status = 503, description = "Index disabled, or not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_balance_history(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(query): Query<BalanceHistoryQuery>,
+) -> Result<Json<BalanceHistory>, Response> {
+    let parsed_address = network::parse_address(&state, &address)?;
+    let window = query.window.unwrap_or_else(|| "24h".to_string());
+    let window_seconds = parse_window(&window).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            "Invalid window (expected 1h, 24h or 7d)",
+        )
+    })?;
+    let resolution_seconds = query
+        .resolution
+        .unwrap_or(window_seconds / DEFAULT_POINTS)
+        .clamp(window_seconds.div_ceil(MAX_POINTS), window_seconds);
+    let bucket_ms = resolution_seconds * 1000;
+    let index = require_index(&state)?;
+
+    let balance = {
+        let client_guard = state.client.read().await;
+        let client = client_guard.as_ref().ok_or_else(not_connected)?;
+        rpc::retried("get_balance_by_address", || {
+            client.get_balance_by_address(parsed_address.clone())
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get balance of {}: {:?}", parsed_address, e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch balance (is --utxoindex enabled?)",
+            )
+        })?
+    };
+
+    let now = now_ms();
+    let first_bucket = now.saturating_sub(window_seconds * 1000);
+    let first_bucket = first_bucket - first_bucket % bucket_ms;
+    let address = parsed_address.to_string();
+    let lookup_failed = |e: anyhow::Error| {
+        tracing::error!("Index lookup of flows for {} failed: {:?}", address, e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    };
+    let flows = index
+        .address_flows(address.clone(), first_bucket)
+        .await
+        .map_err(lookup_failed)?;
+    let truncated = index
+        .flows_truncated(first_bucket)
+        .await
+        .map_err(lookup_failed)?;
+
+    // The balance before the window: the current one with the window's flows undone.
+    let net: i128 = flows
+        .iter()
+        .map(|(_, received, sent)| *received as i128 - *sent as i128)
+        .sum();
+    let mut running = balance as i128 - net;
+    let mut flows = flows.into_iter().peekable();
+    let mut points = Vec::new();
+    let mut bucket = first_bucket;
+    while bucket <= now {
+        let (mut received, mut sent) = (0u64, 0u64);
+        let last = bucket + bucket_ms > now; // Also takes blocks timestamped ahead of us
+        while let Some((_, r, s)) =
+            flows.next_if(|(timestamp, ..)| last || *timestamp < bucket + bucket_ms)
+        {
+            received += r;
+            sent += s;
+        }
+        running += received as i128 - sent as i128;
+        points.push(BalancePoint {
+            timestamp: bucket,
+            balance: running.clamp(0, u64::MAX as i128) as u64,
+            received,
+            sent,
+        });
+        bucket += bucket_ms;
+    }
+
+    Ok(Json(BalanceHistory {
+        address,
+        window,
+        resolution_seconds,
+        points,
+        truncated,
+    }))
+}
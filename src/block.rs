@@ -0,0 +1,398 @@
+//! Block detail view.
+//!
+//! Besides the header and its place in the DAG, the detail splits what the block is worth to
+//! its miner: `block_reward` is the subsidy recorded in the coinbase payload (the emission
+//! schedule's value at the block's DAA score), and `total_fees` is the sum of the fees of its
+//! own transactions, with inputs resolved like in the transaction view. Both are paid out by
+//! the chain block that merges this one as blue, so they appear in that block's coinbase.
+//! `coinbase_value` is what this block's own coinbase pays for its mergeset. `mined_by` names
+//! the pool or mining software when the signature table (`pools.rs`) recognizes the coinbase.
+//!
+//! Blocks below the pruning point keep their header but lose their transactions; these
+//! answer `410 Gone` rather than a detail without a coinbase.
+//!
+//! Kaspa has no block heights, so `/block/by-daa/:score` stands in for a height lookup: it
+//! answers the selected-chain block nearest to a DAA score. The index supplies the blocks
+//! nearest the score, the first of those kaspad reports on the selected chain is taken, and
+//! its selected parents (chain blocks too, with strictly lower DAA scores) are followed down
+//! to the score. Both steps are bounded, so a lookup costs a handful of header fetches.
+
+use crate::chain::{ChainSource, Client};
+use crate::labels::{self, Label};
+use crate::pools::MinedBy;
+use crate::resolver::{self, Outpoint};
+use crate::{
+    block_size, error_response, miners, not_connected, pruning, require_index, rpc, AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcBlock;
+use serde::Serialize;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockDetail {
+    hash: String,
+    version: u16,
+    daa_score: u64,
+    blue_score: u64,
+    blue_work: String, // Hex
+    timestamp: u64,    // Unix ms
+    bits: u32,
+    difficulty: Option<f64>,
+    selected_parent_hash: Option<String>,
+    is_chain_block: Option<bool>,
+    parents: Vec<String>, // Direct (level 0) parents
+    parents_truncated: bool,
+    merge_set_blues: Option<usize>,
+    merge_set_reds: Option<usize>,
+    tx_count: usize,
+    size: u64, // Serialized bytes
+    mass: u64,
+    mass_utilization: f64, // Of the consensus block mass limit
+    miner: Option<String>, // Coinbase address, if the payload holds a standard script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    miner_label: Option<Label>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mined_by: Option<MinedBy>, // Pool or mining software, from the signature table
+    #[serde(serialize_with = "crate::units::sompi")]
+    coinbase_value: u64,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    block_reward: Option<u64>, // Subsidy from the coinbase payload
+    // None when a spent output could not be resolved.
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    total_fees: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockSummary {
+    hash: String,
+    daa_score: u64,
+    blue_score: u64,
+    timestamp: u64, // Unix ms
+    difficulty: Option<f64>,
+    tx_count: usize, // 0 once pruned
+    size: u64,       // Serialized bytes
+    mass: u64,
+    mass_utilization: f64,
+    miner: Option<String>, // Coinbase address, if the payload holds a standard script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mined_by: Option<MinedBy>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockByDaa {
+    daa_score: u64, // As requested
+    exact: bool,    // The block has exactly the requested DAA score
+    block: BlockSummary,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/block/{hash}",
+    tag = "blocks",
+    params(("hash" = String, Path, description = "Block hash")),
+    responses(
+        (status = 200, description = "Block header, DAG position, reward and fees", body = BlockDetail),
+        (status = 400, description = "Invalid hash", body = ErrorResponse),
+        (status = 404, description = "Block not found", body = ErrorResponse),
+        (status = 410, description = "Block pruned; only its header is kept", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_block(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+) -> Result<Json<BlockDetail>, Response> {
+    let hash = hash
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid block hash"))?;
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let block = rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(hash, true).await },
+    )
+    .await
+    .map_err(|e| {
+        if rpc::is_not_found(&e) {
+            error_response(StatusCode::NOT_FOUND, "Block not found")
+        } else {
+            tracing::error!("Failed to fetch block {}: {:?}", hash, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block")
+        }
+    })?;
+    // Every block with its body has a coinbase.
+    if block.transactions.is_empty() {
+        return Err(pruning::pruned_response(client, block.header.daa_score).await);
+    }
+
+    // The coinbase is always first; only the other transactions spend anything.
+    let spending: Vec<(Vec<Outpoint>, u64)> = block
+        .transactions
+        .iter()
+        .skip(1)
+        .map(|tx| {
+            let spent = tx
+                .inputs
+                .iter()
+                .map(|input| {
+                    (
+                        input.previous_outpoint.transaction_id.to_string(),
+                        input.previous_outpoint.index,
+                    )
+                })
+                .collect();
+            (spent, tx.outputs.iter().map(|o| o.value).sum())
+        })
+        .collect();
+    let outpoints: Vec<Outpoint> = spending
+        .iter()
+        .flat_map(|(spent, _)| spent.iter().cloned())
+        .collect();
+    let resolved = resolver::resolve(&state, client, &outpoints).await;
+    drop(client_guard);
+    let total_fees = spending
+        .iter()
+        .map(|(spent, output_value)| {
+            resolver::input_value(spent, &resolved)?.checked_sub(*output_value)
+        })
+        .sum();
+
+    let max_parents = state.config.limits.max_block_parents;
+    let mut seen = HashSet::new();
+    let parents: Vec<String> = block
+        .header
+        .parents_by_level
+        .first()
+        .into_iter()
+        .flatten()
+        .filter(|hash| seen.insert(**hash))
+        .map(ToString::to_string)
+        .collect();
+    let miner = miners::coinbase_miner(&block);
+    let miner_label = match &miner {
+        Some(miner) => labels::get(&state, miner).await,
+        None => None,
+    };
+    let mined_by = state
+        .pools
+        .as_ref()
+        .and_then(|pools| pools.identify(&block));
+    let verbose = block.verbose_data.as_ref();
+    let mass = block_size::mass(&block, state.network);
+    let blue_work = block.header.blue_work.to_be_bytes();
+    let blue_work_start = blue_work
+        .iter()
+        .position(|b| *b != 0)
+        .unwrap_or(blue_work.len());
+
+    Ok(Json(BlockDetail {
+        hash: block.header.hash.to_string(),
+        version: block.header.version,
+        daa_score: block.header.daa_score,
+        blue_score: block.header.blue_score,
+        blue_work: hex::encode(&blue_work[blue_work_start..]),
+        timestamp: block.header.timestamp,
+        bits: block.header.bits,
+        difficulty: verbose.map(|v| v.difficulty),
+        selected_parent_hash: verbose
+            .map(|v| v.selected_parent_hash)
+            .filter(|hash| *hash != Hash::default())
+            .map(|hash| hash.to_string()),
+        is_chain_block: verbose.map(|v| v.is_chain_block),
+        parents_truncated: parents.len() > max_parents,
+        parents: parents.into_iter().take(max_parents).collect(),
+        merge_set_blues: verbose.map(|v| v.merge_set_blues_hashes.len()),
+        merge_set_reds: verbose.map(|v| v.merge_set_reds_hashes.len()),
+        tx_count: block.transactions.len(),
+        size: block_size::size(&block),
+        mass,
+        mass_utilization: block_size::utilization(mass),
+        miner,
+        miner_label,
+        mined_by,
+        coinbase_value: block
+            .transactions
+            .first()
+            .map_or(0, |coinbase| coinbase.outputs.iter().map(|o| o.value).sum()),
+        block_reward: miners::coinbase_subsidy(&block),
+        total_fees,
+    }))
+}
+
+// Indexed blocks tried per side of the score, and selected parents followed at most.
+const MAX_CANDIDATES: usize = 32;
+const MAX_PARENT_STEPS: usize = 32;
+
+fn is_chain_block(block: &RpcBlock) -> bool {
+    block
+        .verbose_data
+        .as_ref()
+        .is_some_and(|v| v.is_chain_block)
+}
+
+fn selected_parent(block: &RpcBlock) -> Option<Hash> {
+    block
+        .verbose_data
+        .as_ref()
+        .map(|v| v.selected_parent_hash)
+        .filter(|hash| *hash != Hash::default())
+}
+
+async fn fetch_block(
+    state: &AppState,
+    client: &Client,
+    hash: Hash,
+    include_transactions: bool,
+) -> Result<RpcBlock, Response> {
+    rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(hash, include_transactions).await },
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch chain block {}: {:?}", hash, e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch chain block",
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/block/by-daa/{score}",
+    tag = "blocks",
+    params(("score" = u64, Path, description = "DAA score")),
+    responses(
+        (status = 200, description = "Selected-chain block at or nearest to the DAA score", body = BlockByDaa),
+        (status = 400, description = "Invalid DAA score", body = ErrorResponse),
+        (status = 404, description = "Nothing indexed yet", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, or index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_block_by_daa(
+    State(state): State<AppState>,
+    Path(score): Path<String>,
+) -> Result<Json<BlockByDaa>, Response> {
+    let score = score
+        .parse::<u64>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid DAA score"))?;
+    let index = require_index(&state)?;
+
+    let lookup = |e: anyhow::Error| {
+        tracing::error!("Index lookup for DAA score {} failed: {:?}", score, e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index query failed")
+    };
+    let candidates_above = index
+        .blocks_near_daa(score, true, MAX_CANDIDATES)
+        .await
+        .map_err(lookup)?;
+    let candidates_below = index
+        .blocks_near_daa(score, false, MAX_CANDIDATES)
+        .await
+        .map_err(lookup)?;
+    if candidates_above.is_empty() && candidates_below.is_empty() {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "No blocks indexed yet",
+        ));
+    }
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+
+    // A chain block at or above the score, lowered along selected parents to the first chain
+    // block at or above it; the parent where that stops is the chain block below.
+    let mut above = None;
+    for hash in candidates_above
+        .iter()
+        .filter_map(|hash| hash.parse::<Hash>().ok())
+    {
+        let block = fetch_block(&state, client, hash, false).await?;
+        if is_chain_block(&block) {
+            above = Some(block);
+            break;
+        }
+    }
+    let mut below = None;
+    if let Some(mut lowest) = above.take() {
+        for _ in 0..MAX_PARENT_STEPS {
+            let Some(parent) = selected_parent(&lowest) else {
+                break;
+            };
+            let parent = fetch_block(&state, client, parent, false).await?;
+            if parent.header.daa_score < score {
+                below = Some(parent);
+                break;
+            }
+            lowest = parent;
+        }
+        above = Some(lowest);
+    } else {
+        // The score is past the sink, or past every indexed chain block.
+        for hash in candidates_below
+            .iter()
+            .filter_map(|hash| hash.parse::<Hash>().ok())
+        {
+            let block = fetch_block(&state, client, hash, false).await?;
+            if is_chain_block(&block) {
+                below = Some(block);
+                break;
+            }
+        }
+    }
+
+    let nearest = match (below, above) {
+        (Some(below), Some(above)) => {
+            // Ties go to the earlier block.
+            if above.header.daa_score.abs_diff(score) < below.header.daa_score.abs_diff(score) {
+                above
+            } else {
+                below
+            }
+        }
+        (Some(block), None) | (None, Some(block)) => block,
+        (None, None) => {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                "No chain block near this DAA score",
+            ))
+        }
+    };
+    let block = fetch_block(&state, client, nearest.header.hash, true).await?;
+    drop(client_guard);
+    let mass = block_size::mass(&block, state.network);
+
+    Ok(Json(BlockByDaa {
+        daa_score: score,
+        exact: block.header.daa_score == score,
+        block: BlockSummary {
+            hash: block.header.hash.to_string(),
+            daa_score: block.header.daa_score,
+            blue_score: block.header.blue_score,
+            timestamp: block.header.timestamp,
+            difficulty: block.verbose_data.as_ref().map(|v| v.difficulty),
+            tx_count: block.transactions.len(),
+            size: block_size::size(&block),
+            mass,
+            mass_utilization: block_size::utilization(mass),
+            miner: miners::coinbase_miner(&block),
+            mined_by: state
+                .pools
+                .as_ref()
+                .and_then(|pools| pools.identify(&block)),
+        },
+    }))
+}
@@ -0,0 +1,113 @@
+//! Block interval statistics, for checking the network runs at its target rate.
+//!
+//! Computed from the timestamps of the indexed blocks in the window: the intervals are the
+//! gaps between consecutive timestamps in time order, so with several blocks per second many
+//! are zero (blocks mined in parallel on different branches of the DAG). The realized rate is
+//! the blocks after the first over the time the window's blocks span, so an index started
+//! partway through the window does not dilute it.
+
+use crate::emission::Schedule;
+use crate::sampler::now_ms;
+use crate::{error_response, require_index, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BlockIntervalsQuery {
+    /// 1m, 10m, 1h or 24h (default 1h)
+    window: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockIntervalsResponse {
+    window: String,
+    blocks: usize,
+    first_timestamp: Option<u64>, // Unix ms
+    last_timestamp: Option<u64>,
+    // Inter-block times in ms; None with fewer than two blocks.
+    mean_interval_ms: Option<f64>,
+    median_interval_ms: Option<u64>,
+    p95_interval_ms: Option<u64>,
+    max_interval_ms: Option<u64>,
+    realized_bps: Option<f64>,
+    target_bps: u64,           // The network's, or [emission] blocks_per_second
+    target_ratio: Option<f64>, // realized_bps / target_bps
+}
+
+fn parse_window(window: &str) -> Option<u64> {
+    match window {
+        "1m" => Some(60),
+        "10m" => Some(600),
+        "1h" => Some(3_600),
+        "24h" => Some(86_400),
+        _ => None,
+    }
+}
+
+/// Nearest-rank percentile of sorted `values`.
+fn percentile(values: &[u64], p: f64) -> u64 {
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/block-intervals",
+    tag = "stats",
+    params(BlockIntervalsQuery),
+    responses(
+        (status = 200, description = "Inter-block time statistics and realized blocks per second", body = BlockIntervalsResponse),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_block_intervals(
+    State(state): State<AppState>,
+    Query(query): Query<BlockIntervalsQuery>,
+) -> Result<Json<BlockIntervalsResponse>, Response> {
+    let window = query.window.unwrap_or_else(|| "1h".to_string());
+    let window_seconds = parse_window(&window).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            "Invalid window (expected 1m, 10m, 1h or 24h)",
+        )
+    })?;
+    let index = require_index(&state)?;
+
+    let since = now_ms().saturating_sub(window_seconds * 1000);
+    let timestamps = index.block_timestamps(since).await.map_err(|e| {
+        tracing::error!("Index lookup of block timestamps failed: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    })?;
+    let mut intervals: Vec<u64> = timestamps.windows(2).map(|w| w[1] - w[0]).collect();
+    intervals.sort_unstable();
+    let span_ms = match (timestamps.first(), timestamps.last()) {
+        (Some(first), Some(last)) => last - first,
+        _ => 0,
+    };
+    let target_bps = Schedule::new(state.network, &state.config.emission).blocks_per_second;
+    let realized_bps = (span_ms > 0).then(|| intervals.len() as f64 * 1000.0 / span_ms as f64);
+    let has_intervals = !intervals.is_empty();
+
+    Ok(Json(BlockIntervalsResponse {
+        window,
+        blocks: timestamps.len(),
+        first_timestamp: timestamps.first().copied(),
+        last_timestamp: timestamps.last().copied(),
+        mean_interval_ms: has_intervals.then(|| span_ms as f64 / intervals.len() as f64),
+        median_interval_ms: has_intervals.then(|| percentile(&intervals, 50.0)),
+        p95_interval_ms: has_intervals.then(|| percentile(&intervals, 95.0)),
+        max_interval_ms: intervals.last().copied(),
+        realized_bps,
+        target_bps,
+        target_ratio: realized_bps
+            .filter(|_| target_bps > 0)
+            .map(|bps| bps / target_bps as f64),
+    }))
+}
@@ -0,0 +1,123 @@
+//! Block size and mass, for seeing how full blocks get under load.
+//!
+//! The size is the block's consensus serialization (`raw.rs`). Mass has three dimensions
+//! that consensus limits separately: compute and transient mass, which the mass calculator
+//! derives from each transaction alone, and the storage mass a transaction commits to. Each is
+//! summed over the block's transactions, and a block's mass is the largest of the three sums;
+//! its utilization is that over the consensus block mass limit. Block summaries carry all
+//! three. The indexer stores size and mass with every block, and `GET /stats/block-size`
+//! averages them over the indexed blocks of the last minute, ten minutes, hour and day.
+//! Blocks indexed before size and mass were recorded are left out.
+
+use crate::fee_histogram::BLOCK_MASS_LIMIT;
+use crate::network::Network;
+use crate::sampler::now_ms;
+use crate::{error_response, raw, require_index, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_consensus_core::mass::{MassCalculator, NonContextualMasses};
+use kaspa_consensus_core::tx::Transaction;
+use kaspa_rpc_core::{RpcBlock, RpcTransaction};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const WINDOWS: [(&str, u64); 4] = [("1m", 60), ("10m", 600), ("1h", 3_600), ("24h", 86_400)];
+
+// Compute and transient mass of `tx`; kaspad's compute mass if it does not convert.
+fn non_contextual_masses(calculator: &MassCalculator, tx: &RpcTransaction) -> NonContextualMasses {
+    match Transaction::try_from(tx.clone()) {
+        Ok(tx) => calculator.calc_non_contextual_masses(&tx),
+        Err(_) => NonContextualMasses {
+            compute_mass: tx.verbose_data.as_ref().map_or(0, |v| v.compute_mass),
+            transient_mass: 0,
+        },
+    }
+}
+
+/// Serialized size of `block` in bytes: header, transaction count and transactions.
+pub fn size(block: &RpcBlock) -> u64 {
+    raw::serialize_block(block).len() as u64
+}
+
+/// Mass of `block` on `network`: the largest of its transactions' summed compute, storage
+/// and transient mass.
+pub fn mass(block: &RpcBlock, network: Network) -> u64 {
+    let calculator = MassCalculator::new_with_consensus_params(&network.params());
+    let (mut compute, mut storage, mut transient) = (0u64, 0u64, 0u64);
+    for tx in &block.transactions {
+        let masses = non_contextual_masses(&calculator, tx);
+        compute = compute.saturating_add(masses.compute_mass);
+        storage = storage.saturating_add(tx.mass);
+        transient = transient.saturating_add(masses.transient_mass);
+    }
+    compute.max(storage).max(transient)
+}
+
+/// Share of the consensus block mass limit `mass` takes up.
+pub fn utilization(mass: u64) -> f64 {
+    mass as f64 / BLOCK_MASS_LIMIT as f64
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockSizeWindow {
+    window: String,
+    blocks: u64,
+    // Blocks with a recorded size and mass, which the figures below cover.
+    measured_blocks: u64,
+    mean_size: Option<f64>, // Bytes; None without measured blocks
+    max_size: Option<u64>,
+    mean_mass: Option<f64>,
+    max_mass: Option<u64>,
+    mean_mass_utilization: Option<f64>, // mean_mass / mass_limit
+    max_mass_utilization: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockSizeResponse {
+    mass_limit: u64,
+    windows: Vec<BlockSizeWindow>, // Shortest first
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/block-size",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Mean and maximum block size, mass and mass utilization over the last minute, 10 minutes, hour and day", body = BlockSizeResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_block_size(
+    State(state): State<AppState>,
+) -> Result<Json<BlockSizeResponse>, Response> {
+    let index = require_index(&state)?;
+    let now = now_ms();
+    let mut windows = Vec::with_capacity(WINDOWS.len());
+    for (window, seconds) in WINDOWS {
+        let stats = index
+            .block_size_stats(now.saturating_sub(seconds * 1000))
+            .await
+            .map_err(|e| {
+                tracing::error!("Index lookup of block sizes failed: {:?}", e);
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+            })?;
+        windows.push(BlockSizeWindow {
+            window: window.to_string(),
+            blocks: stats.blocks,
+            measured_blocks: stats.measured_blocks,
+            mean_size: stats.mean_size,
+            max_size: stats.max_size,
+            mean_mass: stats.mean_mass,
+            max_mass: stats.max_mass,
+            mean_mass_utilization: stats.mean_mass.map(|mass| mass / BLOCK_MASS_LIMIT as f64),
+            max_mass_utilization: stats.max_mass.map(utilization),
+        });
+    }
+    Ok(Json(BlockSizeResponse {
+        mass_limit: BLOCK_MASS_LIMIT,
+        windows,
+    }))
+}
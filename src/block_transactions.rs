@@ -0,0 +1,221 @@
+//! Paginated transaction list of a single block.
+//!
+//! A block can carry hundreds of transactions, too many for one response. The block is
+//! fetched once with its transactions and summarized into a small cache, so paging through
+//! it costs no further RPCs. Fees are resolved per page from the index, which knows the
+//! amounts of the outputs the inputs spend; they are `None` for coinbase transactions and
+//! whenever a spent output is not indexed.
+
+use crate::chain::ChainSource;
+use crate::resolver::{self, Outpoint};
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcBlock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 500;
+// Blocks are immutable, so entries never go stale; this only bounds memory.
+pub const MAX_CACHED_BLOCKS: usize = 64;
+
+/// Summarized transactions of recently requested blocks, oldest first for eviction.
+pub type BlockTransactionsCache = Arc<RwLock<VecDeque<(Hash, Arc<Vec<TransactionSummary>>)>>>;
+
+/// A transaction as cached: the response fields, plus the outpoints its inputs spend.
+#[derive(Debug)]
+pub struct TransactionSummary {
+    id: String,
+    input_count: usize,
+    output_count: usize,
+    output_value: u64,
+    mass: u64,
+    spent_outpoints: Vec<Outpoint>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BlockTransactionsQuery {
+    /// Position of the first transaction to return (default 0)
+    offset: Option<usize>,
+    /// Number of transactions (default 100, max 500)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockTransaction {
+    id: String,
+    input_count: usize,
+    output_count: usize,
+    #[serde(serialize_with = "crate::units::sompi")]
+    output_value: u64,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    fee: Option<u64>,
+    mass: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockTransactionsResponse {
+    block_hash: String,
+    total_count: usize,
+    offset: usize,
+    transactions: Vec<BlockTransaction>,
+    // Pass as offset to continue; None on the last page.
+    next_offset: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/block/{hash}/transactions",
+    tag = "blocks",
+    params(
+        ("hash" = String, Path, description = "Block hash"),
+        BlockTransactionsQuery
+    ),
+    responses(
+        (status = 200, description = "One page of the block's transactions, in block order", body = BlockTransactionsResponse),
+        (status = 400, description = "Invalid hash or limit", body = ErrorResponse),
+        (status = 404, description = "Block not found", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_block_transactions(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    Query(query): Query<BlockTransactionsQuery>,
+) -> Result<Json<BlockTransactionsResponse>, Response> {
+    let hash = hash
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid block hash"))?;
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+
+    let summaries = summaries(&state, hash).await?;
+    let page: Vec<&TransactionSummary> = summaries.iter().skip(offset).take(limit).collect();
+    let fees = resolve_fees(&state, &page).await;
+    let next_offset = Some(offset + page.len()).filter(|&next| next < summaries.len());
+    let transactions = page
+        .into_iter()
+        .zip(fees)
+        .map(|(tx, fee)| BlockTransaction {
+            id: tx.id.clone(),
+            input_count: tx.input_count,
+            output_count: tx.output_count,
+            output_value: tx.output_value,
+            fee,
+            mass: tx.mass,
+        })
+        .collect();
+
+    Ok(Json(BlockTransactionsResponse {
+        block_hash: hash.to_string(),
+        total_count: summaries.len(),
+        offset,
+        transactions,
+        next_offset,
+    }))
+}
+
+async fn summaries(state: &AppState, hash: Hash) -> Result<Arc<Vec<TransactionSummary>>, Response> {
+    if let Some((_, cached)) = state
+        .block_transactions
+        .read()
+        .await
+        .iter()
+        .find(|(cached, _)| *cached == hash)
+    {
+        return Ok(cached.clone());
+    }
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let block = rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(hash, true).await },
+    )
+    .await
+    .map_err(|e| {
+        if rpc::is_not_found(&e) {
+            error_response(StatusCode::NOT_FOUND, "Block not found")
+        } else {
+            tracing::error!("Failed to fetch block {} with transactions: {:?}", hash, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block")
+        }
+    })?;
+    drop(client_guard);
+
+    let summaries = Arc::new(summarize(block));
+    let mut cache = state.block_transactions.write().await;
+    if !cache.iter().any(|(cached, _)| *cached == hash) {
+        if cache.len() == MAX_CACHED_BLOCKS {
+            cache.pop_front();
+        }
+        cache.push_back((hash, summaries.clone()));
+    }
+    Ok(summaries)
+}
+
+fn summarize(block: RpcBlock) -> Vec<TransactionSummary> {
+    block
+        .transactions
+        .into_iter()
+        .map(|tx| {
+            let (id, mass) = match &tx.verbose_data {
+                Some(v) => (v.transaction_id.to_string(), v.compute_mass),
+                None => (String::new(), tx.mass),
+            };
+            TransactionSummary {
+                id,
+                input_count: tx.inputs.len(),
+                output_count: tx.outputs.len(),
+                output_value: tx.outputs.iter().map(|o| o.value).sum(),
+                mass,
+                spent_outpoints: tx
+                    .inputs
+                    .iter()
+                    .map(|input| {
+                        (
+                            input.previous_outpoint.transaction_id.to_string(),
+                            input.previous_outpoint.index,
+                        )
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Fee of each transaction on the page, where every spent output is in the index.
+async fn resolve_fees(state: &AppState, page: &[&TransactionSummary]) -> Vec<Option<u64>> {
+    let outpoints: Vec<Outpoint> = page
+        .iter()
+        .flat_map(|tx| tx.spent_outpoints.iter().cloned())
+        .collect();
+    let resolved = resolver::from_index(state, &outpoints).await;
+    page.iter()
+        .map(|tx| {
+            // Coinbase transactions spend nothing and pay no fee.
+            if tx.spent_outpoints.is_empty() {
+                return None;
+            }
+            resolver::input_value(&tx.spent_outpoints, &resolved)
+                .and_then(|input| input.checked_sub(tx.output_value))
+        })
+        .collect()
+}
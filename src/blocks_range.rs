@@ -0,0 +1,165 @@
+//! Blocks in a time or DAA score range, from the index.
+//!
+//! Answers questions like "how many blocks were produced between 14:00 and 15:00": the
+//! response carries the number of blocks in the whole range, and one page of compact
+//! summaries. Pages follow each other through `next_cursor`, which encodes the sort key and
+//! hash of the last block returned, so pages stay stable while new blocks are indexed.
+
+use crate::{block_size, error_response, require_index, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BlocksRangeQuery {
+    /// Start of the range, inclusive: Unix ms, or a DAA score with by=daa
+    from: u64,
+    /// End of the range, inclusive
+    to: u64,
+    /// time or daa (default time)
+    by: Option<String>,
+    /// Blocks per page (default 100, max 1000)
+    limit: Option<usize>,
+    /// next_cursor of the previous page
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockSummary {
+    hash: String,
+    daa_score: u64,
+    blue_score: u64,
+    timestamp: u64, // Unix ms
+    tx_count: usize,
+    // None for blocks indexed before sizes were recorded.
+    size: Option<u64>,
+    mass: Option<u64>,
+    mass_utilization: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlocksRangeResponse {
+    from: u64,
+    to: u64,
+    by: String,
+    total_blocks: u64, // In the whole range, not just this page
+    blocks: Vec<BlockSummary>,
+    // Pass as cursor to continue; None on the last page.
+    next_cursor: Option<String>,
+}
+
+pub fn parse_cursor(cursor: &str) -> Option<(u64, String)> {
+    let (key, hash) = cursor.split_once(':')?;
+    Some((key.parse().ok()?, hash.to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/blocks/range",
+    tag = "blocks",
+    params(BlocksRangeQuery),
+    responses(
+        (status = 200, description = "Block count and a page of block summaries in the range", body = BlocksRangeResponse),
+        (status = 400, description = "Invalid range, by, limit or cursor", body = ErrorResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_blocks_range(
+    State(state): State<AppState>,
+    Query(query): Query<BlocksRangeQuery>,
+) -> Result<Json<BlocksRangeResponse>, Response> {
+    let by = query.by.unwrap_or_else(|| "time".to_string());
+    let by_timestamp = match by.as_str() {
+        "time" => true,
+        "daa" => false,
+        _ => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "Invalid by (expected time or daa)",
+            ))
+        }
+    };
+    // The index stores keys as SQLite integers, which stop at i64::MAX.
+    if query.from > i64::MAX as u64 {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("from must not exceed {}", i64::MAX),
+        ));
+    }
+    let to = query.to.min(i64::MAX as u64);
+    if query.from > query.to {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "from must not be after to",
+        ));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let after = match query.cursor.as_deref() {
+        Some(cursor) => Some(
+            parse_cursor(cursor)
+                .filter(|(key, _)| *key <= i64::MAX as u64)
+                .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Invalid cursor"))?,
+        ),
+        None => None,
+    };
+    let index = require_index(&state)?;
+
+    let lookup_failed = |e: anyhow::Error| {
+        tracing::error!("Index lookup of blocks in range failed: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    };
+    let total_blocks = index
+        .count_blocks_in_range(query.from, to, by_timestamp)
+        .await
+        .map_err(lookup_failed)?;
+    // One extra block tells whether another page follows.
+    let mut blocks = index
+        .blocks_in_range(query.from, to, by_timestamp, after, limit + 1)
+        .await
+        .map_err(lookup_failed)?;
+    let more = blocks.len() > limit;
+    blocks.truncate(limit);
+    let next_cursor = blocks.last().filter(|_| more).map(|last| {
+        let key = if by_timestamp {
+            last.timestamp
+        } else {
+            last.daa_score
+        };
+        format!("{}:{}", key, last.hash)
+    });
+
+    Ok(Json(BlocksRangeResponse {
+        from: query.from,
+        to: query.to,
+        by,
+        total_blocks,
+        blocks: blocks
+            .into_iter()
+            .map(|block| BlockSummary {
+                tx_count: block.transaction_ids.len(),
+                hash: block.hash,
+                daa_score: block.daa_score,
+                blue_score: block.blue_score,
+                timestamp: block.timestamp,
+                size: block.size,
+                mass: block.mass,
+                mass_utilization: block.mass.map(block_size::utilization),
+            })
+            .collect(),
+        next_cursor,
+    }))
+}
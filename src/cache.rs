@@ -0,0 +1,294 @@
+//! In-memory response cache.
+//!
+//! GET responses of the routes named in `[cache]` are kept in memory, keyed by full path and
+//! query string, so polls that many clients repeat are answered without going back to
+//! kaspad. Each route has its own TTL: by default `/blocks` and `/mempool` live 1s, `/info`
+//! and `/peers` 5s, `/block/:hash` 10s and `/emission` (the circulating supply) 60s. Routes
+//! listed as `immutable` (none by default) stay until `max_bytes` evicts them. A block is not
+//! one of them: its chain membership changes on a reorg, a label can be added later and its
+//! `total_fees` is null until the index resolves its spent outputs. Only 200 responses are
+//! stored.
+//!
+//! Concurrent misses on one key run the handler once: the other requests wait for it and get
+//! its response, error or not, so a burst of identical requests after an expiry costs one set
+//! of kaspad calls. Responses carry `X-Cache: hit`, `stale` or `miss`, and cached ones their
+//! age in milliseconds as `X-Data-Age`.
+//!
+//! Routes with a `stale` window are served stale-while-revalidate: for that long past its TTL
+//! the last response is still answered at once, while a single background request per key
+//! fetches its replacement. A refresh that fails, times out (`[timeouts] heavy_ms`) or ends in
+//! a handler's fallback answer (see `degraded`) leaves the stale response in place, so a slow
+//! or briefly failing kaspad is hidden for the length of the window rather than surfacing as
+//! errors or emptied lists.
+//!
+//! The cache sits inside the rate limits and timeouts, and before signing, field selection,
+//! units and formatting, which are still applied to every response.
+
+use crate::config::CacheConfig;
+use crate::{error_response, metrics, timeouts, AppState};
+use axum::{
+    body::{Body, Bytes},
+    extract::{OriginalUri, Request, State},
+    http::{Extensions, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use moka::future::Cache;
+use moka::Expiry;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const CACHE_HEADER: HeaderName = HeaderName::from_static("x-cache");
+const DATA_AGE_HEADER: HeaderName = HeaderName::from_static("x-data-age");
+
+/// How long a route's responses are fresh, and for how long after that they may be served
+/// while being refreshed.
+#[derive(Debug, Clone, Copy)]
+struct Policy {
+    ttl: Option<Duration>, // None for immutable routes
+    stale: Duration,
+}
+
+/// Marks a handler's fallback answer, such as a last known value served while kaspad fails.
+#[derive(Clone, Copy)]
+struct Degraded;
+
+/// `response` as a fallback answer: it is sent, but never cached and never replaces a stale
+/// response.
+pub fn degraded(response: impl IntoResponse) -> Response {
+    let mut response = response.into_response();
+    response.extensions_mut().insert(Degraded);
+    response
+}
+
+/// A buffered response, replayed for every request it answers.
+pub struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    extensions: Extensions, // Markers for the outer layers, e.g. list routes' field
+    body: Bytes,
+    stored_at: Instant,
+    policy: Policy,
+}
+
+impl CachedResponse {
+    fn cacheable(&self) -> bool {
+        self.status == StatusCode::OK && self.extensions.get::<Degraded>().is_none()
+    }
+
+    /// The response for one request; `result` is what the cache did: hit, stale or miss.
+    fn response(&self, result: &'static str) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        *response.extensions_mut() = self.extensions.clone();
+        let headers = response.headers_mut();
+        headers.insert(CACHE_HEADER, HeaderValue::from_static(result));
+        if result != "miss" {
+            headers.insert(
+                DATA_AGE_HEADER,
+                HeaderValue::from(self.stored_at.elapsed().as_millis() as u64),
+            );
+        }
+        response
+    }
+}
+
+struct RouteExpiry;
+
+impl Expiry<String, Arc<CachedResponse>> for RouteExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &String,
+        value: &Arc<CachedResponse>,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        let policy = value.policy;
+        policy.ttl.map(|ttl| ttl + policy.stale)
+    }
+}
+
+/// Cached responses of one network's API.
+#[derive(Clone)]
+pub struct ResponseCache {
+    responses: Cache<String, Arc<CachedResponse>>,
+    refreshing: Arc<Mutex<HashSet<String>>>, // Keys with a background refresh running
+}
+
+impl ResponseCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let responses = Cache::builder()
+            .max_capacity(config.max_bytes)
+            .weigher(|key: &String, value: &Arc<CachedResponse>| {
+                (key.len() + value.body.len())
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            })
+            .expire_after(RouteExpiry)
+            .build();
+        ResponseCache {
+            responses,
+            refreshing: Arc::default(),
+        }
+    }
+
+    /// Cached responses, approximately: expired ones count until they are evicted.
+    pub fn entry_count(&self) -> u64 {
+        self.responses.entry_count()
+    }
+
+    /// Drops every cached response, returning how many there were.
+    pub fn clear(&self) -> u64 {
+        let entries = self.responses.entry_count();
+        self.responses.invalidate_all();
+        entries
+    }
+
+    /// Claims the refresh of `key`; false when one is already running.
+    fn start_refresh(&self, key: &str) -> bool {
+        self.refreshing
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key.to_string())
+    }
+
+    fn finish_refresh(&self, key: &str) {
+        self.refreshing
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(key);
+    }
+}
+
+/// The caching of `route`'s responses, or None when the route is not cached.
+fn route_policy(config: &CacheConfig, route: &str) -> Option<Policy> {
+    if config.immutable.iter().any(|immutable| immutable == route) {
+        return Some(Policy {
+            ttl: None,
+            stale: Duration::ZERO,
+        });
+    }
+    let ttl_ms = config
+        .routes
+        .get(route)
+        .copied()
+        .filter(|&ttl_ms| ttl_ms > 0)?;
+    Some(Policy {
+        ttl: Some(Duration::from_millis(ttl_ms)),
+        stale: Duration::from_millis(config.stale.get(route).copied().unwrap_or(0)),
+    })
+}
+
+async fn buffer(state: &AppState, response: Response, policy: Policy) -> Option<CachedResponse> {
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, state.config.limits.max_response_bytes).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for the cache: {:?}", e);
+            return None;
+        }
+    };
+    Some(CachedResponse {
+        status: parts.status,
+        headers: parts.headers,
+        extensions: parts.extensions,
+        body,
+        stored_at: Instant::now(),
+        policy,
+    })
+}
+
+/// Replaces the stale response under `key` in the background, unless a refresh of it is
+/// already running.
+fn revalidate(state: AppState, key: String, policy: Policy, request: Request, next: Next) {
+    if !state.response_cache.start_refresh(&key) {
+        return;
+    }
+    let shutdown = state.shutdown.clone();
+    shutdown.spawn(async move {
+        let budget_ms = state.config.timeouts.heavy_ms;
+        let refresh = async { buffer(&state, next.run(request).await, policy).await };
+        let refreshed = if budget_ms == 0 {
+            refresh.await
+        } else {
+            tokio::time::timeout(Duration::from_millis(budget_ms), refresh)
+                .await
+                .unwrap_or_else(|_| {
+                    tracing::warn!("Refresh of {} timed out after {}ms", key, budget_ms);
+                    None
+                })
+        };
+        match refreshed {
+            Some(response) if response.cacheable() => {
+                state
+                    .response_cache
+                    .responses
+                    .insert(key.clone(), Arc::new(response))
+                    .await;
+            }
+            _ => tracing::debug!("Refresh of {} failed; keeping the stale response", key),
+        }
+        state.response_cache.finish_refresh(&key);
+    });
+}
+
+pub async fn cached(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let config = &state.config.cache;
+    if !config.enabled || request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let Some((route, policy)) = timeouts::route_key(&request)
+        .and_then(|route| Some((route.to_string(), route_policy(config, route)?)))
+    else {
+        return next.run(request).await;
+    };
+
+    // The full URI: responses differ between /api/v1 and the deprecated /api aliases.
+    let key = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().to_string(), |uri| uri.0.to_string());
+    if let Some(cached) = state.response_cache.responses.get(&key).await {
+        let stale = cached
+            .policy
+            .ttl
+            .is_some_and(|ttl| cached.stored_at.elapsed() >= ttl);
+        let result = if stale { "stale" } else { "hit" };
+        metrics::response_cache(&route, result);
+        if stale {
+            revalidate(state.clone(), key, cached.policy, request, next);
+        }
+        return cached.response(result);
+    }
+
+    let entry = state
+        .response_cache
+        .responses
+        .entry(key)
+        .or_try_insert_with(async {
+            match buffer(&state, next.run(request).await, policy).await {
+                Some(response) if response.cacheable() => Ok(Arc::new(response)),
+                response => Err(response),
+            }
+        })
+        .await;
+    match entry {
+        Ok(entry) => {
+            let result = if entry.is_fresh() { "miss" } else { "hit" };
+            metrics::response_cache(&route, result);
+            entry.value().response(result)
+        }
+        // Not cached, but shared with the requests that waited on the same key.
+        Err(error) => {
+            metrics::response_cache(&route, "miss");
+            match error.as_ref() {
+                Some(response) => response.response("miss"),
+                None => error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to buffer the response",
+                ),
+            }
+        }
+    }
+}
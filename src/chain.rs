@@ -0,0 +1,267 @@
+//! Where chain data comes from.
+//!
+//! Handlers and background tasks read the chain through `ChainSource`, the part of kaspad's
+//! RPC API the explorer uses, rather than through the gRPC client directly. kaspad over gRPC
+//! is one source; the generated chain of `--mock` (see `mock.rs`) is the other, so the
+//! frontend can be worked on without a node. Methods keep kaspad's names and signatures, so
+//! call sites read the same as with `RpcApi`.
+
+use async_channel::Receiver;
+use async_trait::async_trait;
+use kaspa_grpc_client::GrpcClient;
+use kaspa_notify::listener::ListenerId;
+use kaspa_notify::scope::Scope;
+use kaspa_rpc_core::api::rpc::RpcApi;
+use kaspa_rpc_core::{
+    GetBlockDagInfoResponse, GetBlockTemplateResponse, GetBlocksResponse, GetCoinSupplyResponse,
+    GetConnectedPeerInfoResponse, GetInfoResponse, GetServerInfoResponse,
+    GetVirtualChainFromBlockResponse, Notification, RpcAddress, RpcBalancesByAddressesEntry,
+    RpcBlock, RpcExtraData, RpcFeeEstimate, RpcHash, RpcMempoolEntry, RpcMempoolEntryByAddress,
+    RpcResult, RpcTransaction, RpcTransactionId, RpcUtxosByAddressesEntry,
+};
+use std::sync::Arc;
+
+/// A connection to a chain source, shared by the handlers using it.
+pub type Client = Arc<dyn ChainSource>;
+
+#[async_trait]
+pub trait ChainSource: Send + Sync {
+    async fn get_info(&self) -> RpcResult<GetInfoResponse>;
+
+    async fn get_server_info(&self) -> RpcResult<GetServerInfoResponse>;
+
+    async fn get_block_dag_info(&self) -> RpcResult<GetBlockDagInfoResponse>;
+
+    async fn get_sink_blue_score(&self) -> RpcResult<u64>;
+
+    async fn get_coin_supply(&self) -> RpcResult<GetCoinSupplyResponse>;
+
+    async fn get_connected_peer_info(&self) -> RpcResult<GetConnectedPeerInfoResponse>;
+
+    async fn get_fee_estimate(&self) -> RpcResult<RpcFeeEstimate>;
+
+    async fn estimate_network_hashes_per_second(
+        &self,
+        window_size: u32,
+        start_hash: Option<RpcHash>,
+    ) -> RpcResult<u64>;
+
+    async fn get_block(&self, hash: RpcHash, include_transactions: bool) -> RpcResult<RpcBlock>;
+
+    async fn get_blocks(
+        &self,
+        low_hash: Option<RpcHash>,
+        include_blocks: bool,
+        include_transactions: bool,
+    ) -> RpcResult<GetBlocksResponse>;
+
+    async fn get_virtual_chain_from_block(
+        &self,
+        start_hash: RpcHash,
+        include_accepted_transaction_ids: bool,
+        min_confirmation_count: Option<u64>,
+    ) -> RpcResult<GetVirtualChainFromBlockResponse>;
+
+    async fn get_block_template(
+        &self,
+        pay_address: RpcAddress,
+        extra_data: RpcExtraData,
+    ) -> RpcResult<GetBlockTemplateResponse>;
+
+    async fn get_mempool_entry(
+        &self,
+        transaction_id: RpcTransactionId,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<RpcMempoolEntry>;
+
+    async fn get_mempool_entries(
+        &self,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<Vec<RpcMempoolEntry>>;
+
+    async fn get_mempool_entries_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<Vec<RpcMempoolEntryByAddress>>;
+
+    async fn get_utxos_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+    ) -> RpcResult<Vec<RpcUtxosByAddressesEntry>>;
+
+    async fn get_balance_by_address(&self, address: RpcAddress) -> RpcResult<u64>;
+
+    async fn get_balances_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+    ) -> RpcResult<Vec<RpcBalancesByAddressesEntry>>;
+
+    async fn submit_transaction(
+        &self,
+        transaction: RpcTransaction,
+        allow_orphan: bool,
+    ) -> RpcResult<RpcTransactionId>;
+
+    /// The channel every subscribed notification arrives on; it closes on disconnect.
+    fn notification_channel_receiver(&self) -> Receiver<Notification>;
+
+    async fn start_notify(&self, id: ListenerId, scope: Scope) -> RpcResult<()>;
+
+    async fn disconnect(&self) -> anyhow::Result<()>;
+}
+
+#[async_trait]
+impl ChainSource for GrpcClient {
+    async fn get_info(&self) -> RpcResult<GetInfoResponse> {
+        RpcApi::get_info(self).await
+    }
+
+    async fn get_server_info(&self) -> RpcResult<GetServerInfoResponse> {
+        RpcApi::get_server_info(self).await
+    }
+
+    async fn get_block_dag_info(&self) -> RpcResult<GetBlockDagInfoResponse> {
+        RpcApi::get_block_dag_info(self).await
+    }
+
+    async fn get_sink_blue_score(&self) -> RpcResult<u64> {
+        RpcApi::get_sink_blue_score(self).await
+    }
+
+    async fn get_coin_supply(&self) -> RpcResult<GetCoinSupplyResponse> {
+        RpcApi::get_coin_supply(self).await
+    }
+
+    async fn get_connected_peer_info(&self) -> RpcResult<GetConnectedPeerInfoResponse> {
+        RpcApi::get_connected_peer_info(self).await
+    }
+
+    async fn get_fee_estimate(&self) -> RpcResult<RpcFeeEstimate> {
+        RpcApi::get_fee_estimate(self).await
+    }
+
+    async fn estimate_network_hashes_per_second(
+        &self,
+        window_size: u32,
+        start_hash: Option<RpcHash>,
+    ) -> RpcResult<u64> {
+        RpcApi::estimate_network_hashes_per_second(self, window_size, start_hash).await
+    }
+
+    async fn get_block(&self, hash: RpcHash, include_transactions: bool) -> RpcResult<RpcBlock> {
+        RpcApi::get_block(self, hash, include_transactions).await
+    }
+
+    async fn get_blocks(
+        &self,
+        low_hash: Option<RpcHash>,
+        include_blocks: bool,
+        include_transactions: bool,
+    ) -> RpcResult<GetBlocksResponse> {
+        RpcApi::get_blocks(self, low_hash, include_blocks, include_transactions).await
+    }
+
+    async fn get_virtual_chain_from_block(
+        &self,
+        start_hash: RpcHash,
+        include_accepted_transaction_ids: bool,
+        min_confirmation_count: Option<u64>,
+    ) -> RpcResult<GetVirtualChainFromBlockResponse> {
+        RpcApi::get_virtual_chain_from_block(
+            self,
+            start_hash,
+            include_accepted_transaction_ids,
+            min_confirmation_count,
+        )
+        .await
+    }
+
+    async fn get_block_template(
+        &self,
+        pay_address: RpcAddress,
+        extra_data: RpcExtraData,
+    ) -> RpcResult<GetBlockTemplateResponse> {
+        RpcApi::get_block_template(self, pay_address, extra_data).await
+    }
+
+    async fn get_mempool_entry(
+        &self,
+        transaction_id: RpcTransactionId,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<RpcMempoolEntry> {
+        RpcApi::get_mempool_entry(
+            self,
+            transaction_id,
+            include_orphan_pool,
+            filter_transaction_pool,
+        )
+        .await
+    }
+
+    async fn get_mempool_entries(
+        &self,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<Vec<RpcMempoolEntry>> {
+        RpcApi::get_mempool_entries(self, include_orphan_pool, filter_transaction_pool).await
+    }
+
+    async fn get_mempool_entries_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<Vec<RpcMempoolEntryByAddress>> {
+        RpcApi::get_mempool_entries_by_addresses(
+            self,
+            addresses,
+            include_orphan_pool,
+            filter_transaction_pool,
+        )
+        .await
+    }
+
+    async fn get_utxos_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+    ) -> RpcResult<Vec<RpcUtxosByAddressesEntry>> {
+        RpcApi::get_utxos_by_addresses(self, addresses).await
+    }
+
+    async fn get_balance_by_address(&self, address: RpcAddress) -> RpcResult<u64> {
+        RpcApi::get_balance_by_address(self, address).await
+    }
+
+    async fn get_balances_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+    ) -> RpcResult<Vec<RpcBalancesByAddressesEntry>> {
+        RpcApi::get_balances_by_addresses(self, addresses).await
+    }
+
+    async fn submit_transaction(
+        &self,
+        transaction: RpcTransaction,
+        allow_orphan: bool,
+    ) -> RpcResult<RpcTransactionId> {
+        RpcApi::submit_transaction(self, transaction, allow_orphan).await
+    }
+
+    fn notification_channel_receiver(&self) -> Receiver<Notification> {
+        GrpcClient::notification_channel_receiver(self)
+    }
+
+    async fn start_notify(&self, id: ListenerId, scope: Scope) -> RpcResult<()> {
+        RpcApi::start_notify(self, id, scope).await
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        GrpcClient::disconnect(self).await?;
+        Ok(())
+    }
+}
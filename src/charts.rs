@@ -0,0 +1,205 @@
+//! Time-series chart data built from the background sampler.
+//!
+//! Windows up to one hour are served from the raw samples; longer windows use the
+//! per-minute rollups, so the finest resolution there is one minute. The rollups only reach
+//! back to the explorer's start, so with the index enabled the longer `block_count` windows
+//! are built from the indexed blocks instead: the latest sampled DAG block count, less the
+//! blocks indexed after each bucket. Buckets before the index's first block are left out.
+
+use crate::index::IndexHandle;
+use crate::sampler::{now_ms, NetworkSample, ROLLUP_INTERVAL_MS, SAMPLE_INTERVAL};
+use crate::{error_response, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use utoipa::{IntoParams, ToSchema};
+
+// Default number of points returned when no resolution is requested.
+const DEFAULT_POINTS: u64 = 120;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChartQuery {
+    /// 1h, 24h or 7d (default 1h)
+    window: Option<String>,
+    resolution: Option<u64>, // Bucket size in seconds
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChartPoint {
+    timestamp: u64, // Bucket start, Unix time in milliseconds
+    value: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChartResponse {
+    metric: String,
+    window: String,
+    resolution_seconds: u64,
+    points: Vec<ChartPoint>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Metric {
+    Difficulty,
+    Hashrate,
+    BlockCount,
+    TxCount,
+    MempoolSize,
+    Fees,
+}
+
+impl Metric {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "difficulty" => Some(Metric::Difficulty),
+            "hashrate" => Some(Metric::Hashrate),
+            "block_count" => Some(Metric::BlockCount),
+            "tx_count" => Some(Metric::TxCount),
+            "mempool_size" => Some(Metric::MempoolSize),
+            "fees" => Some(Metric::Fees),
+            _ => None,
+        }
+    }
+
+    fn value(self, sample: &NetworkSample) -> f64 {
+        match self {
+            Metric::Difficulty => sample.difficulty,
+            Metric::Hashrate => sample.hashrate as f64,
+            Metric::BlockCount => sample.block_count as f64,
+            Metric::TxCount => sample.new_transactions as f64,
+            Metric::MempoolSize => sample.mempool_size as f64,
+            Metric::Fees => sample.fee_rate,
+        }
+    }
+
+    /// Counters are summed per bucket; gauges are averaged.
+    fn is_counter(self) -> bool {
+        matches!(self, Metric::TxCount)
+    }
+}
+
+pub fn parse_window(window: &str) -> Option<u64> {
+    match window {
+        "1h" => Some(3_600),
+        "24h" => Some(86_400),
+        "7d" => Some(604_800),
+        _ => None,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/charts/{metric}",
+    tag = "stats",
+    params(
+        ("metric" = String, Path, description = "difficulty, hashrate, block_count, tx_count, mempool_size or fees"),
+        ChartQuery
+    ),
+    responses(
+        (status = 200, description = "Time series for the metric", body = ChartResponse),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 404, description = "Unknown metric", body = ErrorResponse)
+    )
+)]
+pub async fn get_chart(
+    State(state): State<AppState>,
+    Path(metric_name): Path<String>,
+    Query(query): Query<ChartQuery>,
+) -> Result<Json<ChartResponse>, Response> {
+    let metric = Metric::parse(&metric_name).ok_or_else(|| {
+        error_response(
+            StatusCode::NOT_FOUND,
+            "Unknown metric (expected difficulty, hashrate, block_count, tx_count, mempool_size or fees)",
+        )
+    })?;
+
+    let window = query.window.unwrap_or_else(|| "1h".to_string());
+    let window_seconds = parse_window(&window)
+        .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Invalid window (expected 1h, 24h or 7d)"))?;
+
+    let sampler = state.sampler.read().await;
+    let (source, min_resolution) = if window_seconds <= 3_600 {
+        (sampler.samples(), SAMPLE_INTERVAL.as_secs())
+    } else {
+        (sampler.rollups(), ROLLUP_INTERVAL_MS / 1000)
+    };
+    let resolution_seconds = query
+        .resolution
+        .unwrap_or(window_seconds / DEFAULT_POINTS)
+        .clamp(min_resolution, window_seconds);
+    let bucket_ms = resolution_seconds * 1000;
+
+    let since = now_ms().saturating_sub(window_seconds * 1000);
+    let latest_block_count = sampler.samples().back().map(|sample| sample.block_count);
+    let points = match (&state.index, latest_block_count) {
+        (Some(index), Some(latest)) if matches!(metric, Metric::BlockCount) && window_seconds > 3_600 => {
+            drop(sampler);
+            indexed_block_counts(index, since, bucket_ms, latest).await.map_err(|e| {
+                tracing::error!("Index lookup of block counts failed: {:?}", e);
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+            })?
+        }
+        _ => sampled(source, metric, since, bucket_ms),
+    };
+
+    Ok(Json(ChartResponse {
+        metric: metric_name,
+        window,
+        resolution_seconds,
+        points,
+    }))
+}
+
+// Samples from `since` on, bucketed: counters summed, gauges averaged.
+fn sampled(source: &VecDeque<NetworkSample>, metric: Metric, since: u64, bucket_ms: u64) -> Vec<ChartPoint> {
+    let mut points: Vec<ChartPoint> = Vec::new();
+    let mut count = 0usize;
+    for sample in source.iter().filter(|s| s.timestamp >= since) {
+        let bucket = sample.timestamp - sample.timestamp % bucket_ms;
+        let value = metric.value(sample);
+        match points.last_mut() {
+            Some(point) if point.timestamp == bucket => {
+                if metric.is_counter() {
+                    point.value += value;
+                } else {
+                    // Running average over the samples in this bucket.
+                    count += 1;
+                    point.value += (value - point.value) / count as f64;
+                }
+            }
+            _ => {
+                count = 1;
+                points.push(ChartPoint { timestamp: bucket, value });
+            }
+        }
+    }
+    points
+}
+
+// The DAG block count at the end of each bucket, walked back from `latest` through the
+// blocks indexed since.
+async fn indexed_block_counts(
+    index: &IndexHandle,
+    since: u64,
+    bucket_ms: u64,
+    latest: u64,
+) -> anyhow::Result<Vec<ChartPoint>> {
+    let buckets = index.block_buckets(since, bucket_ms).await?;
+    let mut later = 0u64;
+    let mut points: Vec<ChartPoint> = buckets
+        .into_iter()
+        .rev()
+        .map(|(timestamp, blocks)| {
+            let value = latest.saturating_sub(later) as f64;
+            later += blocks;
+            ChartPoint { timestamp, value }
+        })
+        .collect();
+    points.reverse();
+    Ok(points)
+}
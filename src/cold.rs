@@ -0,0 +1,202 @@
+//! Cold tier of the index: old outputs and inputs packed into zstd-compressed segments.
+//!
+//! Output and input rows make up most of the index. Once they are `[index] cold_depth` DAA
+//! scores behind the indexed tip, they are moved out of the hot tables into segments: one
+//! compressed blob per epoch and `SEGMENT_SPAN` DAA scores, stored in `cold_segments`. Blocks
+//! and transaction ids stay hot, so lookups by id still hit a B-tree and only the segment
+//! that holds the rows has to be decompressed; the last few segments read are kept in memory.
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// DAA scores per segment, about an hour of testnet-12 blocks.
+pub const SEGMENT_SPAN: u64 = 36_000;
+const ZSTD_LEVEL: i32 = 9;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColdOutput {
+    pub tx_id: String,
+    pub idx: u32,
+    pub address: Option<String>,
+    pub amount: u64,
+    pub daa_score: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColdInput {
+    pub tx_id: String,
+    pub idx: u32,
+    pub prev_tx_id: String,
+    pub prev_idx: u32,
+    pub daa_score: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Segment {
+    pub outputs: Vec<ColdOutput>,
+    pub inputs: Vec<ColdInput>,
+    // Position of each output in `outputs`, built on load.
+    #[serde(skip)]
+    output_positions: HashMap<(String, u32), usize>,
+}
+
+impl Segment {
+    pub fn output(&self, tx_id: &str, idx: u32) -> Option<&ColdOutput> {
+        self.output_positions
+            .get(&(tx_id.to_string(), idx))
+            .map(|&i| &self.outputs[i])
+    }
+
+    fn encode(&self) -> rusqlite::Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        zstd::encode_all(json.as_slice(), ZSTD_LEVEL)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    }
+
+    fn decode(data: &[u8]) -> rusqlite::Result<Self> {
+        let json = zstd::decode_all(data)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))?;
+        let mut segment: Segment = serde_json::from_slice(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e)))?;
+        segment.output_positions = segment
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(i, output)| ((output.tx_id.clone(), output.idx), i))
+            .collect();
+        Ok(segment)
+    }
+}
+
+/// First DAA score of the segment holding `daa_score`.
+pub fn segment_start(daa_score: u64) -> u64 {
+    daa_score - daa_score % SEGMENT_SPAN
+}
+
+/// Packs the oldest hot segment of `epoch` if it ends at or below `below_daa`. Returns the
+/// packed range and row count, or None when nothing is old enough.
+pub fn pack_next(
+    conn: &mut Connection,
+    epoch: i64,
+    below_daa: u64,
+) -> rusqlite::Result<Option<(u64, u64, usize)>> {
+    let oldest: Option<i64> = conn.query_row(
+        "SELECT MIN(daa_score) FROM (
+             SELECT MIN(daa_score) AS daa_score FROM outputs WHERE epoch = ?1
+             UNION ALL SELECT MIN(daa_score) FROM inputs WHERE epoch = ?1
+         )",
+        [epoch],
+        |row| row.get(0),
+    )?;
+    let Some(oldest) = oldest else {
+        return Ok(None);
+    };
+    let start = segment_start(oldest as u64);
+    let end = start + SEGMENT_SPAN;
+    if end > below_daa {
+        return Ok(None);
+    }
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let range = params![epoch, start as i64, end as i64];
+    // Rows indexed late into an already packed range are merged into its segment.
+    let mut segment = match tx
+        .query_row(
+            "SELECT data FROM cold_segments WHERE epoch = ?1 AND start_daa = ?2",
+            params![epoch, start as i64],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .optional()?
+    {
+        Some(data) => Segment::decode(&data)?,
+        None => Segment::default(),
+    };
+    let packed = {
+        let mut outputs = tx.prepare_cached(
+            "SELECT tx_id, idx, address, amount, daa_score FROM outputs
+             WHERE epoch = ?1 AND daa_score >= ?2 AND daa_score < ?3",
+        )?;
+        let outputs = outputs.query_map(range, |row| {
+            Ok(ColdOutput {
+                tx_id: row.get(0)?,
+                idx: row.get(1)?,
+                address: row.get(2)?,
+                amount: row.get::<_, i64>(3)? as u64,
+                daa_score: row.get::<_, i64>(4)? as u64,
+            })
+        })?;
+        let before = segment.outputs.len() + segment.inputs.len();
+        for output in outputs {
+            segment.outputs.push(output?);
+        }
+        let mut inputs = tx.prepare_cached(
+            "SELECT tx_id, idx, prev_tx_id, prev_idx, daa_score FROM inputs
+             WHERE epoch = ?1 AND daa_score >= ?2 AND daa_score < ?3",
+        )?;
+        let inputs = inputs.query_map(range, |row| {
+            Ok(ColdInput {
+                tx_id: row.get(0)?,
+                idx: row.get(1)?,
+                prev_tx_id: row.get(2)?,
+                prev_idx: row.get(3)?,
+                daa_score: row.get::<_, i64>(4)? as u64,
+            })
+        })?;
+        for input in inputs {
+            segment.inputs.push(input?);
+        }
+        segment.outputs.len() + segment.inputs.len() - before
+    };
+    tx.execute(
+        "INSERT OR REPLACE INTO cold_segments (epoch, start_daa, end_daa, output_count, input_count, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            epoch,
+            start as i64,
+            end as i64,
+            segment.outputs.len() as i64,
+            segment.inputs.len() as i64,
+            segment.encode()?
+        ],
+    )?;
+    tx.execute(
+        "DELETE FROM outputs WHERE epoch = ?1 AND daa_score >= ?2 AND daa_score < ?3",
+        range,
+    )?;
+    tx.execute(
+        "DELETE FROM inputs WHERE epoch = ?1 AND daa_score >= ?2 AND daa_score < ?3",
+        range,
+    )?;
+    tx.commit()?;
+    Ok(Some((start, end, packed)))
+}
+
+/// Loads the segment of `epoch` starting at `start`, if one was packed.
+pub fn load(conn: &Connection, epoch: i64, start: u64) -> rusqlite::Result<Option<Arc<Segment>>> {
+    conn.prepare_cached("SELECT data FROM cold_segments WHERE epoch = ?1 AND start_daa = ?2")?
+        .query_row(params![epoch, start as i64], |row| row.get::<_, Vec<u8>>(0))
+        .optional()?
+        .map(|data| Segment::decode(&data).map(Arc::new))
+        .transpose()
+}
+
+/// Start DAA scores of the packed segments of `epoch` that overlap `from..=to`.
+pub fn segments_in_range(
+    conn: &Connection,
+    epoch: i64,
+    from: u64,
+    to: u64,
+) -> rusqlite::Result<Vec<u64>> {
+    conn.prepare_cached(
+        "SELECT start_daa FROM cold_segments
+         WHERE epoch = ?1 AND end_daa > ?2 AND start_daa <= ?3 ORDER BY start_daa",
+    )?
+    .query_map(params![epoch, from as i64, to as i64], |row| {
+        row.get::<_, i64>(0).map(|start| start as u64)
+    })?
+    .collect()
+}
@@ -0,0 +1,21 @@
+//! Response compression.
+//!
+//! gzip or brotli, whichever the client prefers in `Accept-Encoding`. Responses below the
+//! configured size (when their size is known up front), images, event streams and index
+//! snapshots (zstd already) are sent as-is. Signing and the response size limit see the uncompressed body.
+
+use crate::config::CompressionConfig;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+
+pub fn layer(config: &CompressionConfig) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(config.min_size_bytes)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::const_new("application/zstd"));
+    CompressionLayer::new()
+        .gzip(config.gzip)
+        .br(config.brotli)
+        .compress_when(predicate)
+}
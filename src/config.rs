@@ -0,0 +1,535 @@
+//! Optional TOML configuration file, passed with `--config`.
+//!
+//! Everything in it has a default, so the explorer runs without one. Example:
+//!
+//! ```toml
+//! [features]
+//! analytics = true
+//! admin = false
+//!
+//! [limits]
+//! max_utxos = 100
+//! ```
+//!
+//! The loaded configuration is also serialized for `/admin/diagnostics`; fields that point at
+//! secrets are marked with `redact` so only their presence shows.
+
+use crate::auth::Scope;
+use crate::network::Network;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub features: Features,
+    pub limits: Limits,
+    pub signing: Signing,
+    pub index: IndexConfig,
+    pub telemetry: TelemetryConfig,
+    pub rate_limit: RateLimitConfig,
+    pub timeouts: TimeoutsConfig,
+    pub cache: CacheConfig,
+    pub cors: CorsConfig,
+    pub compression: CompressionConfig,
+    pub hedging: HedgingConfig,
+    pub retry: RetryConfig,
+    pub rpc: RpcConfig,
+    pub nodes: NodesConfig,
+    pub crawler: CrawlerConfig,
+    pub geoip: GeoIpConfig,
+    pub pools: PoolsConfig,
+    pub emission: EmissionConfig,
+    pub format: FormatConfig,
+    pub faucet: FaucetConfig,
+    pub auth: AuthConfig,
+    pub whales: WhalesConfig,
+    pub networks: Vec<NetworkConfig>, // Served next to the --network one, under their prefix
+}
+
+/// Route groups that can be switched off at startup. Disabled groups are not mounted at all,
+/// so their paths return 404 rather than an error from the handler.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    pub faucet: bool,
+    pub admin: bool,
+    pub analytics: bool, // Network stats and charts
+    pub export: bool,
+    pub metrics: bool, // Prometheus /metrics
+    pub crawler: bool, // P2P peer crawl and /api/network/map
+}
+
+impl Default for Features {
+    // Public-facing groups are on; groups that spend funds or expose operator controls are opt-in.
+    fn default() -> Self {
+        Features {
+            faucet: false,
+            admin: false,
+            analytics: true,
+            export: true,
+            metrics: true,
+            crawler: false,
+        }
+    }
+}
+
+/// Caps on response size. Lists cut short by these report `truncated` and `total_available`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub max_response_bytes: usize,
+    pub max_utxos: usize,
+    pub max_mempool_transactions: usize,
+    pub max_block_parents: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_response_bytes: 8 * 1024 * 1024,
+            max_utxos: 100,
+            max_mempool_transactions: 50,
+            max_block_parents: 100,
+        }
+    }
+}
+
+/// Response signing; disabled unless a key file is given.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Signing {
+    #[serde(serialize_with = "redact")]
+    pub key_file: Option<PathBuf>, // Hex-encoded 32-byte Ed25519 seed
+}
+
+/// Local transaction index, required by history endpoints such as `/api/filter/match`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    pub auto_reset: bool, // Wipe and rebuild when the node is on a different network
+    // Move outputs and inputs this many DAA scores behind the tip to the cold tier.
+    pub cold_depth: Option<u64>,
+    // Track every address's balance for /stats/rich-list; subscribes to all UTXO changes.
+    pub rich_list: bool,
+    pub tokens: bool, // Apply KRC-20 (Kasplex) inscriptions while indexing, for /tokens
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        IndexConfig {
+            enabled: true,
+            path: PathBuf::from("explorer-index.sqlite"),
+            auto_reset: false,
+            cold_depth: None,
+            rich_list: false,
+            tokens: false,
+        }
+    }
+}
+
+/// OpenTelemetry trace export; disabled unless an OTLP endpoint is given.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: Option<String>, // e.g. http://localhost:4317
+    pub service_name: String,
+    pub sample_ratio: f64, // Fraction of traces exported, 0.0 to 1.0
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            otlp_endpoint: None,
+            service_name: "kaspa-testnet12-explorer".to_string(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+/// Per-IP request budgets, per route class.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub default_per_minute: u32,
+    pub heavy_per_minute: u32, // Blocks, mempool, address and index-backed routes
+    pub trust_forwarded_for: bool, // Use X-Forwarded-For; only safe behind a reverse proxy
+    // Reverse proxies in front of the explorer that each append to X-Forwarded-For.
+    pub trusted_proxies: usize,
+}
+
+impl RateLimitConfig {
+    /// X-Forwarded-For entries appended by trusted proxies; 0 when the header is not trusted.
+    pub fn forwarded_hops(&self) -> usize {
+        if self.trust_forwarded_for {
+            self.trusted_proxies.max(1)
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            default_per_minute: 600,
+            heavy_per_minute: 60,
+            trust_forwarded_for: false,
+            trusted_proxies: 1,
+        }
+    }
+}
+
+/// Time budgets of API requests, per rate limit class with per-route overrides (see
+/// `timeouts.rs`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    pub default_ms: u64,
+    pub heavy_ms: u64,
+    pub routes: BTreeMap<String, u64>, // Route pattern under /api, e.g. "/address/:address"
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        TimeoutsConfig {
+            default_ms: 10_000,
+            heavy_ms: 30_000, // Above the 20s cap on address UTXO enumeration
+            routes: BTreeMap::new(),
+        }
+    }
+}
+
+/// In-memory cache of API responses (see `cache.rs`). Routes are keyed by their path
+/// pattern under /api, as in `[timeouts]`; routes in neither `routes` nor `immutable` are not
+/// cached.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub max_bytes: u64, // Bodies and keys of all cached responses, per network
+    pub routes: BTreeMap<String, u64>, // Route pattern -> TTL in milliseconds
+    pub stale: BTreeMap<String, u64>, // Route pattern -> stale-while-revalidate window in ms
+    pub immutable: Vec<String>, // Route patterns cached until evicted
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: true,
+            max_bytes: 64 * 1024 * 1024,
+            routes: BTreeMap::from([
+                ("/blocks".to_string(), 1_000),
+                ("/mempool".to_string(), 1_000),
+                ("/info".to_string(), 5_000),
+                ("/peers".to_string(), 5_000),
+                ("/block/:hash".to_string(), 10_000), // Chain membership and fees can change
+                ("/emission".to_string(), 60_000),    // Circulating supply
+            ]),
+            stale: BTreeMap::from([
+                ("/blocks".to_string(), 15_000),
+                ("/mempool".to_string(), 15_000),
+                ("/info".to_string(), 15_000),
+                ("/peers".to_string(), 15_000),
+            ]),
+            immutable: Vec::new(),
+        }
+    }
+}
+
+/// Cross-origin policy; see `cors.rs` for how an empty origin list is treated.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub allow_all: bool,
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allow_all: false,
+            allowed_origins: Vec::new(),
+            allowed_methods: vec!["GET".to_string(), "HEAD".to_string(), "POST".to_string()],
+            allowed_headers: vec![
+                "content-type".to_string(),
+                "if-none-match".to_string(),
+                "x-request-id".to_string(),
+                "x-api-key".to_string(),
+            ],
+        }
+    }
+}
+
+/// Response compression, negotiated per request via `Accept-Encoding`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionConfig {
+    pub gzip: bool,
+    pub brotli: bool,
+    pub min_size_bytes: u16, // Smaller responses are sent uncompressed
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            gzip: true,
+            brotli: true,
+            min_size_bytes: 1024,
+        }
+    }
+}
+
+/// Hedged reads against backup nodes; off unless backup nodes are listed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HedgingConfig {
+    pub backup_nodes: Vec<String>, // kaspad gRPC addresses, like --kaspad-url
+    pub percentile: f64,           // Primary latency percentile after which a read is hedged
+    pub min_delay_ms: u64,
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        HedgingConfig {
+            backup_nodes: Vec::new(),
+            percentile: 95.0,
+            min_delay_ms: 20,
+        }
+    }
+}
+
+/// Retries of kaspad reads that fail with a transient error (see `rpc.rs`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub attempts: u32, // Including the first; 1 disables retries
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub jitter: f64, // Fraction of each backoff randomized away, 0.0 to 1.0
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2_000,
+            jitter: 0.5,
+        }
+    }
+}
+
+/// Connections to the primary kaspad.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    pub pool_size: usize, // Extra connections for heavy reads; 0 sends everything to one
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        RpcConfig { pool_size: 4 }
+    }
+}
+
+/// Other kaspad nodes whose tips are compared with the primary's for `/api/nodes`; no
+/// comparison runs unless some are listed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodesConfig {
+    pub urls: Vec<String>, // kaspad gRPC addresses, like --kaspad-url
+    pub poll_interval_secs: u64,
+    pub max_daa_lag: u64, // DAA scores a node may trail the highest one before it is flagged
+}
+
+impl Default for NodesConfig {
+    fn default() -> Self {
+        NodesConfig {
+            urls: Vec::new(),
+            poll_interval_secs: 10,
+            max_daa_lag: 600, // A minute at 10 blocks per second
+        }
+    }
+}
+
+/// Peer crawl for `/api/network/map`, run when `features.crawler` is on.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlerConfig {
+    pub interval_secs: u64,
+    pub rpc_port: Option<u16>, // gRPC port tried on each peer's IP; the network's default if unset
+    pub max_nodes: usize,      // Peers queried per crawl
+    pub concurrency: usize,    // Peers queried at once
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        CrawlerConfig {
+            interval_secs: 600,
+            rpc_port: None,
+            max_nodes: 500,
+            concurrency: 16,
+        }
+    }
+}
+
+/// MaxMind databases used to locate peers; either may be left out.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeoIpConfig {
+    pub city_db: Option<PathBuf>, // GeoLite2-City.mmdb
+    pub asn_db: Option<PathBuf>,  // GeoLite2-ASN.mmdb
+}
+
+/// An additional network served by the same process (`[[networks]]`), with its own kaspad,
+/// caches and notification listener, under `/<prefix>/api`. Without `index_path` it runs
+/// without an index.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub prefix: String, // Path segment, e.g. "tn10"
+    pub network: Network,
+    pub kaspad_url: Option<String>, // Defaults to 127.0.0.1 on the network's gRPC port
+    pub index_path: Option<PathBuf>,
+}
+
+/// Pool and mining-software signatures for `mined_by` (see `pools.rs`); none by default.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolsConfig {
+    pub signatures_file: Option<PathBuf>, // JSON array of signatures
+}
+
+/// Overrides of the emission schedule for `/api/emission`. Unset values follow the served
+/// network's consensus parameters; a testnet launched with other values can override them.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmissionConfig {
+    pub blocks_per_second: Option<u64>,
+    pub deflationary_phase_daa_score: Option<u64>,
+    pub pre_deflationary_subsidy: Option<u64>, // Sompi per second, split across its blocks
+    pub deflationary_initial_subsidy: Option<u64>, // Sompi per second in the first deflationary month
+}
+
+/// Response formatting defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    pub units: Units, // Overridden per request by ?units=
+}
+
+/// How money fields are serialized (see `units.rs`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Sompi, // Integers
+    Both, // {"sompi": "12345", "kas": "0.00012345"}
+}
+
+/// Testnet faucet, mounted with the faucet feature.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FaucetConfig {
+    #[serde(serialize_with = "redact")]
+    pub key_file: Option<PathBuf>, // Hex-encoded 32-byte secp256k1 private key of the funds
+    pub amount: u64,                // Sompi per drip
+    pub ip_interval_secs: u64,      // Between drips to one client IP
+    pub address_interval_secs: u64, // Between drips to one address
+    // Captcha check: a siteverify endpoint (hCaptcha, Turnstile, reCAPTCHA) and its secret.
+    pub captcha_verify_url: Option<String>,
+    #[serde(serialize_with = "redact")]
+    pub captcha_secret: Option<String>,
+    pub captcha_site_key: Option<String>, // Public; the faucet page renders the widget with it
+    pub captcha_script_url: Option<String>, // Widget script, e.g. https://js.hcaptcha.com/1/api.js
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        FaucetConfig {
+            key_file: None,
+            amount: 10 * 100_000_000,
+            ip_interval_secs: 24 * 3600,
+            address_interval_secs: 24 * 3600,
+            captcha_verify_url: None,
+            captcha_secret: None,
+            captcha_site_key: None,
+            captcha_script_url: None,
+        }
+    }
+}
+
+/// What `/api/stats/whales` counts as a large transaction and how long it is listed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WhalesConfig {
+    pub threshold: u64,     // Sompi of output value, at least
+    pub window_secs: u64,   // Transactions first seen longer ago are dropped
+    pub max_entries: usize, // Beyond this many, the smallest are dropped
+}
+
+impl Default for WhalesConfig {
+    fn default() -> Self {
+        WhalesConfig {
+            threshold: 100_000 * 100_000_000,
+            window_secs: 24 * 3600,
+            max_entries: 1_000,
+        }
+    }
+}
+
+/// Bearer tokens for the privileged routes (see `auth.rs`). Off by default, which leaves
+/// those routes as open as before.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    pub protected: Vec<Scope>, // Scopes whose routes need a token; /admin always does
+    pub tokens: Vec<ApiTokenConfig>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            enabled: false,
+            protected: vec![Scope::Transactions, Scope::Faucet, Scope::Watch],
+            tokens: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiTokenConfig {
+    pub name: String, // Logged with the requests made with the token
+    #[serde(default, serialize_with = "redact")]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub token_env: Option<String>, // Environment variable holding the token instead
+    pub scopes: Vec<Scope>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+}
+
+/// Serializes a set secret (or the path to one) as `"<redacted>"`.
+fn redact<T, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(_) => serializer.serialize_str("<redacted>"),
+        None => serializer.serialize_none(),
+    }
+}
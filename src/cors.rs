@@ -0,0 +1,72 @@
+//! CORS policy.
+//!
+//! Built from the `[cors]` config section and the `--cors-origin` / `--cors-allow-all` flags:
+//!
+//! - `allow_all`: any origin, method and header (development only).
+//! - Explicit origins: only those origins, with the configured methods and headers.
+//! - No origins: the read-only API stays open to any origin, unless a route group that
+//!   submits transactions (the faucet) is enabled, in which case cross-origin access is off.
+
+use crate::config::{CorsConfig, Features};
+use crate::{format, telemetry};
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+// Response headers browsers may read cross-origin.
+fn exposed_headers() -> [HeaderName; 6] {
+    [
+        header::ETAG,
+        HeaderName::from_static("x-content-digest"),
+        HeaderName::from_static("x-signature"),
+        HeaderName::from_static("x-signature-key"),
+        telemetry::REQUEST_ID_HEADER,
+        format::LIST_META_HEADER,
+    ]
+}
+
+pub fn layer(config: &CorsConfig, features: &Features) -> anyhow::Result<CorsLayer> {
+    if config.allow_all {
+        tracing::warn!("CORS allows all origins, methods and headers");
+        return Ok(CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers(exposed_headers()));
+    }
+
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|m| m.parse::<Method>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid CORS method: {}", e))?;
+    let headers = config
+        .allowed_headers
+        .iter()
+        .map(|h| h.parse::<HeaderName>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid CORS header: {}", e))?;
+
+    let origin = if !config.allowed_origins.is_empty() {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|o| o.parse::<HeaderValue>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow::anyhow!("Invalid CORS origin: {}", e))?;
+        AllowOrigin::list(origins)
+    } else if features.faucet {
+        tracing::info!(
+            "Faucet enabled and no CORS origins configured: cross-origin requests are disabled"
+        );
+        AllowOrigin::list([])
+    } else {
+        AllowOrigin::any()
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .expose_headers(exposed_headers()))
+}
@@ -0,0 +1,411 @@
+//! P2P network crawler.
+//!
+//! kaspad has no RPC to walk the P2P network, but every node lists the peers it is connected
+//! to. Every `interval_secs` the crawler asks the primary node for its peers, then tries the
+//! gRPC port (`rpc_port`) on each peer's IP and asks the ones that answer for theirs, breadth
+//! first, up to `max_nodes` peers per crawl. Most nodes do not expose RPC, so many are only
+//! known from their neighbours' peer lists, which still carry their user agent and protocol
+//! version. Nodes not reported by anyone for a day are dropped from the map. With a GeoIP
+//! database configured, nodes are located when first seen and `/api/network/geo` aggregates
+//! them by country, coordinates and network operator.
+
+use crate::chain::ChainSource;
+use crate::config::CrawlerConfig;
+use crate::geoip::Location;
+use crate::sampler::now_ms;
+use crate::{error_response, retry_after_response, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_rpc_core::RpcPeerInfo;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use utoipa::ToSchema;
+
+// Connecting to and querying one peer.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const STALE_AFTER_MS: u64 = 24 * 3600 * 1000;
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+pub type CrawlerHandle = Arc<RwLock<NetworkMap>>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CrawledNode {
+    address: String, // P2P ip:port
+    user_agent: Option<String>,
+    protocol_version: Option<u32>,
+    first_seen: u64, // Unix ms
+    last_seen: u64,  // Unix ms, when a crawled node last reported it as a peer
+    // Answered RPC on the configured port during the last crawl.
+    rpc_reachable: bool,
+    server_version: Option<String>,
+    is_synced: Option<bool>,
+    peer_count: Option<usize>,
+    location: Option<Location>,
+}
+
+#[derive(Debug, Default)]
+pub struct NetworkMap {
+    crawled_at: Option<u64>, // Unix ms
+    crawl_duration_ms: u64,
+    nodes: HashMap<String, CrawledNode>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VersionCount {
+    user_agent: String,
+    nodes: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NetworkMapResponse {
+    crawled_at: u64, // Unix ms
+    crawl_duration_ms: u64,
+    total_nodes: usize,
+    rpc_reachable: usize,
+    versions: Vec<VersionCount>, // Most common first; nodes with no known user agent are left out
+    nodes: Vec<CrawledNode>,     // Most recently seen first
+}
+
+/// What one peer told us about itself and its peers.
+struct Probe {
+    server_version: String,
+    is_synced: bool,
+    peers: Vec<RpcPeerInfo>,
+}
+
+async fn probe(ip: IpAddr, port: u16) -> anyhow::Result<Probe> {
+    let url = SocketAddr::new(ip, port).to_string();
+    let client = timeout(PROBE_TIMEOUT, rpc::connect(&url))
+        .await
+        .map_err(|_| anyhow::anyhow!("Connection timed out"))??;
+    let result = timeout(PROBE_TIMEOUT, async {
+        // Probes of discovered peers stay single-shot; PROBE_TIMEOUT bounds them anyway.
+        let info = rpc::traced("get_info", client.get_info()).await?;
+        let peers =
+            rpc::traced("get_connected_peer_info", client.get_connected_peer_info()).await?;
+        anyhow::Ok(Probe {
+            server_version: info.server_version,
+            is_synced: info.is_synced,
+            peers: peers.peer_info,
+        })
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Timed out"));
+    let _ = client.disconnect().await;
+    result?
+}
+
+/// Peers seen during one crawl, merged into the map when it finishes.
+#[derive(Default)]
+struct Crawl {
+    sightings: HashMap<String, (String, u32)>, // Address -> (user agent, protocol version)
+    probes: HashMap<IpAddr, Probe>,
+    queue: VecDeque<IpAddr>,
+    queued: HashSet<IpAddr>,
+}
+
+impl Crawl {
+    fn add_peers(&mut self, peers: &[RpcPeerInfo]) {
+        for peer in peers {
+            let address = peer.address.to_string();
+            if let Ok(socket) = address.parse::<SocketAddr>() {
+                if self.queued.insert(socket.ip()) {
+                    self.queue.push_back(socket.ip());
+                }
+            }
+            self.sightings.insert(
+                address,
+                (peer.user_agent.clone(), peer.advertised_protocol_version),
+            );
+        }
+    }
+}
+
+async fn crawl(state: &AppState, config: &CrawlerConfig) -> anyhow::Result<usize> {
+    let started = Instant::now();
+    let seeds = {
+        let client_guard = state.client.read().await;
+        let client = client_guard
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Not connected to kaspad"))?;
+        rpc::retried("get_connected_peer_info", || {
+            client.get_connected_peer_info()
+        })
+        .await?
+        .peer_info
+    };
+    let mut crawl = Crawl::default();
+    crawl.add_peers(&seeds);
+
+    let mut probes = JoinSet::new();
+    let mut started_probes = 0;
+    loop {
+        while probes.len() < config.concurrency.max(1) && started_probes < config.max_nodes {
+            let Some(ip) = crawl.queue.pop_front() else {
+                break;
+            };
+            let port = config
+                .rpc_port
+                .unwrap_or_else(|| state.network.default_rpc_port());
+            probes.spawn(async move { (ip, probe(ip, port).await) });
+            started_probes += 1;
+        }
+        let Some(joined) = probes.join_next().await else {
+            break;
+        };
+        match joined {
+            Ok((ip, Ok(probe))) => {
+                crawl.add_peers(&probe.peers);
+                crawl.probes.insert(ip, probe);
+            }
+            Ok((ip, Err(e))) => tracing::debug!("Peer {} not reachable over RPC: {}", ip, e),
+            Err(e) => tracing::warn!("Crawler probe task failed: {:?}", e),
+        }
+    }
+
+    let now = now_ms();
+    let mut map = state.crawler.write().await;
+    for node in map.nodes.values_mut() {
+        node.rpc_reachable = false;
+    }
+    for (address, (user_agent, protocol_version)) in crawl.sightings {
+        let node = map
+            .nodes
+            .entry(address.clone())
+            .or_insert_with(|| CrawledNode {
+                address: address.clone(),
+                user_agent: None,
+                protocol_version: None,
+                first_seen: now,
+                last_seen: now,
+                rpc_reachable: false,
+                server_version: None,
+                is_synced: None,
+                peer_count: None,
+                location: state
+                    .geoip
+                    .as_ref()
+                    .and_then(|geoip| geoip.lookup_address(&address)),
+            });
+        node.user_agent = Some(user_agent);
+        node.protocol_version = Some(protocol_version);
+        node.last_seen = now;
+        let probe = address
+            .parse::<SocketAddr>()
+            .ok()
+            .and_then(|socket| crawl.probes.get(&socket.ip()));
+        if let Some(probe) = probe {
+            node.rpc_reachable = true;
+            node.server_version = Some(probe.server_version.clone());
+            node.is_synced = Some(probe.is_synced);
+            node.peer_count = Some(probe.peers.len());
+        }
+    }
+    map.nodes
+        .retain(|_, node| now.saturating_sub(node.last_seen) < STALE_AFTER_MS);
+    map.crawled_at = Some(now);
+    map.crawl_duration_ms = started.elapsed().as_millis() as u64;
+    Ok(map.nodes.len())
+}
+
+pub fn spawn(state: AppState) {
+    let config = state.config.crawler.clone();
+    state.shutdown.clone().spawn(async move {
+        loop {
+            match crawl(&state, &config).await {
+                Ok(nodes) => {
+                    tracing::info!("Network crawl finished, {} nodes known", nodes);
+                    sleep(Duration::from_secs(config.interval_secs.max(1))).await;
+                }
+                Err(e) => {
+                    tracing::warn!("Network crawl failed: {:?}", e);
+                    sleep(RETRY_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/network/map",
+    tag = "network",
+    responses(
+        (status = 200, description = "P2P nodes found by the crawler, with counts by version", body = NetworkMapResponse),
+        (status = 503, description = "The first crawl has not finished", body = ErrorResponse)
+    )
+)]
+pub async fn get_network_map(
+    State(state): State<AppState>,
+) -> Result<Json<NetworkMapResponse>, Response> {
+    let map = state.crawler.read().await;
+    let Some(crawled_at) = map.crawled_at else {
+        return Err(retry_after_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Network not crawled yet",
+            RETRY_INTERVAL,
+        ));
+    };
+    let mut versions: BTreeMap<&str, usize> = BTreeMap::new();
+    for user_agent in map
+        .nodes
+        .values()
+        .filter_map(|node| node.user_agent.as_deref())
+    {
+        *versions.entry(user_agent).or_default() += 1;
+    }
+    let mut versions: Vec<VersionCount> = versions
+        .into_iter()
+        .map(|(user_agent, nodes)| VersionCount {
+            user_agent: user_agent.to_string(),
+            nodes,
+        })
+        .collect();
+    versions.sort_by_key(|version| Reverse(version.nodes));
+    let mut nodes: Vec<CrawledNode> = map.nodes.values().cloned().collect();
+    nodes.sort_by(|a, b| {
+        b.last_seen
+            .cmp(&a.last_seen)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+
+    Ok(Json(NetworkMapResponse {
+        crawled_at,
+        crawl_duration_ms: map.crawl_duration_ms,
+        total_nodes: nodes.len(),
+        rpc_reachable: nodes.iter().filter(|node| node.rpc_reachable).count(),
+        versions,
+        nodes,
+    }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CountryCount {
+    country_code: Option<String>,
+    country: Option<String>,
+    nodes: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GeoPoint {
+    latitude: f64,
+    longitude: f64,
+    city: Option<String>,
+    country_code: Option<String>,
+    nodes: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OperatorCount {
+    asn: u32,
+    organization: Option<String>,
+    nodes: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NetworkGeoResponse {
+    crawled_at: u64, // Unix ms
+    located_nodes: usize,
+    unlocated_nodes: usize,        // Private addresses, or not in the database
+    countries: Vec<CountryCount>,  // Most nodes first
+    points: Vec<GeoPoint>,         // One per distinct coordinate, for map markers
+    operators: Vec<OperatorCount>, // By autonomous system, most nodes first
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/network/geo",
+    tag = "network",
+    responses(
+        (status = 200, description = "Crawled nodes aggregated by country, coordinates and network operator", body = NetworkGeoResponse),
+        (status = 503, description = "No GeoIP database configured, or the first crawl has not finished", body = ErrorResponse)
+    )
+)]
+pub async fn get_network_geo(
+    State(state): State<AppState>,
+) -> Result<Json<NetworkGeoResponse>, Response> {
+    if state.geoip.is_none() {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No GeoIP database configured ([geoip] city_db or asn_db)",
+        ));
+    }
+    let map = state.crawler.read().await;
+    let Some(crawled_at) = map.crawled_at else {
+        return Err(retry_after_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Network not crawled yet",
+            RETRY_INTERVAL,
+        ));
+    };
+
+    let located: Vec<&Location> = map
+        .nodes
+        .values()
+        .filter_map(|node| node.location.as_ref())
+        .collect();
+    let mut countries: HashMap<Option<&str>, CountryCount> = HashMap::new();
+    // Coordinates are keyed by their bit patterns; equal coordinates come from the same record.
+    let mut points: HashMap<(u64, u64), GeoPoint> = HashMap::new();
+    let mut operators: HashMap<u32, OperatorCount> = HashMap::new();
+    for location in &located {
+        countries
+            .entry(location.country_code.as_deref())
+            .or_insert_with(|| CountryCount {
+                country_code: location.country_code.clone(),
+                country: location.country.clone(),
+                nodes: 0,
+            })
+            .nodes += 1;
+        if let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude) {
+            points
+                .entry((latitude.to_bits(), longitude.to_bits()))
+                .or_insert_with(|| GeoPoint {
+                    latitude,
+                    longitude,
+                    city: location.city.clone(),
+                    country_code: location.country_code.clone(),
+                    nodes: 0,
+                })
+                .nodes += 1;
+        }
+        if let Some(asn) = location.asn {
+            operators
+                .entry(asn)
+                .or_insert_with(|| OperatorCount {
+                    asn,
+                    organization: location.as_organization.clone(),
+                    nodes: 0,
+                })
+                .nodes += 1;
+        }
+    }
+    let mut countries: Vec<CountryCount> = countries.into_values().collect();
+    countries.sort_by(|a, b| {
+        b.nodes
+            .cmp(&a.nodes)
+            .then_with(|| a.country_code.cmp(&b.country_code))
+    });
+    let mut points: Vec<GeoPoint> = points.into_values().collect();
+    points.sort_by(|a, b| b.nodes.cmp(&a.nodes).then_with(|| a.city.cmp(&b.city)));
+    let mut operators: Vec<OperatorCount> = operators.into_values().collect();
+    operators.sort_by(|a, b| b.nodes.cmp(&a.nodes).then_with(|| a.asn.cmp(&b.asn)));
+
+    Ok(Json(NetworkGeoResponse {
+        crawled_at,
+        located_nodes: located.len(),
+        unlocated_nodes: map.nodes.len() - located.len(),
+        countries,
+        points,
+        operators,
+    }))
+}
@@ -0,0 +1,249 @@
+//! DAA score ↔ wall-clock time conversion.
+//!
+//! Lock times and maturity windows are expressed in DAA scores, which advance at roughly the
+//! block rate but not exactly. A conversion interpolates between two known (DAA score,
+//! timestamp) points around the target: indexed blocks when the index covers it, otherwise
+//! the sampler's samples (every 5 seconds for the last hour, then per-minute rollups for a
+//! week, which are only accurate to the minute). Outside all known points, the estimate
+//! extrapolates from the nearest one at the DAA score rate measured by the sampler, or at the
+//! network's target rate from its consensus parameters before the sampler has measured one.
+
+use crate::sampler::{now_ms, NetworkSample, SAMPLE_INTERVAL};
+use crate::{error_response, retry_after_response, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateBasis {
+    Block,        // An indexed block has exactly this DAA score or timestamp
+    Index,        // Interpolated between indexed blocks
+    Samples,      // Interpolated between network samples
+    Extrapolated, // Beyond all known points, at the measured DAA score rate
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DaaTimeEstimate {
+    daa_score: u64,
+    timestamp: u64, // Unix ms
+    basis: EstimateBasis,
+    seconds_from_now: i64,          // Negative in the past
+    virtual_daa_score: Option<u64>, // From the latest sample
+}
+
+/// A known (DAA score, timestamp) pair.
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    daa_score: u64,
+    timestamp: u64,
+}
+
+impl From<(u64, u64)> for Point {
+    fn from((daa_score, timestamp): (u64, u64)) -> Self {
+        Point {
+            daa_score,
+            timestamp,
+        }
+    }
+}
+
+impl From<&NetworkSample> for Point {
+    fn from(sample: &NetworkSample) -> Self {
+        Point {
+            daa_score: sample.daa_score,
+            timestamp: sample.timestamp,
+        }
+    }
+}
+
+/// Maps one coordinate of a point to the other; `key` is the one being converted from.
+trait Axis {
+    const BY_TIMESTAMP: bool;
+    fn key(point: &Point) -> u64;
+    fn other(point: &Point) -> u64;
+    /// `other` at `target`, going from `from` at `daa_per_second`.
+    fn extrapolate(from: &Point, target: u64, daa_per_second: f64) -> u64;
+}
+
+struct ByDaaScore;
+struct ByTimestamp;
+
+impl Axis for ByDaaScore {
+    const BY_TIMESTAMP: bool = false;
+    fn key(point: &Point) -> u64 {
+        point.daa_score
+    }
+    fn other(point: &Point) -> u64 {
+        point.timestamp
+    }
+    fn extrapolate(from: &Point, target: u64, daa_per_second: f64) -> u64 {
+        let seconds = (target as f64 - from.daa_score as f64) / daa_per_second;
+        (from.timestamp as f64 + seconds * 1000.0).max(0.0) as u64
+    }
+}
+
+impl Axis for ByTimestamp {
+    const BY_TIMESTAMP: bool = true;
+    fn key(point: &Point) -> u64 {
+        point.timestamp
+    }
+    fn other(point: &Point) -> u64 {
+        point.daa_score
+    }
+    fn extrapolate(from: &Point, target: u64, daa_per_second: f64) -> u64 {
+        let seconds = (target as f64 - from.timestamp as f64) / 1000.0;
+        (from.daa_score as f64 + seconds * daa_per_second).max(0.0) as u64
+    }
+}
+
+fn interpolate<A: Axis>(below: &Point, above: &Point, target: u64) -> u64 {
+    let (low, high) = (A::key(below), A::key(above));
+    if high <= low {
+        return A::other(below);
+    }
+    let fraction = (target - low) as f64 / (high - low) as f64;
+    let (from, to) = (A::other(below) as f64, A::other(above) as f64);
+    (from + (to - from) * fraction).round() as u64
+}
+
+/// Nearest points at or below and at or above `target` among `points`.
+fn around<A: Axis>(points: &[Point], target: u64) -> (Option<Point>, Option<Point>) {
+    let below = points
+        .iter()
+        .filter(|p| A::key(p) <= target)
+        .max_by_key(|p| A::key(p))
+        .copied();
+    let above = points
+        .iter()
+        .filter(|p| A::key(p) >= target)
+        .min_by_key(|p| A::key(p))
+        .copied();
+    (below, above)
+}
+
+/// Estimates the other coordinate at `target`, with the basis of the estimate.
+async fn estimate<A: Axis>(
+    state: &AppState,
+    target: u64,
+) -> Result<(u64, EstimateBasis, Option<u64>), Response> {
+    let mut indexed = (None, None);
+    if let Some(index) = &state.index {
+        if index.current_epoch_id().is_some() && index.network_mismatch().is_none() {
+            match index.blocks_around(target, A::BY_TIMESTAMP).await {
+                Ok((below, above)) => indexed = (below.map(Point::from), above.map(Point::from)),
+                // Samples still give an estimate.
+                Err(e) => {
+                    tracing::warn!("Index lookup of blocks around {} failed: {:?}", target, e)
+                }
+            }
+        }
+    }
+    let sampler = state.sampler.read().await;
+    let virtual_daa_score = sampler.latest().map(|s| s.daa_score);
+    match indexed {
+        (Some(below), _) if A::key(&below) == target => {
+            return Ok((A::other(&below), EstimateBasis::Block, virtual_daa_score));
+        }
+        (Some(below), Some(above)) => {
+            let value = interpolate::<A>(&below, &above, target);
+            return Ok((value, EstimateBasis::Index, virtual_daa_score));
+        }
+        _ => {}
+    }
+
+    let first_sample = sampler.samples().front().map(|s| s.timestamp);
+    let points: Vec<Point> = sampler
+        .rollups()
+        .iter()
+        .filter(|rollup| first_sample.is_some_and(|first| rollup.timestamp < first))
+        .chain(sampler.samples())
+        .map(Point::from)
+        .collect();
+    let (below, above) = around::<A>(&points, target);
+    if let (Some(below), Some(above)) = (below, above) {
+        let value = interpolate::<A>(&below, &above, target);
+        return Ok((value, EstimateBasis::Samples, virtual_daa_score));
+    }
+
+    // Extrapolate from the known point nearest to the target.
+    let nearest = [below, above, indexed.0, indexed.1]
+        .into_iter()
+        .flatten()
+        .min_by_key(|p| A::key(p).abs_diff(target))
+        .ok_or_else(|| {
+            retry_after_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "No network samples collected yet",
+                SAMPLE_INTERVAL,
+            )
+        })?;
+    let value = A::extrapolate(&nearest, target, sampler.daa_per_second(state.network));
+    Ok((value, EstimateBasis::Extrapolated, virtual_daa_score))
+}
+
+fn seconds_from_now(timestamp: u64) -> i64 {
+    (timestamp as i64 - now_ms() as i64) / 1000
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/daa/{score}",
+    tag = "stats",
+    params(("score" = u64, Path, description = "DAA score")),
+    responses(
+        (status = 200, description = "Estimated wall-clock time of the DAA score", body = DaaTimeEstimate),
+        (status = 400, description = "Invalid DAA score", body = ErrorResponse),
+        (status = 503, description = "No blocks or samples to estimate from yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_daa_time(
+    State(state): State<AppState>,
+    Path(score): Path<String>,
+) -> Result<Json<DaaTimeEstimate>, Response> {
+    let daa_score = score
+        .parse::<u64>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid DAA score"))?;
+    let (timestamp, basis, virtual_daa_score) = estimate::<ByDaaScore>(&state, daa_score).await?;
+    Ok(Json(DaaTimeEstimate {
+        daa_score,
+        timestamp,
+        basis,
+        seconds_from_now: seconds_from_now(timestamp),
+        virtual_daa_score,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/time/{unix_ts}",
+    tag = "stats",
+    params(("unix_ts" = u64, Path, description = "Unix time in seconds")),
+    responses(
+        (status = 200, description = "Estimated DAA score at the time", body = DaaTimeEstimate),
+        (status = 400, description = "Invalid timestamp", body = ErrorResponse),
+        (status = 503, description = "No blocks or samples to estimate from yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_time_daa(
+    State(state): State<AppState>,
+    Path(unix_ts): Path<String>,
+) -> Result<Json<DaaTimeEstimate>, Response> {
+    let timestamp = unix_ts
+        .parse::<u64>()
+        .ok()
+        .and_then(|seconds| seconds.checked_mul(1000))
+        .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Invalid Unix timestamp"))?;
+    let (daa_score, basis, virtual_daa_score) = estimate::<ByTimestamp>(&state, timestamp).await?;
+    Ok(Json(DaaTimeEstimate {
+        daa_score,
+        timestamp,
+        basis,
+        seconds_from_now: seconds_from_now(timestamp),
+        virtual_daa_score,
+    }))
+}
@@ -0,0 +1,191 @@
+//! Runtime counters at `/admin/debug/stats`, for debugging a running explorer without
+//! attaching a profiler.
+//!
+//! Each network keeps its own `Counters` in `AppState`, bumped where the work happens: kaspad
+//! RPC calls and their errors per method (by `rpc::traced`), notifications handled per type
+//! (`notifications.rs`), and balance-cache lookups (the address view falls back to the last
+//! UTXO enumeration when a new one fails). `traced` finds the counters of the network it
+//! calls for in a task-local, set by the `count_calls` layer for requests and by
+//! `Shutdown::spawn` for background tasks.
+//! Background tasks that panicked are counted by `Shutdown`, which all networks share. Sizes
+//! of the in-memory buffers are read from `AppState` when the endpoint is called. Like the
+//! rest of `/admin` it needs the admin token; an additional network answers under
+//! `/<prefix>/admin/debug/stats`. Unlike `/metrics` the answer is plain JSON, meant for
+//! `curl | jq` rather than Prometheus.
+
+use crate::{block_transactions, home, sampler, AppState};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{Json, Response},
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, ToSchema)]
+pub struct RpcCalls {
+    calls: u64,
+    errors: u64,
+}
+
+/// Counters of one network, since startup.
+#[derive(Debug, Default)]
+pub struct Counters {
+    rpc_calls: Mutex<BTreeMap<&'static str, RpcCalls>>,
+    notifications: Mutex<BTreeMap<&'static str, u64>>,
+    balance_cache_hits: AtomicU64,
+    balance_cache_misses: AtomicU64,
+}
+
+impl Counters {
+    fn rpc_call(&self, method: &'static str, failed: bool) {
+        let mut calls = self
+            .rpc_calls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = calls.entry(method).or_default();
+        entry.calls += 1;
+        if failed {
+            entry.errors += 1;
+        }
+    }
+
+    /// Records one kaspad notification handed to the handlers.
+    pub fn notification(&self, kind: &'static str) {
+        *self
+            .notifications
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    /// Records a balance-cache lookup and whether it found the address.
+    pub fn balance_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.balance_cache_hits
+        } else {
+            &self.balance_cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+tokio::task_local! {
+    static COUNTERS: Arc<Counters>;
+}
+
+/// Counts the kaspad calls made while handling a request in the counters of the network
+/// serving it.
+pub async fn count_calls(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    COUNTERS
+        .scope(state.debug_stats.clone(), next.run(request))
+        .await
+}
+
+/// Runs `task` with the kaspad calls made in it counted in `counters`, for work outside a
+/// request: background tasks, and lookups a handler spawns.
+pub async fn counting<F: Future>(counters: Arc<Counters>, task: F) -> F::Output {
+    COUNTERS.scope(counters, task).await
+}
+
+/// Records one finished kaspad RPC call in the counters of the network it was made for.
+pub fn rpc_call(method: &'static str, failed: bool) {
+    let _ = COUNTERS.try_with(|counters| counters.rpc_call(method, failed));
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DebugStats {
+    uptime_seconds: u64,
+    balance_cache: BalanceCacheStats,
+    // Homepage block buffer, filled by block-added notifications.
+    recent_blocks: RingStats,
+    block_transactions: RingStats, // Blocks with cached transaction summaries
+    network_samples: RingStats,
+    // Since the homepage mempool was last re-read; None before the first read.
+    mempool_cache_age_ms: Option<u64>,
+    response_cache_entries: u64, // Approximate; see cache.rs
+    // Handled since startup, by type.
+    notifications: BTreeMap<&'static str, u64>,
+    // Since startup, by method.
+    rpc: BTreeMap<&'static str, RpcCalls>,
+    tasks: TaskStats, // Shared by all networks
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BalanceCacheStats {
+    entries: usize,
+    hits: u64,
+    misses: u64,
+    hit_rate: Option<f64>, // None before the first lookup
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RingStats {
+    len: usize,
+    capacity: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskStats {
+    running: usize, // Background tasks spawned through `Shutdown::spawn` and not yet finished
+    panicked: u64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/debug/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cache sizes and hit rates, buffer fill, notification and RPC counters of the network, and background task health", body = DebugStats),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse)
+    )
+)]
+pub async fn get_debug_stats(State(state): State<AppState>) -> Json<DebugStats> {
+    let counters = &state.debug_stats;
+    let hits = counters.balance_cache_hits.load(Ordering::Relaxed);
+    let misses = counters.balance_cache_misses.load(Ordering::Relaxed);
+    let (recent_blocks, mempool_cache_age) = home::buffer_stats(&state).await;
+    let sampler = state.sampler.read().await;
+    Json(DebugStats {
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+        balance_cache: BalanceCacheStats {
+            entries: state.balance_cache.read().await.len(),
+            hits,
+            misses,
+            hit_rate: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+        },
+        recent_blocks: RingStats {
+            len: recent_blocks,
+            capacity: home::MAX_BLOCKS,
+        },
+        block_transactions: RingStats {
+            len: state.block_transactions.read().await.len(),
+            capacity: block_transactions::MAX_CACHED_BLOCKS,
+        },
+        network_samples: RingStats {
+            len: sampler.samples().len(),
+            capacity: sampler::MAX_SAMPLES,
+        },
+        mempool_cache_age_ms: mempool_cache_age.map(|age| age.as_millis() as u64),
+        response_cache_entries: state.response_cache.entry_count(),
+        notifications: counters
+            .notifications
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone(),
+        rpc: counters
+            .rpc_calls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone(),
+        tasks: TaskStats {
+            running: state.shutdown.running(),
+            panicked: state.shutdown.panicked(),
+        },
+    })
+}
@@ -0,0 +1,364 @@
+//! Script public key decoder.
+//!
+//! `/decode-script` takes a hex script public key, as a query parameter or a JSON body, and
+//! returns its standard class (`pubkey`, `pubkeyecdsa`, `scripthash` or `nonstandard`, as
+//! kaspa_txscript classifies it), the address it pays to on the configured network when it is
+//! standard, and its disassembly. The script is split by kaspa_txscript's own parser, and
+//! opcodes are named as in kaspad, including the KIP-10 transaction introspection opcodes
+//! (`OpTxVersion` to `OpTxOutputSpk`); codes past those are shown as `OpUnknown<code>`. A
+//! push running past the end of the script ends the disassembly with an `error`, since such
+//! scripts can still appear on chain.
+
+use crate::{error_response, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_consensus_core::hashing::sighash::SigHashReusedValuesUnsync;
+use kaspa_consensus_core::tx::{PopulatedTransaction, ScriptPublicKey};
+use kaspa_txscript::opcodes::parse_script;
+use kaspa_txscript::script_class::ScriptClass;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+// kaspad's limit on script sizes.
+const MAX_SCRIPT_BYTES: usize = 10_000;
+
+const OP_PUSH_DATA_1: u8 = 0x4c;
+const OP_PUSH_DATA_2: u8 = 0x4d;
+const OP_PUSH_DATA_4: u8 = 0x4e;
+
+// Names of the opcodes from Op1Negate (0x4f) to OpTxOutputSpk (0xc3).
+const OPCODE_NAMES: [&str; 117] = [
+    "Op1Negate",
+    "OpReserved",
+    "OpTrue",
+    "Op2",
+    "Op3",
+    "Op4",
+    "Op5",
+    "Op6",
+    "Op7",
+    "Op8",
+    "Op9",
+    "Op10",
+    "Op11",
+    "Op12",
+    "Op13",
+    "Op14",
+    "Op15",
+    "Op16",
+    "OpNop",
+    "OpVer",
+    "OpIf",
+    "OpNotIf",
+    "OpVerIf",
+    "OpVerNotIf",
+    "OpElse",
+    "OpEndIf",
+    "OpVerify",
+    "OpReturn",
+    "OpToAltStack",
+    "OpFromAltStack",
+    "Op2Drop",
+    "Op2Dup",
+    "Op3Dup",
+    "Op2Over",
+    "Op2Rot",
+    "Op2Swap",
+    "OpIfDup",
+    "OpDepth",
+    "OpDrop",
+    "OpDup",
+    "OpNip",
+    "OpOver",
+    "OpPick",
+    "OpRoll",
+    "OpRot",
+    "OpSwap",
+    "OpTuck",
+    "OpCat",
+    "OpSubStr",
+    "OpLeft",
+    "OpRight",
+    "OpSize",
+    "OpInvert",
+    "OpAnd",
+    "OpOr",
+    "OpXor",
+    "OpEqual",
+    "OpEqualVerify",
+    "OpReserved1",
+    "OpReserved2",
+    "Op1Add",
+    "Op1Sub",
+    "Op2Mul",
+    "Op2Div",
+    "OpNegate",
+    "OpAbs",
+    "OpNot",
+    "Op0NotEqual",
+    "OpAdd",
+    "OpSub",
+    "OpMul",
+    "OpDiv",
+    "OpMod",
+    "OpLShift",
+    "OpRShift",
+    "OpBoolAnd",
+    "OpBoolOr",
+    "OpNumEqual",
+    "OpNumEqualVerify",
+    "OpNumNotEqual",
+    "OpLessThan",
+    "OpGreaterThan",
+    "OpLessThanOrEqual",
+    "OpGreaterThanOrEqual",
+    "OpMin",
+    "OpMax",
+    "OpWithin",
+    "OpUnknown166",
+    "OpUnknown167",
+    "OpSHA256",
+    "OpCheckMultiSigECDSA",
+    "OpBlake2b",
+    "OpCheckSigECDSA",
+    "OpCheckSig",
+    "OpCheckSigVerify",
+    "OpCheckMultiSig",
+    "OpCheckMultiSigVerify",
+    "OpCheckLockTimeVerify",
+    "OpCheckSequenceVerify",
+    "OpTxVersion",
+    "OpTxInputCount",
+    "OpTxOutputCount",
+    "OpTxLockTime",
+    "OpTxSubnetId",
+    "OpTxGas",
+    "OpTxPayload",
+    "OpTxInputIndex",
+    "OpOutpointTxId",
+    "OpOutpointIndex",
+    "OpTxInputScriptSig",
+    "OpTxInputSeq",
+    "OpTxInputAmount",
+    "OpTxInputSpk",
+    "OpTxInputBlockDaaScore",
+    "OpTxInputIsCoinbase",
+    "OpTxOutputAmount",
+    "OpTxOutputSpk",
+];
+
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct DecodeScriptRequest {
+    /// Hex of the script
+    script: String,
+    /// Script public key version
+    #[serde(default)]
+    version: u16,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Opcode {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>, // Hex of the pushed bytes
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DecodedScript {
+    script: String,
+    version: u16,
+    class: String,
+    address: Option<String>, // None for non-standard scripts
+    asm: String,             // Opcodes and pushed data, space separated
+    opcodes: Vec<Opcode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>, // Where the disassembly stopped
+}
+
+fn opcode_name(code: u8) -> String {
+    match code {
+        0x00 => "OpFalse".to_string(),
+        0x01..=0x4b => format!("OpData{}", code),
+        OP_PUSH_DATA_1 => "OpPushData1".to_string(),
+        OP_PUSH_DATA_2 => "OpPushData2".to_string(),
+        OP_PUSH_DATA_4 => "OpPushData4".to_string(),
+        0x4f..=0xc3 => OPCODE_NAMES[(code - 0x4f) as usize].to_string(),
+        _ => format!("OpUnknown{}", code),
+    }
+}
+
+/// Splits `script` into opcodes and the data each push carries, with kaspa_txscript's parser,
+/// stopping with an error at a push that runs past its end.
+pub fn instructions(script: &[u8]) -> (Vec<(u8, Option<Vec<u8>>)>, Option<String>) {
+    let mut instructions = Vec::new();
+    // The transaction types only matter for executing opcodes, not for parsing them.
+    for opcode in parse_script::<PopulatedTransaction, SigHashReusedValuesUnsync>(script) {
+        match opcode {
+            Ok(opcode) => {
+                let code = opcode.value();
+                let data = (0x01..=OP_PUSH_DATA_4)
+                    .contains(&code)
+                    .then(|| opcode.get_data().to_vec());
+                instructions.push((code, data));
+            }
+            Err(e) => {
+                let error = format!("Opcode {} does not parse: {}", instructions.len(), e);
+                return (instructions, Some(error));
+            }
+        }
+    }
+    (instructions, None)
+}
+
+fn disassemble(script: &[u8]) -> (Vec<Opcode>, Option<String>) {
+    let (instructions, error) = instructions(script);
+    let opcodes = instructions
+        .into_iter()
+        .map(|(code, data)| Opcode {
+            name: opcode_name(code),
+            data: data.map(hex::encode),
+        })
+        .collect();
+    (opcodes, error)
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn decode(state: &AppState, request: DecodeScriptRequest) -> Result<DecodedScript, Response> {
+    let script = hex::decode(request.script.trim())
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "script must be hex"))?;
+    if script.len() > MAX_SCRIPT_BYTES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("script is longer than {} bytes", MAX_SCRIPT_BYTES),
+        ));
+    }
+    let script_public_key = ScriptPublicKey::from_vec(request.version, script.clone());
+    let class = ScriptClass::from_script(&script_public_key);
+    let address =
+        kaspa_txscript::extract_script_pub_key_address(&script_public_key, state.network.prefix())
+            .ok()
+            .map(|address| address.to_string());
+    let (opcodes, error) = disassemble(&script);
+    let asm = opcodes
+        .iter()
+        .map(|opcode| match &opcode.data {
+            Some(data) => format!("{} {}", opcode.name, data),
+            None => opcode.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(DecodedScript {
+        script: hex::encode(&script),
+        version: request.version,
+        class: class.to_string(),
+        address,
+        asm,
+        opcodes,
+        error,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/decode-script",
+    tag = "tools",
+    params(DecodeScriptRequest),
+    responses(
+        (status = 200, description = "Class, address and disassembly of the script", body = DecodedScript),
+        (status = 400, description = "Script is not hex or too long", body = ErrorResponse)
+    )
+)]
+pub async fn get_decode_script(
+    State(state): State<AppState>,
+    Query(request): Query<DecodeScriptRequest>,
+) -> Result<Json<DecodedScript>, Response> {
+    decode(&state, request).map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/decode-script",
+    tag = "tools",
+    request_body = DecodeScriptRequest,
+    responses(
+        (status = 200, description = "Class, address and disassembly of the script", body = DecodedScript),
+        (status = 400, description = "Script is not hex or too long", body = ErrorResponse)
+    )
+)]
+pub async fn post_decode_script(
+    State(state): State<AppState>,
+    Json(request): Json<DecodeScriptRequest>,
+) -> Result<Json<DecodedScript>, Response> {
+    decode(&state, request).map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_pushes_and_opcodes() {
+        let mut script = vec![0x20];
+        script.extend([7; 32]);
+        script.push(0xac);
+        let (instructions, error) = instructions(&script);
+        assert_eq!(error, None);
+        assert_eq!(instructions, vec![(0x20, Some(vec![7; 32])), (0xac, None)]);
+    }
+
+    #[test]
+    fn push_data_carries_its_bytes() {
+        let script = [
+            0x00,
+            OP_PUSH_DATA_1,
+            3,
+            1,
+            2,
+            3,
+            OP_PUSH_DATA_2,
+            1,
+            0,
+            9,
+            0x51,
+        ];
+        let (instructions, error) = instructions(&script);
+        assert_eq!(error, None);
+        assert_eq!(
+            instructions,
+            vec![
+                (0x00, None),
+                (OP_PUSH_DATA_1, Some(vec![1, 2, 3])),
+                (OP_PUSH_DATA_2, Some(vec![9])),
+                (0x51, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_push_stops_with_an_error() {
+        let (instructions, error) = instructions(&[0x51, 0x05, 1, 2]);
+        assert_eq!(instructions, vec![(0x51, None)]);
+        assert!(error.unwrap().starts_with("Opcode 1 does not parse"));
+    }
+
+    #[test]
+    fn opcodes_are_named_as_in_kaspad() {
+        assert_eq!(opcode_name(0x00), "OpFalse");
+        assert_eq!(opcode_name(0x20), "OpData32");
+        assert_eq!(opcode_name(OP_PUSH_DATA_4), "OpPushData4");
+        assert_eq!(opcode_name(0x4f), "Op1Negate");
+        assert_eq!(opcode_name(0x87), "OpEqual");
+        assert_eq!(opcode_name(0xaa), "OpBlake2b");
+        assert_eq!(opcode_name(0xab), "OpCheckSigECDSA");
+        assert_eq!(opcode_name(0xac), "OpCheckSig");
+        assert_eq!(opcode_name(0xb1), "OpCheckSequenceVerify");
+        assert_eq!(opcode_name(0xb2), "OpTxVersion");
+        assert_eq!(opcode_name(0xc3), "OpTxOutputSpk");
+        assert_eq!(opcode_name(0xc4), "OpUnknown196");
+        assert_eq!(opcode_name(0xff), "OpUnknown255");
+    }
+}
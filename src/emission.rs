@@ -0,0 +1,183 @@
+//! Emission schedule.
+//!
+//! Kaspa pays a fixed subsidy per second until `deflationary_phase_daa_score`, then reduces
+//! it every month (2629800 seconds) by a factor of 2^(-1/12), which halves it once a year.
+//! A second's subsidy is split across its blocks, rounding up. The parameters come from the
+//! served network's consensus parameters, each overridable in `[emission]`; the subsidy the
+//! sink block actually claims in its coinbase is reported next to the computed one, so a
+//! testnet with different parameters shows up as a mismatch rather than silently wrong dates.
+
+use crate::chain::ChainSource;
+use crate::config::EmissionConfig;
+use crate::network::Network;
+use crate::sampler::now_ms;
+use crate::{error_response, miners, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const SECONDS_PER_MONTH: u64 = 2_629_800;
+// Sompi per second in the first deflationary month, the head of kaspad's subsidy table.
+const DEFLATIONARY_INITIAL_SUBSIDY: u64 = 44_000_000_000;
+
+/// The emission schedule in effect: the network's consensus parameters with the `[emission]`
+/// overrides applied.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub blocks_per_second: u64,
+    deflationary_phase_daa_score: u64,
+    pre_deflationary_subsidy: u64, // Sompi per second
+    deflationary_initial_subsidy: u64,
+}
+
+impl Schedule {
+    pub fn new(network: Network, config: &EmissionConfig) -> Self {
+        let params = network.params();
+        Schedule {
+            blocks_per_second: config
+                .blocks_per_second
+                .unwrap_or_else(|| network.blocks_per_second()),
+            deflationary_phase_daa_score: config
+                .deflationary_phase_daa_score
+                .unwrap_or(params.deflationary_phase_daa_score),
+            pre_deflationary_subsidy: config
+                .pre_deflationary_subsidy
+                .unwrap_or(params.pre_deflationary_phase_base_subsidy),
+            deflationary_initial_subsidy: config
+                .deflationary_initial_subsidy
+                .unwrap_or(DEFLATIONARY_INITIAL_SUBSIDY),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmissionPhase {
+    PreDeflationary,
+    Deflationary,
+    Finished, // The monthly subsidy has reached zero
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmissionInfo {
+    daa_score: u64, // Virtual DAA score
+    phase: EmissionPhase,
+    #[serde(serialize_with = "crate::units::sompi")]
+    block_subsidy: u64, // Sompi per block at `daa_score`, from the schedule
+    // What the sink block claims in its coinbase, and whether the schedule agrees at its DAA score.
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    observed_subsidy: Option<u64>,
+    schedule_matches: Option<bool>,
+    blocks_per_second: u64,
+    deflationary_phase_daa_score: u64,
+    month: Option<u64>, // Months since the deflationary phase started
+    next_reduction_daa_score: Option<u64>,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    next_block_subsidy: Option<u64>,
+    next_reduction_estimated_at: Option<u64>, // Unix ms, at the measured DAA score rate
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    circulating_supply: Option<u64>,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    max_supply: Option<u64>,
+}
+
+fn month_of(config: &Schedule, daa_score: u64) -> Option<u64> {
+    let daa_per_month = SECONDS_PER_MONTH * config.blocks_per_second.max(1);
+    daa_score
+        .checked_sub(config.deflationary_phase_daa_score)
+        .map(|since| since / daa_per_month)
+}
+
+fn monthly_subsidy(config: &Schedule, month: u64) -> u64 {
+    (config.deflationary_initial_subsidy as f64 * 2f64.powf(-(month as f64) / 12.0)) as u64
+}
+
+/// Subsidy of a block at `daa_score`, in sompi.
+pub fn block_subsidy(config: &Schedule, daa_score: u64) -> u64 {
+    let per_second = match month_of(config, daa_score) {
+        None => config.pre_deflationary_subsidy,
+        Some(month) => monthly_subsidy(config, month),
+    };
+    per_second.div_ceil(config.blocks_per_second.max(1))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/emission",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Current subsidy, emission phase and next reduction", body = EmissionInfo),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_emission(State(state): State<AppState>) -> Result<Json<EmissionInfo>, Response> {
+    let config = &Schedule::new(state.network, &state.config.emission);
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get DAG info: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to get DAG info")
+        })?;
+    let sink = match rpc::retried("get_block", || client.get_block(dag_info.sink, true)).await {
+        Ok(block) => Some(block),
+        Err(e) => {
+            tracing::warn!("Failed to fetch sink {}: {:?}", dag_info.sink, e);
+            None
+        }
+    };
+    let supply = match rpc::retried("get_coin_supply", || client.get_coin_supply()).await {
+        Ok(supply) => Some(supply),
+        Err(e) => {
+            tracing::warn!("Failed to get the coin supply: {:?}", e);
+            None
+        }
+    };
+    drop(client_guard);
+
+    let daa_score = dag_info.virtual_daa_score;
+    let block_subsidy = block_subsidy(config, daa_score);
+    let month = month_of(config, daa_score);
+    let phase = match month {
+        None => EmissionPhase::PreDeflationary,
+        Some(month) if monthly_subsidy(config, month) > 0 => EmissionPhase::Deflationary,
+        Some(_) => EmissionPhase::Finished,
+    };
+    let next_reduction_daa_score = match (phase, month) {
+        (EmissionPhase::PreDeflationary, _) => Some(config.deflationary_phase_daa_score),
+        (EmissionPhase::Deflationary, Some(month)) => Some(
+            config.deflationary_phase_daa_score
+                + (month + 1) * SECONDS_PER_MONTH * config.blocks_per_second.max(1),
+        ),
+        _ => None,
+    };
+    let daa_per_second = state.sampler.read().await.daa_per_second(state.network);
+    let observed_subsidy = sink.as_ref().and_then(miners::coinbase_subsidy);
+
+    Ok(Json(EmissionInfo {
+        daa_score,
+        phase,
+        block_subsidy,
+        schedule_matches: sink
+            .as_ref()
+            .zip(observed_subsidy)
+            .map(|(sink, observed)| observed == self::block_subsidy(config, sink.header.daa_score)),
+        observed_subsidy,
+        blocks_per_second: config.blocks_per_second,
+        deflationary_phase_daa_score: config.deflationary_phase_daa_score,
+        month,
+        next_reduction_daa_score,
+        next_block_subsidy: next_reduction_daa_score.map(|next| self::block_subsidy(config, next)),
+        next_reduction_estimated_at: next_reduction_daa_score.map(|next| {
+            let seconds = next.saturating_sub(daa_score) as f64 / daa_per_second;
+            now_ms() + (seconds * 1000.0) as u64
+        }),
+        circulating_supply: supply.as_ref().map(|s| s.circulating_sompi),
+        max_supply: supply.as_ref().map(|s| s.max_sompi),
+    }))
+}
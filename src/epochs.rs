@@ -0,0 +1,202 @@
+//! Testnet reset detection.
+//!
+//! Testnet-12 is relaunched from a new genesis from time to time, and a node pointed at the
+//! relaunched network serves a DAG that shares nothing with the indexed one. The indexer calls
+//! `check` periodically; when the node's DAA score falls below what the current epoch already
+//! indexed, or the epoch's genesis or last pruning point is unknown to the node, a new epoch is
+//! started and the index continues from the new pruning point without mixing the two networks.
+//! An epoch's genesis is found when it starts by following pruning point headers back from the
+//! node's pruning point; genesis is the one without a pruning point of its own.
+//!
+//! A node on a different network altogether (say mainnet, or another testnet) is not a reset:
+//! the index is marked as mismatched and not served until the node is switched back, or it is
+//! wiped and rebuilt, either when `auto_reset` (`--auto-reset-index`) is set or when the
+//! operator confirms it with `POST /admin/index/wipe`.
+
+use crate::chain::{ChainSource, Client};
+use crate::index::{Epoch, IndexHandle};
+use crate::{error_response, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// Slack for blocks indexed from tips that virtual has not merged yet.
+const DAA_REGRESSION_TOLERANCE: u64 = 1_000;
+// Pruning points followed back when looking for the genesis, one header lookup each.
+const MAX_PRUNING_POINTS: usize = 10_000;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EpochsResponse {
+    epochs: Vec<Epoch>, // Newest first
+    // Set while the index is not served because kaspad is on another network.
+    network_mismatch: Option<String>,
+}
+
+/// Checks the node's network against the current epoch, then starts the first epoch, or a
+/// new one if the network behind the node was reset. Reset detection is skipped while the
+/// node is syncing, since an IBD node reports a low DAA score on the same network.
+pub async fn check(client: &Client, index: &IndexHandle, auto_reset: bool) -> anyhow::Result<()> {
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await?;
+    let network = dag_info.network.to_string();
+    let pruning_point = dag_info.pruning_point_hash.to_string();
+
+    let mut current = index.current_epoch().await?;
+    if let Some(epoch) = current.as_ref().filter(|epoch| epoch.network != network) {
+        let mismatch = format!(
+            "The index was built from {} but kaspad is on {}",
+            epoch.network, network
+        );
+        if !auto_reset {
+            if index.network_mismatch().is_none() {
+                tracing::error!(
+                    "{}; not serving it (restart with --auto-reset-index, or POST /admin/index/wipe, to rebuild)",
+                    mismatch
+                );
+            }
+            index.set_network_mismatch(Some(mismatch));
+            return Ok(());
+        }
+        tracing::warn!("{}; wiping and rebuilding the index", mismatch);
+        index.wipe().await?;
+        current = None;
+    } else if index.network_mismatch().is_some() {
+        tracing::info!("kaspad is back on {}; serving the index again", network);
+        index.set_network_mismatch(None);
+    }
+
+    if !rpc::retried("get_info", || client.get_info())
+        .await?
+        .is_synced
+    {
+        return Ok(());
+    }
+    let Some(epoch) = current else {
+        let genesis = find_genesis(client, dag_info.pruning_point_hash).await?;
+        let id = index
+            .start_epoch(
+                network,
+                genesis.to_string(),
+                pruning_point,
+                dag_info.virtual_daa_score,
+                None,
+            )
+            .await?;
+        tracing::info!("Started index epoch {} (genesis {})", id, genesis);
+        return Ok(());
+    };
+
+    let genesis_known = match &epoch.genesis {
+        Some(genesis) => is_known(client, genesis).await?,
+        None => true,
+    };
+    let reset_reason =
+        if dag_info.virtual_daa_score + DAA_REGRESSION_TOLERANCE < epoch.last_daa_score {
+            Some(format!(
+                "DAA score regressed from {} to {}",
+                epoch.last_daa_score, dag_info.virtual_daa_score
+            ))
+        } else if let (Some(genesis), false) = (&epoch.genesis, genesis_known) {
+            Some(format!("Genesis {} is unknown to the node", genesis))
+        } else if epoch.pruning_point != pruning_point
+            && !is_known(client, &epoch.pruning_point).await?
+        {
+            Some(format!(
+                "Pruning point {} is unknown to the node",
+                epoch.pruning_point
+            ))
+        } else {
+            None
+        };
+
+    match reset_reason {
+        Some(reason) => {
+            tracing::warn!("Network reset detected: {}", reason);
+            let genesis = find_genesis(client, dag_info.pruning_point_hash).await?;
+            let id = index
+                .start_epoch(
+                    network,
+                    genesis.to_string(),
+                    pruning_point,
+                    dag_info.virtual_daa_score,
+                    Some(reason),
+                )
+                .await?;
+            tracing::info!("Started index epoch {} (genesis {})", id, genesis);
+        }
+        None => {
+            if epoch.pruning_point != pruning_point {
+                index.set_epoch_pruning_point(pruning_point).await?;
+            }
+            if epoch.genesis.is_none() {
+                let genesis = find_genesis(client, dag_info.pruning_point_hash).await?;
+                tracing::info!("Index epoch {} is on genesis {}", epoch.id, genesis);
+                index.set_epoch_genesis(genesis.to_string()).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Genesis of the node's network: the pruning point chain from `pruning_point` back to the
+/// block whose header names no pruning point.
+async fn find_genesis(client: &Client, pruning_point: Hash) -> anyhow::Result<Hash> {
+    let mut hash = pruning_point;
+    for _ in 0..MAX_PRUNING_POINTS {
+        let block = rpc::retried("get_block", || client.get_block(hash, false)).await?;
+        if block.header.pruning_point == Hash::default() {
+            return Ok(hash);
+        }
+        hash = block.header.pruning_point;
+    }
+    anyhow::bail!(
+        "No genesis within {} pruning points of {}",
+        MAX_PRUNING_POINTS,
+        pruning_point
+    )
+}
+
+/// Whether the node has `hash`. Past pruning points keep their headers, so on an unreset
+/// network this holds for every pruning point the epoch has recorded.
+async fn is_known(client: &Client, hash: &str) -> anyhow::Result<bool> {
+    let hash = hash
+        .parse::<Hash>()
+        .map_err(|e| anyhow::anyhow!("Invalid pruning point {}: {:?}", hash, e))?;
+    match rpc::retried("get_block", || client.get_block(hash, false)).await {
+        Ok(_) => Ok(true),
+        // Anything other than a clean "not found" (kaspad down, timeouts) must not end an epoch.
+        Err(e) if rpc::is_not_found(&e) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/epochs",
+    tag = "network",
+    responses(
+        (status = 200, description = "Index epochs, one per detected network launch", body = EpochsResponse),
+        (status = 503, description = "Index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn get_epochs(State(state): State<AppState>) -> Result<Json<EpochsResponse>, Response> {
+    // Served even on a network mismatch, since it explains why the index is unavailable.
+    let index = state.index.clone().ok_or_else(|| {
+        error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The transaction index is disabled",
+        )
+    })?;
+    let epochs = index.epochs().await.map_err(|e| {
+        tracing::error!("Failed to read index epochs: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index query failed")
+    })?;
+    Ok(Json(EpochsResponse {
+        epochs,
+        network_mismatch: index.network_mismatch(),
+    }))
+}
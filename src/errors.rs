@@ -0,0 +1,232 @@
+//! The API error model.
+//!
+//! Every error the API answers with is an `ApiError`, sent as an RFC 7807 problem document
+//! (`application/problem+json`). Besides the standard `type`, `title`, `status` and `detail`
+//! members it carries a stable `code` clients can branch on (`NOT_CONNECTED`, `NOT_FOUND`,
+//! `UTXO_INDEX_REQUIRED`, `UPSTREAM_TIMEOUT`, ...), `retry_after_ms` for conditions worth
+//! retrying (also sent as `Retry-After`), and the `api_version`.
+//!
+//! Handlers build specific variants where the cause is known; `error_response` maps a plain
+//! status and message onto the matching generic variant, so older call sites produce the same
+//! shape. Errors produced outside the handlers, such as axum's plain-text extractor
+//! rejections and the 404 of unknown API paths, are rewritten by `rejections_as_problems`.
+
+use crate::format;
+use crate::API_VERSION;
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+// Rejection messages are short; anything longer is not one.
+const MAX_REJECTION_BYTES: usize = 16 * 1024;
+
+// How long clients should wait before retrying when kaspad is unreachable.
+pub const KASPAD_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Gone(String), // Pruned data
+    RateLimited {
+        detail: String,
+        retry_after: Duration,
+    },
+    NotConnected,
+    UtxoIndexRequired(String),
+    IndexUnavailable(String), // Index disabled or built from another network
+    Unavailable {
+        detail: String,
+        retry_after: Option<Duration>,
+    },
+    UpstreamError(String),
+    UpstreamTimeout(String),
+    Internal(String),
+    Other(StatusCode, String), // Any other status, with a code derived from it
+}
+
+/// RFC 7807 problem document.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    #[serde(rename = "type")]
+    problem_type: &'static str, // Always about:blank; `code` identifies the problem
+    title: String, // Reason phrase of the status
+    status: u16,
+    detail: String,
+    code: String, // Machine-readable, e.g. NOT_CONNECTED
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_ms: Option<u64>,
+    api_version: &'static str,
+}
+
+impl ApiError {
+    /// The generic variant for `status`.
+    pub fn from_status(status: StatusCode, detail: &str) -> Self {
+        let detail = detail.to_string();
+        match status {
+            StatusCode::BAD_REQUEST => ApiError::BadRequest(detail),
+            StatusCode::UNAUTHORIZED => ApiError::Unauthorized(detail),
+            StatusCode::FORBIDDEN => ApiError::Forbidden(detail),
+            StatusCode::NOT_FOUND => ApiError::NotFound(detail),
+            StatusCode::CONFLICT => ApiError::Conflict(detail),
+            StatusCode::GONE => ApiError::Gone(detail),
+            StatusCode::SERVICE_UNAVAILABLE => ApiError::Unavailable {
+                detail,
+                retry_after: None,
+            },
+            StatusCode::BAD_GATEWAY => ApiError::UpstreamError(detail),
+            StatusCode::GATEWAY_TIMEOUT => ApiError::UpstreamTimeout(detail),
+            StatusCode::INTERNAL_SERVER_ERROR => ApiError::Internal(detail),
+            _ => ApiError::Other(status, detail),
+        }
+    }
+
+    /// Adds a retry hint to a 503 or 429 error; other errors are returned unchanged.
+    pub fn with_retry_after(self, retry_after: Duration) -> Self {
+        match self {
+            ApiError::Unavailable { detail, .. } => ApiError::Unavailable {
+                detail,
+                retry_after: Some(retry_after),
+            },
+            ApiError::Other(StatusCode::TOO_MANY_REQUESTS, detail) => ApiError::RateLimited {
+                detail,
+                retry_after,
+            },
+            other => other,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Gone(_) => StatusCode::GONE,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::NotConnected
+            | ApiError::UtxoIndexRequired(_)
+            | ApiError::IndexUnavailable(_)
+            | ApiError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::UpstreamError(_) => StatusCode::BAD_GATEWAY,
+            ApiError::UpstreamTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Other(status, _) => *status,
+        }
+    }
+
+    pub fn code(&self) -> String {
+        let code = match self {
+            ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::Gone(_) => "GONE",
+            ApiError::RateLimited { .. } => "RATE_LIMITED",
+            ApiError::NotConnected => "NOT_CONNECTED",
+            ApiError::UtxoIndexRequired(_) => "UTXO_INDEX_REQUIRED",
+            ApiError::IndexUnavailable(_) => "INDEX_UNAVAILABLE",
+            ApiError::Unavailable { .. } => "UNAVAILABLE",
+            ApiError::UpstreamError(_) => "UPSTREAM_ERROR",
+            ApiError::UpstreamTimeout(_) => "UPSTREAM_TIMEOUT",
+            ApiError::Internal(_) => "INTERNAL_ERROR",
+            ApiError::Other(status, _) => {
+                // e.g. 413 Payload Too Large -> PAYLOAD_TOO_LARGE
+                return status
+                    .canonical_reason()
+                    .unwrap_or("ERROR")
+                    .to_uppercase()
+                    .replace([' ', '-'], "_");
+            }
+        };
+        code.to_string()
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            ApiError::NotConnected => "Not connected to kaspad".to_string(),
+            ApiError::RateLimited { detail, .. } | ApiError::Unavailable { detail, .. } => {
+                detail.clone()
+            }
+            ApiError::BadRequest(detail)
+            | ApiError::Unauthorized(detail)
+            | ApiError::Forbidden(detail)
+            | ApiError::NotFound(detail)
+            | ApiError::Conflict(detail)
+            | ApiError::Gone(detail)
+            | ApiError::UtxoIndexRequired(detail)
+            | ApiError::IndexUnavailable(detail)
+            | ApiError::UpstreamError(detail)
+            | ApiError::UpstreamTimeout(detail)
+            | ApiError::Internal(detail)
+            | ApiError::Other(_, detail) => detail.clone(),
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited { retry_after, .. } => Some(*retry_after),
+            ApiError::NotConnected => Some(KASPAD_RETRY_AFTER),
+            ApiError::Unavailable { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let retry_after = self.retry_after();
+        let body = ErrorResponse {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: self.code(),
+            retry_after_ms: retry_after.map(|d| d.as_millis() as u64),
+            api_version: API_VERSION,
+        };
+        let mut response = (status, Json(body)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        if let Some(retry_after) = retry_after {
+            // Retry-After only has second resolution: round up, so a client that waits that
+            // long is never early, and never advertise 0.
+            let seconds = retry_after.as_millis().div_ceil(1000).max(1) as u64;
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
+}
+
+/// Turns error responses that are not JSON (extractor rejections, unmatched paths) into
+/// problem documents, keeping their status and using their text as the detail.
+pub async fn rejections_as_problems(response: Response) -> Response {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(format::is_json);
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+    let text = axum::body::to_bytes(response.into_body(), MAX_REJECTION_BYTES)
+        .await
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+        .filter(|text| !text.is_empty());
+    let detail = text.unwrap_or_else(|| status.canonical_reason().unwrap_or("Error").to_string());
+    ApiError::from_status(status, &detail).into_response()
+}
@@ -0,0 +1,88 @@
+//! ETags and conditional GETs.
+//!
+//! The frontend polls `/api/blocks`, `/api/mempool` and `/api/info` every few seconds, and most
+//! polls see the same data. Successful JSON GET responses get a weak ETag derived from the
+//! final body (after signing and the syncing flag); a request whose `If-None-Match` matches it
+//! gets an empty 304 instead. `Cache-Control: no-cache` makes browsers revalidate on every
+//! poll rather than reuse a stale copy.
+
+use crate::{error_response, AppState};
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+pub async fn conditional_get(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_get = matches!(*request.method(), Method::GET | Method::HEAD);
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    if !is_get || response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let max = state.config.limits.max_response_bytes;
+    let bounded = body
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len as usize <= max);
+    if !is_json || !bounded {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match axum::body::to_bytes(body, max).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for ETag: {:?}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to buffer the response",
+            );
+        }
+    };
+    // Weak, since compression may send the same JSON in different encodings.
+    let etag = format!("W/\"{}\"", hex::encode(&Sha256::digest(&bytes)[..16]));
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    parts.headers.insert(header::ETAG, etag_value);
+    parts
+        .headers
+        .entry(header::CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static("no-cache"));
+
+    if if_none_match.is_some_and(|value| matches_etag(&value, &etag)) {
+        let mut headers = HeaderMap::new();
+        for name in [header::ETAG, header::CACHE_CONTROL, header::VARY] {
+            if let Some(value) = parts.headers.get(&name) {
+                headers.insert(name, value.clone());
+            }
+        }
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Weak comparison against an `If-None-Match` list (RFC 9110 13.1.2).
+fn matches_etag(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    value
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
@@ -0,0 +1,303 @@
+//! Explorer events and the sinks they are delivered to.
+//!
+//! An `Event` is the JSON document downstream consumers receive for something that happened
+//! on the chain. Sinks write one event at a time: as a line of NDJSON on stdout, or as the
+//! body of a POST to a webhook URL (retried with backoff before giving up). Webhooks with a
+//! secret carry `X-Explorer-Signature: sha256=<hex>`, the HMAC-SHA256 of the body.
+//!
+//! Signed webhooks take their URL from API clients (`watch.rs`), so they only reach public
+//! addresses: the host is resolved when the watch is registered and again on every delivery,
+//! and loopback, private, link-local, unique-local and similar addresses are refused.
+//! Redirects are never followed, so a public host cannot bounce a delivery inwards.
+
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use serde::Serialize;
+use sha2::Sha256;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+
+const WEBHOOK_ATTEMPTS: u32 = 4;
+const WEBHOOK_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const SIGNATURE_HEADER: &str = "x-explorer-signature";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Block(BlockEvent),
+    BalanceChanged(BalanceChangedEvent),
+    TransactionConfirmed(TransactionConfirmedEvent),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockEvent {
+    pub hash: String,
+    pub daa_score: u64,
+    pub blue_score: u64,
+    pub timestamp: u64, // Unix ms
+    pub transaction_ids: Vec<String>,
+    pub replayed: bool, // Re-emitted from the index rather than observed live
+}
+
+/// UTXOs of a watched address added or removed by one kaspad notification.
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceChangedEvent {
+    pub watch_id: String,
+    pub address: String,
+    pub received: u64, // Sum of the UTXOs added
+    pub spent: u64,    // Sum of the UTXOs removed
+    pub utxos_added: usize,
+    pub utxos_removed: usize,
+    pub transaction_ids: Vec<String>, // Transactions that created the added UTXOs
+}
+
+/// A watched transaction accepted by a selected-chain block for the first time.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionConfirmedEvent {
+    pub watch_id: String,
+    pub transaction_id: String,
+    pub block_hash: String, // The accepting chain block
+    // None when the accepting block's header could not be read.
+    pub daa_score: Option<u64>,
+    pub timestamp: Option<u64>, // Block timestamp, Unix ms
+}
+
+pub enum Sink {
+    Stdout,
+    Webhook {
+        client: reqwest::Client,
+        url: String,
+        secret: Option<Vec<u8>>, // HMAC key the body is signed with
+        public_only: bool,       // Refuse non-public addresses
+    },
+}
+
+/// Whether `ip` is a public internet address a client-supplied webhook may be sent to.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))) // Shared address space (CGNAT)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || first & 0xfe00 == 0xfc00 // Unique local
+                || first & 0xffc0 == 0xfe80) // Link local
+        }
+    }
+}
+
+/// Addresses of `host`, if it resolves and all of them are public.
+async fn resolve_public(host: &str, port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if addresses.is_empty() {
+        anyhow::bail!("{} does not resolve", host);
+    }
+    if let Some(address) = addresses.iter().find(|address| !is_public(address.ip())) {
+        anyhow::bail!("{} resolves to non-public address {}", host, address.ip());
+    }
+    Ok(addresses)
+}
+
+/// Checks that `url` points at a public host; an error names what was refused.
+pub async fn ensure_public(url: &Url) -> anyhow::Result<()> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host"))?;
+    // IPv6 literals keep their brackets in `host_str`.
+    match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) if is_public(ip) => Ok(()),
+        Ok(ip) => anyhow::bail!("{} is not a public address", ip),
+        Err(_) => resolve_public(host, url.port_or_known_default().unwrap_or(0))
+            .await
+            .map(|_| ()),
+    }
+}
+
+/// DNS resolver of public-only webhooks: the address checked is the address connected to,
+/// so a name cannot resolve to a public address at registration and a private one later.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = resolve_public(name.as_str(), 0).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+impl Sink {
+    pub fn webhook(url: String) -> anyhow::Result<Self> {
+        Self::build_webhook(url, None, false)
+    }
+
+    /// A webhook to a client-supplied URL: signed with `secret`, and only sent to public
+    /// addresses.
+    pub fn signed_webhook(url: String, secret: Vec<u8>) -> anyhow::Result<Self> {
+        Self::build_webhook(url, Some(secret), true)
+    }
+
+    fn build_webhook(
+        url: String,
+        secret: Option<Vec<u8>>,
+        public_only: bool,
+    ) -> anyhow::Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .redirect(Policy::none());
+        if public_only {
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+        }
+        Ok(Sink::Webhook {
+            client: builder.build()?,
+            url,
+            secret,
+            public_only,
+        })
+    }
+
+    pub async fn emit(&self, event: &Event) -> anyhow::Result<()> {
+        match self {
+            Sink::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                serde_json::to_writer(&mut stdout, event)?;
+                stdout.write_all(b"\n")?;
+                Ok(())
+            }
+            Sink::Webhook {
+                client,
+                url,
+                secret,
+                public_only,
+            } => {
+                // Host names are checked by the resolver; this catches IP literals.
+                if *public_only {
+                    ensure_public(&Url::parse(url)?).await.map_err(|e| {
+                        anyhow::anyhow!("Webhook delivery to {} refused: {}", url, e)
+                    })?;
+                }
+                let body = serde_json::to_vec(event)?;
+                let signature = match secret {
+                    Some(secret) => {
+                        let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                            .map_err(|e| anyhow::anyhow!("Invalid webhook secret: {}", e))?;
+                        mac.update(&body);
+                        Some(format!(
+                            "sha256={}",
+                            hex::encode(mac.finalize().into_bytes())
+                        ))
+                    }
+                    None => None,
+                };
+                let mut backoff = WEBHOOK_INITIAL_BACKOFF;
+                let mut attempt = 1;
+                loop {
+                    let mut request = client
+                        .post(url)
+                        .header(CONTENT_TYPE, "application/json")
+                        .body(body.clone());
+                    if let Some(signature) = &signature {
+                        request = request.header(SIGNATURE_HEADER, signature);
+                    }
+                    let result = request
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status());
+                    match result {
+                        Ok(_) => return Ok(()),
+                        Err(e) if attempt < WEBHOOK_ATTEMPTS => {
+                            tracing::warn!(
+                                "Webhook delivery to {} failed (attempt {}/{}): {}",
+                                url,
+                                attempt,
+                                WEBHOOK_ATTEMPTS,
+                                e
+                            );
+                            sleep(backoff).await;
+                            backoff *= 2;
+                            attempt += 1;
+                        }
+                        Err(e) => {
+                            return Err(anyhow::anyhow!(
+                                "Webhook delivery to {} failed: {}",
+                                url,
+                                e
+                            ))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["1.1.1.1", "8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn literal_internal_urls_are_refused() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "https://[::1]/hook",
+            "http://localhost/hook",
+        ] {
+            assert!(
+                ensure_public(&Url::parse(url).unwrap()).await.is_err(),
+                "{}",
+                url
+            );
+        }
+        assert!(ensure_public(&Url::parse("https://1.1.1.1/hook").unwrap())
+            .await
+            .is_ok());
+    }
+}
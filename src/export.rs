@@ -0,0 +1,267 @@
+//! CSV and NDJSON exports of long lists.
+//!
+//! Endpoints whose lists can run to thousands of rows take `?format=csv` or `?format=ndjson`
+//! besides the default JSON. Exports are not paged: a producer task sends rows over a channel
+//! and the response body encodes them as they arrive, so a download starts right away and
+//! memory stays flat. Streamed bodies have no known length, which keeps them out of the ETag,
+//! signing and reformatting layers. If the producer fails halfway, the body ends with an
+//! error and the client sees a truncated transfer rather than a short file that looks whole.
+//!
+//! CSV fields are ids, addresses and numbers, none of which need quoting.
+//!
+//! The `export` subcommand writes the same rows straight from the index to stdout or a file,
+//! without a running explorer.
+
+use crate::address_data::AddressHistoryEntry;
+use crate::config::Config;
+use crate::index::Index;
+use crate::sampler::now_ms;
+use crate::{error_response, units, AppState};
+use axum::{
+    body::{Body, Bytes},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use kaspa_addresses::Address;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+// Rows buffered between the producer and a slow client.
+const CHANNEL_ROWS: usize = 256;
+// Rows read from the index per query by the subcommand.
+const PAGE_SIZE: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+/// Parses `?format=`; the export formats are refused with 404 when `features.export` is off.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+pub fn parse_format(state: &AppState, format: Option<&str>) -> Result<Format, Response> {
+    let format = match format.unwrap_or("json") {
+        "json" => return Ok(Format::Json),
+        "csv" => Format::Csv,
+        "ndjson" => Format::Ndjson,
+        _ => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "format must be json, csv or ndjson",
+            ))
+        }
+    };
+    if !state.config.features.export {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "Exports are disabled (features.export)",
+        ));
+    }
+    Ok(format)
+}
+
+/// A row of an export.
+pub trait Row: Serialize + Send + 'static {
+    const CSV_HEADER: &'static str;
+    fn csv_line(&self) -> String;
+}
+
+/// Starts a download named `filename` (without extension) in `format`, which must be an
+/// export format. Rows sent on the returned channel make up the body; send an error to abort
+/// it, and drop the sender to finish.
+pub fn stream<T: Row>(
+    format: Format,
+    filename: &str,
+) -> (mpsc::Sender<anyhow::Result<T>>, Response) {
+    let (sender, receiver) = mpsc::channel::<anyhow::Result<T>>(CHANNEL_ROWS);
+    let (content_type, extension) = match format {
+        Format::Csv => ("text/csv; charset=utf-8", "csv"),
+        _ => ("application/x-ndjson", "ndjson"),
+    };
+    let header_line =
+        (format == Format::Csv).then(|| Ok(Bytes::from(format!("{}\n", T::CSV_HEADER))));
+    // The body is polled outside the request task, so the request's units are carried over.
+    let units = units::current();
+    let lines = ReceiverStream::new(receiver).map(move |row| match row {
+        Ok(row) if format == Format::Csv => Ok(Bytes::from(row.csv_line() + "\n")),
+        Ok(row) => units::scoped(units, || serde_json::to_vec(&row))
+            .map(|mut line| {
+                line.push(b'\n');
+                Bytes::from(line)
+            })
+            .map_err(std::io::Error::other),
+        Err(e) => {
+            tracing::warn!("Export aborted: {:?}", e);
+            Err(std::io::Error::other(e.to_string()))
+        }
+    });
+    let body = Body::from_stream(tokio_stream::iter(header_line).chain(lines));
+    let response = (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", filename, extension),
+            ),
+        ],
+        body,
+    )
+        .into_response();
+    (sender, response)
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    target: ExportTarget,
+
+    /// csv or ndjson
+    #[arg(long, default_value = "csv", global = true)]
+    format: String,
+
+    /// Write to this file instead of stdout
+    #[arg(long, short, global = true)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum ExportTarget {
+    /// Transactions that paid an address or spent its outputs, newest first
+    Transactions { address: String },
+    /// Blocks with timestamps in a range, oldest first
+    Blocks {
+        /// Start of the range, Unix time in milliseconds (inclusive)
+        #[arg(long)]
+        from: u64,
+        /// End of the range, Unix time in milliseconds (inclusive; default now)
+        #[arg(long)]
+        to: Option<u64>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct BlockRow {
+    hash: String,
+    daa_score: u64,
+    blue_score: u64,
+    timestamp: u64,
+    transactions: usize,
+}
+
+impl Row for BlockRow {
+    const CSV_HEADER: &'static str = "hash,daa_score,blue_score,timestamp,transactions";
+
+    fn csv_line(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.hash, self.daa_score, self.blue_score, self.timestamp, self.transactions
+        )
+    }
+}
+
+fn write_row<T: Row>(out: &mut dyn Write, format: Format, row: &T) -> anyhow::Result<()> {
+    match format {
+        Format::Csv => writeln!(out, "{}", row.csv_line())?,
+        _ => {
+            serde_json::to_writer(&mut *out, row)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn run(args: ExportArgs, config: &Config) -> anyhow::Result<()> {
+    let format = match args.format.as_str() {
+        "csv" => Format::Csv,
+        "ndjson" => Format::Ndjson,
+        other => anyhow::bail!("--format must be csv or ndjson, not {}", other),
+    };
+    let index = Index::open(&config.index.path)?;
+    if index.current_epoch_id().is_none() {
+        anyhow::bail!(
+            "The index at {} has no blocks yet",
+            config.index.path.display()
+        );
+    }
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", path.display(), e))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+
+    let mut rows = 0u64;
+    match args.target {
+        ExportTarget::Transactions { address } => {
+            let address = Address::try_from(address.as_str())
+                .map_err(|_| anyhow::anyhow!("Invalid address {}", address))?
+                .to_string();
+            if format == Format::Csv {
+                writeln!(out, "{}", AddressHistoryEntry::CSV_HEADER)?;
+            }
+            let mut before = None;
+            loop {
+                let page = index
+                    .address_transactions(address.clone(), before.take(), PAGE_SIZE)
+                    .await?;
+                let done = page.len() < PAGE_SIZE;
+                before = page.last().map(|tx| (tx.daa_score, tx.tx_id.clone()));
+                for tx in page {
+                    write_row(&mut out, format, &AddressHistoryEntry::from(tx))?;
+                    rows += 1;
+                }
+                if done {
+                    break;
+                }
+            }
+            if index.address_history_truncated(address.clone()).await? {
+                tracing::warn!(
+                    "{} has older activity in the cold tier, which is not exported",
+                    address
+                );
+            }
+        }
+        ExportTarget::Blocks { from, to } => {
+            let to = to.unwrap_or_else(now_ms);
+            if from > to {
+                anyhow::bail!("--from ({}) is after --to ({})", from, to);
+            }
+            if format == Format::Csv {
+                writeln!(out, "{}", BlockRow::CSV_HEADER)?;
+            }
+            let mut after = None;
+            loop {
+                let page = index
+                    .blocks_in_range(from, to, true, after.take(), PAGE_SIZE)
+                    .await?;
+                let done = page.len() < PAGE_SIZE;
+                after = page
+                    .last()
+                    .map(|block| (block.timestamp, block.hash.clone()));
+                for block in page {
+                    let row = BlockRow {
+                        transactions: block.transaction_ids.len(),
+                        hash: block.hash,
+                        daa_score: block.daa_score,
+                        blue_score: block.blue_score,
+                        timestamp: block.timestamp,
+                    };
+                    write_row(&mut out, format, &row)?;
+                    rows += 1;
+                }
+                if done {
+                    break;
+                }
+            }
+        }
+    }
+    out.flush()?;
+    tracing::info!("Exported {} row(s)", rows);
+    Ok(())
+}
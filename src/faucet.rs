@@ -0,0 +1,516 @@
+//! Testnet faucet.
+//!
+//! With the faucet feature, `POST /api/faucet` sends `[faucet] amount` to the requested
+//! address from a configured private key: the explorer selects the key's UTXOs, signs a
+//! transaction and submits it through the connected node. Each client IP and each address
+//! gets one drip per interval, and a captcha can be required on top. Drips are serialized, and
+//! outpoints spent by a drip stay reserved until they leave the UTXO set, so back-to-back
+//! drips never double spend before the first is accepted; a reservation lapses after
+//! `RESERVATION_TTL`, in case the node dropped the drip. The fee and the committed storage
+//! mass come from the consensus mass calculator. Rate-limit state and the drip history live
+//! in memory and start over on restart.
+
+use crate::chain::ChainSource;
+use crate::config::FaucetConfig;
+use crate::rate_limit::client_ip;
+use crate::sampler::now_ms;
+use crate::{error_response, network, not_connected, retry_after_response, rpc, AppState};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, Json, Response},
+};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_consensus_core::mass::MassCalculator;
+use kaspa_consensus_core::sign::sign_with_multiple_v2;
+use kaspa_consensus_core::subnets::SUBNETWORK_ID_NATIVE;
+use kaspa_consensus_core::tx::{
+    PopulatedTransaction, ScriptPublicKey, SignableTransaction, Transaction, TransactionInput,
+    TransactionOutpoint, TransactionOutput, UtxoEntry,
+};
+use kaspa_rpc_core::{RpcTransaction, RpcTransactionOutpoint, RpcUtxosByAddressesEntry};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+const HISTORY_LENGTH: usize = 50;
+const MAX_INPUTS: usize = 80;
+const CAPTCHA_TIMEOUT: Duration = Duration::from_secs(10);
+// Change below this is left to the miner: tiny outputs weigh heavily in storage mass (KIP-9).
+const MIN_CHANGE: u64 = 100_000_000;
+// A Schnorr P2PK signature script: one push of the signature and its sighash type.
+const SIGNATURE_SCRIPT_LEN: usize = 66;
+// Long enough for a drip to be accepted or dropped by the node.
+const RESERVATION_TTL: Duration = Duration::from_secs(600);
+
+pub struct Faucet {
+    key: [u8; 32],
+    public_key: [u8; 32], // x-only Schnorr key the funds are paid to
+    captcha: Option<reqwest::Client>,
+    limits: Mutex<Limits>,
+    history: Mutex<VecDeque<Drip>>,
+    // Held from UTXO selection to submission; outpoints spent by recent drips, with the time
+    // they were reserved.
+    sending: tokio::sync::Mutex<HashMap<RpcTransactionOutpoint, Instant>>,
+}
+
+#[derive(Default)]
+struct Limits {
+    by_ip: HashMap<IpAddr, Instant>,
+    by_address: HashMap<String, Instant>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Drip {
+    transaction_id: String,
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64,
+    timestamp: u64, // Unix ms
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FaucetRequest {
+    address: String,
+    captcha: Option<String>, // Widget response token, when a captcha is configured
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FaucetInfo {
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64, // Per drip
+    ip_interval_secs: u64,
+    address_interval_secs: u64,
+    captcha_site_key: Option<String>,
+    captcha_script_url: Option<String>,
+    drips: Vec<Drip>, // Newest first, since the explorer started
+}
+
+#[derive(Debug, Deserialize)]
+struct CaptchaVerdict {
+    success: bool,
+}
+
+impl Faucet {
+    /// Reads a 32-byte secp256k1 private key stored as hex.
+    pub fn load(path: &Path, config: &FaucetConfig) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read faucet key {}: {}", path.display(), e))?;
+        let key: [u8; 32] = hex::decode(contents.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Faucet key {} must be 64 hex characters", path.display())
+            })?;
+        let keypair = secp256k1::Keypair::from_seckey_slice(secp256k1::SECP256K1, &key)
+            .map_err(|e| anyhow::anyhow!("Invalid faucet key {}: {}", path.display(), e))?;
+        if config.captcha_verify_url.is_some() != config.captcha_secret.is_some() {
+            anyhow::bail!("[faucet] captcha_verify_url and captcha_secret must be set together");
+        }
+        let captcha = match &config.captcha_verify_url {
+            Some(_) => Some(
+                reqwest::Client::builder()
+                    .timeout(CAPTCHA_TIMEOUT)
+                    .build()?,
+            ),
+            None => None,
+        };
+        Ok(Faucet {
+            key,
+            public_key: keypair.x_only_public_key().0.serialize(),
+            captcha,
+            limits: Mutex::default(),
+            history: Mutex::default(),
+            sending: tokio::sync::Mutex::default(),
+        })
+    }
+
+    pub fn address(&self, prefix: Prefix) -> Address {
+        Address::new(prefix, Version::PubKey, &self.public_key)
+    }
+
+    /// Time left before `ip` or `address` may get another drip.
+    fn wait_time(&self, config: &FaucetConfig, ip: IpAddr, address: &str) -> Option<Duration> {
+        let limits = self.limits.lock().unwrap_or_else(|e| e.into_inner());
+        let left = |last: Option<&Instant>, interval: u64| {
+            last.and_then(|last| Duration::from_secs(interval).checked_sub(last.elapsed()))
+        };
+        left(limits.by_ip.get(&ip), config.ip_interval_secs)
+            .into_iter()
+            .chain(left(
+                limits.by_address.get(address),
+                config.address_interval_secs,
+            ))
+            .max()
+    }
+
+    fn record(&self, config: &FaucetConfig, ip: IpAddr, drip: Drip) {
+        let now = Instant::now();
+        let mut limits = self.limits.lock().unwrap_or_else(|e| e.into_inner());
+        let ip_interval = Duration::from_secs(config.ip_interval_secs);
+        let address_interval = Duration::from_secs(config.address_interval_secs);
+        limits.by_ip.retain(|_, last| last.elapsed() < ip_interval);
+        limits
+            .by_address
+            .retain(|_, last| last.elapsed() < address_interval);
+        limits.by_ip.insert(ip, now);
+        limits.by_address.insert(drip.address.clone(), now);
+        drop(limits);
+
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        history.push_front(drip);
+        history.truncate(HISTORY_LENGTH);
+    }
+
+    async fn check_captcha(
+        &self,
+        config: &FaucetConfig,
+        token: Option<&str>,
+        ip: IpAddr,
+    ) -> Result<(), Response> {
+        let (Some(client), Some(url), Some(secret)) = (
+            &self.captcha,
+            &config.captcha_verify_url,
+            &config.captcha_secret,
+        ) else {
+            return Ok(());
+        };
+        let token = token
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| error_response(StatusCode::FORBIDDEN, "Captcha required"))?;
+        let ip = ip.to_string();
+        let verdict = async {
+            client
+                .post(url)
+                .form(&[
+                    ("secret", secret.as_str()),
+                    ("response", token),
+                    ("remoteip", ip.as_str()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<CaptchaVerdict>()
+                .await
+        }
+        .await
+        .map_err(|e| {
+            tracing::warn!("Captcha verification failed: {:?}", e);
+            error_response(StatusCode::BAD_GATEWAY, "Captcha verification failed")
+        })?;
+        if !verdict.success {
+            return Err(error_response(StatusCode::FORBIDDEN, "Captcha rejected"));
+        }
+        Ok(())
+    }
+}
+
+fn utxo_entry(entry: &RpcUtxosByAddressesEntry) -> UtxoEntry {
+    UtxoEntry::new(
+        entry.utxo_entry.amount,
+        entry.utxo_entry.script_public_key.clone(),
+        entry.utxo_entry.block_daa_score,
+        entry.utxo_entry.is_coinbase,
+    )
+}
+
+/// An unsigned transaction spending `inputs` into `outputs`. The signature scripts are
+/// placeholders of the signed size, so its mass is that of the signed transaction.
+fn transaction(
+    inputs: &[RpcUtxosByAddressesEntry],
+    outputs: Vec<TransactionOutput>,
+) -> Transaction {
+    Transaction::new(
+        0,
+        inputs
+            .iter()
+            .map(|entry| {
+                let outpoint =
+                    TransactionOutpoint::new(entry.outpoint.transaction_id, entry.outpoint.index);
+                TransactionInput::new(outpoint, vec![0; SIGNATURE_SCRIPT_LEN], 0, 1)
+            })
+            .collect(),
+        outputs,
+        0,
+        SUBNETWORK_ID_NATIVE,
+        0,
+        vec![],
+    )
+}
+
+/// Storage mass of `tx` and its fee: the larger of its compute and storage mass at the
+/// minimum relay fee of 1 sompi per gram. None if the storage mass overflows.
+fn storage_mass_and_fee(
+    calculator: &MassCalculator,
+    tx: &Transaction,
+    inputs: &[RpcUtxosByAddressesEntry],
+) -> Option<(u64, u64)> {
+    let compute_mass = calculator.calc_non_contextual_masses(tx).compute_mass;
+    let entries = inputs.iter().map(utxo_entry).collect();
+    let storage_mass = calculator
+        .calc_contextual_masses(&PopulatedTransaction::new(tx, entries))?
+        .storage_mass;
+    Some((storage_mass, compute_mass.max(storage_mass)))
+}
+
+/// Picks the largest UTXOs until they cover `amount` and the fee, and builds the unsigned
+/// transaction paying `recipient`, with its storage mass committed. Change is returned to
+/// `change_to` unless it is too small to be worth an output.
+fn build(
+    calculator: &MassCalculator,
+    mut candidates: Vec<RpcUtxosByAddressesEntry>,
+    amount: u64,
+    recipient: &ScriptPublicKey,
+    change_to: &ScriptPublicKey,
+) -> Option<(Transaction, Vec<RpcUtxosByAddressesEntry>)> {
+    candidates.sort_by_key(|entry| Reverse(entry.utxo_entry.amount));
+    candidates.truncate(MAX_INPUTS);
+    let payment = TransactionOutput::new(amount, recipient.clone());
+    let mut total = 0u64;
+    for count in 1..=candidates.len() {
+        total = total.checked_add(candidates[count - 1].utxo_entry.amount)?;
+        let inputs = &candidates[..count];
+        // Larger change only lowers the storage mass, so the fee with MIN_CHANGE covers it.
+        let probe = transaction(
+            inputs,
+            vec![
+                payment.clone(),
+                TransactionOutput::new(MIN_CHANGE, change_to.clone()),
+            ],
+        );
+        let change = storage_mass_and_fee(calculator, &probe, inputs)
+            .and_then(|(_, fee)| total.checked_sub(amount)?.checked_sub(fee))
+            .filter(|&change| change >= MIN_CHANGE);
+        let outputs = match change {
+            Some(change) => vec![
+                payment.clone(),
+                TransactionOutput::new(change, change_to.clone()),
+            ],
+            None => vec![payment.clone()],
+        };
+        let tx = transaction(inputs, outputs);
+        let Some((storage_mass, fee)) = storage_mass_and_fee(calculator, &tx, inputs) else {
+            continue;
+        };
+        let sent: u64 = tx.outputs.iter().map(|output| output.value).sum();
+        if total.checked_sub(sent).is_some_and(|paid| paid >= fee) {
+            tx.set_mass(storage_mass);
+            return Some((tx, inputs.to_vec()));
+        }
+    }
+    None
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn faucet(state: &AppState) -> Result<&Faucet, Response> {
+    state
+        .faucet
+        .as_deref()
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "The faucet is not enabled"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/faucet",
+    tag = "faucet",
+    responses(
+        (status = 200, description = "Faucet address, balance, drip rules and recent drips", body = FaucetInfo),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_faucet(State(state): State<AppState>) -> Result<Json<FaucetInfo>, Response> {
+    let faucet = faucet(&state)?;
+    let config = &state.config.faucet;
+    let address = faucet.address(state.network.prefix());
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let balance = rpc::retried("get_balance_by_address", || {
+        client.get_balance_by_address(address.clone())
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get the faucet balance: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch the faucet balance (is --utxoindex enabled?)",
+        )
+    })?;
+    drop(client_guard);
+    let drips = faucet
+        .history
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect();
+    Ok(Json(FaucetInfo {
+        address: address.to_string(),
+        balance,
+        amount: config.amount,
+        ip_interval_secs: config.ip_interval_secs,
+        address_interval_secs: config.address_interval_secs,
+        captcha_site_key: config.captcha_site_key.clone(),
+        captcha_script_url: config.captcha_script_url.clone(),
+        drips,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/faucet",
+    tag = "faucet",
+    request_body = FaucetRequest,
+    responses(
+        (status = 200, description = "Submitted drip", body = Drip),
+        (status = 400, description = "Invalid address or an address of another network", body = ErrorResponse),
+        (status = 403, description = "Captcha missing or rejected", body = ErrorResponse),
+        (status = 429, description = "This IP or address got a drip too recently", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, or the faucet is out of funds", body = ErrorResponse)
+    )
+)]
+pub async fn post_faucet(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<FaucetRequest>,
+) -> Result<Json<Drip>, Response> {
+    let faucet = faucet(&state)?;
+    let config = &state.config.faucet;
+    let recipient = network::parse_address(&state, request.address.trim())?;
+    let address = recipient.to_string();
+    let ip = client_ip(&headers, peer, state.config.rate_limit.forwarded_hops());
+    let too_soon = |wait: Duration| {
+        retry_after_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "The faucet already paid this IP or address recently",
+            wait,
+        )
+    };
+    if let Some(wait) = faucet.wait_time(config, ip, &address) {
+        return Err(too_soon(wait));
+    }
+    faucet
+        .check_captcha(config, request.captcha.as_deref(), ip)
+        .await?;
+
+    let mut sending = faucet.sending.lock().await;
+    // Checked again: a concurrent request for the same IP or address may have just finished.
+    if let Some(wait) = faucet.wait_time(config, ip, &address) {
+        return Err(too_soon(wait));
+    }
+    let own_address = faucet.address(state.network.prefix());
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let virtual_daa_score = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get block DAG info: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get block DAG info",
+            )
+        })?
+        .virtual_daa_score;
+    // Coinbase outputs (when the faucet key is mined to) are spendable this many DAA scores later.
+    let coinbase_maturity = state
+        .network
+        .params()
+        .coinbase_maturity()
+        .get(virtual_daa_score);
+    let entries = rpc::retried("get_utxos_by_addresses", || {
+        client.get_utxos_by_addresses(vec![own_address.clone()])
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get the faucet UTXOs: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch the faucet UTXOs (is --utxoindex enabled?)",
+        )
+    })?;
+    // Reserved outpoints that left the UTXO set were spent; the rest stay off limits until
+    // their reservation lapses.
+    let unspent: HashSet<&RpcTransactionOutpoint> =
+        entries.iter().map(|entry| &entry.outpoint).collect();
+    sending.retain(|outpoint, reserved| {
+        unspent.contains(outpoint) && reserved.elapsed() < RESERVATION_TTL
+    });
+    let candidates: Vec<RpcUtxosByAddressesEntry> = entries
+        .iter()
+        .filter(|entry| !sending.contains_key(&entry.outpoint))
+        .filter(|entry| {
+            !entry.utxo_entry.is_coinbase
+                || entry.utxo_entry.block_daa_score + coinbase_maturity <= virtual_daa_score
+        })
+        .cloned()
+        .collect();
+    let calculator = MassCalculator::new_with_consensus_params(&state.network.params());
+    let (tx, inputs) = build(
+        &calculator,
+        candidates,
+        config.amount,
+        &kaspa_txscript::pay_to_address_script(&recipient),
+        &kaspa_txscript::pay_to_address_script(&own_address),
+    )
+    .ok_or_else(|| {
+        tracing::warn!("Faucet {} cannot cover a drip", own_address);
+        error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The faucet is out of funds",
+        )
+    })?;
+    let utxos = inputs.iter().map(utxo_entry).collect();
+    let signed = sign_with_multiple_v2(SignableTransaction::with_entries(tx, utxos), &[faucet.key])
+        .fully_signed()
+        .map_err(|e| {
+            tracing::error!("Failed to sign a faucet transaction: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to sign the transaction",
+            )
+        })?;
+    let transaction_id = rpc::traced(
+        "submit_transaction",
+        client.submit_transaction(RpcTransaction::from(&signed.tx), false),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Faucet transaction was rejected: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "The node rejected the faucet transaction",
+        )
+    })?;
+    drop(client_guard);
+    let reserved = Instant::now();
+    sending.extend(inputs.into_iter().map(|entry| (entry.outpoint, reserved)));
+
+    let drip = Drip {
+        transaction_id: transaction_id.to_string(),
+        address,
+        amount: config.amount,
+        timestamp: now_ms(),
+    };
+    tracing::info!(
+        "Faucet sent {} sompi to {} in {}",
+        drip.amount,
+        drip.address,
+        drip.transaction_id
+    );
+    // Recorded before releasing `sending`, so a request waiting on it sees this drip's limits.
+    faucet.record(config, ip, drip.clone());
+    drop(sending);
+    Ok(Json(drip))
+}
+
+pub async fn page() -> Html<&'static str> {
+    Html(include_str!("../static/faucet.html"))
+}
@@ -0,0 +1,138 @@
+//! Mempool fee-rate histogram, for picking a competitive fee under load.
+//!
+//! Transaction pool entries (orphans cannot be mined yet) are bucketed by fee rate in sompi
+//! per gram of mass, highest rate first. A bucket's cumulative mass counts everything at or
+//! above its lower bound: what miners would take before a new transaction paying that rate,
+//! which `blocks_ahead` expresses in full blocks.
+
+use crate::chain::ChainSource;
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// Lower bounds in sompi/gram; the last bucket is open-ended.
+const BUCKET_BOUNDS: [f64; 21] = [
+    0.0, 1.0, 2.0, 3.0, 5.0, 8.0, 10.0, 15.0, 20.0, 30.0, 50.0, 75.0, 100.0, 150.0, 200.0, 300.0,
+    500.0, 1_000.0, 2_000.0, 5_000.0, 10_000.0,
+];
+// Consensus mass limit of a block.
+pub const BLOCK_MASS_LIMIT: u64 = 500_000;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeeBucket {
+    min_feerate: f64,
+    max_feerate: Option<f64>, // Exclusive; None for the top bucket
+    count: usize,
+    mass: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    fees: u64,
+    cumulative_count: usize,
+    cumulative_mass: u64,
+    blocks_ahead: f64, // cumulative_mass / block mass limit
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeeHistogramResponse {
+    count: usize,
+    mass: u64,
+    min_feerate: Option<f64>,
+    median_feerate: Option<f64>, // Mass-weighted
+    max_feerate: Option<f64>,
+    buckets: Vec<FeeBucket>, // Highest rate first, empty buckets omitted
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/mempool/fee-histogram",
+    tag = "mempool",
+    responses(
+        (status = 200, description = "Mempool transactions bucketed by fee rate with counts and cumulative mass", body = FeeHistogramResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_fee_histogram(
+    State(state): State<AppState>,
+) -> Result<Json<FeeHistogramResponse>, Response> {
+    let entries = {
+        let client_guard = state.client.read().await;
+        let client = client_guard.as_ref().ok_or_else(not_connected)?;
+        // (include_orphan_pool=false, filter_transaction_pool=false) => transaction pool
+        rpc::retried("get_mempool_entries", || {
+            client.get_mempool_entries(false, false)
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get mempool entries: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch mempool entries",
+            )
+        })?
+    };
+
+    // (feerate, mass, fee), highest rate first.
+    let mut rates: Vec<(f64, u64, u64)> = entries
+        .iter()
+        .map(|entry| {
+            let mass = entry
+                .transaction
+                .verbose_data
+                .as_ref()
+                .map(|v| v.compute_mass)
+                .filter(|mass| *mass > 0)
+                .unwrap_or(entry.transaction.mass);
+            let feerate = if mass > 0 {
+                entry.fee as f64 / mass as f64
+            } else {
+                0.0
+            };
+            (feerate, mass, entry.fee)
+        })
+        .collect();
+    rates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let total_mass: u64 = rates.iter().map(|(_, mass, _)| mass).sum();
+
+    let mut buckets: Vec<FeeBucket> = Vec::new();
+    let (mut cumulative_count, mut cumulative_mass) = (0, 0);
+    let mut median_feerate = None;
+    let mut rest = rates.as_slice();
+    for (i, &min_feerate) in BUCKET_BOUNDS.iter().enumerate().rev() {
+        let split = rest.partition_point(|(feerate, _, _)| *feerate >= min_feerate);
+        let (bucket, below) = rest.split_at(split);
+        rest = below;
+        for &(feerate, mass, _) in bucket {
+            if median_feerate.is_none() && (cumulative_mass + mass) * 2 >= total_mass {
+                median_feerate = Some(feerate);
+            }
+            cumulative_count += 1;
+            cumulative_mass += mass;
+        }
+        if bucket.is_empty() {
+            continue;
+        }
+        buckets.push(FeeBucket {
+            min_feerate,
+            max_feerate: BUCKET_BOUNDS.get(i + 1).copied(),
+            count: bucket.len(),
+            mass: bucket.iter().map(|(_, mass, _)| mass).sum(),
+            fees: bucket.iter().map(|(_, _, fee)| fee).sum(),
+            cumulative_count,
+            cumulative_mass,
+            blocks_ahead: cumulative_mass as f64 / BLOCK_MASS_LIMIT as f64,
+        });
+    }
+
+    Ok(Json(FeeHistogramResponse {
+        count: rates.len(),
+        mass: total_mass,
+        min_feerate: rates.last().map(|(feerate, _, _)| *feerate),
+        median_feerate,
+        max_feerate: rates.first().map(|(feerate, _, _)| *feerate),
+        buckets,
+    }))
+}
@@ -0,0 +1,114 @@
+//! Atom feed of the latest blocks, at `/feed.xml`.
+//!
+//! Rendered from the homepage's block cache (the last blocks kaspad announced), so following
+//! testnet activity in a feed reader or a chat-ops bot costs no RPCs. Entries link to the
+//! block's API resource; links are absolute, built from the request's `Host` header (and
+//! `X-Forwarded-Proto` behind a trusted proxy).
+
+use crate::home::{self, HomeBlock};
+use crate::sampler::now_ms;
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+
+const CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats Unix ms as an RFC 3339 UTC timestamp, e.g. `2024-06-10T06:13:20.000Z`.
+fn rfc3339(ms: u64) -> String {
+    let seconds = ms / 1000;
+    let (days, rest) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rest / 3_600,
+        rest % 3_600 / 60,
+        rest % 60,
+        ms % 1000
+    )
+}
+
+fn base_url(headers: &HeaderMap, trust_forwarded_for: bool) -> String {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = trust_forwarded_for
+        .then(|| headers.get("x-forwarded-proto"))
+        .flatten()
+        .and_then(|v| v.to_str().ok())
+        .filter(|proto| *proto == "https")
+        .unwrap_or("http");
+    format!("{}://{}", scheme, host)
+}
+
+fn entry(base: &str, network: &str, block: &HomeBlock) -> String {
+    let miner = block.miner.as_deref().unwrap_or("unknown");
+    format!(
+        "  <entry>\n    <title>Block {} at DAA score {}</title>\n    <id>urn:kaspa:{}:block:{}</id>\n    <updated>{}</updated>\n    <link href=\"{}/api/v1/block/{}\"/>\n    <summary>DAA score {}, blue score {}, {} transactions, mined by {}</summary>\n  </entry>\n",
+        block.hash,
+        block.daa_score,
+        network,
+        block.hash,
+        rfc3339(block.timestamp),
+        base,
+        block.hash,
+        block.daa_score,
+        block.blue_score,
+        block.tx_count,
+        escape(miner),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/feed.xml",
+    tag = "blocks",
+    responses(
+        (status = 200, description = "Atom feed of the latest blocks", body = String, content_type = "application/atom+xml")
+    )
+)]
+pub async fn get_feed(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let blocks = home::recent_blocks(&state).await;
+    let network = escape(&state.network_info.read().await.network);
+    let base = escape(&base_url(
+        &headers,
+        state.config.rate_limit.trust_forwarded_for,
+    ));
+    // An empty feed still needs an `updated`; use when the explorer started.
+    let updated = blocks.first().map_or_else(
+        || now_ms().saturating_sub(state.started_at.elapsed().as_millis() as u64),
+        |block| block.timestamp,
+    );
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <title>Kaspa {} blocks</title>\n  <id>urn:kaspa:{}:blocks</id>\n  <updated>{}</updated>\n  <link rel=\"self\" href=\"{}/feed.xml\"/>\n  <link href=\"{}/\"/>\n  <author><name>Kaspa {} Explorer</name></author>\n",
+        network, network, rfc3339(updated), base, base, network
+    );
+    for block in &blocks {
+        xml.push_str(&entry(&base, &network, block));
+    }
+    xml.push_str("</feed>\n");
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], xml).into_response()
+}
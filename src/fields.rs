@@ -0,0 +1,91 @@
+//! Sparse fieldsets.
+//!
+//! `?fields=a,b` keeps only the named top-level fields of a JSON response (of each element,
+//! when the response is an array), so a client that needs a few fields of a large response
+//! does not download the rest. Unknown names are ignored and error responses are left whole.
+//! The layer runs inside signing and ETag handling, so both cover the filtered body. For an
+//! NDJSON list, keep the list field itself among the selected fields.
+
+use crate::{error_response, AppState};
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Query, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+#[derive(Debug, Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+fn select(value: &mut Value, fields: &HashSet<&str>) {
+    match value {
+        Value::Object(object) => object.retain(|key, _| fields.contains(key.as_str())),
+        Value::Array(items) => items.iter_mut().for_each(|item| select(item, fields)),
+        _ => {}
+    }
+}
+
+pub async fn select_fields(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requested = Query::<FieldsQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(query)| query.fields);
+    let response = next.run(request).await;
+    let Some(requested) = requested else {
+        return response;
+    };
+    let fields: HashSet<&str> = requested
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+    if fields.is_empty() || response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let max = state.config.limits.max_response_bytes;
+    let bounded = body
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len as usize <= max);
+    if !is_json || !bounded {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match axum::body::to_bytes(body, max).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for field selection: {:?}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to buffer the response",
+            );
+        }
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    select(&mut value, &fields);
+    match serde_json::to_vec(&value) {
+        Ok(selected) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(selected))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
@@ -0,0 +1,199 @@
+//! Bloom-filter matching for light clients.
+//!
+//! A client builds a Bloom filter over the elements it cares about (its addresses, and the
+//! outpoints it owns as `<txid>:<index>`), and asks which indexed transactions in a DAA score
+//! range touch any of them. False positives are expected and give the client some privacy.
+//!
+//! Filter construction: a bit array of `8 * filter.len()` bits (bit `n` is
+//! `filter[n / 8] & (1 << (n % 8))`). Element `e` sets bit
+//! `u64_le(sha256(tweak_le32 || i_le32 || e)[..8]) % bits` for each `i` in `0..hash_functions`.
+
+use crate::{cold, error_response, require_index, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashSet};
+use utoipa::ToSchema;
+
+// Same bounds as BIP37 filters.
+const MAX_FILTER_BYTES: usize = 36_000;
+const MAX_HASH_FUNCTIONS: u32 = 50;
+// One day of DAA scores at 10 per second.
+const MAX_DAA_RANGE: u64 = 864_000;
+const MAX_MATCHES: usize = 10_000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FilterMatchRequest {
+    filter: String, // Hex-encoded bit array
+    hash_functions: u32,
+    #[serde(default)]
+    tweak: u32,
+    from_daa_score: u64,
+    to_daa_score: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FilterMatchResponse {
+    from_daa_score: u64,
+    to_daa_score: u64,
+    // Highest DAA score the index covers; matches above it are not reported yet.
+    indexed_daa_score: Option<u64>,
+    transaction_ids: Vec<String>,
+    truncated: bool,
+    total_available: usize,
+}
+
+struct BloomFilter {
+    bits: Vec<u8>,
+    hash_functions: u32,
+    tweak: u32,
+}
+
+impl BloomFilter {
+    fn contains(&self, element: &str) -> bool {
+        let bit_count = self.bits.len() as u64 * 8;
+        (0..self.hash_functions).all(|i| {
+            let digest = Sha256::new()
+                .chain_update(self.tweak.to_le_bytes())
+                .chain_update(i.to_le_bytes())
+                .chain_update(element.as_bytes())
+                .finalize();
+            let bit =
+                u64::from_le_bytes(digest[..8].try_into().expect("digest is 32 bytes")) % bit_count;
+            self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+        })
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/filter/match",
+    tag = "transactions",
+    request_body = FilterMatchRequest,
+    responses(
+        (status = 200, description = "Transactions in the range matching the filter", body = FilterMatchResponse),
+        (status = 400, description = "Invalid filter or range", body = ErrorResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn match_filter(
+    State(state): State<AppState>,
+    Json(request): Json<FilterMatchRequest>,
+) -> Result<Json<FilterMatchResponse>, Response> {
+    let index = require_index(&state)?;
+
+    let bits = hex::decode(&request.filter)
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Filter must be hex-encoded"))?;
+    if bits.is_empty() || bits.len() > MAX_FILTER_BYTES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Filter must be between 1 and {} bytes", MAX_FILTER_BYTES),
+        ));
+    }
+    if request.hash_functions == 0 || request.hash_functions > MAX_HASH_FUNCTIONS {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "hash_functions must be between 1 and {}",
+                MAX_HASH_FUNCTIONS
+            ),
+        ));
+    }
+    if request.to_daa_score < request.from_daa_score
+        || request.to_daa_score - request.from_daa_score > MAX_DAA_RANGE
+    {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "DAA score range must be ascending and span at most {}",
+                MAX_DAA_RANGE
+            ),
+        ));
+    }
+
+    let filter = BloomFilter {
+        bits,
+        hash_functions: request.hash_functions,
+        tweak: request.tweak,
+    };
+    let (from, to) = (request.from_daa_score as i64, request.to_daa_score as i64);
+    let epoch = index.current_epoch_id().unwrap_or(0);
+    let cold_index = index.clone();
+
+    // Scan outputs (by address) and inputs (by spent outpoint) in the range; the filter is
+    // applied in Rust since SQLite cannot evaluate it.
+    let matches = index
+        .run(move |conn| {
+            let mut matches: BTreeSet<(i64, String)> = BTreeSet::new();
+            let mut outputs = conn.prepare_cached(
+                "SELECT tx_id, address, daa_score FROM outputs WHERE epoch = ?1 AND daa_score BETWEEN ?2 AND ?3 AND address IS NOT NULL",
+            )?;
+            let mut rows = outputs.query(params![epoch, from, to])?;
+            while let Some(row) = rows.next()? {
+                let address: String = row.get(1)?;
+                if filter.contains(&address) {
+                    matches.insert((row.get(2)?, row.get(0)?));
+                }
+            }
+            let mut inputs = conn.prepare_cached(
+                "SELECT tx_id, prev_tx_id, prev_idx, daa_score FROM inputs WHERE epoch = ?1 AND daa_score BETWEEN ?2 AND ?3",
+            )?;
+            let mut rows = inputs.query(params![epoch, from, to])?;
+            while let Some(row) = rows.next()? {
+                let outpoint = format!("{}:{}", row.get::<_, String>(1)?, row.get::<_, i64>(2)?);
+                if filter.contains(&outpoint) {
+                    matches.insert((row.get(3)?, row.get(0)?));
+                }
+            }
+            // Rows moved to the cold tier, one decompressed segment at a time.
+            let (from, to) = (from as u64, to as u64);
+            for start in cold::segments_in_range(conn, epoch, from, to)? {
+                let Some(segment) = cold_index.cold_segment(conn, epoch, start)? else {
+                    continue;
+                };
+                let in_range = |daa_score: u64| (from..=to).contains(&daa_score);
+                for output in segment.outputs.iter().filter(|o| in_range(o.daa_score)) {
+                    if output.address.as_ref().is_some_and(|a| filter.contains(a)) {
+                        matches.insert((output.daa_score as i64, output.tx_id.clone()));
+                    }
+                }
+                for input in segment.inputs.iter().filter(|i| in_range(i.daa_score)) {
+                    let outpoint = format!("{}:{}", input.prev_tx_id, input.prev_idx);
+                    if filter.contains(&outpoint) {
+                        matches.insert((input.daa_score as i64, input.tx_id.clone()));
+                    }
+                }
+            }
+            Ok(matches)
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Filter match query failed: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index query failed")
+        })?;
+
+    let indexed_daa_score = index.indexed_daa_score().await.ok().flatten();
+
+    // Ordered by DAA score, then id; a transaction seen in several blocks is listed once.
+    let mut seen = HashSet::new();
+    let transaction_ids: Vec<String> = matches
+        .into_iter()
+        .map(|(_, id)| id)
+        .filter(|id| seen.insert(id.clone()))
+        .collect();
+    let total_available = transaction_ids.len();
+
+    Ok(Json(FilterMatchResponse {
+        from_daa_score: request.from_daa_score,
+        to_daa_score: request.to_daa_score,
+        indexed_daa_score,
+        truncated: total_available > MAX_MATCHES,
+        total_available,
+        transaction_ids: transaction_ids.into_iter().take(MAX_MATCHES).collect(),
+    }))
+}
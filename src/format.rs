@@ -0,0 +1,142 @@
+//! Alternative wire formats for JSON responses.
+//!
+//! Responses are compact JSON by default. `?pretty=true` indents any JSON response for humans
+//! reading it with curl. List endpoints also honour `Accept: application/x-ndjson`, sending
+//! one list item per line for scripts that process items as they arrive; the response's
+//! other fields (totals, paging cursors) then move to an `X-List-Meta` header as compact JSON.
+//!
+//! With response signing enabled, bodies are always sent in the canonical form that is
+//! signed, so `?pretty` is ignored; NDJSON responses are not signed.
+
+use crate::errors::PROBLEM_JSON;
+use crate::{error_response, AppState};
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use std::future::{ready, Ready};
+
+const NDJSON: &str = "application/x-ndjson";
+pub const LIST_META_HEADER: HeaderName = HeaderName::from_static("x-list-meta");
+// Set by the signing layer for the JSON body, which NDJSON replaces.
+const SIGNATURE_HEADERS: [&str; 3] = ["x-content-digest", "x-signature", "x-signature-key"];
+
+/// Marks a response as a list endpoint's; the list is the named field of the JSON object,
+/// or the body itself when it is an array.
+#[derive(Clone, Copy)]
+struct ListField(&'static str);
+
+/// Response mapper for list routes, e.g. `get(handler).layer(map_response(list_of("blocks")))`.
+pub fn list_of(field: &'static str) -> impl Fn(Response) -> Ready<Response> + Clone {
+    move |mut response: Response| {
+        response.extensions_mut().insert(ListField(field));
+        // The representation depends on Accept, so caches must key on it.
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        ready(response)
+    }
+}
+
+/// Whether `content_type` is JSON, error problem documents included.
+pub fn is_json(content_type: &str) -> bool {
+    content_type.starts_with("application/json") || content_type.starts_with(PROBLEM_JSON)
+}
+
+pub async fn negotiate(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let pretty = request
+        .uri()
+        .query()
+        .is_some_and(|query| query.split('&').any(|pair| pair == "pretty=true"))
+        && state.signer.is_none();
+    let ndjson = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim().starts_with(NDJSON)));
+    let response = next.run(request).await;
+    let list_field = response.extensions().get::<ListField>().copied();
+    let ndjson = ndjson && list_field.is_some() && response.status() == StatusCode::OK;
+    if !pretty && !ndjson {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(is_json);
+    let max = state.config.limits.max_response_bytes;
+    let bounded = body
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len as usize <= max);
+    if !is_json || !bounded {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match axum::body::to_bytes(body, max).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for reformatting: {:?}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to buffer the response",
+            );
+        }
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let reformatted = match list_field.filter(|_| ndjson) {
+        Some(ListField(field)) => to_ndjson(value, field).map(|(lines, meta)| {
+            parts
+                .headers
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(NDJSON));
+            if let Some(meta) = meta.and_then(|m| HeaderValue::from_str(&m).ok()) {
+                parts.headers.insert(LIST_META_HEADER, meta);
+            }
+            for name in SIGNATURE_HEADERS {
+                parts.headers.remove(name);
+            }
+            lines
+        }),
+        None => serde_json::to_vec_pretty(&value).ok(),
+    };
+    match reformatted {
+        Some(reformatted) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(reformatted))
+        }
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+/// One line per list item, plus the object's remaining fields as compact JSON.
+fn to_ndjson(value: Value, field: &str) -> Option<(Vec<u8>, Option<String>)> {
+    let (items, meta) = match value {
+        Value::Array(items) => (items, None),
+        Value::Object(mut object) => match object.remove(field) {
+            Some(Value::Array(items)) => {
+                let meta = (!object.is_empty())
+                    .then(|| serde_json::to_string(&object).ok())
+                    .flatten();
+                (items, meta)
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let mut lines = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut lines, &item).ok()?;
+        lines.push(b'\n');
+    }
+    Some((lines, meta))
+}
@@ -0,0 +1,99 @@
+//! Peer geolocation from MaxMind GeoLite2 databases.
+//!
+//! Both databases are optional and read into memory once at startup; the city database gives
+//! country, city and coordinates, the ASN database the network operator. Private and unknown
+//! addresses have no location.
+
+use crate::config::GeoIpConfig;
+use maxminddb::{geoip2, Reader};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Location {
+    pub country_code: Option<String>, // ISO 3166-1 alpha-2
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub asn: Option<u32>,
+    pub as_organization: Option<String>,
+}
+
+pub struct GeoIp {
+    city: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+fn open(path: &Path) -> anyhow::Result<Reader<Vec<u8>>> {
+    let reader = Reader::open_readfile(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open GeoIP database {}: {}", path.display(), e))?;
+    tracing::info!(
+        "Loaded GeoIP database {} ({})",
+        path.display(),
+        reader.metadata.database_type
+    );
+    Ok(reader)
+}
+
+impl GeoIp {
+    /// Opens the configured databases; None when neither is configured.
+    pub fn load(config: &GeoIpConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        if config.city_db.is_none() && config.asn_db.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Arc::new(GeoIp {
+            city: config.city_db.as_deref().map(open).transpose()?,
+            asn: config.asn_db.as_deref().map(open).transpose()?,
+        })))
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<Location> {
+        let city = self
+            .city
+            .as_ref()
+            .and_then(|reader| reader.lookup::<geoip2::City>(ip).ok());
+        let asn = self
+            .asn
+            .as_ref()
+            .and_then(|reader| reader.lookup::<geoip2::Asn>(ip).ok());
+        if city.is_none() && asn.is_none() {
+            return None;
+        }
+        let english = |names: Option<&std::collections::BTreeMap<&str, &str>>| {
+            names
+                .and_then(|names| names.get("en"))
+                .map(|name| name.to_string())
+        };
+        let country = city.as_ref().and_then(|city| city.country.as_ref());
+        let location = city.as_ref().and_then(|city| city.location.as_ref());
+        Some(Location {
+            country_code: country.and_then(|c| c.iso_code).map(str::to_string),
+            country: english(country.and_then(|c| c.names.as_ref())),
+            city: english(
+                city.as_ref()
+                    .and_then(|city| city.city.as_ref())
+                    .and_then(|c| c.names.as_ref()),
+            ),
+            latitude: location.and_then(|l| l.latitude),
+            longitude: location.and_then(|l| l.longitude),
+            asn: asn.as_ref().and_then(|asn| asn.autonomous_system_number),
+            as_organization: asn
+                .as_ref()
+                .and_then(|asn| asn.autonomous_system_organization)
+                .map(str::to_string),
+        })
+    }
+
+    /// Looks up a peer address, `ip:port` or a bare IP.
+    pub fn lookup_address(&self, address: &str) -> Option<Location> {
+        let ip = match address.parse::<SocketAddr>() {
+            Ok(socket) => socket.ip(),
+            Err(_) => address.parse::<IpAddr>().ok()?,
+        };
+        self.lookup(ip)
+    }
+}
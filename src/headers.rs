@@ -0,0 +1,217 @@
+//! Compact block headers along the selected chain, for header-only (SPV-style) clients.
+//!
+//! Each header is serialized exactly as kaspad hashes it (see `serialize_header`), so a client
+//! can recompute the block hash from the bytes alone and check the parent links itself.
+
+use crate::chain::ChainSource;
+use crate::{error_response, not_connected, require_index, rpc, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcHeader;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 100;
+// Each header costs one get_block RPC.
+const MAX_LIMIT: usize = 500;
+// Blue score past the start block requested per header, and at least MIN_WINDOW in all, so
+// a window spans several chain blocks even though one can add up to k + 1 blue score. Every
+// chain block adds at least one, so kaspad returns a bounded number of chain hashes however
+// far the start block is behind the sink.
+const BLUE_SCORE_PER_HEADER: u64 = 20;
+const MIN_WINDOW: u64 = 1_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HeadersQuery {
+    /// First DAA score to return headers from
+    from_daa: u64,
+    /// Number of headers (default 100, max 500)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompactHeader {
+    hash: String,
+    daa_score: u64,
+    blue_score: u64,
+    header: String, // Hex of the serialization kaspad hashes
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HeadersResponse {
+    from_daa: u64,
+    headers: Vec<CompactHeader>,
+    // Pass as from_daa to continue; None once the sink is reached.
+    next_from_daa: Option<u64>,
+}
+
+/// version, parent levels, merkle roots, UTXO commitment, timestamp, bits, nonce, DAA score,
+/// blue score, blue work (big-endian, leading zeros trimmed) and pruning point, with
+/// little-endian integers and u64 length prefixes, as in consensus header hashing.
+pub fn serialize_header(header: &RpcHeader) -> Vec<u8> {
+    let mut out = Vec::with_capacity(256);
+    out.extend_from_slice(&header.version.to_le_bytes());
+    out.extend_from_slice(&(header.parents_by_level.len() as u64).to_le_bytes());
+    for level in &header.parents_by_level {
+        out.extend_from_slice(&(level.len() as u64).to_le_bytes());
+        for parent in level {
+            out.extend_from_slice(&parent.as_bytes());
+        }
+    }
+    out.extend_from_slice(&header.hash_merkle_root.as_bytes());
+    out.extend_from_slice(&header.accepted_id_merkle_root.as_bytes());
+    out.extend_from_slice(&header.utxo_commitment.as_bytes());
+    out.extend_from_slice(&header.timestamp.to_le_bytes());
+    out.extend_from_slice(&header.bits.to_le_bytes());
+    out.extend_from_slice(&header.nonce.to_le_bytes());
+    out.extend_from_slice(&header.daa_score.to_le_bytes());
+    out.extend_from_slice(&header.blue_score.to_le_bytes());
+    let blue_work = header.blue_work.to_be_bytes();
+    let start = blue_work
+        .iter()
+        .position(|&b| b != 0)
+        .unwrap_or(blue_work.len());
+    out.extend_from_slice(&((blue_work.len() - start) as u64).to_le_bytes());
+    out.extend_from_slice(&blue_work[start..]);
+    out.extend_from_slice(&header.pruning_point.as_bytes());
+    out
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/headers",
+    tag = "blocks",
+    params(HeadersQuery),
+    responses(
+        (status = 200, description = "Serialized selected-chain headers in ascending order", body = HeadersResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 404, description = "No indexed block at or above from_daa", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, or index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_headers(
+    State(state): State<AppState>,
+    Query(query): Query<HeadersQuery>,
+) -> Result<Json<HeadersResponse>, Response> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let index = require_index(&state)?;
+
+    // The index maps DAA scores to blocks; the chain walk itself comes from kaspad.
+    let start = index
+        .first_block_at_or_above(query.from_daa)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Index lookup for DAA score {} failed: {:?}",
+                query.from_daa,
+                e
+            );
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index query failed")
+        })?
+        .and_then(|hash| hash.parse::<Hash>().ok())
+        .ok_or_else(|| {
+            error_response(
+                StatusCode::NOT_FOUND,
+                "No indexed block at or above from_daa",
+            )
+        })?;
+
+    let start_blue_score = index
+        .block_blue_score(start.to_string())
+        .await
+        .map_err(|e| {
+            tracing::error!("Index lookup of block {} failed: {:?}", start, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index query failed")
+        })?
+        .unwrap_or_default();
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+
+    // Only chain blocks with enough confirmations are returned, which stops the walk a
+    // bounded distance past the start block instead of at the sink.
+    let sink_blue_score = rpc::retried("get_sink_blue_score", || client.get_sink_blue_score())
+        .await
+        .map_err(|e| {
+            tracing::warn!("get_sink_blue_score failed: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read the selected chain",
+            )
+        })?;
+    let window_end = start_blue_score + (limit as u64 * BLUE_SCORE_PER_HEADER).max(MIN_WINDOW);
+    let min_confirmations = sink_blue_score.checked_sub(window_end);
+
+    // The start block may be off the selected chain; the chain returned then begins at its
+    // chain ancestor, so blocks below from_daa are skipped.
+    let chain = rpc::retried("get_virtual_chain_from_block", || {
+        client.get_virtual_chain_from_block(start, false, min_confirmations)
+    })
+    .await
+    .map_err(|e| {
+        tracing::warn!(
+            "get_virtual_chain_from_block from {} failed: {:?}",
+            start,
+            e
+        );
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to read the selected chain",
+        )
+    })?;
+
+    let mut headers = Vec::with_capacity(limit);
+    // More chain blocks follow when the page fills up or the window stopped short of the sink.
+    let mut more = min_confirmations.is_some();
+    for hash in &chain.added_chain_block_hashes {
+        if headers.len() == limit {
+            more = true;
+            break;
+        }
+        let hash = *hash;
+        let block = rpc::hedged(
+            state.hedging.as_deref(),
+            client,
+            "get_block",
+            |c| async move { c.get_block(hash, false).await },
+        )
+        .await
+        .map_err(|_| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch chain block",
+            )
+        })?;
+        if block.header.daa_score < query.from_daa {
+            continue;
+        }
+        headers.push(CompactHeader {
+            hash: block.header.hash.to_string(),
+            daa_score: block.header.daa_score,
+            blue_score: block.header.blue_score,
+            header: hex::encode(serialize_header(&block.header)),
+        });
+    }
+
+    // A window without chain blocks at or above from_daa still moves the caller on.
+    let next_from_daa = match headers.last() {
+        Some(last) => Some(last.daa_score + 1).filter(|_| more),
+        None => Some(query.from_daa + 1).filter(|_| more),
+    };
+    Ok(Json(HeadersResponse {
+        from_daa: query.from_daa,
+        headers,
+        next_from_daa,
+    }))
+}
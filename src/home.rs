@@ -0,0 +1,353 @@
+//! Homepage feed with deltas, for clients polling every second.
+//!
+//! Every change the homepage shows gets a sequence number: blocks as block-added
+//! notifications arrive, mempool additions and removals as the mempool is re-read (at most
+//! once per `MEMPOOL_REFRESH_INTERVAL`, however many clients poll), and the stats whenever
+//! they differ from the last value served. `GET /home?since=<cursor>` returns only what
+//! changed after the cursor plus a new cursor. Without a cursor, or with one the server can
+//! no longer answer (restarted, or removals already trimmed), the response is a full
+//! snapshot with `full` set. Mempool differences also go to the event stream (`sse.rs`).
+
+use crate::chain::ChainSource;
+use crate::miners::coinbase_miner;
+use crate::pools::MinedBy;
+use crate::sampler::now_ms;
+use crate::sse::{self, MempoolEvent, StreamEvent};
+use crate::{block_size, rpc, AppState, TransactionInfo};
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use kaspa_rpc_core::BlockAddedNotification;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+
+pub const MAX_BLOCKS: usize = 20;
+// Removals kept for clients catching up; older cursors get a full snapshot.
+const MAX_REMOVALS: usize = 10_000;
+const MEMPOOL_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub type HomeFeed = Arc<RwLock<Feed>>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HomeBlock {
+    pub hash: String,
+    pub daa_score: u64,
+    pub blue_score: u64,
+    pub timestamp: u64, // Unix ms
+    pub tx_count: usize,
+    pub size: u64, // Serialized bytes
+    pub mass: u64,
+    pub mass_utilization: f64, // Of the consensus block mass limit
+    pub miner: Option<String>, // Coinbase address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mined_by: Option<MinedBy>, // Pool or mining software, from the signature table
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct HomeStats {
+    is_connected: bool,
+    is_synced: Option<bool>,
+    virtual_daa_score: Option<u64>,
+    sink_blue_score: Option<u64>,
+    peer_count: Option<usize>,
+    mempool_size: Option<u64>,
+    difficulty: Option<f64>,
+    hashrate: Option<u64>,
+}
+
+pub struct Feed {
+    instance: u64, // Start time in ms; cursors from another run are not honoured
+    seq: u64,
+    blocks: VecDeque<(u64, HomeBlock)>,
+    mempool: HashMap<String, (u64, TransactionInfo)>,
+    removals: VecDeque<(u64, String)>,
+    // Oldest sequence number a delta can still be computed from.
+    history_start: u64,
+    mempool_refreshed_at: Option<Instant>,
+    stats: Option<(u64, HomeStats)>,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Feed {
+            instance: now_ms(),
+            seq: 0,
+            blocks: VecDeque::new(),
+            mempool: HashMap::new(),
+            removals: VecDeque::new(),
+            history_start: 0,
+            mempool_refreshed_at: None,
+            stats: None,
+        }
+    }
+}
+
+impl Feed {
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn cursor(&self) -> String {
+        format!("{}-{}", self.instance, self.seq)
+    }
+
+    /// The sequence number `cursor` stands for, if a delta can be computed from it.
+    fn parse_cursor(&self, cursor: &str) -> Option<u64> {
+        let (instance, seq) = cursor.split_once('-')?;
+        let (instance, seq) = (instance.parse::<u64>().ok()?, seq.parse::<u64>().ok()?);
+        (instance == self.instance && seq >= self.history_start && seq <= self.seq).then_some(seq)
+    }
+
+    /// Records the differences to `current`; returns the added and removed ids.
+    fn apply_mempool(
+        &mut self,
+        current: HashMap<String, TransactionInfo>,
+    ) -> (Vec<String>, Vec<String>) {
+        let removed: Vec<String> = self
+            .mempool
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect();
+        for id in &removed {
+            self.mempool.remove(id);
+            let seq = self.next_seq();
+            self.removals.push_back((seq, id.clone()));
+        }
+        while self.removals.len() > MAX_REMOVALS {
+            if let Some((seq, _)) = self.removals.pop_front() {
+                self.history_start = seq;
+            }
+        }
+        let mut added = Vec::new();
+        for (id, transaction) in current {
+            if !self.mempool.contains_key(&id) {
+                let seq = self.next_seq();
+                added.push(id.clone());
+                self.mempool.insert(id, (seq, transaction));
+            }
+        }
+        (added, removed)
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HomeQuery {
+    /// Cursor from the previous response
+    since: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HomeResponse {
+    cursor: String,
+    full: bool, // Everything is included, not just changes
+    // New blocks, newest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<HomeBlock>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mempool_added: Vec<TransactionInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mempool_removed: Vec<String>,
+    // More mempool additions than `[limits] max_mempool_transactions`; the rest are left out.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    mempool_truncated: bool,
+    // Only present when changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<HomeStats>,
+}
+
+pub async fn on_block_added(state: &AppState, notification: &BlockAddedNotification) {
+    let block = &notification.block;
+    let mass = block_size::mass(block, state.network);
+    let mut feed = state.home.write().await;
+    let seq = feed.next_seq();
+    feed.blocks.push_front((
+        seq,
+        HomeBlock {
+            hash: block.header.hash.to_string(),
+            daa_score: block.header.daa_score,
+            blue_score: block.header.blue_score,
+            timestamp: block.header.timestamp,
+            tx_count: block.transactions.len(),
+            size: block_size::size(block),
+            mass,
+            mass_utilization: block_size::utilization(mass),
+            miner: coinbase_miner(block),
+            mined_by: state.pools.as_ref().and_then(|pools| pools.identify(block)),
+        },
+    ));
+    feed.blocks.truncate(MAX_BLOCKS);
+}
+
+/// The latest blocks announced by kaspad, newest first.
+pub async fn recent_blocks(state: &AppState) -> Vec<HomeBlock> {
+    let feed = state.home.read().await;
+    feed.blocks.iter().map(|(_, block)| block.clone()).collect()
+}
+
+/// How many recent blocks are buffered, and how long ago the mempool was last re-read.
+pub async fn buffer_stats(state: &AppState) -> (usize, Option<Duration>) {
+    let feed = state.home.read().await;
+    (
+        feed.blocks.len(),
+        feed.mempool_refreshed_at.map(|at| at.elapsed()),
+    )
+}
+
+/// Re-reads the mempool if the last read is older than `MEMPOOL_REFRESH_INTERVAL`, and
+/// publishes the differences to the event stream.
+pub async fn refresh_mempool(state: &AppState) {
+    {
+        let mut feed = state.home.write().await;
+        if feed
+            .mempool_refreshed_at
+            .is_some_and(|at| at.elapsed() < MEMPOOL_REFRESH_INTERVAL)
+        {
+            return;
+        }
+        // Claimed before the RPC so concurrent polls do not all refresh.
+        feed.mempool_refreshed_at = Some(Instant::now());
+    }
+    let client_guard = state.client.read().await;
+    let Some(client) = client_guard.as_ref() else {
+        return;
+    };
+    let entries = match rpc::retried("get_mempool_entries", || {
+        client.get_mempool_entries(true, false)
+    })
+    .await
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Failed to refresh the homepage mempool: {:?}", e);
+            return;
+        }
+    };
+    drop(client_guard);
+    let current = entries
+        .iter()
+        .filter_map(|entry| {
+            let tx = &entry.transaction;
+            let id = tx.verbose_data.as_ref()?.transaction_id.to_string();
+            let info = TransactionInfo {
+                id: id.clone(),
+                input_count: tx.inputs.len(),
+                output_count: tx.outputs.len(),
+                amount: tx.outputs.iter().map(|o| o.value).sum(),
+            };
+            Some((id, info))
+        })
+        .collect();
+    let (added, removed, size) = {
+        let mut feed = state.home.write().await;
+        let (added, removed) = feed.apply_mempool(current);
+        (added, removed, feed.mempool.len())
+    };
+    if !added.is_empty() || !removed.is_empty() {
+        sse::publish(state, || {
+            StreamEvent::Mempool(MempoolEvent {
+                added,
+                removed,
+                size,
+            })
+        });
+    }
+}
+
+async fn current_stats(state: &AppState) -> HomeStats {
+    let info = state.network_info.read().await.clone();
+    let (daa_score, blue_score) = {
+        let virtual_state = state.virtual_state.read().await;
+        (virtual_state.daa_score(), virtual_state.blue_score())
+    };
+    let sampler = state.sampler.read().await;
+    let latest = sampler.latest();
+    HomeStats {
+        is_connected: info.is_connected,
+        is_synced: info.is_synced,
+        virtual_daa_score: daa_score.or(info.virtual_daa_score),
+        sink_blue_score: blue_score,
+        peer_count: info.peer_count,
+        mempool_size: info.mempool_size,
+        difficulty: latest.map(|s| s.difficulty),
+        hashrate: latest.map(|s| s.hashrate),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/home",
+    tag = "network",
+    params(HomeQuery),
+    responses(
+        (status = 200, description = "Homepage changes since the cursor, or a full snapshot", body = HomeResponse)
+    )
+)]
+pub async fn get_home(
+    State(state): State<AppState>,
+    Query(query): Query<HomeQuery>,
+) -> Json<HomeResponse> {
+    refresh_mempool(&state).await;
+    let stats = current_stats(&state).await;
+    let max_transactions = state.config.limits.max_mempool_transactions;
+
+    let mut feed = state.home.write().await;
+    if feed.stats.as_ref().is_none_or(|(_, last)| *last != stats) {
+        let seq = feed.next_seq();
+        feed.stats = Some((seq, stats));
+    }
+    let since = query.since.as_deref().and_then(|c| feed.parse_cursor(c));
+    let after = since.unwrap_or(0);
+
+    let blocks = feed
+        .blocks
+        .iter()
+        .filter(|(seq, _)| *seq > after)
+        .map(|(_, block)| block.clone())
+        .collect();
+    let mut added: Vec<&(u64, TransactionInfo)> = feed
+        .mempool
+        .values()
+        .filter(|(seq, _)| *seq > after)
+        .collect();
+    // Newest first, so a truncated list keeps the latest additions.
+    added.sort_by_key(|(seq, _)| std::cmp::Reverse(*seq));
+    let mempool_truncated = added.len() > max_transactions;
+    let mempool_added = added
+        .into_iter()
+        .take(max_transactions)
+        .map(|(_, tx)| tx.clone())
+        .collect();
+    let mempool_removed = match since {
+        Some(after) => feed
+            .removals
+            .iter()
+            .filter(|(seq, _)| *seq > after)
+            .map(|(_, id)| id.clone())
+            .collect(),
+        None => Vec::new(),
+    };
+    let stats = feed
+        .stats
+        .as_ref()
+        .filter(|(seq, _)| *seq > after)
+        .map(|(_, stats)| stats.clone());
+
+    Json(HomeResponse {
+        cursor: feed.cursor(),
+        full: since.is_none(),
+        blocks,
+        mempool_added,
+        mempool_removed,
+        mempool_truncated,
+        stats,
+    })
+}
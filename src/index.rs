@@ -0,0 +1,1669 @@
+//! Local SQLite index of blocks and transactions.
+//!
+//! Filled by the background indexer (`indexer.rs`) and read by endpoints that need history
+//! kaspad does not serve directly. Hashes and transaction ids are stored as hex strings.
+//! SQLite calls are blocking, so all access goes through `Index::run` on the blocking pool.
+//! The schema is versioned; changes to it are added as migrations in `migrations.rs`.
+//!
+//! Every row is tagged with the epoch it was indexed in. Testnet-12 gets relaunched from a new
+//! genesis now and then; `epochs.rs` detects that and starts a new epoch, and reads are scoped
+//! to the current one so data from different networks never mixes.
+//!
+//! Output and input rows far enough behind the tip can be moved to a compressed cold tier
+//! (`cold.rs`); `outputs` and the filter scan read through to it.
+//!
+//! The same database also keeps saved portfolios and webhook watches, which are not chain
+//! data and survive wipes.
+
+use crate::sampler::now_ms;
+use crate::{cold, migrations, tokens};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{File, TryLockError};
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use utoipa::ToSchema;
+
+pub type IndexHandle = Arc<Index>;
+
+// Decompressed cold segments by (epoch, start DAA score), oldest first.
+type ColdCache = VecDeque<((i64, u64), Arc<cold::Segment>)>;
+
+// Meta key holding the hash the indexer resumes from.
+const CURSOR_KEY: &str = "cursor";
+// Decompressed cold segments kept in memory.
+const COLD_CACHE_SEGMENTS: usize = 4;
+// Cold segments searched for the spender of an outpoint.
+const COLD_SPENDER_SEGMENTS: usize = 24;
+// Bounds how long one tiering pass holds the connection.
+const COLD_SEGMENTS_PER_PASS: usize = 4;
+
+/// A block flattened into the rows the index stores.
+#[derive(Debug)]
+pub struct IndexedBlock {
+    pub hash: String,
+    pub daa_score: u64,
+    pub blue_score: u64,
+    pub timestamp: u64,
+    pub miner: Option<String>, // Coinbase address, if the payload holds a standard script
+    pub size: u64,             // Serialized bytes, see `block_size.rs`
+    pub mass: u64,
+    // Blocks this block merged as red; only recorded for blocks on the selected chain when
+    // indexed. Later chain changes are recorded by `set_colours`.
+    pub red_hashes: Vec<String>,
+    pub transactions: Vec<IndexedTransaction>,
+}
+
+#[derive(Debug)]
+pub struct IndexedTransaction {
+    pub id: String,
+    pub inputs: Vec<(String, u32)>,               // Previous outpoints
+    pub outputs: Vec<(Option<String>, u64)>,      // Address (None for non-standard scripts), amount
+    pub inscription: Option<tokens::Inscription>, // KRC-20 operation, with [index] tokens
+}
+
+/// An indexed block as read back, with the ids of its transactions.
+#[derive(Debug)]
+pub struct StoredBlock {
+    pub hash: String,
+    pub daa_score: u64,
+    pub blue_score: u64,
+    pub timestamp: u64,
+    pub size: Option<u64>, // None for blocks indexed before sizes were recorded
+    pub mass: Option<u64>,
+    pub transaction_ids: Vec<String>,
+}
+
+/// Size and mass of the blocks in a time window, from `block_size_stats`.
+#[derive(Debug)]
+pub struct BlockSizeStats {
+    pub blocks: u64,
+    pub measured_blocks: u64, // With a recorded size and mass
+    pub mean_size: Option<f64>,
+    pub max_size: Option<u64>,
+    pub mean_mass: Option<f64>,
+    pub max_mass: Option<u64>,
+}
+
+/// What one transaction received and spent for an address.
+#[derive(Debug)]
+pub struct AddressTransaction {
+    pub tx_id: String,
+    pub daa_score: u64,
+    pub timestamp: Option<u64>, // Of the earliest indexed block including the transaction
+    pub received: u64,
+    pub sent: u64, // Value of the address's outputs spent by the transaction
+}
+
+/// Blocks and red blocks mined by one coinbase address.
+#[derive(Debug)]
+pub struct MinerBlocks {
+    pub address: String,
+    pub blocks: u64,
+    pub red_blocks: u64,
+}
+
+/// Balance and UTXO count of one address, as kept for the rich list.
+#[derive(Debug)]
+pub struct AddressBalance {
+    pub address: String,
+    pub balance: u64,
+    pub utxo_count: u64,
+}
+
+/// A stored webhook watch; address and transaction id lists are JSON.
+#[derive(Debug)]
+pub struct StoredWatch {
+    pub id: String,
+    pub callback_url: String,
+    pub secret: String,
+    pub addresses: String,
+    pub transaction_ids: String,
+}
+
+/// An operator-assigned address label.
+#[derive(Debug)]
+pub struct StoredLabel {
+    pub address: String,
+    pub name: String,
+    pub tag: Option<String>,
+}
+
+/// A selected-chain reorganization: chain blocks kaspad removed and added in one
+/// virtual-chain-changed notification.
+#[derive(Debug)]
+pub struct StoredReorg {
+    pub id: i64,
+    pub detected_at: u64,       // Unix ms
+    pub daa_score: Option<u64>, // Virtual DAA score when detected
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+/// The chain block that accepted a transaction. DAA and blue score are None until the
+/// indexer has reached the block.
+#[derive(Debug)]
+pub struct Acceptance {
+    pub block_hash: String,
+    pub daa_score: Option<u64>,
+    pub blue_score: Option<u64>,
+}
+
+/// Size and rough contents of the database, for diagnostics.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IndexStats {
+    pub schema_version: u32,
+    pub size_bytes: u64,
+    pub cursor: Option<String>,
+    pub current_epoch: Option<Epoch>,
+    pub network_mismatch: Option<String>,
+    // Highest rowid per table, which is cheap to read; deleted rows are still counted.
+    pub approximate_rows: BTreeMap<String, u64>,
+}
+
+/// One run of the network as seen by the indexer, from first sighting to the detected reset.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Epoch {
+    pub id: i64,
+    pub network: String,
+    pub genesis: Option<String>, // Of the epoch's network; None until the indexer finds it
+    pub pruning_point: String,   // Latest pruning point seen during the epoch
+    pub started_at: u64,         // Unix ms
+    pub start_daa_score: u64,
+    pub last_daa_score: u64, // Highest indexed DAA score
+    pub ended_at: Option<u64>,
+    pub end_reason: Option<String>,
+    pub current: bool,
+}
+
+pub struct Index {
+    conn: Mutex<Connection>,
+    epoch: AtomicI64, // Current epoch id, 0 before the first one starts
+    // Set while the node is on a different network than the index was built from.
+    network_mismatch: RwLock<Option<String>>,
+    cold_cache: Mutex<ColdCache>,
+    _lock: File, // See `lock`
+}
+
+/// Takes the advisory lock on the index at `path`, held for as long as the file is open.
+/// Every open index holds it shared; a snapshot import takes it exclusively, so it waits
+/// until no explorer or command has the index open.
+pub fn lock(path: &Path, exclusive: bool) -> anyhow::Result<File> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    let file = File::create(&name)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", Path::new(&name).display(), e))?;
+    let locked = if exclusive {
+        file.try_lock()
+    } else {
+        file.try_lock_shared()
+    };
+    match locked {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(anyhow::anyhow!(
+            "The index {} is in use; stop the explorer first",
+            path.display()
+        )),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+impl Index {
+    pub fn open(path: &Path) -> anyhow::Result<IndexHandle> {
+        let lock = lock(path, false)?;
+        let mut conn = Connection::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open index {}: {}", path.display(), e))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        migrations::migrate(&mut conn)
+            .map_err(|e| anyhow::anyhow!("Failed to migrate index {}: {}", path.display(), e))?;
+        let epoch: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(id), 0) FROM epochs WHERE ended_at IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(Arc::new(Index {
+            conn: Mutex::new(conn),
+            epoch: AtomicI64::new(epoch),
+            network_mismatch: RwLock::new(None),
+            cold_cache: Mutex::new(VecDeque::new()),
+            _lock: lock,
+        }))
+    }
+
+    /// Runs `f` against the connection on the blocking thread pool.
+    pub async fn run<T, F>(self: &Arc<Self>, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let index = self.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = index
+                .conn
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            f(&mut conn)
+        })
+        .await?;
+        Ok(result?)
+    }
+
+    pub async fn cursor(self: &Arc<Self>) -> anyhow::Result<Option<String>> {
+        self.run(|conn| {
+            conn.query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                [CURSOR_KEY],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+        .await
+    }
+
+    pub async fn stats(self: &Arc<Self>) -> anyhow::Result<IndexStats> {
+        let current_epoch = self.current_epoch().await?;
+        let cursor = self.cursor().await?;
+        let (schema_version, size_bytes, approximate_rows) = self
+            .run(|conn| {
+                let schema_version: u32 =
+                    conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+                let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+                let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+                let mut rows = BTreeMap::new();
+                for table in [
+                    "blocks",
+                    "transactions",
+                    "outputs",
+                    "inputs",
+                    "cold_segments",
+                    "address_activity",
+                    "balances",
+                    "token_operations",
+                    "accepted_transactions",
+                    "portfolios",
+                    "watches",
+                ] {
+                    let count: i64 = conn.query_row(
+                        &format!("SELECT COALESCE(MAX(rowid), 0) FROM {}", table),
+                        [],
+                        |row| row.get(0),
+                    )?;
+                    rows.insert(table.to_string(), count as u64);
+                }
+                Ok((schema_version, (page_count * page_size) as u64, rows))
+            })
+            .await?;
+        Ok(IndexStats {
+            schema_version,
+            size_bytes,
+            cursor,
+            current_epoch,
+            network_mismatch: self.network_mismatch(),
+            approximate_rows,
+        })
+    }
+
+    /// Stores `blocks` and advances the cursor in a single transaction, so a crash never
+    /// leaves the cursor ahead of the data.
+    pub async fn insert_blocks(
+        self: &Arc<Self>,
+        blocks: Vec<IndexedBlock>,
+        cursor: String,
+    ) -> anyhow::Result<()> {
+        let epoch = self
+            .current_epoch_id()
+            .ok_or_else(|| anyhow::anyhow!("No index epoch started"))?;
+        let index = self.clone();
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut insert_block = tx.prepare_cached(
+                    "INSERT OR IGNORE INTO blocks (hash, epoch, daa_score, blue_score, timestamp, miner, size, mass) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                )?;
+                let mut insert_transaction = tx.prepare_cached(
+                    "INSERT OR IGNORE INTO transactions (id, block_hash, epoch, daa_score) VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                let mut insert_input = tx.prepare_cached(
+                    "INSERT OR IGNORE INTO inputs (tx_id, idx, prev_tx_id, prev_idx, epoch, daa_score) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                let mut insert_output = tx.prepare_cached(
+                    "INSERT OR IGNORE INTO outputs (tx_id, idx, address, amount, epoch, daa_score) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                let mut record_activity = tx.prepare_cached(
+                    "INSERT INTO address_activity (address, epoch, first_daa_score, last_daa_score) VALUES (?1, ?2, ?3, ?3)
+                     ON CONFLICT (address, epoch) DO UPDATE SET
+                         first_daa_score = MIN(first_daa_score, excluded.first_daa_score),
+                         last_daa_score = MAX(last_daa_score, excluded.last_daa_score)",
+                )?;
+                for block in &blocks {
+                    let daa_score = block.daa_score as i64;
+                    insert_block.execute(params![block.hash, epoch, daa_score, block.blue_score as i64, block.timestamp as i64, block.miner, block.size as i64, block.mass as i64])?;
+                    for transaction in &block.transactions {
+                        insert_transaction.execute(params![transaction.id, block.hash, epoch, daa_score])?;
+                        for (i, (prev_tx_id, prev_idx)) in transaction.inputs.iter().enumerate() {
+                            insert_input.execute(params![transaction.id, i as i64, prev_tx_id, prev_idx, epoch, daa_score])?;
+                            // Spending counts as activity of the address that owned the output.
+                            if let Some((Some(address), ..)) = index.output(&tx, epoch, prev_tx_id, *prev_idx)? {
+                                record_activity.execute(params![address, epoch, daa_score])?;
+                            }
+                        }
+                        for (i, (address, amount)) in transaction.outputs.iter().enumerate() {
+                            insert_output.execute(params![transaction.id, i as i64, address, *amount as i64, epoch, daa_score])?;
+                            if let Some(address) = address {
+                                record_activity.execute(params![address, epoch, daa_score])?;
+                            }
+                        }
+                        if let Some(inscription) = &transaction.inscription {
+                            tokens::record(&tx, epoch, &transaction.id, block.daa_score, inscription)?;
+                        }
+                    }
+                }
+                // Colours from virtual-chain-changed notifications take precedence.
+                let mut mark_red = tx.prepare_cached("INSERT OR IGNORE INTO red_blocks (hash, epoch, chain_block) VALUES (?1, ?2, ?3)")?;
+                for block in &blocks {
+                    for hash in &block.red_hashes {
+                        mark_red.execute(params![hash, epoch, block.hash])?;
+                    }
+                }
+            }
+            if let Some(max_daa_score) = blocks.iter().map(|block| block.daa_score).max() {
+                tx.execute(
+                    "UPDATE epochs SET last_daa_score = MAX(last_daa_score, ?1) WHERE id = ?2",
+                    params![max_daa_score as i64, epoch],
+                )?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                params![CURSOR_KEY, cursor],
+            )?;
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Folds the write-ahead log back into the database file, so a copied or backed-up
+    /// index file is complete on its own.
+    pub async fn checkpoint(self: &Arc<Self>) -> anyhow::Result<()> {
+        self.run(|conn| conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(())))
+            .await
+    }
+
+    /// Address (if standard) and amount of each indexed output in `outpoints`.
+    pub async fn outputs(
+        self: &Arc<Self>,
+        outpoints: Vec<(String, u32)>,
+    ) -> anyhow::Result<HashMap<(String, u32), (Option<String>, u64)>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let index = self.clone();
+        self.run(move |conn| {
+            let mut found = HashMap::new();
+            for (tx_id, idx) in outpoints {
+                if let Some((address, amount, _)) = index.output(conn, epoch, &tx_id, idx)? {
+                    found.insert((tx_id, idx), (address, amount));
+                }
+            }
+            Ok(found)
+        })
+        .await
+    }
+
+    /// Address, amount and DAA score of one output, from the hot table or else the cold
+    /// tier, where it is found through its transaction's DAA score.
+    fn output(
+        &self,
+        conn: &Connection,
+        epoch: i64,
+        tx_id: &str,
+        idx: u32,
+    ) -> rusqlite::Result<Option<(Option<String>, u64, u64)>> {
+        let hot = conn
+            .prepare_cached(
+                "SELECT address, amount, daa_score FROM outputs WHERE epoch = ?1 AND tx_id = ?2 AND idx = ?3",
+            )?
+            .query_row(params![epoch, tx_id, idx], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                ))
+            })
+            .optional()?;
+        if hot.is_some() {
+            return Ok(hot);
+        }
+        let Some(daa_score) = conn
+            .prepare_cached(
+                "SELECT daa_score FROM transactions WHERE epoch = ?1 AND id = ?2 ORDER BY daa_score LIMIT 1",
+            )?
+            .query_row(params![epoch, tx_id], |row| row.get::<_, i64>(0))
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let segment = self.cold_segment(conn, epoch, cold::segment_start(daa_score as u64))?;
+        Ok(segment
+            .as_ref()
+            .and_then(|s| s.output(tx_id, idx))
+            .map(|output| (output.address.clone(), output.amount, output.daa_score)))
+    }
+
+    /// Outputs spent by inputs indexed with DAA scores in `after_daa + 1..=to_daa`, as
+    /// (address, amount, DAA score of the output), for standard outputs the index knows.
+    /// Inputs already moved to the cold tier are read from their segments.
+    pub async fn spent_between(
+        self: &Arc<Self>,
+        after_daa: u64,
+        to_daa: u64,
+    ) -> anyhow::Result<Vec<(String, u64, u64)>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let index = self.clone();
+        self.run(move |conn| {
+            let mut outpoints = conn
+                .prepare_cached(
+                    "SELECT prev_tx_id, prev_idx FROM inputs WHERE epoch = ?1 AND daa_score > ?2 AND daa_score <= ?3",
+                )?
+                .query_map(params![epoch, after_daa as i64, to_daa as i64], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for start in cold::segments_in_range(conn, epoch, after_daa + 1, to_daa)? {
+                let Some(segment) = index.cold_segment(conn, epoch, start)? else {
+                    continue;
+                };
+                outpoints.extend(
+                    segment
+                        .inputs
+                        .iter()
+                        .filter(|input| input.daa_score > after_daa && input.daa_score <= to_daa)
+                        .map(|input| (input.prev_tx_id.clone(), input.prev_idx)),
+                );
+            }
+            let mut spent = Vec::new();
+            for (tx_id, idx) in outpoints {
+                if let Some((Some(address), amount, daa_score)) =
+                    index.output(conn, epoch, &tx_id, idx)?
+                {
+                    spent.push((address, amount, daa_score));
+                }
+            }
+            Ok(spent)
+        })
+        .await
+    }
+
+    /// First and last DAA score at which `address` received or spent, in the current epoch.
+    pub async fn address_activity(
+        self: &Arc<Self>,
+        address: String,
+    ) -> anyhow::Result<Option<(u64, u64)>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT first_daa_score, last_daa_score FROM address_activity WHERE address = ?1 AND epoch = ?2",
+                params![address, epoch],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+            )
+            .optional()
+        })
+        .await
+    }
+
+    /// Whether `address` was active below the cold tier's horizon, so `address_transactions`
+    /// misses part of its history. Addresses whose activity was packed before the activity
+    /// table existed (schema version 3) are not recognized.
+    pub async fn address_history_truncated(
+        self: &Arc<Self>,
+        address: String,
+    ) -> anyhow::Result<bool> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM address_activity WHERE address = ?1 AND epoch = ?2
+                     AND first_daa_score < (SELECT MAX(end_daa) FROM cold_segments WHERE epoch = ?2))",
+                params![address, epoch],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    /// Whether blocks with timestamps (Unix ms) of at least `since` have had their outputs and
+    /// inputs moved to the cold tier, so `address_flows` misses part of the window.
+    pub async fn flows_truncated(self: &Arc<Self>, since: u64) -> anyhow::Result<bool> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM blocks WHERE epoch = ?1 AND timestamp >= ?2
+                     AND daa_score < (SELECT MAX(end_daa) FROM cold_segments WHERE epoch = ?1))",
+                params![epoch, since as i64],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    /// Up to `limit` transactions that paid or spent from `address` in the current epoch,
+    /// newest first by (DAA score, id) and starting below `before`. Only the hot tables are
+    /// searched, so activity already moved to the cold tier is not listed; see
+    /// `address_history_truncated`.
+    pub async fn address_transactions(
+        self: &Arc<Self>,
+        address: String,
+        before: Option<(u64, String)>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<AddressTransaction>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let (before_daa, before_id) = before.unwrap_or((i64::MAX as u64, String::new()));
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT tx_id, MAX(daa_score) AS daa, SUM(received), SUM(sent),
+                     (SELECT MIN(b.timestamp) FROM transactions t JOIN blocks b ON b.hash = t.block_hash
+                      WHERE t.id = x.tx_id AND t.epoch = ?2)
+                 FROM (
+                     SELECT tx_id, daa_score, amount AS received, 0 AS sent FROM outputs
+                     WHERE address = ?1 AND epoch = ?2
+                     UNION ALL
+                     SELECT i.tx_id, i.daa_score, 0, o.amount FROM outputs o
+                     JOIN inputs i ON i.prev_tx_id = o.tx_id AND i.prev_idx = o.idx
+                     WHERE o.address = ?1 AND o.epoch = ?2 AND i.epoch = ?2
+                 ) x
+                 GROUP BY tx_id
+                 HAVING daa < ?3 OR (daa = ?3 AND tx_id < ?4)
+                 ORDER BY daa DESC, tx_id DESC LIMIT ?5",
+            )?
+            .query_map(
+                params![address, epoch, before_daa as i64, before_id, limit as i64],
+                |row| {
+                    Ok(AddressTransaction {
+                        tx_id: row.get(0)?,
+                        daa_score: row.get::<_, i64>(1)? as u64,
+                        received: row.get::<_, i64>(2)? as u64,
+                        sent: row.get::<_, i64>(3)? as u64,
+                        timestamp: row.get::<_, Option<i64>>(4)?.map(|t| t as u64),
+                    })
+                },
+            )?
+            .collect()
+        })
+        .await
+    }
+
+    /// What each accepted transaction in blocks with timestamps (Unix ms) of at least `since`
+    /// received and spent for `address`, oldest first, as (timestamp, received, sent). Rows
+    /// are narrowed to the window's DAA scores before grouping. Only the hot tables are
+    /// searched (see `flows_truncated`), and only acceptances the index recorded count.
+    pub async fn address_flows(
+        self: &Arc<Self>,
+        address: String,
+        since: u64,
+    ) -> anyhow::Result<Vec<(u64, u64, u64)>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "WITH window_start AS (
+                     SELECT MIN(daa_score) AS daa FROM blocks WHERE epoch = ?2 AND timestamp >= ?3
+                 )
+                 SELECT (SELECT MIN(b.timestamp) FROM transactions t
+                         JOIN blocks b ON b.hash = t.block_hash AND b.epoch = t.epoch
+                         WHERE t.id = x.tx_id AND t.epoch = ?2) AS ts,
+                     SUM(x.received), SUM(x.sent)
+                 FROM (
+                     SELECT tx_id, amount AS received, 0 AS sent FROM outputs
+                     WHERE address = ?1 AND epoch = ?2
+                         AND daa_score >= (SELECT daa FROM window_start)
+                     UNION ALL
+                     SELECT i.tx_id, 0, o.amount FROM outputs o
+                     JOIN inputs i ON i.prev_tx_id = o.tx_id AND i.prev_idx = o.idx
+                     WHERE o.address = ?1 AND o.epoch = ?2 AND i.epoch = ?2
+                         AND i.daa_score >= (SELECT daa FROM window_start)
+                 ) x
+                 JOIN accepted_transactions a ON a.tx_id = x.tx_id AND a.epoch = ?2
+                 GROUP BY x.tx_id
+                 HAVING ts >= ?3
+                 ORDER BY ts",
+            )?
+            .query_map(params![address, epoch, since as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                ))
+            })?
+            .collect()
+        })
+        .await
+    }
+
+    /// Blocks per coinbase address with timestamps (Unix ms) of at least `since`, most
+    /// blocks first.
+    pub async fn miner_blocks(self: &Arc<Self>, since: u64) -> anyhow::Result<Vec<MinerBlocks>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT b.miner, COUNT(*), COUNT(r.hash) FROM blocks b
+                 LEFT JOIN red_blocks r ON r.hash = b.hash AND r.epoch = b.epoch
+                 WHERE b.epoch = ?1 AND b.timestamp >= ?2 AND b.miner IS NOT NULL
+                 GROUP BY b.miner ORDER BY COUNT(*) DESC, b.miner",
+            )?
+            .query_map(params![epoch, since as i64], |row| {
+                Ok(MinerBlocks {
+                    address: row.get(0)?,
+                    blocks: row.get::<_, i64>(1)? as u64,
+                    red_blocks: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect()
+        })
+        .await
+    }
+
+    /// (blocks, red blocks) of the current epoch with timestamps (Unix ms) of at least `since`.
+    pub async fn block_counts(self: &Arc<Self>, since: u64) -> anyhow::Result<(u64, u64)> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT COUNT(*), COUNT(r.hash) FROM blocks b
+                 LEFT JOIN red_blocks r ON r.hash = b.hash AND r.epoch = b.epoch
+                 WHERE b.epoch = ?1 AND b.timestamp >= ?2",
+            )?
+            .query_row(params![epoch, since as i64], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+            })
+        })
+        .await
+    }
+
+    /// Blocks of the current epoch per `bucket_ms` from `since` (Unix ms) on, as (bucket start,
+    /// blocks) in ascending order; buckets without blocks are left out.
+    pub async fn block_buckets(
+        self: &Arc<Self>,
+        since: u64,
+        bucket_ms: u64,
+    ) -> anyhow::Result<Vec<(u64, u64)>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT timestamp - timestamp % ?3 AS bucket, COUNT(*) FROM blocks
+                 WHERE epoch = ?1 AND timestamp >= ?2 GROUP BY bucket ORDER BY bucket",
+            )?
+            .query_map(params![epoch, since as i64, bucket_ms as i64], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect()
+        })
+        .await
+    }
+
+    /// Timestamps (Unix ms) of the current epoch's blocks from `since` on, in ascending order.
+    pub async fn block_timestamps(self: &Arc<Self>, since: u64) -> anyhow::Result<Vec<u64>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT timestamp FROM blocks WHERE epoch = ?1 AND timestamp >= ?2
+                 ORDER BY timestamp",
+            )?
+            .query_map(params![epoch, since as i64], |row| {
+                Ok(row.get::<_, i64>(0)? as u64)
+            })?
+            .collect()
+        })
+        .await
+    }
+
+    /// Size and mass of the current epoch's blocks from `since` (Unix ms) on.
+    pub async fn block_size_stats(self: &Arc<Self>, since: u64) -> anyhow::Result<BlockSizeStats> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT COUNT(*), COUNT(mass), AVG(size), MAX(size), AVG(mass), MAX(mass)
+                 FROM blocks WHERE epoch = ?1 AND timestamp >= ?2",
+            )?
+            .query_row(params![epoch, since as i64], |row| {
+                Ok(BlockSizeStats {
+                    blocks: row.get::<_, i64>(0)? as u64,
+                    measured_blocks: row.get::<_, i64>(1)? as u64,
+                    mean_size: row.get(2)?,
+                    max_size: row.get::<_, Option<i64>>(3)?.map(|size| size as u64),
+                    mean_mass: row.get(4)?,
+                    max_mass: row.get::<_, Option<i64>>(5)?.map(|mass| mass as u64),
+                })
+            })
+        })
+        .await
+    }
+
+    /// Up to `limit` addresses seen in the current epoch, in order and starting after `after`.
+    pub async fn active_addresses(
+        self: &Arc<Self>,
+        after: String,
+        limit: usize,
+    ) -> anyhow::Result<Vec<String>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT address FROM address_activity WHERE epoch = ?1 AND address > ?2
+                 ORDER BY address LIMIT ?3",
+            )?
+            .query_map(params![epoch, after, limit as i64], |row| row.get(0))?
+            .collect()
+        })
+        .await
+    }
+
+    /// Up to `limit` addresses of the current epoch picked at random, and how many there are.
+    pub async fn sample_addresses(
+        self: &Arc<Self>,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<String>, u64)> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            let sample = conn
+                .prepare_cached(
+                    "SELECT address FROM address_activity WHERE epoch = ?1 ORDER BY RANDOM() LIMIT ?2",
+                )?
+                .query_map(params![epoch, limit as i64], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            let total: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM address_activity WHERE epoch = ?1",
+                [epoch],
+                |row| row.get(0),
+            )?;
+            Ok((sample, total as u64))
+        })
+        .await
+    }
+
+    /// Stores balances read from kaspad as they are; addresses without UTXOs are dropped.
+    pub async fn set_balances(
+        self: &Arc<Self>,
+        balances: Vec<AddressBalance>,
+    ) -> anyhow::Result<()> {
+        let epoch = self
+            .current_epoch_id()
+            .ok_or_else(|| anyhow::anyhow!("No index epoch started"))?;
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut upsert = tx.prepare_cached(
+                    "INSERT INTO balances (address, epoch, balance, utxo_count) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (address, epoch) DO UPDATE SET balance = excluded.balance, utxo_count = excluded.utxo_count",
+                )?;
+                let mut delete = tx.prepare_cached("DELETE FROM balances WHERE address = ?1 AND epoch = ?2")?;
+                for entry in balances {
+                    if entry.utxo_count == 0 {
+                        delete.execute(params![entry.address, epoch])?;
+                    } else {
+                        upsert.execute(params![entry.address, epoch, entry.balance as i64, entry.utxo_count as i64])?;
+                    }
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Adds per-address (balance, UTXO count) differences to the stored balances.
+    pub async fn apply_balance_changes(
+        self: &Arc<Self>,
+        changes: Vec<(String, i64, i64)>,
+    ) -> anyhow::Result<()> {
+        let Some(epoch) = self.current_epoch_id() else {
+            return Ok(());
+        };
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                // Clamped at zero: a change can reach an address the last scan missed.
+                let mut apply = tx.prepare_cached(
+                    "INSERT INTO balances (address, epoch, balance, utxo_count) VALUES (?1, ?2, MAX(?3, 0), MAX(?4, 0))
+                     ON CONFLICT (address, epoch) DO UPDATE SET
+                         balance = MAX(balance + ?3, 0),
+                         utxo_count = MAX(utxo_count + ?4, 0)",
+                )?;
+                let mut drop_empty = tx.prepare_cached(
+                    "DELETE FROM balances WHERE address = ?1 AND epoch = ?2 AND utxo_count = 0",
+                )?;
+                for (address, balance, utxo_count) in changes {
+                    apply.execute(params![address, epoch, balance, utxo_count])?;
+                    if utxo_count < 0 {
+                        drop_empty.execute(params![address, epoch])?;
+                    }
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// The `limit` largest balances of the current epoch and the number of funded addresses.
+    pub async fn rich_list(
+        self: &Arc<Self>,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<AddressBalance>, u64)> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            let balances = conn
+                .prepare_cached(
+                    "SELECT address, balance, utxo_count FROM balances WHERE epoch = ?1
+                     ORDER BY balance DESC, address LIMIT ?2",
+                )?
+                .query_map(params![epoch, limit as i64], |row| {
+                    Ok(AddressBalance {
+                        address: row.get(0)?,
+                        balance: row.get::<_, i64>(1)? as u64,
+                        utxo_count: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let total: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM balances WHERE epoch = ?1",
+                [epoch],
+                |row| row.get(0),
+            )?;
+            Ok((balances, total as u64))
+        })
+        .await
+    }
+
+    /// The packed segment of `epoch` starting at `start`, decompressed once and then cached.
+    pub fn cold_segment(
+        &self,
+        conn: &Connection,
+        epoch: i64,
+        start: u64,
+    ) -> rusqlite::Result<Option<Arc<cold::Segment>>> {
+        let key = (epoch, start);
+        let mut cache = self
+            .cold_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((_, segment)) = cache.iter().find(|(cached, _)| *cached == key) {
+            return Ok(Some(segment.clone()));
+        }
+        let Some(segment) = cold::load(conn, epoch, start)? else {
+            return Ok(None);
+        };
+        if cache.len() == COLD_CACHE_SEGMENTS {
+            cache.pop_front();
+        }
+        cache.push_back((key, segment.clone()));
+        Ok(Some(segment))
+    }
+
+    /// Moves output and input rows at least `depth` DAA scores behind the indexed tip into
+    /// cold segments, a few segments per call. Returns the packed ranges and row counts.
+    pub async fn move_to_cold(
+        self: &Arc<Self>,
+        depth: u64,
+    ) -> anyhow::Result<Vec<(u64, u64, usize)>> {
+        let Some(epoch) = self.current_epoch_id() else {
+            return Ok(Vec::new());
+        };
+        let Some(tip) = self.indexed_daa_score().await? else {
+            return Ok(Vec::new());
+        };
+        let Some(below) = tip.checked_sub(depth) else {
+            return Ok(Vec::new());
+        };
+        let index = self.clone();
+        self.run(move |conn| {
+            let mut packed = Vec::new();
+            while packed.len() < COLD_SEGMENTS_PER_PASS {
+                let Some(segment) = cold::pack_next(conn, epoch, below)? else {
+                    break;
+                };
+                // A merged segment must be read back from the database.
+                index
+                    .cold_cache
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .retain(|(key, _)| *key != (epoch, segment.0));
+                packed.push(segment);
+            }
+            Ok(packed)
+        })
+        .await
+    }
+
+    /// Id of an indexed transaction that spends outpoint `tx_id:idx`. Cold segments are only
+    /// searched from the output's own segment on, and at most `COLD_SPENDER_SEGMENTS` of them.
+    pub async fn spending_transaction(
+        self: &Arc<Self>,
+        tx_id: String,
+        idx: u32,
+    ) -> anyhow::Result<Option<String>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let index = self.clone();
+        self.run(move |conn| {
+            let hot = conn
+                .prepare_cached(
+                    "SELECT tx_id FROM inputs WHERE epoch = ?1 AND prev_tx_id = ?2 AND prev_idx = ?3 LIMIT 1",
+                )?
+                .query_row(params![epoch, tx_id, idx], |row| row.get(0))
+                .optional()?;
+            if hot.is_some() {
+                return Ok(hot);
+            }
+            let Some(created) = conn
+                .prepare_cached(
+                    "SELECT daa_score FROM transactions WHERE epoch = ?1 AND id = ?2 ORDER BY daa_score LIMIT 1",
+                )?
+                .query_row(params![epoch, tx_id], |row| row.get::<_, i64>(0))
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            let starts = cold::segments_in_range(conn, epoch, created as u64, i64::MAX as u64)?;
+            for start in starts.into_iter().take(COLD_SPENDER_SEGMENTS) {
+                let Some(segment) = index.cold_segment(conn, epoch, start)? else {
+                    continue;
+                };
+                if let Some(input) = segment
+                    .inputs
+                    .iter()
+                    .find(|input| input.prev_tx_id == tx_id && input.prev_idx == idx)
+                {
+                    return Ok(Some(input.tx_id.clone()));
+                }
+            }
+            Ok(None)
+        })
+        .await
+    }
+
+    /// Hashes of the indexed blocks that include transaction `tx_id`, oldest first.
+    pub async fn transaction_blocks(
+        self: &Arc<Self>,
+        tx_id: String,
+    ) -> anyhow::Result<Vec<String>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT block_hash FROM transactions WHERE epoch = ?1 AND id = ?2 ORDER BY daa_score, rowid",
+            )?
+            .query_map(params![epoch, tx_id], |row| row.get(0))?
+            .collect()
+        })
+        .await
+    }
+
+    /// Blue score of indexed block `hash`.
+    pub async fn block_blue_score(self: &Arc<Self>, hash: String) -> anyhow::Result<Option<u64>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached("SELECT blue_score FROM blocks WHERE hash = ?1 AND epoch = ?2")?
+                .query_row(params![hash, epoch], |row| row.get::<_, i64>(0))
+                .optional()
+                .map(|blue_score| blue_score.map(|b| b as u64))
+        })
+        .await
+    }
+
+    /// Applies one selected-chain change: acceptances by the `removed` chain blocks are
+    /// dropped, then the transactions each `accepted` chain block accepted are recorded.
+    /// KRC-20 operations follow along: those of removed blocks are reverted, newest block
+    /// first as kaspad lists them, and those of accepted blocks applied in chain order.
+    pub async fn apply_acceptance(
+        self: &Arc<Self>,
+        removed: Vec<String>,
+        accepted: Vec<(String, Vec<String>)>,
+    ) -> anyhow::Result<()> {
+        let epoch = self
+            .current_epoch_id()
+            .ok_or_else(|| anyhow::anyhow!("No index epoch started"))?;
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut unaccept = tx.prepare_cached(
+                    "DELETE FROM accepted_transactions WHERE accepting_block = ?1 AND epoch = ?2",
+                )?;
+                for block_hash in &removed {
+                    tokens::revert(&tx, epoch, block_hash)?;
+                    unaccept.execute(params![block_hash, epoch])?;
+                }
+                let mut accept = tx.prepare_cached(
+                    "INSERT OR REPLACE INTO accepted_transactions (tx_id, epoch, accepting_block) VALUES (?1, ?2, ?3)",
+                )?;
+                for (block_hash, tx_ids) in &accepted {
+                    for tx_id in tx_ids {
+                        accept.execute(params![tx_id, epoch, block_hash])?;
+                        tokens::apply(&tx, epoch, tx_id, block_hash)?;
+                    }
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Clears the red blocks the `removed` chain blocks merged and records those each added
+    /// chain block merged as red, from a virtual-chain-changed notification.
+    pub async fn set_colours(
+        self: &Arc<Self>,
+        removed: Vec<String>,
+        added: Vec<(String, Vec<String>)>,
+    ) -> anyhow::Result<()> {
+        let epoch = self
+            .current_epoch_id()
+            .ok_or_else(|| anyhow::anyhow!("No index epoch started"))?;
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut clear = tx.prepare_cached(
+                    "DELETE FROM red_blocks WHERE chain_block = ?1 AND epoch = ?2",
+                )?;
+                for block_hash in &removed {
+                    clear.execute(params![block_hash, epoch])?;
+                }
+                let mut mark_red = tx.prepare_cached(
+                    "INSERT OR REPLACE INTO red_blocks (hash, epoch, chain_block) VALUES (?1, ?2, ?3)",
+                )?;
+                for (block_hash, red_hashes) in &added {
+                    for hash in red_hashes {
+                        mark_red.execute(params![hash, epoch, block_hash])?;
+                    }
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Records that chain block `block_hash` accepted transaction `tx_id`, found outside the
+    /// notification stream. Its KRC-20 operation, if any, is not applied, since it would be
+    /// out of chain order.
+    pub async fn record_acceptance(
+        self: &Arc<Self>,
+        tx_id: String,
+        block_hash: String,
+    ) -> anyhow::Result<()> {
+        let epoch = self
+            .current_epoch_id()
+            .ok_or_else(|| anyhow::anyhow!("No index epoch started"))?;
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO accepted_transactions (tx_id, epoch, accepting_block) VALUES (?1, ?2, ?3)",
+                params![tx_id, epoch, block_hash],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// The chain block that accepted transaction `tx_id`, if the acceptance was recorded.
+    pub async fn acceptance(self: &Arc<Self>, tx_id: String) -> anyhow::Result<Option<Acceptance>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT a.accepting_block, b.daa_score, b.blue_score FROM accepted_transactions a
+                 LEFT JOIN blocks b ON b.hash = a.accepting_block AND b.epoch = a.epoch
+                 WHERE a.tx_id = ?1 AND a.epoch = ?2",
+            )?
+            .query_row(params![tx_id, epoch], |row| {
+                Ok(Acceptance {
+                    block_hash: row.get(0)?,
+                    daa_score: row.get::<_, Option<i64>>(1)?.map(|d| d as u64),
+                    blue_score: row.get::<_, Option<i64>>(2)?.map(|b| b as u64),
+                })
+            })
+            .optional()
+        })
+        .await
+    }
+
+    /// Stores a watch-only portfolio under the SHA-256 of its API key; `addresses` is JSON.
+    pub async fn save_portfolio(
+        self: &Arc<Self>,
+        key_hash: String,
+        name: Option<String>,
+        addresses: String,
+    ) -> anyhow::Result<()> {
+        let now = now_ms() as i64;
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT INTO portfolios (key_hash, name, addresses, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![key_hash, name, addresses, now],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// Name and JSON address list of the portfolio stored under `key_hash`.
+    pub async fn portfolio(
+        self: &Arc<Self>,
+        key_hash: String,
+    ) -> anyhow::Result<Option<(Option<String>, String)>> {
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT name, addresses FROM portfolios WHERE key_hash = ?1",
+                [key_hash],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+        })
+        .await
+    }
+
+    pub async fn save_watch(self: &Arc<Self>, watch: StoredWatch) -> anyhow::Result<()> {
+        let now = now_ms() as i64;
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT INTO watches (id, callback_url, secret, addresses, transaction_ids, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    watch.id,
+                    watch.callback_url,
+                    watch.secret,
+                    watch.addresses,
+                    watch.transaction_ids,
+                    now
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// Replaces the JSON list of transaction ids still watched by `id`.
+    pub async fn update_watch_transactions(
+        self: &Arc<Self>,
+        id: String,
+        transaction_ids: String,
+    ) -> anyhow::Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE watches SET transaction_ids = ?1 WHERE id = ?2",
+                params![transaction_ids, id],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    pub async fn delete_watch(self: &Arc<Self>, id: String) -> anyhow::Result<()> {
+        self.run(move |conn| {
+            conn.execute("DELETE FROM watches WHERE id = ?1", [id])
+                .map(|_| ())
+        })
+        .await
+    }
+
+    pub async fn watches(self: &Arc<Self>) -> anyhow::Result<Vec<StoredWatch>> {
+        self.run(|conn| {
+            conn.prepare_cached(
+                "SELECT id, callback_url, secret, addresses, transaction_ids FROM watches ORDER BY created_at",
+            )?
+            .query_map([], |row| {
+                Ok(StoredWatch {
+                    id: row.get(0)?,
+                    callback_url: row.get(1)?,
+                    secret: row.get(2)?,
+                    addresses: row.get(3)?,
+                    transaction_ids: row.get(4)?,
+                })
+            })?
+            .collect()
+        })
+        .await
+    }
+
+    /// Inserts or replaces the label of `address`.
+    pub async fn save_label(self: &Arc<Self>, label: StoredLabel) -> anyhow::Result<()> {
+        let now = now_ms() as i64;
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO labels (address, name, tag, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                params![label.address, label.name, label.tag, now],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// Removes the label of `address`; false when it had none.
+    pub async fn delete_label(self: &Arc<Self>, address: String) -> anyhow::Result<bool> {
+        self.run(move |conn| {
+            conn.execute("DELETE FROM labels WHERE address = ?1", [address])
+                .map(|deleted| deleted > 0)
+        })
+        .await
+    }
+
+    pub async fn labels(self: &Arc<Self>) -> anyhow::Result<Vec<StoredLabel>> {
+        self.run(|conn| {
+            conn.prepare_cached("SELECT address, name, tag FROM labels ORDER BY address")?
+                .query_map([], |row| {
+                    Ok(StoredLabel {
+                        address: row.get(0)?,
+                        name: row.get(1)?,
+                        tag: row.get(2)?,
+                    })
+                })?
+                .collect()
+        })
+        .await
+    }
+
+    /// Logs a reorg in the current epoch; `id` is assigned by the database.
+    pub async fn record_reorg(self: &Arc<Self>, reorg: StoredReorg) -> anyhow::Result<()> {
+        let epoch = self
+            .current_epoch_id()
+            .ok_or_else(|| anyhow::anyhow!("No index epoch started"))?;
+        let removed = serde_json::to_string(&reorg.removed)?;
+        let added = serde_json::to_string(&reorg.added)?;
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT INTO reorgs (epoch, detected_at, daa_score, depth, removed, added)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    epoch,
+                    reorg.detected_at as i64,
+                    reorg.daa_score.map(|d| d as i64),
+                    reorg.removed.len() as i64,
+                    removed,
+                    added
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// Up to `limit` reorgs of the current epoch at least `min_depth` deep, newest first and
+    /// older than id `before`.
+    pub async fn reorgs(
+        self: &Arc<Self>,
+        min_depth: u64,
+        before: Option<i64>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<StoredReorg>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.prepare_cached(
+                "SELECT id, detected_at, daa_score, removed, added FROM reorgs
+                 WHERE epoch = ?1 AND depth >= ?2 AND id < ?3
+                 ORDER BY id DESC LIMIT ?4",
+            )?
+            .query_map(
+                params![
+                    epoch,
+                    min_depth as i64,
+                    before.unwrap_or(i64::MAX),
+                    limit as i64
+                ],
+                |row| {
+                    let list = |column: usize| -> rusqlite::Result<Vec<String>> {
+                        let json: String = row.get(column)?;
+                        serde_json::from_str(&json).map_err(|e| {
+                            rusqlite::Error::FromSqlConversionFailure(
+                                column,
+                                rusqlite::types::Type::Text,
+                                Box::new(e),
+                            )
+                        })
+                    };
+                    Ok(StoredReorg {
+                        id: row.get(0)?,
+                        detected_at: row.get::<_, i64>(1)? as u64,
+                        daa_score: row.get::<_, Option<i64>>(2)?.map(|d| d as u64),
+                        removed: list(3)?,
+                        added: list(4)?,
+                    })
+                },
+            )?
+            .collect()
+        })
+        .await
+    }
+
+    /// Number of reorgs and the deepest one in the current epoch.
+    pub async fn reorg_totals(self: &Arc<Self>) -> anyhow::Result<(u64, u64)> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT COUNT(*), COALESCE(MAX(depth), 0) FROM reorgs WHERE epoch = ?1",
+                [epoch],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+            )
+        })
+        .await
+    }
+
+    /// Up to `limit` blocks of the current epoch with timestamps (or, without `by_timestamp`,
+    /// DAA scores) in `from..=to`, ordered by (that key, hash) and starting after `after`, so
+    /// callers can page through a range.
+    pub async fn blocks_in_range(
+        self: &Arc<Self>,
+        from: u64,
+        to: u64,
+        by_timestamp: bool,
+        after: Option<(u64, String)>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<StoredBlock>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let column = if by_timestamp {
+            "timestamp"
+        } else {
+            "daa_score"
+        };
+        let (after_key, after_hash) = after.unwrap_or((0, String::new()));
+        self.run(move |conn| {
+            let mut blocks = conn
+                .prepare_cached(&format!(
+                    "SELECT hash, daa_score, blue_score, timestamp, size, mass FROM blocks
+                     WHERE epoch = ?1 AND {column} BETWEEN ?2 AND ?3 AND ({column}, hash) > (?4, ?5)
+                     ORDER BY {column}, hash LIMIT ?6"
+                ))?
+                .query_map(
+                    params![
+                        epoch,
+                        from as i64,
+                        to as i64,
+                        after_key as i64,
+                        after_hash,
+                        limit as i64
+                    ],
+                    |row| {
+                        Ok(StoredBlock {
+                            hash: row.get(0)?,
+                            daa_score: row.get::<_, i64>(1)? as u64,
+                            blue_score: row.get::<_, i64>(2)? as u64,
+                            timestamp: row.get::<_, i64>(3)? as u64,
+                            size: row.get::<_, Option<i64>>(4)?.map(|size| size as u64),
+                            mass: row.get::<_, Option<i64>>(5)?.map(|mass| mass as u64),
+                            transaction_ids: Vec::new(),
+                        })
+                    },
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut transaction_ids = conn.prepare_cached(
+                "SELECT id FROM transactions WHERE block_hash = ?1 ORDER BY rowid",
+            )?;
+            for block in &mut blocks {
+                block.transaction_ids = transaction_ids
+                    .query_map([&block.hash], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()?;
+            }
+            Ok(blocks)
+        })
+        .await
+    }
+
+    /// Number of blocks of the current epoch `blocks_in_range` pages through.
+    pub async fn count_blocks_in_range(
+        self: &Arc<Self>,
+        from: u64,
+        to: u64,
+        by_timestamp: bool,
+    ) -> anyhow::Result<u64> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let column = if by_timestamp {
+            "timestamp"
+        } else {
+            "daa_score"
+        };
+        self.run(move |conn| {
+            conn.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM blocks WHERE epoch = ?1 AND {column} BETWEEN ?2 AND ?3"
+                ),
+                params![epoch, from as i64, to as i64],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as u64)
+        })
+        .await
+    }
+
+    /// Hash of an indexed block with the lowest DAA score at or above `daa_score`.
+    pub async fn first_block_at_or_above(
+        self: &Arc<Self>,
+        daa_score: u64,
+    ) -> anyhow::Result<Option<String>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT hash FROM blocks WHERE epoch = ?1 AND daa_score >= ?2 ORDER BY daa_score LIMIT 1",
+                params![epoch, daa_score as i64],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+        .await
+    }
+
+    /// Hashes of up to `limit` indexed blocks nearest to `daa_score` on one side: at or above
+    /// it in ascending order with `above`, else below it in descending order.
+    pub async fn blocks_near_daa(
+        self: &Arc<Self>,
+        daa_score: u64,
+        above: bool,
+        limit: usize,
+    ) -> anyhow::Result<Vec<String>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            let sql = if above {
+                "SELECT hash FROM blocks WHERE epoch = ?1 AND daa_score >= ?2 ORDER BY daa_score LIMIT ?3"
+            } else {
+                "SELECT hash FROM blocks WHERE epoch = ?1 AND daa_score < ?2 ORDER BY daa_score DESC LIMIT ?3"
+            };
+            conn.prepare_cached(sql)?
+                .query_map(params![epoch, daa_score as i64, limit as i64], |row| row.get(0))?
+                .collect()
+        })
+        .await
+    }
+
+    /// Highest DAA score indexed so far.
+    pub async fn indexed_daa_score(self: &Arc<Self>) -> anyhow::Result<Option<u64>> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT MAX(daa_score) FROM blocks WHERE epoch = ?1",
+                [epoch],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .map(|score| score.map(|s| s as u64))
+        })
+        .await
+    }
+
+    /// (DAA score, timestamp) of the indexed blocks nearest to `value` at or below it and at or
+    /// above it, comparing DAA scores or, with `by_timestamp`, timestamps.
+    pub async fn blocks_around(
+        self: &Arc<Self>,
+        value: u64,
+        by_timestamp: bool,
+    ) -> anyhow::Result<(Option<(u64, u64)>, Option<(u64, u64)>)> {
+        let epoch = self.current_epoch_id().unwrap_or(0);
+        let column = if by_timestamp {
+            "timestamp"
+        } else {
+            "daa_score"
+        };
+        self.run(move |conn| {
+            let nearest = |comparison: &str, order: &str| {
+                conn.query_row(
+                    &format!(
+                        "SELECT daa_score, timestamp FROM blocks WHERE epoch = ?1 AND {column} {comparison} ?2
+                         ORDER BY {column} {order} LIMIT 1"
+                    ),
+                    params![epoch, value as i64],
+                    |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+                )
+                .optional()
+            };
+            Ok((nearest("<=", "DESC")?, nearest(">=", "ASC")?))
+        })
+        .await
+    }
+
+    /// Id of the epoch new rows are tagged with, if one has started.
+    pub fn current_epoch_id(&self) -> Option<i64> {
+        Some(self.epoch.load(Ordering::Acquire)).filter(|&id| id > 0)
+    }
+
+    pub async fn current_epoch(self: &Arc<Self>) -> anyhow::Result<Option<Epoch>> {
+        let Some(id) = self.current_epoch_id() else {
+            return Ok(None);
+        };
+        self.run(move |conn| {
+            conn.query_row(&format!("{} WHERE id = ?1", EPOCH_SELECT), [id], |row| {
+                epoch_from_row(row, id)
+            })
+            .optional()
+        })
+        .await
+    }
+
+    /// All epochs, newest first.
+    pub async fn epochs(self: &Arc<Self>) -> anyhow::Result<Vec<Epoch>> {
+        let current = self.current_epoch_id().unwrap_or(0);
+        self.run(move |conn| {
+            let mut statement = conn.prepare(&format!("{} ORDER BY id DESC", EPOCH_SELECT))?;
+            let epochs = statement
+                .query_map([], |row| epoch_from_row(row, current))?
+                .collect();
+            epochs
+        })
+        .await
+    }
+
+    /// Closes the current epoch (if any) with `end_reason` and starts a new one. The cursor
+    /// is cleared so the indexer restarts from the new network's pruning point.
+    pub async fn start_epoch(
+        self: &Arc<Self>,
+        network: String,
+        genesis: String,
+        pruning_point: String,
+        daa_score: u64,
+        end_reason: Option<String>,
+    ) -> anyhow::Result<i64> {
+        let now = now_ms() as i64;
+        let id = self
+            .run(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "UPDATE epochs SET ended_at = ?1, end_reason = ?2 WHERE ended_at IS NULL",
+                    params![now, end_reason],
+                )?;
+                tx.execute(
+                    "INSERT INTO epochs (network, genesis, pruning_point, started_at, start_daa_score, last_daa_score) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                    params![network, genesis, pruning_point, now, daa_score as i64],
+                )?;
+                let id = tx.last_insert_rowid();
+                tx.execute("DELETE FROM meta WHERE key = ?1", [CURSOR_KEY])?;
+                tx.commit()?;
+                Ok(id)
+            })
+            .await?;
+        self.epoch.store(id, Ordering::Release);
+        Ok(id)
+    }
+
+    /// Deletes every indexed row and all epochs, leaving an empty index.
+    pub async fn wipe(self: &Arc<Self>) -> anyhow::Result<()> {
+        self.run(|conn| {
+            let tx = conn.transaction()?;
+            for table in [
+                "blocks",
+                "transactions",
+                "outputs",
+                "inputs",
+                "cold_segments",
+                "address_activity",
+                "balances",
+                "reorgs",
+                "tokens",
+                "token_balances",
+                "token_operations",
+                "token_inscriptions",
+                "accepted_transactions",
+                "red_blocks",
+                "epochs",
+            ] {
+                tx.execute(&format!("DELETE FROM {}", table), [])?;
+            }
+            tx.execute("DELETE FROM meta WHERE key = ?1", [CURSOR_KEY])?;
+            tx.commit()
+        })
+        .await?;
+        self.epoch.store(0, Ordering::Release);
+        self.set_network_mismatch(None);
+        self.cold_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+        Ok(())
+    }
+
+    /// Why the index must not be served, if the node is on another network.
+    pub fn network_mismatch(&self) -> Option<String> {
+        self.network_mismatch
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set_network_mismatch(&self, mismatch: Option<String>) {
+        *self
+            .network_mismatch
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = mismatch;
+    }
+
+    /// Records the latest pruning point of the current epoch.
+    pub async fn set_epoch_pruning_point(
+        self: &Arc<Self>,
+        pruning_point: String,
+    ) -> anyhow::Result<()> {
+        let Some(id) = self.current_epoch_id() else {
+            return Ok(());
+        };
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE epochs SET pruning_point = ?1 WHERE id = ?2",
+                params![pruning_point, id],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+
+    /// Records the genesis of the current epoch's network.
+    pub async fn set_epoch_genesis(self: &Arc<Self>, genesis: String) -> anyhow::Result<()> {
+        let Some(id) = self.current_epoch_id() else {
+            return Ok(());
+        };
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE epochs SET genesis = ?1 WHERE id = ?2",
+                params![genesis, id],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+}
+
+const EPOCH_SELECT: &str = "SELECT id, network, pruning_point, started_at, start_daa_score, last_daa_score, ended_at, end_reason, genesis FROM epochs";
+
+fn epoch_from_row(row: &rusqlite::Row, current: i64) -> rusqlite::Result<Epoch> {
+    let id: i64 = row.get(0)?;
+    Ok(Epoch {
+        id,
+        network: row.get(1)?,
+        genesis: row.get(8)?,
+        pruning_point: row.get(2)?,
+        started_at: row.get::<_, i64>(3)? as u64,
+        start_daa_score: row.get::<_, i64>(4)? as u64,
+        last_daa_score: row.get::<_, i64>(5)? as u64,
+        ended_at: row.get::<_, Option<i64>>(6)?.map(|t| t as u64),
+        end_reason: row.get(7)?,
+        current: id == current,
+    })
+}
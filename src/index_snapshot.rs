@@ -0,0 +1,200 @@
+//! Index snapshots, for bootstrapping an explorer without re-scanning from the pruning point.
+//!
+//! An archive is the index database compressed with zstd. It is taken with `VACUUM INTO`
+//! on a separate read-only connection, so the copy is consistent and compact even while the
+//! indexer keeps writing, and the serving connection is not held up meanwhile. Importing
+//! decompresses next to the index, checks the copy, and only then moves it into place; a
+//! snapshot from an older release is migrated on the next start like any other index.
+//!
+//! Archives carry chain data only: watches (with their callback secrets), portfolios and
+//! labels are cleared from the copy before it is compressed. An import refuses to run while
+//! an explorer has the index open (see `index::lock`), since it replaces the file underneath it.
+
+use crate::migrations;
+use crate::sampler::now_ms;
+use axum::body::Bytes;
+use rusqlite::{Connection, OpenFlags};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+// Fast enough for multi-gigabyte indexes; the cold tier inside is compressed already.
+const ZSTD_LEVEL: i32 = 3;
+
+// Per-deployment tables that never leave the instance.
+const PRIVATE_TABLES: [&str; 3] = ["watches", "portfolios", "labels"];
+
+#[derive(Debug, clap::Args)]
+pub struct ExportSnapshotArgs {
+    /// Archive to write (zstd-compressed SQLite database)
+    output: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ImportSnapshotArgs {
+    /// Archive written by export-snapshot or /admin/index/snapshot
+    archive: PathBuf,
+
+    /// Replace an existing index
+    #[arg(long)]
+    force: bool,
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Compresses a consistent copy of the index at `index_path` into `out`. Blocking; returns
+/// the size of the uncompressed database.
+pub fn write_archive(index_path: &Path, out: impl Write) -> anyhow::Result<u64> {
+    let copy = sibling(index_path, &format!(".snapshot-{}", now_ms()));
+    let result = (|| {
+        let conn = Connection::open_with_flags(index_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| anyhow::anyhow!("Failed to open index {}: {}", index_path.display(), e))?;
+        conn.execute("VACUUM INTO ?1", [copy.to_string_lossy()])?;
+        drop(conn);
+        // secure_delete overwrites the freed pages, so no trace is left in the archive.
+        let conn = Connection::open(&copy)?;
+        conn.pragma_update(None, "secure_delete", "ON")?;
+        for table in PRIVATE_TABLES {
+            conn.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+        drop(conn);
+        let mut encoder = zstd::Encoder::new(out, ZSTD_LEVEL)?;
+        let size = io::copy(&mut BufReader::new(File::open(&copy)?), &mut encoder)?;
+        encoder.finish()?.flush()?;
+        Ok(size)
+    })();
+    let _ = std::fs::remove_file(&copy);
+    result
+}
+
+/// Sends written bytes to a response body; fails once the client has gone away.
+pub struct ChannelWriter(pub mpsc::Sender<io::Result<Bytes>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Client went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Replaces the index at `index_path` with the database in `archive`. Blocking.
+fn restore(archive: &Path, index_path: &Path, force: bool) -> anyhow::Result<()> {
+    if index_path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to replace it",
+            index_path.display()
+        );
+    }
+    // Held until the new index is in place, so an explorer cannot open it halfway.
+    let _lock = crate::index::lock(index_path, true)?;
+    let importing = sibling(index_path, ".importing");
+    let result = (|| {
+        let input = File::open(archive)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", archive.display(), e))?;
+        let mut decoder = zstd::Decoder::new(input)?;
+        let mut output = BufWriter::new(File::create(&importing)?);
+        let size = io::copy(&mut decoder, &mut output).map_err(|e| {
+            anyhow::anyhow!("{} is not an index snapshot: {}", archive.display(), e)
+        })?;
+        output.flush()?;
+        drop(output);
+
+        let conn = Connection::open(&importing)?;
+        let check: String = conn
+            .query_row("PRAGMA quick_check", [], |row| row.get(0))
+            .map_err(|e| {
+                anyhow::anyhow!("{} is not an index snapshot: {}", archive.display(), e)
+            })?;
+        if check != "ok" {
+            anyhow::bail!("The snapshot is corrupt: {}", check);
+        }
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > migrations::latest_version() {
+            anyhow::bail!(
+                "The snapshot has schema version {}, newer than this release's {}",
+                version,
+                migrations::latest_version()
+            );
+        }
+        let (blocks, epochs): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM blocks), (SELECT COUNT(*) FROM epochs)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| {
+                anyhow::anyhow!("{} is not an index snapshot: {}", archive.display(), e)
+            })?;
+        drop(conn);
+        tracing::info!(
+            "Snapshot checked: {} bytes, schema version {}, {} blocks in {} epoch(s)",
+            size,
+            version,
+            blocks,
+            epochs
+        );
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&importing);
+        return Err(e);
+    }
+    // A leftover write-ahead log belongs to the replaced database.
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling(index_path, suffix));
+    }
+    std::fs::rename(&importing, index_path)?;
+    Ok(())
+}
+
+pub async fn run_export(args: ExportSnapshotArgs, index_path: &Path) -> anyhow::Result<()> {
+    if !index_path.exists() {
+        anyhow::bail!("There is no index at {}", index_path.display());
+    }
+    let index_path = index_path.to_path_buf();
+    let output = args.output;
+    tracing::info!(
+        "Writing a snapshot of {} to {}",
+        index_path.display(),
+        output.display()
+    );
+    let size = tokio::task::spawn_blocking(move || {
+        let file = File::create(&output)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", output.display(), e))?;
+        let size = write_archive(&index_path, BufWriter::new(file));
+        if size.is_err() {
+            let _ = std::fs::remove_file(&output);
+        }
+        size
+    })
+    .await??;
+    tracing::info!("Snapshot written ({} bytes uncompressed)", size);
+    Ok(())
+}
+
+pub async fn run_import(args: ImportSnapshotArgs, index_path: &Path) -> anyhow::Result<()> {
+    let index_path = index_path.to_path_buf();
+    tracing::info!(
+        "Importing {} into {}",
+        args.archive.display(),
+        index_path.display()
+    );
+    let path = index_path.clone();
+    tokio::task::spawn_blocking(move || restore(&args.archive, &path, args.force)).await??;
+    tracing::info!(
+        "Index {} restored; start the explorer to continue indexing from the snapshot",
+        index_path.display()
+    );
+    Ok(())
+}
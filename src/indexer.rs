@@ -0,0 +1,225 @@
+//! Background indexer.
+//!
+//! Walks the DAG forward with `get_blocks` from the stored cursor (the pruning point on first
+//! run) and writes every block and transaction into the local index. Blocks never leave the
+//! DAG once added, so the index only needs to append; a network reset starts a new epoch
+//! instead (see `epochs.rs`). With `[index] cold_depth` set, old rows are periodically moved
+//! to the cold tier (see `cold.rs`).
+
+use crate::chain::{ChainSource, Client};
+use crate::index::{IndexHandle, IndexedBlock, IndexedTransaction};
+use crate::network::Network;
+use crate::{block_size, epochs, miners, rpc, tokens, AppState};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcBlock;
+use tokio::time::{sleep, Duration, Instant};
+
+// Delay between polls once the index has caught up with the sink.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Delay after a failed batch (kaspad down, RPC error).
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+// How often to check for a network reset.
+const EPOCH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// How often to move old rows to the cold tier.
+const COLD_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn spawn(state: AppState, index: IndexHandle) {
+    state.shutdown.clone().spawn(async move {
+        let mut next_epoch_check = Instant::now();
+        let mut next_cold_pass = Instant::now() + COLD_INTERVAL;
+        loop {
+            if Instant::now() >= next_epoch_check {
+                if let Err(e) = check_epoch(&state, &index).await {
+                    tracing::warn!("Epoch check failed: {:?}", e);
+                }
+                next_epoch_check = Instant::now() + EPOCH_CHECK_INTERVAL;
+            }
+            if index.current_epoch_id().is_none() || index.network_mismatch().is_some() {
+                // Nothing is indexed until the node is synced and the first epoch starts, or
+                // while the node is on another network.
+                sleep(RETRY_INTERVAL).await;
+                continue;
+            }
+            if let Some(depth) = state.config.index.cold_depth {
+                if Instant::now() >= next_cold_pass {
+                    move_to_cold(&index, depth).await;
+                    next_cold_pass = Instant::now() + COLD_INTERVAL;
+                }
+            }
+            match index_batch(&state, &index).await {
+                Ok(true) => {}
+                Ok(false) => sleep(POLL_INTERVAL).await,
+                Err(e) => {
+                    tracing::warn!("Indexer batch failed: {:?}", e);
+                    // The cursor may have vanished with a reset; check before retrying.
+                    next_epoch_check = Instant::now();
+                    sleep(RETRY_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+async fn check_epoch(state: &AppState, index: &IndexHandle) -> anyhow::Result<()> {
+    let client_guard = state.client.read().await;
+    match client_guard.as_ref() {
+        Some(client) => epochs::check(client, index, state.config.index.auto_reset).await,
+        None => Ok(()),
+    }
+}
+
+async fn move_to_cold(index: &IndexHandle, depth: u64) {
+    match index.move_to_cold(depth).await {
+        Ok(packed) => {
+            for (start, end, rows) in packed {
+                tracing::info!(
+                    "Moved {} rows with DAA scores {}..{} to the cold tier",
+                    rows,
+                    start,
+                    end
+                );
+            }
+        }
+        Err(e) => tracing::warn!("Moving rows to the cold tier failed: {:?}", e),
+    }
+}
+
+/// Indexes one `get_blocks` batch. Returns whether more blocks may be pending.
+async fn index_batch(state: &AppState, index: &IndexHandle) -> anyhow::Result<bool> {
+    let client_guard = state.client.read().await;
+    let Some(client) = client_guard.as_ref() else {
+        return Ok(false);
+    };
+    let batch = next_batch(
+        client,
+        index,
+        None,
+        state.network,
+        state.config.index.tokens,
+    )
+    .await?;
+    Ok(batch.is_some_and(|batch| !batch.caught_up))
+}
+
+/// Outcome of one indexed `get_blocks` batch.
+pub struct Batch {
+    pub blocks: usize,
+    pub cursor: Hash,
+    pub max_daa_score: u64,
+    // The batch ended where it started: the index has reached the sink.
+    pub caught_up: bool,
+}
+
+/// Indexes the `get_blocks` batch starting at `from`, or else at the stored cursor, or on a
+/// fresh index at the pruning point. The new cursor is stored with the blocks, so an
+/// interrupted walk resumes where the last batch ended. With `index_tokens`, KRC-20
+/// inscriptions are applied as well (see `tokens.rs`). None if kaspad returned nothing.
+pub async fn next_batch(
+    client: &Client,
+    index: &IndexHandle,
+    from: Option<Hash>,
+    network: Network,
+    index_tokens: bool,
+) -> anyhow::Result<Option<Batch>> {
+    let low = match (from, index.cursor().await?) {
+        (Some(from), _) => from,
+        (None, Some(cursor)) => cursor
+            .parse::<Hash>()
+            .map_err(|e| anyhow::anyhow!("Invalid index cursor {}: {:?}", cursor, e))?,
+        (None, None) => {
+            let pruning_point = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+                .await?
+                .pruning_point_hash;
+            tracing::info!("Starting index at pruning point {}", pruning_point);
+            pruning_point
+        }
+    };
+
+    let response = rpc::retried("get_blocks", || client.get_blocks(Some(low), true, true)).await?;
+    let Some(&next_cursor) = response.block_hashes.last() else {
+        return Ok(None);
+    };
+    // The low hash is part of every response; on the very first batch it is not indexed yet.
+    let blocks: Vec<IndexedBlock> = response
+        .blocks
+        .iter()
+        .map(|block| indexed_block(block, network, index_tokens))
+        .collect();
+    let count = blocks.len();
+    let max_daa_score = blocks
+        .iter()
+        .map(|block| block.daa_score)
+        .max()
+        .unwrap_or(0);
+    index.insert_blocks(blocks, next_cursor.to_string()).await?;
+
+    let caught_up = next_cursor == low;
+    if !caught_up {
+        tracing::debug!("Indexed {} blocks up to {}", count, next_cursor);
+    }
+    Ok(Some(Batch {
+        blocks: count,
+        cursor: next_cursor,
+        max_daa_score,
+        caught_up,
+    }))
+}
+
+fn indexed_block(block: &RpcBlock, network: Network, index_tokens: bool) -> IndexedBlock {
+    IndexedBlock {
+        hash: block.header.hash.to_string(),
+        daa_score: block.header.daa_score,
+        blue_score: block.header.blue_score,
+        timestamp: block.header.timestamp,
+        miner: miners::coinbase_miner(block),
+        size: block_size::size(block),
+        mass: block_size::mass(block, network),
+        red_hashes: block
+            .verbose_data
+            .as_ref()
+            .filter(|v| v.is_chain_block)
+            .map(|v| {
+                v.merge_set_reds_hashes
+                    .iter()
+                    .map(|hash| hash.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        transactions: block
+            .transactions
+            .iter()
+            .filter_map(|tx| {
+                let id = tx.verbose_data.as_ref()?.transaction_id.to_string();
+                Some(IndexedTransaction {
+                    id,
+                    inputs: tx
+                        .inputs
+                        .iter()
+                        .map(|input| {
+                            (
+                                input.previous_outpoint.transaction_id.to_string(),
+                                input.previous_outpoint.index,
+                            )
+                        })
+                        .collect(),
+                    outputs: tx
+                        .outputs
+                        .iter()
+                        .map(|output| {
+                            let address = output
+                                .verbose_data
+                                .as_ref()
+                                .map(|v| v.script_public_key_address.to_string());
+                            (address, output.value)
+                        })
+                        .collect(),
+                    inscription: if index_tokens {
+                        tokens::parse_inscription(tx)
+                    } else {
+                        None
+                    },
+                })
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,215 @@
+//! Operator-assigned address labels, so well-known testnet actors are recognizable.
+//!
+//! A label is a display name and an optional tag (`faucet`, `pool`, `exchange-sim`,
+//! `team`, ...) for one address. Labels are stored in the index database, loaded into memory
+//! on startup, and changed through `PUT` and `DELETE /admin/labels/{address}`. Address,
+//! block (miner) and transaction responses carry the label of any address they show;
+//! `GET /labels` lists them all.
+
+use crate::index::StoredLabel;
+use crate::{error_response, network, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+const MAX_LABELS: usize = 10_000;
+const MAX_NAME_LENGTH: usize = 64;
+const MAX_TAG_LENGTH: usize = 32;
+
+pub type Labels = Arc<RwLock<HashMap<String, Label>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Label {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>, // Lowercase letters, digits and dashes
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LabeledAddress {
+    address: String,
+    #[serde(flatten)]
+    label: Label,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LabelsResponse {
+    labels: Vec<LabeledAddress>, // Ordered by address
+}
+
+pub async fn load(state: &AppState) {
+    let Some(index) = &state.index else {
+        return;
+    };
+    let stored = match index.labels().await {
+        Ok(stored) => stored,
+        Err(e) => {
+            tracing::warn!("Failed to load address labels: {:?}", e);
+            return;
+        }
+    };
+    let mut labels = state.labels.write().await;
+    for stored in stored {
+        labels.insert(
+            stored.address,
+            Label {
+                name: stored.name,
+                tag: stored.tag,
+            },
+        );
+    }
+    if !labels.is_empty() {
+        tracing::info!("Loaded {} address label(s)", labels.len());
+    }
+}
+
+/// The label of `address`, if it has one.
+pub async fn get(state: &AppState, address: &str) -> Option<Label> {
+    state.labels.read().await.get(address).cloned()
+}
+
+/// Trims the label and checks its name and tag.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn validate(label: Label) -> Result<Label, Response> {
+    let name = label.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("The label name must be 1 to {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+    let tag = label
+        .tag
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty());
+    if let Some(tag) = &tag {
+        let valid = tag.len() <= MAX_TAG_LENGTH
+            && tag
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+        if !valid {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "The tag must be up to {} lowercase letters, digits or dashes",
+                    MAX_TAG_LENGTH
+                ),
+            ));
+        }
+    }
+    Ok(Label { name, tag })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/labels",
+    tag = "addresses",
+    responses(
+        (status = 200, description = "All labeled addresses", body = LabelsResponse)
+    )
+)]
+pub async fn get_labels(State(state): State<AppState>) -> Json<LabelsResponse> {
+    let mut labels: Vec<LabeledAddress> = state
+        .labels
+        .read()
+        .await
+        .iter()
+        .map(|(address, label)| LabeledAddress {
+            address: address.clone(),
+            label: label.clone(),
+        })
+        .collect();
+    labels.sort_by(|a, b| a.address.cmp(&b.address));
+    Json(LabelsResponse { labels })
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/labels/{address}",
+    tag = "admin",
+    params(("address" = String, Path, description = "Kaspa address")),
+    request_body = Label,
+    responses(
+        (status = 200, description = "Label stored, replacing any previous one", body = LabeledAddress),
+        (status = 400, description = "Invalid address, name or tag", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 503, description = "The index is disabled, or the label limit is reached", body = ErrorResponse)
+    )
+)]
+pub async fn set_label(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(label): Json<Label>,
+) -> Result<Json<LabeledAddress>, Response> {
+    let address = network::parse_address(&state, address.trim())?.to_string();
+    let label = validate(label)?;
+    let index = state.index.clone().ok_or_else(|| {
+        error_response(StatusCode::SERVICE_UNAVAILABLE, "Labels require the index")
+    })?;
+
+    let mut labels = state.labels.write().await;
+    if labels.len() >= MAX_LABELS && !labels.contains_key(&address) {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many labels stored",
+        ));
+    }
+    index
+        .save_label(StoredLabel {
+            address: address.clone(),
+            name: label.name.clone(),
+            tag: label.tag.clone(),
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to save label of {}: {:?}", address, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save label")
+        })?;
+    labels.insert(address.clone(), label.clone());
+    tracing::info!("Admin labeled {} as {:?}", address, label.name);
+    Ok(Json(LabeledAddress { address, label }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/labels/{address}",
+    tag = "admin",
+    params(("address" = String, Path, description = "Kaspa address")),
+    responses(
+        (status = 204, description = "Label removed"),
+        (status = 400, description = "Invalid address or an address of another network", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 404, description = "The address has no label", body = ErrorResponse),
+        (status = 503, description = "The index is disabled", body = ErrorResponse)
+    )
+)]
+pub async fn delete_label(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<StatusCode, Response> {
+    let address = network::parse_address(&state, address.trim())?.to_string();
+    let index = state.index.clone().ok_or_else(|| {
+        error_response(StatusCode::SERVICE_UNAVAILABLE, "Labels require the index")
+    })?;
+    let mut labels = state.labels.write().await;
+    let deleted = index.delete_label(address.clone()).await.map_err(|e| {
+        tracing::error!("Failed to delete label of {}: {:?}", address, e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete label")
+    })?;
+    labels.remove(&address);
+    if !deleted {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "The address has no label",
+        ));
+    }
+    tracing::info!("Admin removed the label of {}", address);
+    Ok(StatusCode::NO_CONTENT)
+}
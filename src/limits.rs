@@ -0,0 +1,40 @@
+//! Response size guard.
+//!
+//! Handlers cap their lists using the configured limits; this middleware is the backstop
+//! that refuses to send any single buffered response larger than `max_response_bytes`.
+
+use crate::{error_response, AppState};
+use axum::{
+    body::HttpBody,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+/// Replaces responses whose known size exceeds the configured maximum with a 500 error.
+/// Streaming bodies without a size bound are passed through; they are bounded by their producers.
+pub async fn enforce_max_response_size(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+    let max = state.config.limits.max_response_bytes;
+    match response.body().size_hint().upper() {
+        Some(len) if len as usize > max => {
+            tracing::warn!(
+                "Refusing {} byte response for {} (limit {} bytes)",
+                len,
+                path,
+                max
+            );
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("Response exceeds the {} byte limit", max),
+            )
+        }
+        _ => response,
+    }
+}
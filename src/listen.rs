@@ -0,0 +1,242 @@
+//! Where the server listens.
+//!
+//! `--listen` takes a socket address (`127.0.0.1:3000`, or `[::1]:3000` for IPv6) or a Unix
+//! socket path (`unix:/run/explorer.sock`), and can be repeated to serve the explorer on
+//! several of them at once, e.g. loopback for a reverse proxy plus a socket for local tools.
+//! Without it the explorer listens on `0.0.0.0:<--port>`.
+//!
+//! Clients on a Unix socket are seen as 127.0.0.1 by rate limiting and the faucet; behind a
+//! proxy on the socket, set `trust_forwarded_for` in `[rate_limit]` so that limits apply to
+//! the proxy's clients. A socket file left behind by an earlier run is replaced, and the
+//! socket file is removed at shutdown.
+//!
+//! With TLS configured (`tls.rs`), the TCP listeners serve HTTPS instead.
+
+use crate::tls::{self, Tls};
+use axum::Router;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("unix: needs a socket path, e.g. unix:/run/explorer.sock".to_string());
+            }
+            return Ok(ListenAddr::Unix(PathBuf::from(path)));
+        }
+        s.parse().map(ListenAddr::Tcp).map_err(|_| {
+            format!(
+                "{} is neither host:port (IPv6 as [address]:port) nor unix:/path",
+                s
+            )
+        })
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "http://{}", addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Serves `app` on every address until SIGINT/SIGTERM, then lets in-flight requests finish.
+/// A listener that fails stops the others too, rather than leaving the server half up.
+pub async fn serve(addrs: &[ListenAddr], app: Router, tls: Option<Tls>) -> anyhow::Result<()> {
+    let stop = CancellationToken::new();
+    let mut servers = JoinSet::new();
+    for addr in addrs {
+        match (addr, &tls) {
+            (ListenAddr::Tcp(socket_addr), Some(tls)) => {
+                let listener = std::net::TcpListener::bind(socket_addr)
+                    .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+                    .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", addr, e))?;
+                let server = axum_server::from_tcp_rustls(listener, tls.config.clone());
+                servers.spawn(serve_https(server, app.clone(), stop.clone()));
+                tracing::info!("Starting explorer on https://{}", socket_addr);
+                continue;
+            }
+            (ListenAddr::Tcp(socket_addr), None) => {
+                let listener = tokio::net::TcpListener::bind(socket_addr)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", addr, e))?;
+                let service = app
+                    .clone()
+                    .into_make_service_with_connect_info::<SocketAddr>();
+                let stop = stop.clone();
+                servers.spawn(async move {
+                    axum::serve(listener, service)
+                        .with_graceful_shutdown(stop.cancelled_owned())
+                        .await
+                        .map_err(anyhow::Error::from)
+                });
+            }
+            #[cfg(unix)]
+            (ListenAddr::Unix(path), _) => {
+                let listener = unix::bind(path)?;
+                servers.spawn(unix::serve(
+                    listener,
+                    path.clone(),
+                    app.clone(),
+                    stop.clone(),
+                ));
+            }
+            #[cfg(not(unix))]
+            (ListenAddr::Unix(_), _) => {
+                anyhow::bail!("Cannot listen on {}: Unix sockets need a Unix system", addr)
+            }
+        }
+        tracing::info!("Starting explorer on {}", addr);
+    }
+    if let Some(redirect) = tls.and_then(|tls| tls.redirect) {
+        let https_port = addrs
+            .iter()
+            .find_map(|addr| match addr {
+                ListenAddr::Tcp(socket_addr) => Some(socket_addr.port()),
+                ListenAddr::Unix(_) => None,
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!("--http-redirect needs a TCP listener to redirect to")
+            })?;
+        let listener = tokio::net::TcpListener::bind(redirect)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to listen on http://{}: {}", redirect, e))?;
+        let stop = stop.clone();
+        servers.spawn(async move {
+            axum::serve(listener, tls::redirect_app(https_port))
+                .with_graceful_shutdown(stop.cancelled_owned())
+                .await
+                .map_err(anyhow::Error::from)
+        });
+        tracing::info!("Redirecting http://{} to HTTPS", redirect);
+    }
+
+    let failed = tokio::select! {
+        _ = crate::shutdown::signal() => None,
+        Some(result) = servers.join_next() => Some(result),
+    };
+    stop.cancel();
+    let mut results: Vec<_> = failed.into_iter().collect();
+    while let Some(result) = servers.join_next().await {
+        results.push(result);
+    }
+    for result in results {
+        result??;
+    }
+    Ok(())
+}
+
+async fn serve_https(
+    server: axum_server::Server<axum_server::tls_rustls::RustlsAcceptor>,
+    app: Router,
+    stop: CancellationToken,
+) -> anyhow::Result<()> {
+    let handle = axum_server::Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        stop.cancelled().await;
+        shutdown.graceful_shutdown(None);
+    });
+    server
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .map_err(anyhow::Error::from)
+}
+
+#[cfg(unix)]
+mod unix {
+    use axum::{extract::ConnectInfo, http::Request, Router};
+    use hyper::body::Incoming;
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::os::unix::fs::FileTypeExt;
+    use std::path::{Path, PathBuf};
+    use tokio::net::UnixListener;
+    use tokio::time::{sleep, Duration};
+    use tokio_util::sync::CancellationToken;
+    use tokio_util::task::TaskTracker;
+    use tower::ServiceExt;
+
+    // Peer address handlers see for socket clients.
+    const SOCKET_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+    // Pause after a failed accept (e.g. out of file descriptors) before the next one.
+    const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+    pub fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+        let stale =
+            std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket());
+        if stale {
+            std::fs::remove_file(path)?;
+        }
+        UnixListener::bind(path)
+            .map_err(|e| anyhow::anyhow!("Failed to listen on unix:{}: {}", path.display(), e))
+    }
+
+    pub async fn serve(
+        listener: UnixListener,
+        path: PathBuf,
+        app: Router,
+        stop: CancellationToken,
+    ) -> anyhow::Result<()> {
+        let connections = TaskTracker::new();
+        loop {
+            let stream = tokio::select! {
+                _ = stop.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Failed to accept on unix:{}: {}", path.display(), e);
+                        sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                },
+            };
+            let app = app.clone();
+            let stop = stop.clone();
+            connections.spawn(async move {
+                let service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+                    request.extensions_mut().insert(ConnectInfo(SOCKET_PEER));
+                    app.clone().oneshot(request)
+                });
+                let builder = Builder::new(TokioExecutor::new());
+                let connection =
+                    builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                tokio::pin!(connection);
+                let result = tokio::select! {
+                    result = connection.as_mut() => result,
+                    _ = stop.cancelled() => {
+                        connection.as_mut().graceful_shutdown();
+                        connection.await
+                    }
+                };
+                if let Err(e) = result {
+                    tracing::debug!("Unix socket connection failed: {}", e);
+                }
+            });
+        }
+
+        connections.close();
+        connections.wait().await;
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::warn!("Failed to remove socket {}: {}", path.display(), e);
+        }
+        Ok(())
+    }
+}
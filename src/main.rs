@@ -1,227 +1,995 @@
+mod acceptance;
+mod activity;
+mod address_data;
+mod address_mempool;
+mod address_summary;
+mod address_validation;
+mod admin;
+mod auth;
+mod backfill;
+mod balance_history;
+mod block;
+mod block_intervals;
+mod block_size;
+mod block_transactions;
+mod blocks_range;
+mod cache;
+mod chain;
+mod charts;
+mod cold;
+mod compression;
+mod config;
+mod crawler;
+mod cors;
+mod daa_time;
+mod debug_stats;
+mod decode_script;
+mod emission;
+mod epochs;
+mod errors;
+mod etag;
+mod events;
+mod export;
+mod faucet;
+mod feed;
+mod fee_histogram;
+mod fields;
+mod filter;
+mod format;
+mod geoip;
+mod headers;
+mod home;
+mod index;
+mod index_snapshot;
+mod indexer;
+mod labels;
+mod limits;
+mod listen;
+mod mempool_history;
+mod metrics;
+mod migrations;
+mod mock;
+mod network;
+mod miners;
+mod mining;
+mod nodes;
+mod notifications;
+mod openapi;
+mod orphans;
+mod outpoint;
+mod payment_uri;
+mod portfolio;
+mod proof;
+mod pools;
+mod pruning;
+mod qr;
+mod rate_limit;
+mod raw;
+mod replay;
+mod reorgs;
+mod resolver;
+mod rich_list;
+mod rpc;
+mod sampler;
+mod shutdown;
+mod signing;
+mod sse;
+mod snapshots;
+mod sync;
+mod telemetry;
+mod timeouts;
+mod tls;
+mod tokens;
+mod transaction;
+mod units;
+mod utxo_stats;
+mod validate;
+mod verify_payment;
+mod virtual_state;
+mod wallet;
+mod watch;
+mod whales;
+
 use axum::{
     extract::State,
-    http::StatusCode,
-    response::{Html, Json},
-    routing::{get, Router},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+    middleware::map_response,
+    routing::{get, post, Router},
 };
-use kaspa_grpc_client::GrpcClient;
-use kaspa_rpc_core::api::rpc::RpcApi;
-use kaspa_rpc_core::notify::mode::NotificationMode;
-use kaspa_addresses::Address;
+use kaspa_rpc_core::GetInfoResponse;
 use kaspa_hashes::Hash;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash as StdHash, Hasher};
 use tokio::sync::RwLock;
+use tokio::task::JoinSet;
 use tokio::time::{timeout, sleep, Duration};
-use tower_http::cors::{Any, CorsLayer};
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
 use tower_http::services::ServeDir;
 use clap::Parser;
+use chain::{ChainSource, Client};
+use errors::ApiError;
 
 // Type alias for balance cache to reduce complexity
 type BalanceCache = Arc<RwLock<HashMap<String, (u64, Option<usize>, Vec<UtxoInfo>)>>>;
 
 #[derive(Clone)]
 struct AppState {
-    client: Arc<RwLock<Option<GrpcClient>>>,
+    client: Arc<RwLock<Option<Client>>>,
+    client_pool: Arc<rpc::ClientPool>, // Extra connections for heavy reads, see rpc::checkout
+    network: network::Network, // Selected with --network; kaspad must be on it
     network_info: Arc<RwLock<NetworkInfo>>,
     balance_cache: BalanceCache, // Cache: address -> (balance, utxos)
     peer_info: Arc<RwLock<Vec<PeerInfo>>>, // Cache peer information
-    mempool_cache: Arc<RwLock<Option<(std::time::Instant, MempoolInfo)>>>, // Cache last successful mempool snapshot
+    sampler: sampler::SamplerHandle, // Ring buffer of background network samples
+    started_at: std::time::Instant,
+    activity_cache: activity::ActivityCache, // Last seen UTXO outpoints per address, for churn
+    block_transactions: block_transactions::BlockTransactionsCache, // Summaries of recently paged blocks
+    snapshots: snapshots::Snapshots, // Bulk balance snapshot jobs
+    watches: watch::Watches, // Webhook watches, loaded from the index at startup
+    labels: labels::Labels, // Address labels, loaded from the index at startup
+    home: home::HomeFeed, // Sequenced homepage changes for /api/home
+    rich_list: rich_list::RichListHandle, // Scan schedule of the rich-list balances
+    utxo_stats: utxo_stats::UtxoStatsHandle, // Latest sampled UTXO set estimate
+    stream: sse::EventBus, // Live events for /api/events subscribers
+    virtual_state: Arc<RwLock<virtual_state::VirtualState>>, // Updated from kaspad notifications
+    config: Arc<config::Config>, // Loaded once at startup
+    signer: Option<Arc<signing::Signer>>, // Set when response signing is configured
+    index: Option<index::IndexHandle>, // Local block/transaction index, unless disabled
+    rate_limiters: Option<Arc<rate_limit::RateLimiters>>, // Per-IP token buckets, unless disabled
+    hedging: Option<Arc<rpc::Hedging>>, // Backup nodes for hedged reads, if configured
+    shutdown: shutdown::Shutdown, // Background tasks, cancelled on SIGINT/SIGTERM
+    log_filter: telemetry::LogFilter, // Runtime-adjustable log level filter
+    recent_errors: telemetry::RecentErrors, // Latest warnings and errors, for /admin/diagnostics
+    admin_token: Option<Arc<String>>, // Bearer token for /admin, set when the admin API is enabled
+    api_tokens: Option<Arc<auth::ApiTokens>>, // Scoped tokens, set when [auth] is enabled
+    faucet: Option<Arc<faucet::Faucet>>, // Set when the faucet feature is enabled
+    nodes: nodes::NodesHandle, // Latest tip comparison across the [nodes] urls
+    crawler: crawler::CrawlerHandle, // P2P nodes found by the crawler
+    geoip: Option<Arc<geoip::GeoIp>>, // Set when a GeoLite2 database is configured
+    pools: Option<Arc<pools::PoolSignatures>>, // Set when a pool signature table is configured
+    mempool_history: mempool_history::MempoolHistoryHandle, // Lifecycles of recent mempool transactions
+    whales: whales::WhalesHandle, // Largest recent transactions, for /api/stats/whales
+    response_cache: cache::ResponseCache, // Responses of the [cache] routes
+    debug_stats: Arc<debug_stats::Counters>, // RPC, notification and cache counters of the network
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Node/DAG fields are filled in by the background refresher and stay `None` until its first
+// successful pass.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 struct NetworkInfo {
     server_url: String,
     network: String,
     is_connected: bool,
+    server_version: Option<String>,
+    rpc_api_version: Option<u16>,
+    is_synced: Option<bool>,
+    is_utxo_indexed: Option<bool>,
+    peer_count: Option<usize>,
+    mempool_size: Option<u64>,
+    virtual_daa_score: Option<u64>,
+    sink: Option<String>,
+    explorer_uptime_seconds: u64,
+    api_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deprecation: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+// How often /api/info's node and DAG fields are refreshed.
+const NETWORK_INFO_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, ToSchema)]
 struct BlockInfo {
     hash: String,
     level: u64,
     parents: String,
+    parents_truncated: bool,
+    parents_total_available: usize,
     tx_count: usize,
     timestamp: i64,
     difficulty: f64,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 struct TransactionInfo {
     id: String,
     input_count: usize,
     output_count: usize,
+    #[serde(serialize_with = "crate::units::sompi")]
     amount: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct AddressBalance {
     address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
     balance: u64,
     utxo_count_total: Option<usize>,
     utxos: Vec<UtxoInfo>,
+    truncated: bool,
+    total_available: Option<usize>, // None when the UTXO enumeration failed or timed out
+    activity: Option<activity::AddressActivity>,
+    summary: Option<address_summary::AddressSummary>, // None when the mempool lookup failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<labels::Label>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 struct UtxoInfo {
     outpoint: String,
+    #[serde(serialize_with = "crate::units::sompi")]
     amount: u64,
     script_public_key: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 struct PeerInfo {
     id: String,
     address: String,
     is_connected: bool,
     last_seen: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<geoip::Location>, // With a GeoIP database, for public addresses
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 struct MempoolInfo {
     size: usize,
     transactions: Vec<TransactionInfo>,
+    truncated: bool,
+    total_available: usize,
+}
+
+// Current API version; routes are mounted under /api/<API_VERSION>.
+const API_VERSION: &str = "v1";
+const UNVERSIONED_DEPRECATION: &str = "Unversioned /api paths are deprecated, use /api/v1";
+
+/// Error response with a retry hint, for conditions clients should back off from; see
+/// `errors.rs` for the body.
+fn retry_after_response(status: StatusCode, error: &str, retry_after: Duration) -> Response {
+    ApiError::from_status(status, error).with_retry_after(retry_after).into_response()
+}
+
+fn error_response(status: StatusCode, error: &str) -> Response {
+    ApiError::from_status(status, error).into_response()
 }
 
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
-    error: String,
+fn not_connected() -> Response {
+    ApiError::NotConnected.into_response()
 }
 
-#[derive(Debug, Serialize)]
+/// The local index, unless it is disabled or was built from another network than kaspad's.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn require_index(state: &AppState) -> Result<index::IndexHandle, Response> {
+    let index = state
+        .index
+        .clone()
+        .ok_or_else(|| ApiError::IndexUnavailable("The transaction index is disabled".to_string()).into_response())?;
+    match index.network_mismatch() {
+        Some(mismatch) => Err(ApiError::IndexUnavailable(mismatch).into_response()),
+        None => Ok(index),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 struct BlocksResponse {
     total_count: usize,
     blocks: Vec<BlockInfo>,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+struct BlocksExistRequest {
+    hashes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct BlocksExistResponse {
+    known: Vec<String>,
+    unknown: Vec<String>,
+}
+
+// Upper bound on hashes per existence check, each one costs a get_block RPC.
+const MAX_EXISTS_HASHES: usize = 1000;
+// get_block lookups of one existence check in flight at once.
+const EXISTS_CONCURRENCY: usize = 16;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::init();
-
     let cli = Cli::parse();
+    let kaspad_url = if cli.mock {
+        mock::url(cli.network)
+    } else {
+        cli.kaspad_url
+            .clone()
+            .unwrap_or_else(|| format!("127.0.0.1:{}", cli.network.default_rpc_port()))
+    };
+    let mut config = match &cli.config {
+        Some(path) => config::Config::load(path)?,
+        None => config::Config::default(),
+    };
+    if cli.cors_allow_all {
+        config.cors.allow_all = true;
+    }
+    if !cli.cors_origin.is_empty() {
+        config.cors.allowed_origins = cli.cors_origin.clone();
+    }
+    if cli.auto_reset_index {
+        config.index.auto_reset = true;
+    }
+    // Subcommands that print to stdout keep their logs out of it.
+    let to_stderr = matches!(cli.command, Some(Command::Replay(_) | Command::Export(_)));
+    let (tracer_provider, log_filter, recent_errors) =
+        telemetry::init(cli.log_format, &config.telemetry, to_stderr)?;
+    rpc::set_retry_policy(&config.retry);
+    if cli.migrate_only {
+        index::Index::open(&config.index.path)?;
+        tracing::info!(
+            "Index {} is at schema version {}",
+            config.index.path.display(),
+            migrations::latest_version()
+        );
+        return Ok(());
+    }
+    match cli.command {
+        Some(Command::Replay(args)) => return replay::run(args, &config).await,
+        Some(Command::Backfill(args)) => return backfill::run(args, &kaspad_url, cli.network, &config).await,
+        Some(Command::Export(args)) => return export::run(args, &config).await,
+        Some(Command::ExportSnapshot(args)) => {
+            return index_snapshot::run_export(args, &config.index.path).await
+        }
+        Some(Command::ImportSnapshot(args)) => {
+            return index_snapshot::run_import(args, &config.index.path).await
+        }
+        Some(Command::Serve) | None => {}
+    }
+    tracing::info!("Enabled features: {:?}", config.features);
+    let admin_token = admin::token(&config.features, cli.admin_token.clone())?;
+    let api_tokens = auth::ApiTokens::load(&config.auth)?;
+    let signer = match &config.signing.key_file {
+        Some(path) => {
+            let signer = signing::Signer::load(path)?;
+            tracing::info!("Signing JSON responses with Ed25519 key {}", signer.public_key_hex());
+            Some(Arc::new(signer))
+        }
+        None => None,
+    };
+    let faucet = if config.features.faucet {
+        let path = config
+            .faucet
+            .key_file
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("The faucet feature requires [faucet] key_file"))?;
+        Some(Arc::new(faucet::Faucet::load(path, &config.faucet)?))
+    } else {
+        None
+    };
+    network::check_config(&config)?;
+    if config.index.cold_depth.is_some_and(|depth| depth < cold::SEGMENT_SPAN) {
+        anyhow::bail!("[index] cold_depth must be at least {} DAA scores", cold::SEGMENT_SPAN);
+    }
+    let tx_index = if config.index.enabled {
+        Some(index::Index::open(&config.index.path)?)
+    } else {
+        None
+    };
+    let cors_layer = cors::layer(&config.cors, &config.features)?;
+    let tls = match (&cli.tls_cert, &cli.tls_key) {
+        (Some(cert), Some(key)) => Some(tls::Tls {
+            config: tls::load(cert, key).await?,
+            redirect: cli.http_redirect,
+        }),
+        _ => None,
+    };
+    let rate_limiters = config
+        .rate_limit
+        .enabled
+        .then(|| rate_limit::RateLimiters::new(&config.rate_limit));
+    let debug_stats = Arc::new(debug_stats::Counters::default());
+    let hedging = rpc::Hedging::connect(&config.hedging).await;
+    let geoip = geoip::GeoIp::load(&config.geoip)?;
+    let pools = pools::PoolSignatures::load(&config.pools, cli.network)?;
+    let response_cache = cache::ResponseCache::new(&config.cache);
     
     let network_info = NetworkInfo {
-        server_url: cli.kaspad_url.clone(),
-        network: "testnet-12".to_string(),
+        server_url: kaspad_url.clone(),
+        network: cli.network.name().to_string(),
         is_connected: false,
+        ..Default::default()
     };
 
     let state = AppState {
         client: Arc::new(RwLock::new(None)),
+        client_pool: Arc::default(),
+        network: cli.network,
         network_info: Arc::new(RwLock::new(network_info)),
         balance_cache: Arc::new(RwLock::new(HashMap::new())),
         peer_info: Arc::new(RwLock::new(Vec::new())),
-        mempool_cache: Arc::new(RwLock::new(None)),
+        sampler: Arc::new(RwLock::new(sampler::Sampler::default())),
+        started_at: std::time::Instant::now(),
+        activity_cache: activity::new_cache(),
+        block_transactions: Arc::default(),
+        snapshots: Arc::default(),
+        watches: Arc::default(),
+        labels: Arc::default(),
+        home: Arc::default(),
+        rich_list: Arc::default(),
+        utxo_stats: Arc::default(),
+        stream: sse::bus(),
+        virtual_state: Arc::new(RwLock::new(virtual_state::VirtualState::default())),
+        config: Arc::new(config),
+        signer,
+        index: tx_index,
+        rate_limiters,
+        hedging,
+        shutdown: shutdown::Shutdown::default().with_counters(debug_stats.clone()),
+        log_filter,
+        recent_errors,
+        admin_token,
+        api_tokens,
+        faucet,
+        nodes: Arc::default(),
+        crawler: Arc::default(),
+        geoip,
+        pools,
+        mempool_history: Arc::default(),
+        whales: Arc::default(),
+        response_cache,
+        debug_stats,
     };
 
     // Connect to kaspad
-    if let Err(e) = connect_to_kaspad(&state, &cli.kaspad_url).await {
-        log::error!("Failed to connect to kaspad: {}", e);
+    tracing::info!("Exploring {}", state.network.name());
+    start(&state, &kaspad_url).await;
+    nodes::spawn(state.clone());
+    if state.config.features.crawler {
+        crawler::spawn(state.clone());
+    }
+    if let Some(limiters) = &state.rate_limiters {
+        rate_limit::spawn_cleanup(limiters.clone(), &state.shutdown);
     }
 
-    // Create router
-    let app = Router::new()
+    // Additional networks, each with its own node, caches and index
+    let mut networks = Vec::new();
+    for extra in &state.config.networks {
+        let url = if cli.mock {
+            mock::url(extra.network)
+        } else {
+            extra
+                .kaspad_url
+                .clone()
+                .unwrap_or_else(|| format!("127.0.0.1:{}", extra.network.default_rpc_port()))
+        };
+        let index = match &extra.index_path {
+            Some(path) => Some(index::Index::open(path)?),
+            None => None,
+        };
+        let network_state = network_state(&state, extra.network, &url, index);
+        tracing::info!("Exploring {} under /{}", extra.network.name(), extra.prefix);
+        start(&network_state, &url).await;
+        networks.push((extra.prefix.clone(), network_state));
+    }
+
+    // Create router. The API is served under /api/v1; the unversioned /api paths remain as
+    // deprecated aliases of the same routes so existing frontends keep working.
+    let api = api_routes(&state);
+    let mut app = Router::new()
         .route("/", get(index))
-        .route("/api/info", get(get_network_info))
-        .route("/api/blocks", get(get_blocks))
-        .route("/api/mempool", get(get_mempool))
-        .route("/api/address/:address", get(get_address_balance))
-        .route("/api/peers", get(get_peer_info))
-        .nest_service("/static", ServeDir::new("static"))
+        .route("/status.txt", get(get_status_text))
+        .route("/feed.xml", get(feed::get_feed))
+        .nest(&format!("/api/{}", API_VERSION), api.clone())
+        .nest(
+            "/api",
+            api.layer(axum::middleware::map_response(mark_deprecated)),
+        )
+        .nest_service("/static", ServeDir::new("static"));
+    if state.config.features.admin {
+        app = app.nest("/admin", admin::routes(&state));
+    }
+    if state.config.features.metrics {
+        app = app.route("/metrics", get(metrics::get_metrics));
+    }
+    for (prefix, network_state) in &networks {
+        let page = Html(network::index_page(prefix));
+        let api = api_routes(network_state)
+            .layer(axum::middleware::map_response_with_state(
+                network_state.clone(),
+                network::mark_served_by,
+            ))
+            .with_state(network_state.clone());
+        app = app
+            .route(
+                &format!("/{}", prefix),
+                get(move || {
+                    let page = page.clone();
+                    async move { page }
+                }),
+            )
+            .nest(&format!("/{}/api/{}", prefix, API_VERSION), api);
+        if state.config.features.admin {
+            app = app.nest(
+                &format!("/{}/admin", prefix),
+                admin::network_routes(network_state).with_state(network_state.clone()),
+            );
+        }
+    }
+    if let Some(faucet) = &state.faucet {
+        tracing::info!(
+            "Faucet enabled, paying from {}",
+            faucet.address(state.network.prefix())
+        );
+        app = app.route("/faucet", get(faucet::page));
+    }
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(state.clone(), limits::enforce_max_response_size))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), units::apply_units))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), fields::select_fields))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), sync::flag_syncing))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), signing::sign_responses))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), format::negotiate))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), etag::conditional_get))
+        .layer(compression::layer(&state.config.compression))
         .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::new(telemetry::REQUEST_ID_HEADER, MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(telemetry::request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis)),
+                )
+                .layer(PropagateRequestIdLayer::new(telemetry::REQUEST_ID_HEADER)),
         )
-        .with_state(state);
+        .layer(cors_layer)
+        .with_state(state.clone());
+
+    let mut addrs = cli.listen.clone();
+    if addrs.is_empty() {
+        addrs.push(listen::ListenAddr::Tcp(SocketAddr::from(([0, 0, 0, 0], cli.port))));
+    }
+    listen::serve(&addrs, app, tls).await?;
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], cli.port));
-    log::info!("Starting explorer on http://{}", addr);
+    // In-flight requests have drained; stop background work before tearing down what it uses.
+    state.shutdown.stop().await;
+    disconnect(&state).await;
+    for (_, network_state) in &networks {
+        disconnect(network_state).await;
+    }
+    tracing::info!("Shutdown complete");
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("Failed to flush OTLP spans: {:?}", e);
+        }
+    }
 
     Ok(())
 }
 
-async fn connect_to_kaspad(state: &AppState, url: &str) -> anyhow::Result<()> {
-    log::info!("Connecting to kaspad at: {}", url);
-    
-    // Always use grpc:// for gRPC connections
-    let grpc_url = if url.starts_with("grpc://") {
-        url.to_string()
-    } else {
-        format!("grpc://{}", url.replace("http://", "").replace("https://", ""))
-    };
-    
-    log::info!("Using gRPC URL: {}", grpc_url);
-    
-    // Prefer the more robust connection used by the Stratum bridge:
-    // - explicit grpc:// prefix
-    // - extended request timeout
-    // - client start()
-    let client = match GrpcClient::connect_with_args(
-        NotificationMode::Direct,
-        grpc_url.clone(),
-        None,
-        true,
-        None,
-        false,
-        Some(500_000),
-        Default::default(),
-    )
-    .await
-    {
-        Ok(c) => {
-            c.start(None).await;
-            c
+/// Connects `state` to kaspad at `url` and starts the background work of its network.
+async fn start(state: &AppState, url: &str) {
+    let connected = debug_stats::counting(state.debug_stats.clone(), connect_to_kaspad(state, url));
+    if let Err(e) = connected.await {
+        tracing::error!("Failed to connect to kaspad for {}: {}", state.network.name(), e);
+    }
+
+    sampler::spawn(state.clone());
+    if state.config.features.analytics {
+        mempool_history::spawn(state.clone());
+    }
+    spawn_network_info_refresher(state.clone());
+    watch::load(state).await;
+    labels::load(state).await;
+    notifications::spawn(state.clone());
+    sse::spawn(state.clone());
+    if let Some(tx_index) = &state.index {
+        indexer::spawn(state.clone(), tx_index.clone());
+        if state.config.index.rich_list {
+            rich_list::spawn(state.clone(), tx_index.clone());
         }
-        Err(e) => {
-            log::warn!("connect_with_args failed, falling back to connect(): {:?}", e);
-            GrpcClient::connect(grpc_url).await?
+        if state.config.features.analytics {
+            utxo_stats::spawn(state.clone(), tx_index.clone());
         }
+    }
+}
+
+/// State of an additional network: its own node, caches, index and event bus, next to the
+/// process-wide config, signing, rate limits, logging and shutdown of `primary`.
+fn network_state(
+    primary: &AppState,
+    network: network::Network,
+    url: &str,
+    index: Option<index::IndexHandle>,
+) -> AppState {
+    let debug_stats = Arc::new(debug_stats::Counters::default());
+    let network_info = NetworkInfo {
+        server_url: url.to_string(),
+        network: network.name().to_string(),
+        is_connected: false,
+        ..Default::default()
     };
-    
+    AppState {
+        client: Arc::new(RwLock::new(None)),
+        client_pool: Arc::default(),
+        network,
+        network_info: Arc::new(RwLock::new(network_info)),
+        balance_cache: Arc::new(RwLock::new(HashMap::new())),
+        peer_info: Arc::new(RwLock::new(Vec::new())),
+        sampler: Arc::new(RwLock::new(sampler::Sampler::default())),
+        activity_cache: activity::new_cache(),
+        block_transactions: Arc::default(),
+        snapshots: Arc::default(),
+        watches: Arc::default(),
+        labels: Arc::default(),
+        home: Arc::default(),
+        rich_list: Arc::default(),
+        utxo_stats: Arc::default(),
+        stream: sse::bus(),
+        virtual_state: Arc::new(RwLock::new(virtual_state::VirtualState::default())),
+        index,
+        hedging: None, // Backup nodes serve the primary network
+        faucet: None,
+        nodes: Arc::default(),
+        crawler: Arc::default(),
+        pools: None, // Signature addresses are checked against the primary network
+        mempool_history: Arc::default(),
+        whales: Arc::default(),
+        response_cache: cache::ResponseCache::new(&primary.config.cache),
+        shutdown: primary.shutdown.with_counters(debug_stats.clone()),
+        debug_stats,
+        ..primary.clone()
+    }
+}
+
+/// Disconnects `state`'s nodes and checkpoints its index, once background work has stopped.
+async fn disconnect(state: &AppState) {
+    if let Some(client) = state.client.write().await.take() {
+        if let Err(e) = client.disconnect().await {
+            tracing::warn!("Failed to disconnect from kaspad: {}", e);
+        }
+    }
+    state.client_pool.disconnect().await;
+    if let Some(hedging) = &state.hedging {
+        hedging.disconnect().await;
+    }
+    if let Some(tx_index) = &state.index {
+        if let Err(e) = tx_index.checkpoint().await {
+            tracing::warn!("Failed to checkpoint the index: {:?}", e);
+        }
+    }
+}
+
+fn api_routes(state: &AppState) -> Router<AppState> {
+    // Routes that fan out into many kaspad RPCs or index scans get the stricter rate limit.
+    let heavy = Router::new()
+        .route("/blocks", get(get_blocks).layer(map_response(format::list_of("blocks"))))
+        .route("/blocks/exists", post(blocks_exist))
+        .route(
+            "/blocks/range",
+            get(blocks_range::get_blocks_range).layer(map_response(format::list_of("blocks"))),
+        )
+        .route("/block/:hash", get(block::get_block))
+        .route("/block/by-daa/:score", get(block::get_block_by_daa))
+        .route("/block/:hash/raw", get(raw::get_block_raw))
+        .route(
+            "/block/:hash/transactions",
+            get(block_transactions::get_block_transactions)
+                .layer(map_response(format::list_of("transactions"))),
+        )
+        .route("/headers", get(headers::get_headers).layer(map_response(format::list_of("headers"))))
+        .route(
+            "/filter/match",
+            post(filter::match_filter).layer(map_response(format::list_of("transaction_ids"))),
+        )
+        .route("/transaction/validate", post(validate::validate_transaction))
+        .route("/transaction/:id", get(transaction::get_transaction))
+        .route("/tx/:id", get(transaction::get_transaction))
+        .route("/tx/:id/acceptance", get(acceptance::get_acceptance))
+        .route("/tx/:id/proof", get(proof::get_proof))
+        .route("/tx/:id/raw", get(raw::get_transaction_raw))
+        .route("/outpoint/:txid/:index", get(outpoint::get_outpoint))
+        .route("/mempool", get(get_mempool).layer(map_response(format::list_of("transactions"))))
+        .route("/mempool/orphans", get(orphans::get_orphans).layer(map_response(format::list_of("orphans"))))
+        .route("/mempool/fee-histogram", get(fee_histogram::get_fee_histogram))
+        .route("/address/:address", get(get_address_balance))
+        .route(
+            "/address/:address/utxos",
+            get(address_data::get_address_utxos).layer(map_response(format::list_of("utxos"))),
+        )
+        .route(
+            "/address/:address/transactions",
+            get(address_data::get_address_transactions)
+                .layer(map_response(format::list_of("transactions"))),
+        )
+        .route(
+            "/address/:address/history",
+            get(balance_history::get_balance_history).layer(map_response(format::list_of("points"))),
+        )
+        .route(
+            "/address/:address/mempool",
+            get(address_mempool::get_address_mempool).layer(map_response(format::list_of("transactions"))),
+        )
+        .route("/verify-payment", post(verify_payment::verify_payment))
+        .route("/portfolio", get(portfolio::get_portfolio).post(portfolio::post_portfolio))
+        .route("/wallet/scan", post(wallet::scan_wallet))
+        .route("/snapshots", post(snapshots::post_snapshot))
+        .route("/snapshots/:id", get(snapshots::get_snapshot))
+        .route("/snapshots/:id/download", get(snapshots::download_snapshot))
+        .route("/miners", get(miners::get_miners).layer(map_response(format::list_of("miners"))))
+        .route("/mining", get(mining::get_mining))
+        .route("/pruning", get(pruning::get_pruning))
+        .route("/reorgs", get(reorgs::get_reorgs).layer(map_response(format::list_of("reorgs"))))
+        .route("/watch", post(watch::post_watch))
+        .route("/watch/:id", get(watch::get_watch).delete(watch::delete_watch))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), cache::cached))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), timeouts::limit_heavy))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), auth::authorize))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit::limit_heavy));
+
+    let mut router = Router::new()
+        .route("/info", get(get_network_info))
+        .route("/peers", get(get_peer_info).layer(map_response(format::list_of("peers"))))
+        .route("/sync", get(sync::get_sync_status))
+        .route("/virtual", get(virtual_state::get_virtual))
+        .route("/nodes", get(nodes::get_nodes))
+        .route("/home", get(home::get_home))
+        .route("/events", get(sse::get_events))
+        .route("/tools/payment-uri", get(payment_uri::get_payment_uri))
+        .route("/validate/:address", get(address_validation::validate_address))
+        .route(
+            "/decode-script",
+            get(decode_script::get_decode_script).post(decode_script::post_decode_script),
+        )
+        .route("/address/:address/qr.svg", get(qr::get_qr_svg))
+        .route("/address/:address/qr.png", get(qr::get_qr_png))
+        .route("/daa/:score", get(daa_time::get_daa_time))
+        .route("/time/:unix_ts", get(daa_time::get_time_daa))
+        .route("/epochs", get(epochs::get_epochs).layer(map_response(format::list_of("epochs"))))
+        .route("/labels", get(labels::get_labels).layer(map_response(format::list_of("labels"))))
+        .route("/tokens", get(tokens::get_tokens).layer(map_response(format::list_of("tokens"))))
+        .route("/token/:tick", get(tokens::get_token))
+        .route("/address/:address/tokens", get(tokens::get_address_tokens))
+        .route("/signing-key", get(signing::get_signing_key))
+        .route("/openapi.json", get(openapi::get_openapi))
+        .route("/docs", get(openapi::get_docs));
+
+    if state.config.features.analytics {
+        router = router
+            .route("/stats/network", get(sampler::get_network_stats))
+            .route("/stats/block-intervals", get(block_intervals::get_block_intervals))
+            .route("/stats/block-size", get(block_size::get_block_size))
+            .route("/stats/tps", get(sampler::get_tps))
+            .route(
+                "/mempool/history",
+                get(mempool_history::get_mempool_history).layer(map_response(format::list_of("transactions"))),
+            )
+            .route("/mempool/history/:id", get(mempool_history::get_transaction_lifecycle))
+            .route("/charts/:metric", get(charts::get_chart).layer(map_response(format::list_of("points"))))
+            .route("/stats/utxos", get(utxo_stats::get_utxo_stats))
+            .route("/emission", get(emission::get_emission))
+            .route(
+                "/stats/whales",
+                get(whales::get_whales).layer(map_response(format::list_of("transactions"))),
+            )
+            .route(
+                "/stats/rich-list",
+                get(rich_list::get_rich_list).layer(map_response(format::list_of("addresses"))),
+            );
+    }
+    if state.config.features.faucet {
+        router = router.route("/faucet", get(faucet::get_faucet).post(faucet::post_faucet));
+    }
+    if state.config.features.crawler {
+        router = router
+            .route(
+                "/network/map",
+                get(crawler::get_network_map).layer(map_response(format::list_of("nodes"))),
+            )
+            .route("/network/geo", get(crawler::get_network_geo));
+    }
+
+    router
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), cache::cached))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), timeouts::limit_default))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), auth::authorize))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit::limit_default))
+        .merge(heavy)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), debug_stats::count_calls))
+        .layer(map_response(errors::rejections_as_problems))
+}
+
+/// Flags responses served through the unversioned /api aliases (RFC 8594 style headers).
+async fn mark_deprecated(mut response: Response) -> Response {
+    let headers = response.headers_mut();
+    headers.insert("deprecation", header::HeaderValue::from_static("true"));
+    headers.insert(
+        header::LINK,
+        header::HeaderValue::from_static("</api/v1>; rel=\"successor-version\""),
+    );
+    response
+}
+
+/// Connects to kaspad at `url` and makes it the active node, disconnecting the previous one.
+/// On failure the current client (if any) stays in place.
+async fn connect_to_kaspad(state: &AppState, url: &str) -> anyhow::Result<GetInfoResponse> {
+    let client = rpc::connect(url).await?;
+
     // Test connection
-    let info = client.get_info().await?;
-    log::info!("Connected to kaspad: {:?}", info);
+    let info = rpc::retried("get_info", || client.get_info()).await?;
+    // A node on another network is refused before it replaces the current one.
+    let server_info = rpc::retried("get_server_info", || client.get_server_info()).await?;
+    if let Err(e) = state.network.check_node(&server_info.network_id.to_string()) {
+        let _ = client.disconnect().await;
+        return Err(e);
+    }
+    tracing::info!("Connected to kaspad: {:?}", info);
     
     // Update state
-    {
+    let previous = {
         let mut client_guard = state.client.write().await;
-        *client_guard = Some(client);
-    }
+        client_guard.replace(client)
+    };
     
     {
         let mut network_info = state.network_info.write().await;
+        network_info.server_url = url.to_string();
         network_info.is_connected = true;
     }
+    state.client_pool.fill(url, state.config.rpc.pool_size).await;
+
+    // Disconnecting closes its notification channel, so the listener moves to the new client.
+    if let Some(previous) = previous {
+        if let Err(e) = previous.disconnect().await {
+            tracing::warn!("Failed to disconnect from the previous kaspad: {}", e);
+        }
+    }
     
-    Ok(())
+    Ok(info)
 }
 
 async fn index() -> Html<&'static str> {
     Html(include_str!("../static/index.html"))
 }
 
-async fn get_network_info(State(state): State<AppState>) -> Json<NetworkInfo> {
-    let network_info = state.network_info.read().await;
-    Json(network_info.clone())
+fn spawn_network_info_refresher(state: AppState) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            refresh_network_info(&state).await;
+            sleep(NETWORK_INFO_REFRESH_INTERVAL).await;
+        }
+    });
 }
 
-async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse>, StatusCode> {
+async fn refresh_network_info(state: &AppState) {
     let client_guard = state.client.read().await;
-    let client = client_guard.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let Some(client) = client_guard.as_ref() else {
+        return;
+    };
 
-    // Use DAG info as the single source of truth for the current virtual and counts.
-    let dag_info = client
-        .get_block_dag_info()
+    let info = match rpc::retried("get_info", || client.get_info()).await {
+        Ok(info) => info,
+        Err(e) => {
+            tracing::warn!("Failed to refresh kaspad info: {:?}", e);
+            state.network_info.write().await.is_connected = false;
+            return;
+        }
+    };
+    // The remaining calls are best-effort; keep the previous value when one of them fails.
+    let server_info = rpc::retried("get_server_info", || client.get_server_info()).await.ok();
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await.ok();
+    let peer_count = rpc::retried("get_connected_peer_info", || client.get_connected_peer_info())
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .ok()
+        .map(|p| p.peer_info.len());
+
+    let mut network_info = state.network_info.write().await;
+    network_info.is_connected = true;
+    network_info.server_version = Some(info.server_version);
+    network_info.is_synced = Some(info.is_synced);
+    network_info.is_utxo_indexed = Some(info.is_utxo_indexed);
+    network_info.mempool_size = Some(info.mempool_size);
+    if let Some(server_info) = server_info {
+        network_info.rpc_api_version = Some(server_info.rpc_api_version);
+    }
+    if let Some(dag_info) = dag_info {
+        network_info.virtual_daa_score = Some(dag_info.virtual_daa_score);
+        network_info.sink = Some(dag_info.sink.to_string());
+    }
+    if peer_count.is_some() {
+        network_info.peer_count = peer_count;
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/info",
+    tag = "network",
+    responses((status = 200, description = "Network information and connection status", body = NetworkInfo))
+)]
+async fn get_network_info(
+    State(state): State<AppState>,
+    axum::extract::OriginalUri(uri): axum::extract::OriginalUri,
+) -> Json<NetworkInfo> {
+    let mut network_info = state.network_info.read().await.clone();
+    network_info.explorer_uptime_seconds = state.started_at.elapsed().as_secs();
+    network_info.api_version = API_VERSION.to_string();
+    if !uri.path().starts_with(&format!("/api/{}/", API_VERSION)) {
+        network_info.deprecation = Some(UNVERSIONED_DEPRECATION.to_string());
+    }
+    Json(network_info)
+}
+
+// A sink older than this is reported as a failure by /status.txt.
+const STATUS_MAX_BLOCK_AGE: Duration = Duration::from_secs(300);
+
+/// Single-line status for external uptime monitors that cannot parse JSON:
+/// `OK <daa_score> <block_age_seconds>` with 200, or `FAIL <reason>` with 503.
+#[utoipa::path(
+    get,
+    path = "/status.txt",
+    tag = "network",
+    responses(
+        (status = 200, description = "OK <daa_score> <block_age_seconds>", body = String, content_type = "text/plain"),
+        (status = 503, description = "FAIL <reason>", body = String, content_type = "text/plain")
+    )
+)]
+async fn get_status_text(State(state): State<AppState>) -> (StatusCode, String) {
+    let fail = |reason: String| (StatusCode::SERVICE_UNAVAILABLE, format!("FAIL {}\n", reason));
+
+    let client_guard = state.client.read().await;
+    let Some(client) = client_guard.as_ref() else {
+        return fail("not connected to kaspad".to_string());
+    };
+
+    let dag_info = match rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await {
+        Ok(info) => info,
+        Err(e) => return fail(format!("get_block_dag_info failed: {}", e)),
+    };
+    let sink = match rpc::retried("get_block", || client.get_block(dag_info.sink, false)).await {
+        Ok(block) => block,
+        Err(e) => return fail(format!("get_block failed for sink {}: {}", dag_info.sink, e)),
+    };
+
+    let block_age_seconds = sampler::now_ms().saturating_sub(sink.header.timestamp) / 1000;
+    if block_age_seconds > STATUS_MAX_BLOCK_AGE.as_secs() {
+        return fail(format!("stale sink {} ({}s old)", dag_info.sink, block_age_seconds));
+    }
+
+    (
+        StatusCode::OK,
+        format!("OK {} {}\n", dag_info.virtual_daa_score, block_age_seconds),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/blocks",
+    tag = "blocks",
+    responses(
+        (status = 200, description = "Latest blocks along the selected parent chain", body = BlocksResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse>, Response> {
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+
+    // Use DAG info as the single source of truth for the current virtual and counts.
+    let hedging = state.hedging.as_deref();
+    let dag_info = rpc::hedged(hedging, client, "get_block_dag_info", |c| async move {
+        c.get_block_dag_info().await
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch block DAG info: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block DAG info")
+    })?;
 
     let total_count = dag_info.block_count as usize;
 
@@ -231,27 +999,33 @@ async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse
     let mut display_blocks: Vec<BlockInfo> = Vec::with_capacity(20);
 
     for _ in 0..20 {
-        let block = client
-            .get_block(current_hash.clone(), false)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let block = rpc::hedged(hedging, client, "get_block", |c| async move {
+            c.get_block(current_hash, false).await
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch block {}: {:?}", current_hash, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block")
+        })?;
 
         let mut seen: HashSet<Hash> = HashSet::new();
         let parent_hashes: Vec<Hash> = block
             .header
             .parents_by_level
-            .get(0)
+            .first()
             .into_iter()
             .flat_map(|level0| level0.iter())
             .cloned()
             .filter(|h| seen.insert(*h))
             .collect();
 
+        let max_parents = state.config.limits.max_block_parents;
         let parents = if parent_hashes.is_empty() {
             "None".to_string()
         } else {
             parent_hashes
                 .iter()
+                .take(max_parents)
                 .map(std::string::ToString::to_string)
                 .collect::<Vec<_>>()
                 .join(", ")
@@ -274,6 +1048,8 @@ async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse
             hash: block.header.hash.to_string(),
             level: block.header.daa_score,
             parents,
+            parents_truncated: parent_hashes.len() > max_parents,
+            parents_total_available: parent_hashes.len(),
             tx_count,
             timestamp: block.header.timestamp as i64,
             difficulty,
@@ -283,7 +1059,7 @@ async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse
         let next_hash = block
             .verbose_data
             .as_ref()
-            .map(|v| v.selected_parent_hash.clone())
+            .map(|v| v.selected_parent_hash)
             .filter(|h| *h != Hash::default())
             .or_else(|| parent_hashes.first().cloned());
 
@@ -293,7 +1069,7 @@ async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse
         }
     }
 
-    log::info!(
+    tracing::info!(
         "Returning {} blocks for display (total count: {})",
         display_blocks.len(),
         total_count
@@ -305,55 +1081,127 @@ async fn get_blocks(State(state): State<AppState>) -> Result<Json<BlocksResponse
     }))
 }
 
-async fn get_mempool(State(state): State<AppState>) -> Result<Json<MempoolInfo>, StatusCode> {
-    let client_guard = state.client.read().await;
-    let client = client_guard.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+/// Reports which of the given block hashes the node knows, preserving request order in both lists.
+/// Lets syncing tools locate a common ancestor without downloading full blocks.
+#[utoipa::path(
+    post,
+    path = "/api/v1/blocks/exists",
+    tag = "blocks",
+    request_body = BlocksExistRequest,
+    responses(
+        (status = 200, description = "Known and unknown block hashes", body = BlocksExistResponse),
+        (status = 400, description = "Invalid hash or too many hashes", body = ErrorResponse),
+        (status = 502, description = "kaspad failed a lookup", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, or a lookup failed to reach it", body = ErrorResponse)
+    )
+)]
+async fn blocks_exist(
+    State(state): State<AppState>,
+    Json(request): Json<BlocksExistRequest>,
+) -> Result<Json<BlocksExistResponse>, Response> {
+    if request.hashes.len() > MAX_EXISTS_HASHES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("At most {} hashes can be checked per request", MAX_EXISTS_HASHES),
+        ));
+    }
 
-    // Always query the full mempool (include orphans) so the UI does not bounce between
-    // different subsets. If this call fails intermittently, return the last successful snapshot.
-    // (include_orphan_pool=true, filter_transaction_pool=false) => TransactionQuery::All
-    let mut last_err: Option<anyhow::Error> = None;
-    let mut response = None;
-    for attempt in 0..3 {
-        match client.get_mempool_entries(true, false).await {
-            Ok(entries) => {
-                log::info!("Fetched mempool entries (all): {}", entries.len());
-                response = Some(entries);
+    let hashes = request
+        .hashes
+        .iter()
+        .map(|h| h.parse::<Hash>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid block hash"))?;
+
+    let client = state.client.read().await.clone().ok_or_else(not_connected)?;
+
+    let mut exists = vec![false; hashes.len()];
+    let mut lookups = JoinSet::new();
+    let mut pending = hashes.iter().copied().enumerate();
+    loop {
+        while lookups.len() < EXISTS_CONCURRENCY {
+            let Some((i, hash)) = pending.next() else {
                 break;
-            }
+            };
+            let client = client.clone();
+            // Header-only lookups are enough to prove the node has the block.
+            lookups.spawn(debug_stats::counting(state.debug_stats.clone(), async move {
+                (i, rpc::retried("get_block", || client.get_block(hash, false)).await)
+            }));
+        }
+        let Some(joined) = lookups.join_next().await else {
+            break;
+        };
+        let (i, result) = joined.map_err(|e| {
+            tracing::error!("Block lookup task failed: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to look up blocks")
+        })?;
+        match result {
+            Ok(_) => exists[i] = true,
+            Err(e) if rpc::is_not_found(&e) => {}
+            // Dropping the set aborts the remaining lookups.
             Err(e) => {
-                log::warn!("Failed to get mempool entries (all) attempt {}: {:?}", attempt + 1, e);
-                last_err = Some(e.into());
-                sleep(Duration::from_millis(150)).await;
+                tracing::error!("Failed to look up block {}: {:?}", hashes[i], e);
+                let status = if rpc::is_retryable(&e) {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::BAD_GATEWAY
+                };
+                return Err(error_response(status, "Failed to look up blocks"));
             }
         }
     }
 
-    let response = match response {
-        Some(r) => r,
-        None => {
-            if let Some(e) = last_err {
-                log::error!("Failed to fetch mempool entries after retries: {:?}", e);
-            }
+    let (known, unknown): (Vec<_>, Vec<_>) = hashes
+        .iter()
+        .zip(exists)
+        .partition(|(_, exists)| *exists);
+    Ok(Json(BlocksExistResponse {
+        known: known.into_iter().map(|(hash, _)| hash.to_string()).collect(),
+        unknown: unknown.into_iter().map(|(hash, _)| hash.to_string()).collect(),
+    }))
+}
 
-            // If RPC fails intermittently, it's better to return a recent snapshot than to
-            // bounce between different views. However, do not serve stale data indefinitely.
-            if let Some((ts, cached)) = state.mempool_cache.read().await.clone() {
-                if ts.elapsed() <= Duration::from_secs(15) {
-                    return Ok(Json(cached));
-                }
-            }
+#[utoipa::path(
+    get,
+    path = "/api/v1/mempool",
+    tag = "mempool",
+    responses(
+        (status = 200, description = "Mempool size and a sample of its transactions", body = MempoolInfo),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+async fn get_mempool(State(state): State<AppState>) -> Result<Response, Response> {
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
 
-            // Last resort fallback: still report size if get_info works.
-            let size = client
-                .get_info()
+    // Always query the full mempool (include orphans) so the UI does not bounce between
+    // different subsets. If this call fails, the response cache keeps serving the last
+    // successful snapshot for its stale window.
+    // (include_orphan_pool=true, filter_transaction_pool=false) => TransactionQuery::All
+    let entries = rpc::hedged(state.hedging.as_deref(), client, "get_mempool_entries", |c| async move {
+        c.get_mempool_entries(true, false).await
+    });
+    let response = match entries.await {
+        Ok(entries) => {
+            tracing::info!("Fetched mempool entries (all): {}", entries.len());
+            entries
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch mempool entries after retries: {:?}", e);
+
+            // Last resort fallback: still report size if get_info works. Marked degraded so
+            // it does not replace a cached snapshot.
+            let size = rpc::retried("get_info", || client.get_info())
                 .await
                 .map(|info| info.mempool_size as usize)
                 .unwrap_or(0);
-            return Ok(Json(MempoolInfo {
+            return Ok(cache::degraded(Json(MempoolInfo {
                 size,
                 transactions: vec![],
-            }));
+                truncated: size > 0,
+                total_available: size,
+            })));
         }
     };
     
@@ -390,7 +1238,7 @@ async fn get_mempool(State(state): State<AppState>) -> Result<Json<MempoolInfo>,
     let seed = hasher.finish() as usize;
 
     let len = entries_with_id.len();
-    let limit = 50usize.min(len);
+    let limit = state.config.limits.max_mempool_transactions.min(len);
     let start = if len == 0 { 0 } else { seed % len };
 
     let mut transactions: Vec<TransactionInfo> = Vec::with_capacity(limit);
@@ -409,77 +1257,60 @@ async fn get_mempool(State(state): State<AppState>) -> Result<Json<MempoolInfo>,
     let mempool_info = MempoolInfo {
         size: total_size, // Show actual mempool size, not limited size
         transactions,
+        truncated: limit < total_size,
+        total_available: total_size,
     };
-
-    {
-        let mut cache = state.mempool_cache.write().await;
-        *cache = Some((std::time::Instant::now(), mempool_info.clone()));
-    }
     
-    Ok(Json(mempool_info))
+    Ok(Json(mempool_info).into_response())
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address")),
+    responses(
+        (status = 200, description = "Balance, UTXOs, activity score and a summary with pending mempool amounts", body = AddressBalance),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad or UTXO index disabled", body = ErrorResponse)
+    )
+)]
 async fn get_address_balance(
     State(state): State<AppState>,
     axum::extract::Path(address): axum::extract::Path<String>,
-) -> Result<Json<AddressBalance>, (StatusCode, Json<ErrorResponse>)> {
-    let client_guard = state.client.read().await;
-    let client = client_guard
-        .as_ref()
-        .ok_or((
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse {
-                error: "Not connected to kaspad".to_string(),
-            }),
-        ))?;
+) -> Result<Json<AddressBalance>, Response> {
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
     
-    log::info!("=== BALANCE REQUEST FOR ADDRESS: {} ===", address);
+    tracing::info!("=== BALANCE REQUEST FOR ADDRESS: {} ===", address);
     
     // Parse the address
-    let parsed_address = Address::try_from(address.as_str())
-        .map_err(|_| {
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Invalid address".to_string(),
-                }),
-            )
-        })?;
+    let parsed_address = network::parse_address(&state, &address)?;
 
     // Balance/UTXO calls require UTXO index.
-    let info = client.get_info().await.map_err(|e| {
-        log::error!("Failed to get kaspad info before balance lookup: {:?}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to query kaspad info".to_string(),
-            }),
-        )
+    let info = rpc::retried("get_info", || client.get_info()).await.map_err(|e| {
+        tracing::error!("Failed to get kaspad info before balance lookup: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to query kaspad info")
     })?;
     if !info.is_utxo_indexed {
-        return Err((
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse {
-                error: "Address balance requires kaspad to run with --utxoindex".to_string(),
-            }),
-        ));
+        // Not transient: the node has to be restarted with the index, so no retry hint.
+        return Err(ApiError::UtxoIndexRequired("Address balance requires kaspad to run with --utxoindex".to_string()).into_response());
     }
     
-    log::info!("Fetching balance for address: {}", address);
+    tracing::info!("Fetching balance for address: {}", address);
 
     // Get a quick indexed balance first (fast path).
     // Then attempt to enumerate UTXOs and compute authoritative balance by summing amounts
     // (same approach used by the Stratum bridge prom balance collector).
-    let indexed_balance = client
-        .get_balance_by_address(parsed_address.clone())
+    let indexed_balance = rpc::retried(
+        "get_balance_by_address",
+        || client.get_balance_by_address(parsed_address.clone()),
+    )
         .await
         .map_err(|e| {
-            log::error!("Failed to get indexed balance for address {}: {:?}", address, e);
-            (
+            tracing::error!("Failed to get indexed balance for address {}: {:?}", address, e);
+            error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to fetch indexed balance (is --utxoindex enabled?)".to_string(),
-                }),
+                "Failed to fetch indexed balance (is --utxoindex enabled?)",
             )
         })?;
 
@@ -487,10 +1318,11 @@ async fn get_address_balance(
     let mut display_utxos = Vec::new();
     let mut utxo_count_total: Option<usize> = None;
     let mut computed_balance: Option<u64> = None;
+    let mut address_activity = None;
 
     match timeout(
         Duration::from_secs(20),
-        client.get_utxos_by_addresses(vec![parsed_address]),
+        rpc::retried("get_utxos_by_addresses", || client.get_utxos_by_addresses(vec![parsed_address.clone()])),
     )
     .await
     {
@@ -500,7 +1332,7 @@ async fn get_address_balance(
             for (i, utxo) in utxos_response.iter().enumerate() {
                 let amount = utxo.utxo_entry.amount;
                 sum += amount;
-                if i < 100 {
+                if i < state.config.limits.max_utxos {
                     display_utxos.push(UtxoInfo {
                         outpoint: format!("{}:{}", utxo.outpoint.transaction_id, utxo.outpoint.index),
                         amount,
@@ -510,8 +1342,26 @@ async fn get_address_balance(
             }
             computed_balance = Some(sum);
 
+            // Activity scoring is best-effort: skip it if the DAG info call fails.
+            match rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await {
+                Ok(dag_info) => {
+                    let daa_per_second = state.sampler.read().await.daa_per_second(state.network);
+                    address_activity = Some(
+                        activity::score(
+                            &state.activity_cache,
+                            &address,
+                            &utxos_response,
+                            dag_info.virtual_daa_score,
+                            daa_per_second,
+                        )
+                        .await,
+                    );
+                }
+                Err(e) => tracing::warn!("Failed to get DAG info for activity score of {}: {:?}", address, e),
+            }
+
             if sum != indexed_balance {
-                log::warn!(
+                tracing::warn!(
                     "Balance mismatch for {}: indexed={} computed_from_utxos={} (utxos={})",
                     address,
                     indexed_balance,
@@ -521,16 +1371,41 @@ async fn get_address_balance(
             }
         }
         Ok(Err(e)) => {
-            log::error!("Failed to get UTXOs for address {}: {:?}", address, e);
+            tracing::error!("Failed to get UTXOs for address {}: {:?}", address, e);
         }
         Err(_) => {
-            log::warn!("Timed out fetching UTXOs for address {} (returning indexed balance only)", address);
+            tracing::warn!("Timed out fetching UTXOs for address {} (returning indexed balance only)", address);
+        }
+    }
+
+    // Without a fresh enumeration, show the last one if the balance has not moved since.
+    if computed_balance.is_none() {
+        let cached = state
+            .balance_cache
+            .read()
+            .await
+            .get(&address)
+            .filter(|(balance, _, _)| *balance == indexed_balance)
+            .cloned();
+        state.debug_stats.balance_cache_lookup(cached.is_some());
+        if let Some((_, count, utxos)) = cached {
+            tracing::info!("Using cached UTXOs for address {}", address);
+            utxo_count_total = count;
+            display_utxos = utxos;
         }
     }
 
     let total_balance = computed_balance.unwrap_or(indexed_balance);
+    let summary = address_summary::summarize(
+        &state,
+        &client,
+        &parsed_address,
+        total_balance,
+        utxo_count_total,
+    )
+    .await;
 
-    log::info!(
+    tracing::info!(
         "Returning balance for address {}: {} KAS (utxos_total={:?})",
         address,
         total_balance / 100000000,
@@ -538,35 +1413,47 @@ async fn get_address_balance(
     );
     
     // Cache the FRESH result (full balance + limited display)
-    {
+    if computed_balance.is_some() {
         let mut cache = state.balance_cache.write().await;
         cache.insert(address.clone(), (total_balance, utxo_count_total, display_utxos.clone()));
-        log::info!("CACHED: Fresh balance {} KAS for address {} (utxos_total={:?}, utxos_display={})", 
+        tracing::info!("CACHED: Fresh balance {} KAS for address {} (utxos_total={:?}, utxos_display={})", 
                    total_balance / 100000000, address, utxo_count_total, display_utxos.len());
     }
     
+    let label = labels::get(&state, &parsed_address.to_string()).await;
     let address_balance = AddressBalance {
         address,
         balance: total_balance, // Always the FULL balance
         utxo_count_total,
+        truncated: utxo_count_total.is_some_and(|total| total > display_utxos.len()),
+        total_available: utxo_count_total,
         utxos: display_utxos, // Limited display
+        activity: address_activity,
+        summary,
+        label,
     };
     
-    log::info!("=== RETURNING FRESH BALANCE: {} KAS for address {} ===", 
+    tracing::info!("=== RETURNING FRESH BALANCE: {} KAS for address {} ===", 
                address_balance.balance / 100000000, address_balance.address);
     
     Ok(Json(address_balance))
 }
 
-async fn get_peer_info(State(state): State<AppState>) -> Json<Vec<PeerInfo>> {
+#[utoipa::path(
+    get,
+    path = "/api/v1/peers",
+    tag = "network",
+    responses((status = 200, description = "Peer connection information", body = Vec<PeerInfo>))
+)]
+async fn get_peer_info(State(state): State<AppState>) -> Response {
     let client_guard = state.client.read().await;
     let client = client_guard.as_ref();
     
     if let Some(client) = client {
         // Get peer information from kaspad
-        match client.get_info().await {
+        match rpc::retried("get_info", || client.get_info()).await {
             Ok(info) => {
-                log::info!("Successfully fetched peer info: {:?}", info);
+                tracing::info!("Successfully fetched peer info: {:?}", info);
                 
                 // Create peer info from connected node
                 let peer_list = vec![
@@ -575,56 +1462,69 @@ async fn get_peer_info(State(state): State<AppState>) -> Json<Vec<PeerInfo>> {
                 address: state.network_info.read().await.server_url.clone(),
                 is_connected: true,
                 last_seen: "now".to_string(),
+                location: None,
             },
             PeerInfo {
                 id: "peer-82.166.83.140".to_string(),
                 address: "82.166.83.140:16311".to_string(),
                 is_connected: true, // Assume peer is connected
                 last_seen: "recent".to_string(),
+                location: None,
             },
         ];
+        let peer_list = match &state.geoip {
+            Some(geoip) => peer_list
+                .into_iter()
+                .map(|peer| PeerInfo { location: geoip.lookup_address(&peer.address), ..peer })
+                .collect(),
+            None => peer_list,
+        };
         
         // Cache and return peer list
         {
             let mut peer_cache = state.peer_info.write().await;
             *peer_cache = peer_list.clone();
         }
-        Json(peer_list)
+        Json(peer_list).into_response()
             }
             Err(e) => {
-                log::error!("Failed to get peer info: {:?}", e);
+                tracing::error!("Failed to get peer info: {:?}", e);
                 
                 // Return cached peer info if available
                 let peer_cache = state.peer_info.read().await;
-                if peer_cache.is_empty() {
-                    Json(vec![
+                let peers = if peer_cache.is_empty() {
+                    vec![
                         PeerInfo {
                             id: "local-node".to_string(),
                             address: state.network_info.read().await.server_url.clone(),
                             is_connected: false,
                             last_seen: "error".to_string(),
+                            location: None,
                         }
-                    ])
+                    ]
                 } else {
-                    Json(peer_cache.clone())
-                }
+                    peer_cache.clone()
+                };
+                cache::degraded(Json(peers))
             }
         }
     } else {
         // No client connection, return cached info
         let peer_cache = state.peer_info.read().await;
-        if peer_cache.is_empty() {
-            Json(vec![
+        let peers = if peer_cache.is_empty() {
+            vec![
                 PeerInfo {
                     id: "local-node".to_string(),
                     address: state.network_info.read().await.server_url.clone(),
                     is_connected: false,
                     last_seen: "disconnected".to_string(),
+                    location: None,
                 }
-            ])
+            ]
         } else {
-            Json(peer_cache.clone())
-        }
+            peer_cache.clone()
+        };
+        cache::degraded(Json(peers))
     }
 }
 
@@ -632,11 +1532,83 @@ async fn get_peer_info(State(state): State<AppState>) -> Json<Vec<PeerInfo>> {
 #[command(name = "kaspa-testnet12-explorer")]
 #[command(about = "Kaspa Testnet 12 Block Explorer - Standalone")]
 struct Cli {
-    /// Port to run the explorer on
-    #[arg(short, long, default_value = "3000")]
+    /// Port to run the explorer on, on all interfaces (unless --listen is given)
+    #[arg(short, long, default_value = "3000", global = true)]
     port: u16,
+
+    /// Address to listen on, repeatable: host:port, [ipv6]:port or unix:/path.sock
+    #[arg(long, global = true)]
+    listen: Vec<listen::ListenAddr>,
+
+    /// PEM certificate chain; with --tls-key, TCP listeners serve HTTPS
+    #[arg(long, requires = "tls_key", global = true)]
+    tls_cert: Option<std::path::PathBuf>,
+
+    /// PEM private key of --tls-cert
+    #[arg(long, requires = "tls_cert", global = true)]
+    tls_key: Option<std::path::PathBuf>,
+
+    /// Also listen for plain HTTP on this address, redirecting to HTTPS (requires --tls-cert)
+    #[arg(long, requires = "tls_cert", global = true)]
+    http_redirect: Option<SocketAddr>,
     
-    /// Kaspad RPC server URL
-    #[arg(short, long, default_value = "127.0.0.1:16210")]
-    kaspad_url: String,
+    /// Kaspad RPC server URL (default: 127.0.0.1 on the network's gRPC port)
+    #[arg(short, long, global = true)]
+    kaspad_url: Option<String>,
+
+    /// Serve a generated chain instead of connecting to kaspad (offline development)
+    #[arg(long, conflicts_with = "kaspad_url", global = true)]
+    mock: bool,
+
+    /// Network to explore; addresses and the connected kaspad must belong to it
+    #[arg(long, value_enum, default_value = "testnet-12", global = true)]
+    network: network::Network,
+
+    /// Log output format
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    log_format: telemetry::LogFormat,
+
+    /// Optional TOML config file
+    #[arg(long, global = true)]
+    config: Option<std::path::PathBuf>,
+
+    /// Allowed CORS origin, repeatable (overrides the config file)
+    #[arg(long, global = true)]
+    cors_origin: Vec<String>,
+
+    /// Allow any CORS origin, method and header (development only)
+    #[arg(long, global = true)]
+    cors_allow_all: bool,
+
+    /// Wipe and rebuild the index if it was built from a different network
+    #[arg(long, global = true)]
+    auto_reset_index: bool,
+
+    /// Bearer token for the /admin API (requires `admin` in [features])
+    #[arg(long, env = "EXPLORER_ADMIN_TOKEN", hide_env_values = true, global = true)]
+    admin_token: Option<String>,
+
+    /// Migrate the index schema to the current version and exit
+    #[arg(long, global = true)]
+    migrate_only: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Without a subcommand the explorer serves, as with `serve`.
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Serve the web UI and API (the default)
+    Serve,
+    /// Index existing history from the pruning point (or a given hash) up to the sink, then exit
+    Backfill(backfill::BackfillArgs),
+    /// Write indexed blocks or an address's transactions as CSV or NDJSON
+    Export(export::ExportArgs),
+    /// Write a compressed snapshot of the index for bootstrapping another explorer
+    ExportSnapshot(index_snapshot::ExportSnapshotArgs),
+    /// Replace the index with a snapshot (stop the explorer first)
+    ImportSnapshot(index_snapshot::ImportSnapshotArgs),
+    /// Re-emit indexed blocks in a time range as events (NDJSON on stdout or a webhook)
+    Replay(replay::ReplayArgs),
 }
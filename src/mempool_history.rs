@@ -0,0 +1,331 @@
+//! Mempool transaction lifecycles.
+//!
+//! kaspad does not announce mempool changes, so a background task snapshots the mempool
+//! every `POLL_INTERVAL` and diffs it with the previous snapshot: new transactions have
+//! entered, and missing ones have left. Block-added notifications mark the tracked
+//! transactions a block includes as confirmed; a transaction that left without being seen in a
+//! block is evicted (expired, replaced or double spent), unless a block including it arrives
+//! later. Entry times are only as precise as the polling, and transactions mined before any
+//! poll saw them are not tracked. History is kept in memory, for the last `MAX_FINISHED`
+//! transactions that left the mempool.
+
+use crate::chain::ChainSource;
+use crate::sampler::now_ms;
+use crate::{error_response, retry_after_response, rpc, whales, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_rpc_core::BlockAddedNotification;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration};
+use utoipa::{IntoParams, ToSchema};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_FINISHED: usize = 10_000;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1_000;
+
+pub type MempoolHistoryHandle = Arc<RwLock<MempoolHistory>>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TransactionLifecycle {
+    transaction_id: String,
+    // pending, confirmed or evicted.
+    status: &'static str,
+    #[serde(serialize_with = "crate::units::sompi")]
+    fee: u64,
+    mass: u64,
+    feerate: f64,              // Sompi per gram
+    entered_at: u64,           // Unix ms of the first poll that saw it
+    left_at: Option<u64>,      // Unix ms of the first poll that missed it
+    confirmed_at: Option<u64>, // Unix ms of the block-added notification
+    block_hash: Option<String>,
+    time_to_confirm_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct MempoolHistory {
+    polled_at: Option<u64>,
+    pending: HashMap<String, TransactionLifecycle>,
+    // Left the mempool, oldest first.
+    finished: VecDeque<TransactionLifecycle>,
+    finished_ids: HashSet<String>,
+    confirmed: u64,
+    evicted: u64,
+}
+
+impl MempoolHistory {
+    fn finish(&mut self, lifecycle: TransactionLifecycle) {
+        match lifecycle.status {
+            "confirmed" => self.confirmed += 1,
+            _ => self.evicted += 1,
+        }
+        self.finished_ids.insert(lifecycle.transaction_id.clone());
+        self.finished.push_back(lifecycle);
+        while self.finished.len() > MAX_FINISHED {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.finished_ids.remove(&oldest.transaction_id);
+            }
+        }
+    }
+
+    fn find(&self, transaction_id: &str) -> Option<&TransactionLifecycle> {
+        self.pending.get(transaction_id).or_else(|| {
+            self.finished_ids
+                .contains(transaction_id)
+                .then(|| {
+                    self.finished
+                        .iter()
+                        .rev()
+                        .find(|lifecycle| lifecycle.transaction_id == transaction_id)
+                })
+                .flatten()
+        })
+    }
+}
+
+async fn poll(state: &AppState) -> anyhow::Result<()> {
+    let entries = {
+        let client_guard = state.client.read().await;
+        let client = client_guard
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Not connected to kaspad"))?;
+        rpc::retried("get_mempool_entries", || {
+            client.get_mempool_entries(false, false)
+        })
+        .await?
+    };
+    whales::on_mempool(state, &entries).await;
+    let now = now_ms();
+    let mut history = state.mempool_history.write().await;
+    let mut present = HashSet::with_capacity(entries.len());
+    for entry in &entries {
+        let Some(verbose) = entry.transaction.verbose_data.as_ref() else {
+            continue;
+        };
+        let transaction_id = verbose.transaction_id.to_string();
+        present.insert(transaction_id.clone());
+        // A snapshot taken just before a block can still list what the block confirmed.
+        if history.pending.contains_key(&transaction_id)
+            || history.finished_ids.contains(&transaction_id)
+        {
+            continue;
+        }
+        let mass = if verbose.compute_mass > 0 {
+            verbose.compute_mass
+        } else {
+            entry.transaction.mass
+        };
+        history.pending.insert(
+            transaction_id.clone(),
+            TransactionLifecycle {
+                transaction_id,
+                status: "pending",
+                fee: entry.fee,
+                mass,
+                feerate: if mass > 0 {
+                    entry.fee as f64 / mass as f64
+                } else {
+                    0.0
+                },
+                entered_at: now,
+                left_at: None,
+                confirmed_at: None,
+                block_hash: None,
+                time_to_confirm_ms: None,
+            },
+        );
+    }
+    let gone: Vec<String> = history
+        .pending
+        .keys()
+        .filter(|id| !present.contains(*id))
+        .cloned()
+        .collect();
+    for transaction_id in gone {
+        if let Some(mut lifecycle) = history.pending.remove(&transaction_id) {
+            lifecycle.status = "evicted";
+            lifecycle.left_at = Some(now);
+            history.finish(lifecycle);
+        }
+    }
+    history.polled_at = Some(now);
+    Ok(())
+}
+
+pub fn spawn(state: AppState) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            if let Err(e) = poll(&state).await {
+                tracing::debug!("Mempool history poll failed: {:?}", e);
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+pub async fn on_block_added(state: &AppState, notification: &BlockAddedNotification) {
+    if !state.config.features.analytics {
+        return;
+    }
+    let block = &notification.block;
+    let now = now_ms();
+    let mut history = state.mempool_history.write().await;
+    for verbose in block
+        .transactions
+        .iter()
+        .filter_map(|tx| tx.verbose_data.as_ref())
+    {
+        let transaction_id = verbose.transaction_id.to_string();
+        let confirm = |lifecycle: &mut TransactionLifecycle| {
+            lifecycle.status = "confirmed";
+            lifecycle.confirmed_at = Some(now);
+            lifecycle.block_hash = Some(block.header.hash.to_string());
+            lifecycle.time_to_confirm_ms = Some(now.saturating_sub(lifecycle.entered_at));
+        };
+        if let Some(mut lifecycle) = history.pending.remove(&transaction_id) {
+            confirm(&mut lifecycle);
+            lifecycle.left_at = Some(now);
+            history.finish(lifecycle);
+        } else if history.finished_ids.contains(&transaction_id) {
+            // Counted as evicted by a poll that ran before this notification.
+            let Some(lifecycle) = history
+                .finished
+                .iter_mut()
+                .rev()
+                .find(|lifecycle| lifecycle.transaction_id == transaction_id)
+            else {
+                continue;
+            };
+            if lifecycle.status == "evicted" {
+                confirm(lifecycle);
+                history.evicted -= 1;
+                history.confirmed += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MempoolHistoryQuery {
+    /// pending, confirmed or evicted (default all)
+    status: Option<String>,
+    /// Transactions returned (default 100, max 1000)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MempoolHistoryResponse {
+    polled_at: u64, // Unix ms of the latest mempool snapshot
+    pending: usize,
+    // Since the explorer started.
+    confirmed: u64,
+    evicted: u64,
+    // Over the confirmed transactions still in the history.
+    median_time_to_confirm_ms: Option<u64>,
+    p95_time_to_confirm_ms: Option<u64>,
+    transactions: Vec<TransactionLifecycle>, // Most recent event first
+}
+
+fn last_event(lifecycle: &TransactionLifecycle) -> u64 {
+    lifecycle
+        .confirmed_at
+        .or(lifecycle.left_at)
+        .unwrap_or(lifecycle.entered_at)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/mempool/history",
+    tag = "mempool",
+    params(MempoolHistoryQuery),
+    responses(
+        (status = 200, description = "Recent mempool transactions with entry, confirmation and eviction times", body = MempoolHistoryResponse),
+        (status = 400, description = "Invalid status or limit", body = ErrorResponse),
+        (status = 503, description = "The mempool has not been polled yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_mempool_history(
+    State(state): State<AppState>,
+    Query(query): Query<MempoolHistoryQuery>,
+) -> Result<Json<MempoolHistoryResponse>, Response> {
+    let status = query.status.as_deref();
+    if !matches!(status, None | Some("pending" | "confirmed" | "evicted")) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Invalid status (expected pending, confirmed or evicted)",
+        ));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+
+    let history = state.mempool_history.read().await;
+    let polled_at = history.polled_at.ok_or_else(|| {
+        retry_after_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Mempool not polled yet",
+            POLL_INTERVAL,
+        )
+    })?;
+    let mut times: Vec<u64> = history
+        .finished
+        .iter()
+        .filter_map(|lifecycle| lifecycle.time_to_confirm_ms)
+        .collect();
+    times.sort_unstable();
+    let percentile =
+        |p: usize| (!times.is_empty()).then(|| times[(times.len() * p).div_ceil(100).max(1) - 1]);
+    let mut transactions: Vec<TransactionLifecycle> = history
+        .pending
+        .values()
+        .chain(history.finished.iter())
+        .filter(|lifecycle| status.is_none_or(|status| lifecycle.status == status))
+        .cloned()
+        .collect();
+    transactions.sort_by_key(|lifecycle| std::cmp::Reverse(last_event(lifecycle)));
+    transactions.truncate(limit);
+
+    Ok(Json(MempoolHistoryResponse {
+        polled_at,
+        pending: history.pending.len(),
+        confirmed: history.confirmed,
+        evicted: history.evicted,
+        median_time_to_confirm_ms: percentile(50),
+        p95_time_to_confirm_ms: percentile(95),
+        transactions,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/mempool/history/{id}",
+    tag = "mempool",
+    params(("id" = String, Path, description = "Transaction ID")),
+    responses(
+        (status = 200, description = "Entry, confirmation or eviction times of the transaction", body = TransactionLifecycle),
+        (status = 404, description = "Not seen in the mempool, or too long ago", body = ErrorResponse)
+    )
+)]
+pub async fn get_transaction_lifecycle(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionLifecycle>, Response> {
+    let history = state.mempool_history.read().await;
+    history.find(id.trim()).cloned().map(Json).ok_or_else(|| {
+        error_response(
+            StatusCode::NOT_FOUND,
+            "Transaction not in the mempool history",
+        )
+    })
+}
@@ -0,0 +1,173 @@
+//! Prometheus metrics at `/metrics`, in OpenMetrics text format.
+//!
+//! kaspad RPC latencies are recorded per method in the `kaspad_rpc_duration_seconds`
+//! histogram. When spans are exported over OTLP, each observation carries the trace id of its
+//! sampled `kaspad_rpc` span as an exemplar, so a slow bucket in Grafana links straight to the
+//! slow call in Jaeger or Tempo. Exemplars only exist in the OpenMetrics format, which is why
+//! that is what this endpoint serves.
+//!
+//! The kaspad connection pool (`rpc.rs`) reports its size, its checkouts per connection and
+//! how many checked-out clients are in use.
+//!
+//! The response cache (`cache.rs`) counts its hits, stale answers and misses per route.
+
+use crate::{error_response, telemetry};
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::exemplar::HistogramWithExemplars;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::exponential_buckets;
+use prometheus_client::registry::Registry;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RpcLabels {
+    method: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct PoolLabels {
+    connection: String, // Pool slot, or "primary" when the pool is empty
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CacheLabels {
+    route: String,
+    result: &'static str, // "hit", "stale" or "miss"
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct TraceExemplar {
+    trace_id: String,
+}
+
+type RpcHistogram = HistogramWithExemplars<TraceExemplar>;
+
+struct Metrics {
+    registry: Registry,
+    rpc_duration: Family<RpcLabels, RpcHistogram, fn() -> RpcHistogram>,
+    pool_connections: Gauge,
+    pool_in_use: Gauge,
+    pool_checkouts: Family<PoolLabels, Counter>,
+    response_cache: Family<CacheLabels, Counter>,
+}
+
+fn rpc_histogram() -> RpcHistogram {
+    // 1ms to ~16s.
+    HistogramWithExemplars::new(exponential_buckets(0.001, 2.0, 15))
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| {
+        let mut registry = Registry::default();
+        let rpc_duration =
+            Family::<RpcLabels, RpcHistogram, fn() -> RpcHistogram>::new_with_constructor(
+                rpc_histogram,
+            );
+        registry.register(
+            "kaspad_rpc_duration_seconds",
+            "Latency of kaspad RPC calls by method",
+            rpc_duration.clone(),
+        );
+        let pool_connections = Gauge::default();
+        registry.register(
+            "kaspad_pool_connections",
+            "Open connections in the kaspad client pool",
+            pool_connections.clone(),
+        );
+        let pool_in_use = Gauge::default();
+        registry.register(
+            "kaspad_pool_in_use",
+            "Checked-out kaspad clients still in use",
+            pool_in_use.clone(),
+        );
+        let pool_checkouts = Family::<PoolLabels, Counter>::default();
+        registry.register(
+            "kaspad_pool_checkouts",
+            "kaspad client checkouts by pool connection",
+            pool_checkouts.clone(),
+        );
+        let response_cache = Family::<CacheLabels, Counter>::default();
+        registry.register(
+            "response_cache_requests",
+            "Cacheable API requests by route and whether the response cache answered them",
+            response_cache.clone(),
+        );
+        Metrics {
+            registry,
+            rpc_duration,
+            pool_connections,
+            pool_in_use,
+            pool_checkouts,
+            response_cache,
+        }
+    })
+}
+
+/// Records one kaspad RPC call; called from inside its `kaspad_rpc` span.
+pub fn observe_rpc(method: &'static str, elapsed: Duration) {
+    let exemplar = telemetry::sampled_trace_id().map(|trace_id| TraceExemplar { trace_id });
+    metrics()
+        .rpc_duration
+        .get_or_create(&RpcLabels {
+            method: method.to_string(),
+        })
+        .observe(elapsed.as_secs_f64(), exemplar);
+}
+
+pub fn set_pool_connections(connections: usize) {
+    metrics().pool_connections.set(connections as i64);
+}
+
+/// Records a checkout of `connection`; `pool_checkin` is called when it is released.
+pub fn pool_checkout(connection: String) {
+    let metrics = metrics();
+    metrics
+        .pool_checkouts
+        .get_or_create(&PoolLabels { connection })
+        .inc();
+    metrics.pool_in_use.inc();
+}
+
+pub fn pool_checkin() {
+    metrics().pool_in_use.dec();
+}
+
+pub fn response_cache(route: &str, result: &'static str) {
+    metrics()
+        .response_cache
+        .get_or_create(&CacheLabels {
+            route: route.to_string(),
+            result,
+        })
+        .inc();
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "network",
+    responses(
+        (status = 200, description = "Metrics in OpenMetrics text format, with trace-id exemplars when tracing is exported", content_type = "application/openmetrics-text", body = String)
+    )
+)]
+pub async fn get_metrics() -> Response {
+    let mut body = String::new();
+    if let Err(e) = prometheus_client::encoding::text::encode(&mut body, &metrics().registry) {
+        tracing::error!("Failed to encode metrics: {:?}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to encode metrics",
+        );
+    }
+    ([(header::CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)], body).into_response()
+}
@@ -0,0 +1,343 @@
+//! Versioned schema migrations for the SQLite index.
+//!
+//! The schema version is kept in SQLite's `user_version` header field. On open, every
+//! migration above the stored version is applied in order, each in its own transaction
+//! together with the version bump, so an interrupted upgrade resumes where it stopped. New
+//! index formats are added as a new entry at the end of `MIGRATIONS`; existing entries must
+//! never change, since databases in the field have already applied them.
+//!
+//! Indexes created before versioning report version 0. The baseline migration only uses
+//! `IF NOT EXISTS`, so it adopts them as they are.
+
+use rusqlite::{Connection, TransactionBehavior};
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    sql: &'static str,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        sql: "
+        CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS epochs (
+            id INTEGER PRIMARY KEY,
+            network TEXT NOT NULL,
+            pruning_point TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            start_daa_score INTEGER NOT NULL,
+            last_daa_score INTEGER NOT NULL,
+            ended_at INTEGER,
+            end_reason TEXT
+        );
+        CREATE TABLE IF NOT EXISTS blocks (
+            hash TEXT PRIMARY KEY,
+            epoch INTEGER NOT NULL,
+            daa_score INTEGER NOT NULL,
+            blue_score INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS blocks_daa_score ON blocks (epoch, daa_score);
+        CREATE INDEX IF NOT EXISTS blocks_timestamp ON blocks (epoch, timestamp);
+        CREATE TABLE IF NOT EXISTS transactions (
+            id TEXT NOT NULL,
+            block_hash TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            daa_score INTEGER NOT NULL,
+            PRIMARY KEY (id, block_hash)
+        );
+        CREATE INDEX IF NOT EXISTS transactions_daa_score ON transactions (epoch, daa_score);
+        CREATE TABLE IF NOT EXISTS outputs (
+            tx_id TEXT NOT NULL,
+            idx INTEGER NOT NULL,
+            address TEXT,
+            amount INTEGER NOT NULL,
+            epoch INTEGER NOT NULL,
+            daa_score INTEGER NOT NULL,
+            PRIMARY KEY (tx_id, idx)
+        );
+        CREATE INDEX IF NOT EXISTS outputs_address ON outputs (address);
+        CREATE INDEX IF NOT EXISTS outputs_daa_score ON outputs (epoch, daa_score);
+        CREATE TABLE IF NOT EXISTS inputs (
+            tx_id TEXT NOT NULL,
+            idx INTEGER NOT NULL,
+            prev_tx_id TEXT NOT NULL,
+            prev_idx INTEGER NOT NULL,
+            epoch INTEGER NOT NULL,
+            daa_score INTEGER NOT NULL,
+            PRIMARY KEY (tx_id, idx)
+        );
+        CREATE INDEX IF NOT EXISTS inputs_prev ON inputs (prev_tx_id, prev_idx);
+        CREATE INDEX IF NOT EXISTS inputs_daa_score ON inputs (epoch, daa_score);
+        CREATE TABLE IF NOT EXISTS portfolios (
+            key_hash TEXT PRIMARY KEY,
+            name TEXT,
+            addresses TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+    ",
+    },
+    Migration {
+        version: 2,
+        description: "cold segments",
+        sql: "
+        CREATE TABLE IF NOT EXISTS cold_segments (
+            epoch INTEGER NOT NULL,
+            start_daa INTEGER NOT NULL,
+            end_daa INTEGER NOT NULL,
+            output_count INTEGER NOT NULL,
+            input_count INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            PRIMARY KEY (epoch, start_daa)
+        );
+    ",
+    },
+    // Backfilled from the hot tables; rows already moved to the cold tier are not counted, so
+    // `Index::address_history_truncated` cannot flag addresses only seen there.
+    Migration {
+        version: 3,
+        description: "address activity",
+        sql: "
+        CREATE TABLE IF NOT EXISTS address_activity (
+            address TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            first_daa_score INTEGER NOT NULL,
+            last_daa_score INTEGER NOT NULL,
+            PRIMARY KEY (address, epoch)
+        );
+        INSERT OR IGNORE INTO address_activity (address, epoch, first_daa_score, last_daa_score)
+        SELECT address, epoch, MIN(daa_score), MAX(daa_score) FROM (
+            SELECT address, epoch, daa_score FROM outputs WHERE address IS NOT NULL
+            UNION ALL
+            SELECT o.address, i.epoch, i.daa_score FROM inputs i
+            JOIN outputs o ON o.tx_id = i.prev_tx_id AND o.idx = i.prev_idx
+            WHERE o.address IS NOT NULL
+        )
+        GROUP BY address, epoch;
+    ",
+    },
+    // Blocks indexed before this version keep no miner and count as blue.
+    Migration {
+        version: 4,
+        description: "block miners and colours",
+        sql: "
+        ALTER TABLE blocks ADD COLUMN miner TEXT;
+        ALTER TABLE blocks ADD COLUMN red INTEGER NOT NULL DEFAULT 0;
+    ",
+    },
+    Migration {
+        version: 5,
+        description: "watches",
+        sql: "
+        CREATE TABLE IF NOT EXISTS watches (
+            id TEXT PRIMARY KEY,
+            callback_url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            addresses TEXT NOT NULL,
+            transaction_ids TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+    ",
+    },
+    // Filled by the rich-list scan (`rich_list.rs`), not from the hot tables.
+    Migration {
+        version: 6,
+        description: "address balances",
+        sql: "
+        CREATE TABLE IF NOT EXISTS balances (
+            address TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            balance INTEGER NOT NULL,
+            utxo_count INTEGER NOT NULL,
+            PRIMARY KEY (address, epoch)
+        );
+        CREATE INDEX IF NOT EXISTS balances_balance ON balances (epoch, balance);
+    ",
+    },
+    // Chain block lists are JSON arrays of hashes in kaspad's order: removed from the old sink
+    // down, added up to the new sink.
+    Migration {
+        version: 7,
+        description: "reorg log",
+        sql: "
+        CREATE TABLE IF NOT EXISTS reorgs (
+            id INTEGER PRIMARY KEY,
+            epoch INTEGER NOT NULL,
+            detected_at INTEGER NOT NULL,
+            daa_score INTEGER,
+            depth INTEGER NOT NULL,
+            removed TEXT NOT NULL,
+            added TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS reorgs_depth ON reorgs (epoch, depth);
+    ",
+    },
+    // Operator-managed, like watches, so not scoped to an epoch or wiped with the chain data.
+    Migration {
+        version: 8,
+        description: "address labels",
+        sql: "
+        CREATE TABLE IF NOT EXISTS labels (
+            address TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            tag TEXT,
+            updated_at INTEGER NOT NULL
+        );
+    ",
+    },
+    // Amounts are decimal text since they exceed 64 bits; balances are zero-padded to 39
+    // digits so they sort numerically.
+    Migration {
+        version: 9,
+        description: "krc-20 tokens",
+        sql: "
+        CREATE TABLE IF NOT EXISTS tokens (
+            tick TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            max_supply TEXT NOT NULL,
+            mint_limit TEXT NOT NULL,
+            decimals INTEGER NOT NULL,
+            minted TEXT NOT NULL,
+            deployer TEXT NOT NULL,
+            deploy_tx_id TEXT NOT NULL,
+            deploy_daa_score INTEGER NOT NULL,
+            PRIMARY KEY (tick, epoch)
+        );
+        CREATE TABLE IF NOT EXISTS token_balances (
+            tick TEXT NOT NULL,
+            address TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            balance TEXT NOT NULL,
+            PRIMARY KEY (tick, address, epoch)
+        );
+        CREATE INDEX IF NOT EXISTS token_balances_address ON token_balances (address, epoch);
+        CREATE INDEX IF NOT EXISTS token_balances_balance ON token_balances (tick, epoch, balance);
+        CREATE TABLE IF NOT EXISTS token_operations (
+            tx_id TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            tick TEXT NOT NULL,
+            op TEXT NOT NULL,
+            from_address TEXT NOT NULL,
+            to_address TEXT,
+            amount TEXT,
+            daa_score INTEGER NOT NULL,
+            error TEXT,
+            PRIMARY KEY (tx_id, epoch)
+        );
+        CREATE INDEX IF NOT EXISTS token_operations_tick ON token_operations (tick, epoch, daa_score);
+    ",
+    },
+    // Filled from virtual-chain-changed notifications (`acceptance.rs`), not from the hot
+    // tables; transactions accepted while the explorer was not listening are added when first
+    // looked up.
+    Migration {
+        version: 10,
+        description: "accepted transactions",
+        sql: "
+        CREATE TABLE IF NOT EXISTS accepted_transactions (
+            tx_id TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            accepting_block TEXT NOT NULL,
+            PRIMARY KEY (tx_id, epoch)
+        );
+        CREATE INDEX IF NOT EXISTS accepted_transactions_block ON accepted_transactions (accepting_block, epoch);
+    ",
+    },
+    // Blocks indexed before this version have no size or mass; `block_size.rs` leaves them out.
+    Migration {
+        version: 11,
+        description: "block sizes and mass",
+        sql: "
+        ALTER TABLE blocks ADD COLUMN size INTEGER;
+        ALTER TABLE blocks ADD COLUMN mass INTEGER;
+    ",
+    },
+    // Inscriptions wait in token_inscriptions until a chain block accepts their transaction.
+    // Operations applied before this version have no accepting block and are not rolled back.
+    Migration {
+        version: 12,
+        description: "krc-20 acceptance",
+        sql: "
+        CREATE TABLE IF NOT EXISTS token_inscriptions (
+            tx_id TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            daa_score INTEGER NOT NULL,
+            from_address TEXT NOT NULL,
+            body TEXT NOT NULL,
+            PRIMARY KEY (tx_id, epoch)
+        );
+        ALTER TABLE token_operations ADD COLUMN accepting_block TEXT;
+        CREATE INDEX IF NOT EXISTS token_operations_accepting_block ON token_operations (accepting_block, epoch);
+    ",
+    },
+    // Block summaries look their transactions up by block hash; the primary key leads with the id.
+    Migration {
+        version: 13,
+        description: "transactions by block",
+        sql: "
+        CREATE INDEX IF NOT EXISTS transactions_block_hash ON transactions (block_hash);
+    ",
+    },
+    // Red blocks keyed by the chain block that merged them, so a reorg can clear the colours
+    // its removed chain blocks assigned. Colours recorded before this version have no chain
+    // block and are kept as they are.
+    Migration {
+        version: 14,
+        description: "red blocks by merging chain block",
+        sql: "
+        CREATE TABLE IF NOT EXISTS red_blocks (
+            hash TEXT NOT NULL,
+            epoch INTEGER NOT NULL,
+            chain_block TEXT,
+            PRIMARY KEY (hash, epoch)
+        );
+        CREATE INDEX IF NOT EXISTS red_blocks_chain_block ON red_blocks (chain_block, epoch);
+        INSERT OR IGNORE INTO red_blocks (hash, epoch) SELECT hash, epoch FROM blocks WHERE red = 1;
+        ALTER TABLE blocks DROP COLUMN red;
+    ",
+    },
+    // Epochs started before this version get their genesis on the next epoch check.
+    Migration {
+        version: 15,
+        description: "epoch genesis",
+        sql: "
+        ALTER TABLE epochs ADD COLUMN genesis TEXT;
+    ",
+    },
+];
+
+/// Schema version this build writes.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Brings the database up to `latest_version`.
+pub fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let current: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = latest_version();
+    if current > latest {
+        anyhow::bail!(
+            "schema version {} is newer than this build supports ({}); upgrade the explorer",
+            current,
+            latest
+        );
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        tracing::info!(
+            "Migrating index schema to version {} ({})",
+            migration.version,
+            migration.description
+        );
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute_batch(migration.sql)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
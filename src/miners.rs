@@ -0,0 +1,210 @@
+//! Per-miner block counts and red rates.
+//!
+//! A block's miner is the address in its coinbase payload, which kaspad lays out as blue
+//! score (u64), subsidy (u64), script version (u16), script length (u8), script, extra data.
+//! A block is red when the selected-chain block merging it classifies it as red in its
+//! mergeset; a high red rate usually means the miner's blocks reach the network late.
+//!
+//! Colours are recorded from virtual-chain-changed notifications: each added chain block's
+//! mergeset reds are fetched and stored against it, and those of removed chain blocks are
+//! cleared, so blocks that join the chain later count as well and reorgs are followed. Chain
+//! blocks indexed while the explorer was not listening contribute their reds as indexed.
+//! Blocks not merged by a chain block yet count as blue, so the newest few seconds slightly
+//! understate red rates.
+
+use crate::chain::ChainSource;
+use crate::charts::parse_window;
+use crate::pools::MinedBy;
+use crate::sampler::now_ms;
+use crate::{error_response, require_index, rpc, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_rpc_core::{RpcBlock, VirtualChainChangedNotification};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+// Miners returned, by block count.
+const MAX_MINERS: usize = 100;
+
+// Script opcodes of the standard pay-to-pubkey and pay-to-script-hash forms.
+const OP_DATA_32: u8 = 0x20;
+const OP_DATA_33: u8 = 0x21;
+const OP_EQUAL: u8 = 0x87;
+const OP_BLAKE2B: u8 = 0xaa;
+const OP_CHECKSIG_ECDSA: u8 = 0xab;
+const OP_CHECKSIG: u8 = 0xac;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MinersQuery {
+    /// 1h, 24h or 7d (default 24h)
+    window: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MinerStats {
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mined_by: Option<MinedBy>, // From the pool signature table, by payout address
+
+    blocks: u64,
+    red_blocks: u64,
+    red_rate: f64, // Red blocks / blocks
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MinersResponse {
+    window: String,
+    total_blocks: u64,
+    total_red_blocks: u64,
+    red_rate: f64, // Over all attributed blocks in the window
+    total_miners: usize,
+    miners: Vec<MinerStats>,
+}
+
+/// Address of the miner of `block`, from its coinbase payload. None for non-standard scripts.
+pub fn coinbase_miner(block: &RpcBlock) -> Option<String> {
+    let coinbase = block.transactions.first()?;
+    let payload = &coinbase.payload;
+    let script_len = *payload.get(18)? as usize;
+    let script = payload.get(19..19 + script_len)?;
+    let (version, key) = match script {
+        [OP_DATA_32, key @ .., OP_CHECKSIG] if key.len() == 32 => (Version::PubKey, key),
+        [OP_DATA_33, key @ .., OP_CHECKSIG_ECDSA] if key.len() == 33 => (Version::PubKeyECDSA, key),
+        [OP_BLAKE2B, OP_DATA_32, hash @ .., OP_EQUAL] if hash.len() == 32 => {
+            (Version::ScriptHash, hash)
+        }
+        _ => return None,
+    };
+    // The coinbase outputs carry the network's prefix; with none to pay, assume testnet.
+    let prefix = coinbase
+        .outputs
+        .iter()
+        .find_map(|output| output.verbose_data.as_ref())
+        .map_or(Prefix::Testnet, |v| v.script_public_key_address.prefix);
+    Some(Address::new(prefix, version, key).to_string())
+}
+
+/// Extra data of `block`'s coinbase payload: whatever follows the payout script, where pools
+/// and mining software leave their tag.
+pub fn coinbase_extra_data(block: &RpcBlock) -> Option<&[u8]> {
+    let payload = &block.transactions.first()?.payload;
+    let script_len = *payload.get(18)? as usize;
+    payload.get(19 + script_len..)
+}
+
+/// Subsidy `block` claims in its coinbase payload, as set by the emission schedule for its
+/// DAA score. It is paid out by the chain block that later merges `block` as blue.
+pub fn coinbase_subsidy(block: &RpcBlock) -> Option<u64> {
+    let payload = &block.transactions.first()?.payload;
+    let subsidy = payload.get(8..16)?;
+    Some(u64::from_le_bytes(subsidy.try_into().ok()?))
+}
+
+/// Records the mergeset reds of the chain blocks a virtual-chain-changed notification adds,
+/// and clears those of the chain blocks it removes.
+pub async fn on_chain_changed(state: &AppState, notification: &VirtualChainChangedNotification) {
+    let Some(index) = &state.index else {
+        return;
+    };
+    if index.current_epoch_id().is_none() || index.network_mismatch().is_some() {
+        return;
+    }
+    let Some(client) = state.client.read().await.clone() else {
+        return;
+    };
+    let removed = notification
+        .removed_chain_block_hashes
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let mut added = Vec::with_capacity(notification.added_chain_block_hashes.len());
+    for &hash in notification.added_chain_block_hashes.iter() {
+        match rpc::retried("get_block", || client.get_block(hash, false)).await {
+            Ok(block) => added.push((
+                hash.to_string(),
+                block
+                    .verbose_data
+                    .map(|v| {
+                        v.merge_set_reds_hashes
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            )),
+            Err(e) => tracing::warn!("Failed to fetch chain block {} for its reds: {:?}", hash, e),
+        }
+    }
+    if let Err(e) = index.set_colours(removed, added).await {
+        tracing::warn!("Failed to record block colours: {:?}", e);
+    }
+}
+
+fn rate(red_blocks: u64, blocks: u64) -> f64 {
+    if blocks == 0 {
+        0.0
+    } else {
+        red_blocks as f64 / blocks as f64
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/miners",
+    tag = "stats",
+    params(MinersQuery),
+    responses(
+        (status = 200, description = "Blocks and red rate per coinbase address", body = MinersResponse),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_miners(
+    State(state): State<AppState>,
+    Query(query): Query<MinersQuery>,
+) -> Result<Json<MinersResponse>, Response> {
+    let window = query.window.unwrap_or_else(|| "24h".to_string());
+    let window_seconds = parse_window(&window).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            "Invalid window (expected 1h, 24h or 7d)",
+        )
+    })?;
+    let index = require_index(&state)?;
+
+    let since = now_ms().saturating_sub(window_seconds * 1000);
+    let miners = index.miner_blocks(since).await.map_err(|e| {
+        tracing::error!("Index lookup of miners failed: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    })?;
+    let total_blocks = miners.iter().map(|m| m.blocks).sum();
+    let total_red_blocks = miners.iter().map(|m| m.red_blocks).sum();
+    let total_miners = miners.len();
+
+    Ok(Json(MinersResponse {
+        window,
+        total_blocks,
+        total_red_blocks,
+        red_rate: rate(total_red_blocks, total_blocks),
+        total_miners,
+        miners: miners
+            .into_iter()
+            .take(MAX_MINERS)
+            .map(|m| MinerStats {
+                red_rate: rate(m.red_blocks, m.blocks),
+                mined_by: state
+                    .pools
+                    .as_ref()
+                    .and_then(|pools| pools.by_address(&m.address)),
+                address: m.address,
+                blocks: m.blocks,
+                red_blocks: m.red_blocks,
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,159 @@
+//! Mining overview, for miners checking their setup against what the node would mine next.
+//!
+//! The template is requested for a throwaway pay-to-pubkey address (an all-zero key of the
+//! network's prefix); only its contents are reported, and nothing is ever submitted. The
+//! target is expanded from the template's compact `bits`. Accepted block counts come from the
+//! index: blocks with timestamps in the last hour, and those merged as red.
+
+use crate::chain::ChainSource;
+use crate::fee_histogram::BLOCK_MASS_LIMIT;
+use crate::sampler::now_ms;
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_addresses::{Address, Version};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const RECENT_WINDOW_SECS: u64 = 3_600;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockTemplateInfo {
+    is_synced: bool, // Blocks mined on an unsynced node's template are likely orphaned
+    daa_score: u64,
+    timestamp: u64, // Unix ms
+    bits: u32,
+    target: String,      // 256-bit big-endian hex
+    parents: usize,      // Direct parents
+    transactions: usize, // Excluding the coinbase
+    mass: u64,
+    mass_limit: u64,
+    mass_usage: f64, // mass / mass_limit
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecentBlocks {
+    window_seconds: u64,
+    blocks: u64,
+    red_blocks: u64,
+    blue_blocks: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MiningInfo {
+    network: String,
+    difficulty: f64,
+    hashrate: Option<u64>, // Hashes per second, from the latest network sample
+    virtual_daa_score: u64,
+    template: BlockTemplateInfo,
+    recent: Option<RecentBlocks>, // None without the index
+}
+
+/// Expands compact `bits` (exponent byte, 23-bit mantissa) into a big-endian 256-bit target.
+fn compact_to_target(bits: u32) -> [u8; 32] {
+    let mut exponent = (bits >> 24) as usize;
+    let mut mantissa = bits & 0x007f_ffff;
+    if exponent < 3 {
+        mantissa >>= 8 * (3 - exponent);
+        exponent = 3;
+    }
+    let mut target = [0u8; 32];
+    for (i, byte) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+        // Byte i of the mantissa is worth 256^(exponent - 1 - i).
+        if let Some(position) = (32 + i).checked_sub(exponent) {
+            if position < 32 {
+                target[position] = *byte;
+            }
+        }
+    }
+    target
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/mining",
+    tag = "network",
+    responses(
+        (status = 200, description = "Difficulty, target, block template usage and recent block counts", body = MiningInfo),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_mining(State(state): State<AppState>) -> Result<Json<MiningInfo>, Response> {
+    let pay_address = Address::new(state.network.prefix(), Version::PubKey, &[0u8; 32]);
+
+    let (dag_info, template) = {
+        let client_guard = state.client.read().await;
+        let client = client_guard.as_ref().ok_or_else(not_connected)?;
+        let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get block DAG info: {:?}", e);
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to fetch DAG info",
+                )
+            })?;
+        let template = rpc::retried("get_block_template", || {
+            client.get_block_template(pay_address.clone(), vec![])
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get block template: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch block template",
+            )
+        })?;
+        (dag_info, template)
+    };
+
+    let block = &template.block;
+    let transactions = block.transactions.get(1..).unwrap_or_default();
+    let mass: u64 = block.transactions.iter().map(|tx| tx.mass).sum();
+
+    let recent = match state
+        .index
+        .as_ref()
+        .filter(|index| index.network_mismatch().is_none())
+    {
+        Some(index) => {
+            let since = now_ms().saturating_sub(RECENT_WINDOW_SECS * 1000);
+            match index.block_counts(since).await {
+                Ok((blocks, red_blocks)) => Some(RecentBlocks {
+                    window_seconds: RECENT_WINDOW_SECS,
+                    blocks,
+                    red_blocks,
+                    blue_blocks: blocks - red_blocks,
+                }),
+                Err(e) => {
+                    tracing::warn!("Index lookup of recent block counts failed: {:?}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    Ok(Json(MiningInfo {
+        network,
+        difficulty: dag_info.difficulty,
+        hashrate: state.sampler.read().await.latest().map(|s| s.hashrate),
+        virtual_daa_score: dag_info.virtual_daa_score,
+        template: BlockTemplateInfo {
+            is_synced: template.is_synced,
+            daa_score: block.header.daa_score,
+            timestamp: block.header.timestamp,
+            bits: block.header.bits,
+            target: hex::encode(compact_to_target(block.header.bits)),
+            parents: block.header.parents_by_level.first().map_or(0, Vec::len),
+            transactions: transactions.len(),
+            mass,
+            mass_limit: BLOCK_MASS_LIMIT,
+            mass_usage: mass as f64 / BLOCK_MASS_LIMIT as f64,
+        },
+        recent,
+    }))
+}
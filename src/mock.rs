@@ -0,0 +1,770 @@
+//! Generated chain for offline development, served with `--mock`.
+//!
+//! Instead of connecting to kaspad, the explorer reads a chain computed on the fly: one block
+//! per second, starting an hour of blocks before the process did, so lists, charts and the
+//! index have history from the first request. Every block is a function of its height and the
+//! network, so all connections of the process (the pool, backfill) see the same chain, and a
+//! restart regenerates the same hashes and transactions; only the timestamps move.
+//!
+//! Each block pays its coinbase to one of eight generated addresses and carries one transfer
+//! spending the previous block's coinbase to another of them, so the addresses have balances,
+//! UTXOs and history. The mempool holds the transfer of the next block, and with the orphan
+//! pool included the one after it, whose input does not exist yet. Block hashes start with
+//! the height in big-endian, which is how the mock finds a block from its hash.
+//!
+//! The chain has no forks, red blocks or peers. Block templates and transaction submission
+//! are refused, and utxos-changed notifications are not sent; block-added, virtual chain,
+//! sink blue score and DAA score notifications are, once per generated block.
+
+use crate::chain::{ChainSource, Client};
+use crate::network::Network;
+use crate::payment_uri::SOMPI_PER_KAS;
+use crate::sampler::now_ms;
+use async_channel::{Receiver, Sender};
+use async_trait::async_trait;
+use kaspa_addresses::{Address, Version};
+use kaspa_consensus_core::network::NetworkId;
+use kaspa_consensus_core::subnets::{SUBNETWORK_ID_COINBASE, SUBNETWORK_ID_NATIVE};
+use kaspa_consensus_core::tx::{
+    Transaction, TransactionInput, TransactionOutpoint, TransactionOutput,
+};
+use kaspa_hashes::{Hash, ZERO_HASH};
+use kaspa_notify::listener::ListenerId;
+use kaspa_notify::scope::Scope;
+use kaspa_rpc_core::{
+    BlockAddedNotification, GetBlockDagInfoResponse, GetBlockTemplateResponse, GetBlocksResponse,
+    GetCoinSupplyResponse, GetConnectedPeerInfoResponse, GetInfoResponse, GetServerInfoResponse,
+    GetVirtualChainFromBlockResponse, Notification, RpcAcceptedTransactionIds, RpcAddress,
+    RpcBalancesByAddressesEntry, RpcBlock, RpcBlockVerboseData, RpcError, RpcExtraData,
+    RpcFeeEstimate, RpcFeerateBucket, RpcHash, RpcHeader, RpcMempoolEntry,
+    RpcMempoolEntryByAddress, RpcResult, RpcTransaction, RpcTransactionId, RpcTransactionOutpoint,
+    RpcTransactionOutputVerboseData, RpcTransactionVerboseData, RpcUtxoEntry,
+    RpcUtxosByAddressesEntry, SinkBlueScoreChangedNotification, VirtualChainChangedNotification,
+    VirtualDaaScoreChangedNotification,
+};
+use kaspa_txscript::script_class::ScriptClass;
+use kaspa_txscript::{extract_script_pub_key_address, pay_to_address_script};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+/// URL prefix of the mock chain: `mock://testnet-12` is the generated testnet-12.
+pub const SCHEME: &str = "mock://";
+
+const BLOCK_INTERVAL_MS: u64 = 1000;
+// Blocks that already exist when the process starts.
+const HISTORY_BLOCKS: u64 = 3600;
+// Generated addresses that mine and trade.
+const ADDRESSES: u64 = 8;
+const SUBSIDY: u64 = 50 * SOMPI_PER_KAS;
+const TRANSFER_FEE: u64 = 2_000;
+const TRANSACTION_MASS: u64 = 2_000;
+const MAX_SOMPI: u64 = 2_900_000_000 * SOMPI_PER_KAS;
+// Blocks per get_blocks answer; callers page with the last hash, as with kaspad.
+const MAX_BLOCKS_PER_CALL: u64 = 1000;
+const BITS: u32 = 0x1e7f_ffff;
+const DIFFICULTY: f64 = 1_000_000.0;
+// What kaspad estimates for DIFFICULTY at one block per second.
+const HASHES_PER_SECOND: u64 = 2_000_000;
+const SERVER_VERSION: &str = "mock";
+
+static GENESIS_MS: OnceLock<u64> = OnceLock::new();
+
+/// The mock chain of `network`, the network name of a `mock://` URL.
+pub fn connect(network: &str) -> anyhow::Result<Client> {
+    let network = <Network as clap::ValueEnum>::from_str(network, false)
+        .map_err(|_| anyhow::anyhow!("{}{} names no known network", SCHEME, network))?;
+    tracing::info!("Using the generated {} chain (--mock)", network.name());
+    Ok(Arc::new(MockChain::new(network)?))
+}
+
+/// The URL `--mock` connects `network` to.
+pub fn url(network: Network) -> String {
+    format!("{}{}", SCHEME, network.name())
+}
+
+fn genesis_ms() -> u64 {
+    *GENESIS_MS.get_or_init(|| now_ms().saturating_sub(HISTORY_BLOCKS * BLOCK_INTERVAL_MS))
+}
+
+/// Height of the newest block.
+fn tip() -> u64 {
+    now_ms().saturating_sub(genesis_ms()) / BLOCK_INTERVAL_MS
+}
+
+fn timestamp(height: u64) -> u64 {
+    genesis_ms() + height * BLOCK_INTERVAL_MS
+}
+
+fn not_found(what: &str, hash: Hash) -> RpcError {
+    RpcError::General(format!("{} {} not found", what, hash))
+}
+
+/// Blocks and transactions of the generated chain, as functions of their height.
+#[derive(Clone)]
+struct Generator {
+    network: Network,
+    addresses: Vec<Address>,
+}
+
+impl Generator {
+    fn new(network: Network) -> Self {
+        let addresses = (0..ADDRESSES)
+            .map(|i| {
+                let key = digest(network, b"address", i);
+                Address::new(network.prefix(), Version::PubKey, &key)
+            })
+            .collect();
+        Generator { network, addresses }
+    }
+
+    fn block_hash(&self, height: u64) -> Hash {
+        let mut bytes = digest(self.network, b"block", height);
+        bytes[..8].copy_from_slice(&height.to_be_bytes());
+        Hash::from_bytes(bytes)
+    }
+
+    /// Height of the block `hash`, if it exists by now.
+    fn height(&self, hash: Hash, tip: u64) -> Option<u64> {
+        let bytes = hash.as_bytes();
+        let height = u64::from_be_bytes(bytes[..8].try_into().ok()?);
+        (height <= tip && self.block_hash(height) == hash).then_some(height)
+    }
+
+    fn miner(&self, height: u64) -> &Address {
+        &self.addresses[(height % ADDRESSES) as usize]
+    }
+
+    fn recipient(&self, height: u64) -> &Address {
+        &self.addresses[((height * 3 + 1) % ADDRESSES) as usize]
+    }
+
+    /// The coinbase of block `height`, its payload laid out as kaspad does (see `miners.rs`).
+    fn coinbase(&self, height: u64) -> Transaction {
+        let script = pay_to_address_script(self.miner(height));
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&height.to_le_bytes());
+        payload.extend_from_slice(&SUBSIDY.to_le_bytes());
+        payload.extend_from_slice(&script.version().to_le_bytes());
+        payload.push(script.script().len() as u8);
+        payload.extend_from_slice(script.script());
+        payload.extend_from_slice(SERVER_VERSION.as_bytes());
+        Transaction::new(
+            0,
+            vec![],
+            vec![TransactionOutput::new(SUBSIDY, script)],
+            0,
+            SUBNETWORK_ID_COINBASE,
+            0,
+            payload,
+        )
+    }
+
+    /// The transfer of block `height`, spending the coinbase of the block before it.
+    fn transfer(&self, height: u64) -> Option<Transaction> {
+        let spent = self.coinbase(height.checked_sub(1)?).id();
+        let input = TransactionInput::new(TransactionOutpoint::new(spent, 0), vec![], 0, 1);
+        let output = TransactionOutput::new(
+            SUBSIDY - TRANSFER_FEE,
+            pay_to_address_script(self.recipient(height)),
+        );
+        Some(Transaction::new(
+            0,
+            vec![input],
+            vec![output],
+            0,
+            SUBNETWORK_ID_NATIVE,
+            0,
+            vec![],
+        ))
+    }
+
+    fn transactions(&self, height: u64) -> Vec<Transaction> {
+        let mut transactions = vec![self.coinbase(height)];
+        transactions.extend(self.transfer(height));
+        transactions
+    }
+
+    /// `transaction` as kaspad serves it, with verbose data; `block` is None in the mempool.
+    fn rpc_transaction(&self, transaction: &Transaction, block: Option<u64>) -> RpcTransaction {
+        let mut rpc = RpcTransaction::from(transaction);
+        for output in &mut rpc.outputs {
+            if let Ok(address) =
+                extract_script_pub_key_address(&output.script_public_key, self.network.prefix())
+            {
+                output.verbose_data = Some(RpcTransactionOutputVerboseData {
+                    script_public_key_type: ScriptClass::PubKey,
+                    script_public_key_address: address,
+                });
+            }
+        }
+        rpc.verbose_data = Some(RpcTransactionVerboseData {
+            transaction_id: transaction.id(),
+            hash: transaction.id(),
+            compute_mass: TRANSACTION_MASS,
+            block_hash: block.map_or(ZERO_HASH, |height| self.block_hash(height)),
+            block_time: block.map_or(0, timestamp),
+        });
+        rpc
+    }
+
+    fn block(&self, height: u64, include_transactions: bool, tip: u64) -> RpcBlock {
+        let hash = self.block_hash(height);
+        let parents: Vec<Hash> = height
+            .checked_sub(1)
+            .map(|parent| self.block_hash(parent))
+            .into_iter()
+            .collect();
+        let transactions = self.transactions(height);
+        let header = RpcHeader {
+            hash,
+            version: 1,
+            parents_by_level: if parents.is_empty() {
+                vec![]
+            } else {
+                vec![parents.clone()]
+            },
+            hash_merkle_root: Hash::from_bytes(digest(self.network, b"merkle", height)),
+            accepted_id_merkle_root: Hash::from_bytes(digest(self.network, b"accepted", height)),
+            utxo_commitment: Hash::from_bytes(digest(self.network, b"utxo", height)),
+            timestamp: timestamp(height),
+            bits: BITS,
+            nonce: height,
+            daa_score: height,
+            blue_work: height.into(),
+            blue_score: height,
+            pruning_point: self.block_hash(0),
+        };
+        let verbose_data = RpcBlockVerboseData {
+            hash,
+            difficulty: DIFFICULTY,
+            selected_parent_hash: parents.first().copied().unwrap_or(ZERO_HASH),
+            transaction_ids: transactions.iter().map(Transaction::id).collect(),
+            is_header_only: false,
+            blue_score: height,
+            children_hashes: if height < tip {
+                vec![self.block_hash(height + 1)]
+            } else {
+                vec![]
+            },
+            merge_set_blues_hashes: parents,
+            merge_set_reds_hashes: vec![],
+            is_chain_block: true,
+        };
+        RpcBlock {
+            header,
+            transactions: if include_transactions {
+                transactions
+                    .iter()
+                    .map(|transaction| self.rpc_transaction(transaction, Some(height)))
+                    .collect()
+            } else {
+                vec![]
+            },
+            verbose_data: Some(verbose_data),
+        }
+    }
+
+    /// The mempool at `tip`: the next block's transfer, and with the orphan pool the one
+    /// after it.
+    fn mempool(
+        &self,
+        tip: u64,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> Vec<RpcMempoolEntry> {
+        let mut entries = Vec::new();
+        let pending = [(tip + 1, false), (tip + 2, true)];
+        for (height, is_orphan) in pending {
+            if (is_orphan && !include_orphan_pool) || (!is_orphan && filter_transaction_pool) {
+                continue;
+            }
+            if let Some(transfer) = self.transfer(height) {
+                entries.push(RpcMempoolEntry {
+                    fee: TRANSFER_FEE,
+                    transaction: self.rpc_transaction(&transfer, None),
+                    is_orphan,
+                });
+            }
+        }
+        entries
+    }
+
+    /// Unspent outputs of `address` at `tip`: the newest coinbase, and every transfer.
+    fn utxos(&self, address: &Address, tip: u64) -> Vec<RpcUtxosByAddressesEntry> {
+        let entry = |transaction: &Transaction, height: u64, is_coinbase: bool| {
+            let output = &transaction.outputs[0];
+            RpcUtxosByAddressesEntry {
+                address: Some(address.clone()),
+                outpoint: RpcTransactionOutpoint {
+                    transaction_id: transaction.id(),
+                    index: 0,
+                },
+                utxo_entry: RpcUtxoEntry {
+                    amount: output.value,
+                    script_public_key: output.script_public_key.clone(),
+                    block_daa_score: height,
+                    is_coinbase,
+                },
+            }
+        };
+        let mut utxos = Vec::new();
+        if self.miner(tip) == address {
+            utxos.push(entry(&self.coinbase(tip), tip, true));
+        }
+        for height in 1..=tip {
+            if self.recipient(height) == address {
+                if let Some(transfer) = self.transfer(height) {
+                    utxos.push(entry(&transfer, height, false));
+                }
+            }
+        }
+        utxos
+    }
+}
+
+fn digest(network: Network, kind: &[u8], index: u64) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(network.name())
+        .chain_update(kind)
+        .chain_update(index.to_le_bytes())
+        .finalize()
+        .into()
+}
+
+/// Notifications asked for with `start_notify`.
+#[derive(Default, Clone, Copy)]
+struct Subscriptions {
+    block_added: bool,
+    virtual_chain_changed: Option<bool>, // With accepted transaction ids
+    sink_blue_score_changed: bool,
+    virtual_daa_score_changed: bool,
+}
+
+/// One connection to the mock chain, with its own notification channel.
+pub struct MockChain {
+    generator: Generator,
+    network_id: NetworkId,
+    sender: Sender<Notification>,
+    receiver: Receiver<Notification>,
+    subscriptions: Arc<Mutex<Option<Subscriptions>>>, // None until the first start_notify
+    stop: CancellationToken,
+}
+
+impl MockChain {
+    fn new(network: Network) -> anyhow::Result<Self> {
+        let network_id = network
+            .name()
+            .parse::<NetworkId>()
+            .map_err(|e| anyhow::anyhow!("No network id for {}: {}", network.name(), e))?;
+        let (sender, receiver) = async_channel::unbounded();
+        Ok(MockChain {
+            generator: Generator::new(network),
+            network_id,
+            sender,
+            receiver,
+            subscriptions: Arc::default(),
+            stop: CancellationToken::new(),
+        })
+    }
+
+    /// Sends the notifications of every new block until disconnected.
+    fn spawn_notifier(&self) {
+        let generator = self.generator.clone();
+        let sender = self.sender.clone();
+        let subscriptions = self.subscriptions.clone();
+        let stop = self.stop.clone();
+        tokio::spawn(async move {
+            let mut ticks = interval(Duration::from_millis(BLOCK_INTERVAL_MS));
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let mut notified = tip();
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => return,
+                    _ = ticks.tick() => {}
+                }
+                let tip = tip();
+                let subscribed = *subscriptions
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let subscribed = subscribed.unwrap_or_default();
+                for height in notified + 1..=tip {
+                    for notification in notifications(&generator, height, subscribed) {
+                        if sender.send(notification).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                notified = tip;
+            }
+        });
+    }
+}
+
+fn notifications(
+    generator: &Generator,
+    height: u64,
+    subscribed: Subscriptions,
+) -> Vec<Notification> {
+    let mut notifications = Vec::new();
+    let hash = generator.block_hash(height);
+    if subscribed.block_added {
+        notifications.push(Notification::BlockAdded(BlockAddedNotification {
+            block: Arc::new(generator.block(height, true, height)),
+        }));
+    }
+    if let Some(include_accepted_transaction_ids) = subscribed.virtual_chain_changed {
+        let accepted_transaction_ids = if include_accepted_transaction_ids {
+            vec![RpcAcceptedTransactionIds {
+                accepting_block_hash: hash,
+                accepted_transaction_ids: generator
+                    .transactions(height)
+                    .iter()
+                    .map(Transaction::id)
+                    .collect(),
+            }]
+        } else {
+            vec![]
+        };
+        notifications.push(Notification::VirtualChainChanged(
+            VirtualChainChangedNotification {
+                removed_chain_block_hashes: Arc::new(vec![]),
+                added_chain_block_hashes: Arc::new(vec![hash]),
+                accepted_transaction_ids: Arc::new(accepted_transaction_ids),
+            },
+        ));
+    }
+    if subscribed.sink_blue_score_changed {
+        notifications.push(Notification::SinkBlueScoreChanged(
+            SinkBlueScoreChangedNotification {
+                sink_blue_score: height,
+            },
+        ));
+    }
+    if subscribed.virtual_daa_score_changed {
+        notifications.push(Notification::VirtualDaaScoreChanged(
+            VirtualDaaScoreChangedNotification {
+                virtual_daa_score: height,
+            },
+        ));
+    }
+    notifications
+}
+
+#[async_trait]
+impl ChainSource for MockChain {
+    async fn get_info(&self) -> RpcResult<GetInfoResponse> {
+        Ok(GetInfoResponse {
+            p2p_id: SERVER_VERSION.to_string(),
+            mempool_size: self.generator.mempool(tip(), false, false).len() as u64,
+            server_version: SERVER_VERSION.to_string(),
+            is_utxo_indexed: true,
+            is_synced: true,
+            has_notify_command: true,
+            has_message_id: true,
+        })
+    }
+
+    async fn get_server_info(&self) -> RpcResult<GetServerInfoResponse> {
+        Ok(GetServerInfoResponse {
+            rpc_api_version: 1,
+            rpc_api_revision: 0,
+            server_version: SERVER_VERSION.to_string(),
+            network_id: self.network_id,
+            has_utxo_index: true,
+            is_synced: true,
+            virtual_daa_score: tip(),
+        })
+    }
+
+    async fn get_block_dag_info(&self) -> RpcResult<GetBlockDagInfoResponse> {
+        let tip = tip();
+        let sink = self.generator.block_hash(tip);
+        Ok(GetBlockDagInfoResponse {
+            network: self.network_id,
+            block_count: tip + 1,
+            header_count: tip + 1,
+            tip_hashes: vec![sink],
+            difficulty: DIFFICULTY,
+            past_median_time: timestamp(tip.saturating_sub(60)),
+            virtual_parent_hashes: vec![sink],
+            pruning_point_hash: self.generator.block_hash(0),
+            virtual_daa_score: tip,
+            sink,
+        })
+    }
+
+    async fn get_sink_blue_score(&self) -> RpcResult<u64> {
+        Ok(tip())
+    }
+
+    async fn get_coin_supply(&self) -> RpcResult<GetCoinSupplyResponse> {
+        Ok(GetCoinSupplyResponse {
+            max_sompi: MAX_SOMPI,
+            circulating_sompi: SUBSIDY * (tip() + 1),
+        })
+    }
+
+    async fn get_connected_peer_info(&self) -> RpcResult<GetConnectedPeerInfoResponse> {
+        Ok(GetConnectedPeerInfoResponse { peer_info: vec![] })
+    }
+
+    async fn get_fee_estimate(&self) -> RpcResult<RpcFeeEstimate> {
+        // Everything makes the next block.
+        let bucket = || RpcFeerateBucket {
+            feerate: 1.0,
+            estimated_seconds: BLOCK_INTERVAL_MS as f64 / 1000.0,
+        };
+        Ok(RpcFeeEstimate {
+            priority_bucket: bucket(),
+            normal_buckets: vec![bucket()],
+            low_buckets: vec![bucket()],
+        })
+    }
+
+    async fn estimate_network_hashes_per_second(
+        &self,
+        _window_size: u32,
+        _start_hash: Option<RpcHash>,
+    ) -> RpcResult<u64> {
+        Ok(HASHES_PER_SECOND)
+    }
+
+    async fn get_block(&self, hash: RpcHash, include_transactions: bool) -> RpcResult<RpcBlock> {
+        let tip = tip();
+        let height = self
+            .generator
+            .height(hash, tip)
+            .ok_or_else(|| not_found("Block", hash))?;
+        Ok(self.generator.block(height, include_transactions, tip))
+    }
+
+    async fn get_blocks(
+        &self,
+        low_hash: Option<RpcHash>,
+        include_blocks: bool,
+        include_transactions: bool,
+    ) -> RpcResult<GetBlocksResponse> {
+        let tip = tip();
+        let low = match low_hash {
+            Some(hash) => self
+                .generator
+                .height(hash, tip)
+                .ok_or_else(|| not_found("Block", hash))?,
+            None => 0,
+        };
+        let heights = low..=tip.min(low + MAX_BLOCKS_PER_CALL - 1);
+        Ok(GetBlocksResponse {
+            block_hashes: heights
+                .clone()
+                .map(|height| self.generator.block_hash(height))
+                .collect(),
+            blocks: if include_blocks {
+                heights
+                    .map(|height| self.generator.block(height, include_transactions, tip))
+                    .collect()
+            } else {
+                vec![]
+            },
+        })
+    }
+
+    async fn get_virtual_chain_from_block(
+        &self,
+        start_hash: RpcHash,
+        include_accepted_transaction_ids: bool,
+        min_confirmation_count: Option<u64>,
+    ) -> RpcResult<GetVirtualChainFromBlockResponse> {
+        let tip = tip();
+        let start = self
+            .generator
+            .height(start_hash, tip)
+            .ok_or_else(|| not_found("Block", start_hash))?;
+        let end = tip.saturating_sub(min_confirmation_count.unwrap_or(0));
+        let added = start + 1..=end;
+        let accepted_transaction_ids = if include_accepted_transaction_ids {
+            added
+                .clone()
+                .map(|height| RpcAcceptedTransactionIds {
+                    accepting_block_hash: self.generator.block_hash(height),
+                    accepted_transaction_ids: self
+                        .generator
+                        .transactions(height)
+                        .iter()
+                        .map(Transaction::id)
+                        .collect(),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        Ok(GetVirtualChainFromBlockResponse {
+            removed_chain_block_hashes: vec![],
+            added_chain_block_hashes: added
+                .map(|height| self.generator.block_hash(height))
+                .collect(),
+            accepted_transaction_ids,
+        })
+    }
+
+    async fn get_block_template(
+        &self,
+        _pay_address: RpcAddress,
+        _extra_data: RpcExtraData,
+    ) -> RpcResult<GetBlockTemplateResponse> {
+        Err(RpcError::General(
+            "The mock chain does not build block templates".to_string(),
+        ))
+    }
+
+    async fn get_mempool_entry(
+        &self,
+        transaction_id: RpcTransactionId,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<RpcMempoolEntry> {
+        self.generator
+            .mempool(tip(), include_orphan_pool, filter_transaction_pool)
+            .into_iter()
+            .find(|entry| {
+                entry
+                    .transaction
+                    .verbose_data
+                    .as_ref()
+                    .is_some_and(|v| v.transaction_id == transaction_id)
+            })
+            .ok_or_else(|| not_found("Transaction", transaction_id))
+    }
+
+    async fn get_mempool_entries(
+        &self,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<Vec<RpcMempoolEntry>> {
+        Ok(self
+            .generator
+            .mempool(tip(), include_orphan_pool, filter_transaction_pool))
+    }
+
+    async fn get_mempool_entries_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+        include_orphan_pool: bool,
+        filter_transaction_pool: bool,
+    ) -> RpcResult<Vec<RpcMempoolEntryByAddress>> {
+        let tip = tip();
+        let entries = self
+            .generator
+            .mempool(tip, include_orphan_pool, filter_transaction_pool);
+        // The pending transfers spend the coinbase of the tip and of the block after it.
+        let spends = |entry: &RpcMempoolEntry, address: &Address| {
+            let height = if entry.is_orphan { tip + 1 } else { tip };
+            self.generator.miner(height) == address
+        };
+        let receives = |entry: &RpcMempoolEntry, address: &Address| {
+            entry.transaction.outputs.iter().any(|output| {
+                output
+                    .verbose_data
+                    .as_ref()
+                    .is_some_and(|v| &v.script_public_key_address == address)
+            })
+        };
+        Ok(addresses
+            .into_iter()
+            .map(|address| RpcMempoolEntryByAddress {
+                sending: entries
+                    .iter()
+                    .filter(|entry| spends(entry, &address))
+                    .cloned()
+                    .collect(),
+                receiving: entries
+                    .iter()
+                    .filter(|entry| receives(entry, &address))
+                    .cloned()
+                    .collect(),
+                address,
+            })
+            .collect())
+    }
+
+    async fn get_utxos_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+    ) -> RpcResult<Vec<RpcUtxosByAddressesEntry>> {
+        let tip = tip();
+        Ok(addresses
+            .iter()
+            .flat_map(|address| self.generator.utxos(address, tip))
+            .collect())
+    }
+
+    async fn get_balance_by_address(&self, address: RpcAddress) -> RpcResult<u64> {
+        Ok(self
+            .generator
+            .utxos(&address, tip())
+            .iter()
+            .map(|utxo| utxo.utxo_entry.amount)
+            .sum())
+    }
+
+    async fn get_balances_by_addresses(
+        &self,
+        addresses: Vec<RpcAddress>,
+    ) -> RpcResult<Vec<RpcBalancesByAddressesEntry>> {
+        let mut balances = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let balance = self.get_balance_by_address(address.clone()).await?;
+            balances.push(RpcBalancesByAddressesEntry {
+                address,
+                balance: Some(balance),
+            });
+        }
+        Ok(balances)
+    }
+
+    async fn submit_transaction(
+        &self,
+        _transaction: RpcTransaction,
+        _allow_orphan: bool,
+    ) -> RpcResult<RpcTransactionId> {
+        Err(RpcError::General(
+            "The mock chain does not accept transactions".to_string(),
+        ))
+    }
+
+    fn notification_channel_receiver(&self) -> Receiver<Notification> {
+        self.receiver.clone()
+    }
+
+    async fn start_notify(&self, _id: ListenerId, scope: Scope) -> RpcResult<()> {
+        let first = {
+            let mut subscriptions = self
+                .subscriptions
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let first = subscriptions.is_none();
+            let subscribed = subscriptions.get_or_insert_with(Subscriptions::default);
+            match scope {
+                Scope::BlockAdded(_) => subscribed.block_added = true,
+                Scope::VirtualChainChanged(scope) => {
+                    subscribed.virtual_chain_changed = Some(scope.include_accepted_transaction_ids)
+                }
+                Scope::SinkBlueScoreChanged(_) => subscribed.sink_blue_score_changed = true,
+                Scope::VirtualDaaScoreChanged(_) => subscribed.virtual_daa_score_changed = true,
+                _ => {}
+            }
+            first
+        };
+        if first {
+            self.spawn_notifier();
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        self.stop.cancel();
+        self.sender.close();
+        Ok(())
+    }
+}
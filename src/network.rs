@@ -0,0 +1,207 @@
+//! The Kaspa network the explorer serves, selected with `--network`.
+//!
+//! The network decides which address prefix user input must carry, the default gRPC port of
+//! kaspad (for `--kaspad-url` and the crawler), and which node the explorer agrees to talk
+//! to: on connecting, kaspad's network id must match, so a testnet-12 explorer pointed at a
+//! testnet-10 node refuses it instead of mixing the two networks' data. Addresses of another
+//! network are rejected with an error naming both networks.
+//!
+//! It also selects kaspad's consensus parameters for the network, which the emission schedule,
+//! DAA score time estimates and mass calculations follow; `[emission]` can still override the
+//! schedule for a testnet launched with other values.
+//!
+//! Further networks can be served by the same process (`[[networks]]` in the config), each
+//! with its own kaspad, caches, index and notification listener, under `/<prefix>/api/v1`
+//! and an explorer page at `/<prefix>`. Process-wide parts are shared: config, rate limits,
+//! response signing and metrics. The admin API, faucet, hedging, node comparison, crawler
+//! and pool signatures belong to the `--network` network only.
+
+use crate::config::Config;
+use crate::{error_response, AppState};
+use axum::{extract::State, http::StatusCode, response::Response};
+use kaspa_addresses::{Address, Prefix};
+use kaspa_consensus_core::config::params::{
+    Params, DEVNET_PARAMS, MAINNET_PARAMS, SIMNET_PARAMS, TESTNET_PARAMS,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    #[value(name = "testnet-10")]
+    #[serde(rename = "testnet-10")]
+    Testnet10,
+    #[value(name = "testnet-12")]
+    #[serde(rename = "testnet-12")]
+    Testnet12,
+    Devnet,
+    Simnet,
+}
+
+impl Network {
+    /// The network id as kaspad reports it, e.g. `testnet-12`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet10 => "testnet-10",
+            Network::Testnet12 => "testnet-12",
+            Network::Devnet => "devnet",
+            Network::Simnet => "simnet",
+        }
+    }
+
+    pub fn prefix(self) -> Prefix {
+        match self {
+            Network::Mainnet => Prefix::Mainnet,
+            Network::Testnet10 | Network::Testnet12 => Prefix::Testnet,
+            Network::Devnet => Prefix::Devnet,
+            Network::Simnet => Prefix::Simnet,
+        }
+    }
+
+    /// kaspad's consensus parameters for this network. kaspa-consensus-core has no separate
+    /// testnet-12 set; it runs testnet-10's parameters under its own genesis.
+    pub fn params(self) -> Params {
+        match self {
+            Network::Mainnet => MAINNET_PARAMS,
+            Network::Testnet10 | Network::Testnet12 => TESTNET_PARAMS,
+            Network::Devnet => DEVNET_PARAMS,
+            Network::Simnet => SIMNET_PARAMS,
+        }
+    }
+
+    /// Target blocks (and DAA scores) per second, since Crescendo.
+    pub fn blocks_per_second(self) -> u64 {
+        self.params().bps().after()
+    }
+
+    /// kaspad's default gRPC port on this network.
+    pub fn default_rpc_port(self) -> u16 {
+        match self {
+            Network::Mainnet => 16110,
+            Network::Testnet10 | Network::Testnet12 => 16210,
+            Network::Simnet => 16510,
+            Network::Devnet => 16610,
+        }
+    }
+
+    /// Parses `address` and checks that it belongs to this network. The error is meant for
+    /// the user: it says which network a foreign address is for.
+    pub fn parse_address(self, address: &str) -> Result<Address, String> {
+        let parsed = Address::try_from(address)
+            .map_err(|_| format!("{} is not a valid Kaspa address", address))?;
+        if parsed.prefix != self.prefix() {
+            let other = match parsed.prefix {
+                Prefix::Mainnet => "mainnet",
+                Prefix::Testnet => "testnet",
+                Prefix::Devnet => "devnet",
+                Prefix::Simnet => "simnet",
+                #[allow(unreachable_patterns)] // Test-only prefixes
+                _ => "another network",
+            };
+            return Err(format!(
+                "{} is a {} address, but this explorer serves {} ({}: addresses)",
+                address,
+                other,
+                self.name(),
+                self.prefix()
+            ));
+        }
+        Ok(parsed)
+    }
+
+    /// Fails unless kaspad's reported network id is this network.
+    pub fn check_node(self, node_network_id: &str) -> anyhow::Result<()> {
+        if node_network_id != self.name() {
+            anyhow::bail!(
+                "kaspad is on {}, but the explorer was started for {}; pass --network {} or connect to a {} node",
+                node_network_id,
+                self.name(),
+                node_network_id,
+                self.name()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// `Network::parse_address` for handlers: a foreign or malformed address is a 400.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+pub fn parse_address(state: &AppState, address: &str) -> Result<Address, Response> {
+    state
+        .network
+        .parse_address(address)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, &e))
+}
+
+// First path segments the primary network's routes use.
+const RESERVED_PREFIXES: &[&str] = &[
+    "api",
+    "static",
+    "admin",
+    "metrics",
+    "faucet",
+    "feed.xml",
+    "status.txt",
+];
+
+/// Checks the `[[networks]]` entries: prefixes must be distinct lowercase path segments that
+/// do not shadow the primary network's routes, and no two networks may share an index file.
+pub fn check_config(config: &Config) -> anyhow::Result<()> {
+    let mut prefixes = HashSet::new();
+    let mut index_paths: HashSet<&Path> = HashSet::new();
+    if config.index.enabled {
+        index_paths.insert(&config.index.path);
+    }
+    for extra in &config.networks {
+        let prefix = extra.prefix.as_str();
+        let valid = !prefix.is_empty()
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid || RESERVED_PREFIXES.contains(&prefix) {
+            anyhow::bail!(
+                "[[networks]] prefix {:?} must be a lowercase path segment other than {}",
+                prefix,
+                RESERVED_PREFIXES.join(", ")
+            );
+        }
+        if !prefixes.insert(prefix) {
+            anyhow::bail!("[[networks]] prefix {:?} is used twice", prefix);
+        }
+        if let Some(path) = &extra.index_path {
+            if !index_paths.insert(path) {
+                anyhow::bail!(
+                    "[[networks]] index_path {} is already used by another network",
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The explorer page for the network under `/<prefix>`, pointed at its API.
+pub fn index_page(prefix: &str) -> String {
+    include_str!("../static/index.html").replacen(
+        "<head>",
+        &format!(
+            "<head>\n    <script>window.API_PREFIX = '/{}';</script>",
+            prefix
+        ),
+        1,
+    )
+}
+
+/// Marks a response as served by an additional network, so app-wide middleware that reports
+/// on kaspad (`sync::flag_syncing`) looks at that network's node instead of the primary's.
+#[derive(Clone)]
+pub struct ServedBy(pub AppState);
+
+pub async fn mark_served_by(State(state): State<AppState>, mut response: Response) -> Response {
+    response.extensions_mut().insert(ServedBy(state));
+    response
+}
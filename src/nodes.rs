@@ -0,0 +1,284 @@
+//! Tip comparison across several kaspad nodes, for spotting netsplits.
+//!
+//! When `[nodes] urls` lists other nodes, a background task polls each of them and the primary
+//! node every `poll_interval_secs` for its sink and virtual DAA score. A node is `lagging` when
+//! its DAA score trails the highest one by more than `max_daa_lag`, and `diverged` when it and
+//! the primary do not know each other's sink from the previous poll: a full interval is ample
+//! time for a block to propagate between nodes on the same network, so a sink still unknown
+//! then is on another branch. `/api/nodes` serves the latest comparison.
+
+use crate::chain::{ChainSource, Client};
+use crate::config::NodesConfig;
+use crate::sampler::now_ms;
+use crate::{error_response, retry_after_response, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, timeout, Duration};
+use utoipa::ToSchema;
+
+// A node taking longer than this to answer is reported unreachable for the poll.
+const POLL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The latest comparison; None until the first poll finishes.
+pub type NodesHandle = Arc<RwLock<Option<NodesReport>>>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NodeStatus {
+    url: String,
+    primary: bool,
+    // ok, lagging, diverged or unreachable.
+    status: &'static str,
+    server_version: Option<String>,
+    is_synced: Option<bool>,
+    sink: Option<String>,
+    virtual_daa_score: Option<u64>,
+    daa_lag: Option<u64>, // Behind the highest DAA score among reachable nodes
+    // Whether this node and the primary know each other's previous sink; None for the
+    // primary and until two polls have succeeded.
+    shares_chain: Option<bool>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NodesReport {
+    checked_at: u64, // Unix ms
+    max_daa_lag: u64,
+    divergent: bool,        // Some node is lagging or diverged
+    nodes: Vec<NodeStatus>, // Primary first, then in configuration order
+}
+
+struct Tip {
+    server_version: String,
+    is_synced: bool,
+    sink: Hash,
+    virtual_daa_score: u64,
+}
+
+async fn tip(client: &Client) -> anyhow::Result<Tip> {
+    let info = rpc::retried("get_info", || client.get_info()).await?;
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await?;
+    Ok(Tip {
+        server_version: info.server_version,
+        is_synced: info.is_synced,
+        sink: dag_info.sink,
+        virtual_daa_score: dag_info.virtual_daa_score,
+    })
+}
+
+async fn knows(client: &Client, hash: Hash) -> bool {
+    matches!(
+        timeout(
+            POLL_TIMEOUT,
+            rpc::retried("get_block", || client.get_block(hash, false))
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+/// One of the configured nodes, connected on demand and dropped after a failed poll.
+struct Peer {
+    url: String,
+    client: Option<Client>,
+    previous_sink: Option<Hash>,
+}
+
+impl Peer {
+    async fn poll(&mut self) -> anyhow::Result<Tip> {
+        if self.client.is_none() {
+            let client = timeout(POLL_TIMEOUT, rpc::connect(&self.url))
+                .await
+                .map_err(|_| anyhow::anyhow!("Connection timed out"))??;
+            self.client = Some(client);
+        }
+        let client = self.client.as_ref().expect("connected above");
+        match timeout(POLL_TIMEOUT, tip(client)).await {
+            Ok(Ok(tip)) => Ok(tip),
+            failed => {
+                if let Some(client) = self.client.take() {
+                    let _ = client.disconnect().await;
+                }
+                match failed {
+                    Ok(Err(e)) => Err(e),
+                    _ => Err(anyhow::anyhow!("Timed out")),
+                }
+            }
+        }
+    }
+}
+
+fn unreachable_node(url: &str, primary: bool, error: String) -> NodeStatus {
+    NodeStatus {
+        url: url.to_string(),
+        primary,
+        status: "unreachable",
+        server_version: None,
+        is_synced: None,
+        sink: None,
+        virtual_daa_score: None,
+        daa_lag: None,
+        shares_chain: None,
+        error: Some(error),
+    }
+}
+
+fn reachable_node(url: &str, primary: bool, tip: &Tip, shares_chain: Option<bool>) -> NodeStatus {
+    NodeStatus {
+        url: url.to_string(),
+        primary,
+        status: "ok",
+        server_version: Some(tip.server_version.clone()),
+        is_synced: Some(tip.is_synced),
+        sink: Some(tip.sink.to_string()),
+        virtual_daa_score: Some(tip.virtual_daa_score),
+        daa_lag: None,
+        shares_chain,
+        error: None,
+    }
+}
+
+async fn compare(
+    state: &AppState,
+    config: &NodesConfig,
+    peers: &mut [Peer],
+    primary_previous_sink: &mut Option<Hash>,
+) -> NodesReport {
+    let primary_url = state.network_info.read().await.server_url.clone();
+    // A clone, so polling slow peers does not hold up an admin reconnect.
+    let primary = state.client.read().await.clone();
+    let primary_tip = match primary.as_ref() {
+        Some(client) => timeout(POLL_TIMEOUT, tip(client))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Timed out"))),
+        None => Err(anyhow::anyhow!("Not connected to kaspad")),
+    };
+
+    let mut nodes = vec![match &primary_tip {
+        Ok(tip) => reachable_node(&primary_url, true, tip, None),
+        Err(e) => unreachable_node(&primary_url, true, e.to_string()),
+    }];
+    for peer in peers.iter_mut() {
+        let tip = match peer.poll().await {
+            Ok(tip) => tip,
+            Err(e) => {
+                peer.previous_sink = None;
+                nodes.push(unreachable_node(&peer.url, false, e.to_string()));
+                continue;
+            }
+        };
+        let shares_chain = match (
+            primary.as_ref().filter(|_| primary_tip.is_ok()),
+            peer.client.as_ref(),
+            *primary_previous_sink,
+            peer.previous_sink,
+        ) {
+            (Some(primary), Some(client), Some(primary_sink), Some(peer_sink)) => {
+                Some(knows(primary, peer_sink).await && knows(client, primary_sink).await)
+            }
+            _ => None,
+        };
+        peer.previous_sink = Some(tip.sink);
+        nodes.push(reachable_node(&peer.url, false, &tip, shares_chain));
+    }
+    *primary_previous_sink = primary_tip.ok().map(|tip| tip.sink);
+
+    let highest = nodes.iter().filter_map(|node| node.virtual_daa_score).max();
+    for node in &mut nodes {
+        let (Some(daa_score), Some(highest)) = (node.virtual_daa_score, highest) else {
+            continue;
+        };
+        let lag = highest - daa_score;
+        node.daa_lag = Some(lag);
+        if node.shares_chain == Some(false) {
+            node.status = "diverged";
+        } else if lag > config.max_daa_lag {
+            node.status = "lagging";
+        }
+    }
+    NodesReport {
+        checked_at: now_ms(),
+        max_daa_lag: config.max_daa_lag,
+        divergent: nodes
+            .iter()
+            .any(|node| matches!(node.status, "lagging" | "diverged")),
+        nodes,
+    }
+}
+
+pub fn spawn(state: AppState) {
+    let config = state.config.nodes.clone();
+    if config.urls.is_empty() {
+        return;
+    }
+    tracing::info!("Comparing tips with {} other node(s)", config.urls.len());
+    let interval = Duration::from_secs(config.poll_interval_secs.max(1));
+    state.shutdown.clone().spawn(async move {
+        let mut peers: Vec<Peer> = config
+            .urls
+            .iter()
+            .map(|url| Peer {
+                url: url.clone(),
+                client: None,
+                previous_sink: None,
+            })
+            .collect();
+        let mut primary_previous_sink = None;
+        let mut was_divergent = false;
+        loop {
+            let report = compare(&state, &config, &mut peers, &mut primary_previous_sink).await;
+            if report.divergent && !was_divergent {
+                for node in report
+                    .nodes
+                    .iter()
+                    .filter(|node| matches!(node.status, "lagging" | "diverged"))
+                {
+                    tracing::warn!(
+                        "Node {} is {} (DAA score {:?}, sink {:?})",
+                        node.url,
+                        node.status,
+                        node.virtual_daa_score,
+                        node.sink
+                    );
+                }
+            } else if was_divergent && !report.divergent {
+                tracing::info!("Compared nodes agree again");
+            }
+            was_divergent = report.divergent;
+            *state.nodes.write().await = Some(report);
+            sleep(interval).await;
+        }
+    });
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/nodes",
+    tag = "network",
+    responses(
+        (status = 200, description = "Tip of the primary and each compared node, with lagging or diverged nodes flagged", body = NodesReport),
+        (status = 503, description = "No nodes configured to compare, or the first poll has not finished", body = ErrorResponse)
+    )
+)]
+pub async fn get_nodes(State(state): State<AppState>) -> Result<Json<NodesReport>, Response> {
+    if state.config.nodes.urls.is_empty() {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No nodes to compare; list them in [nodes] urls",
+        ));
+    }
+    match state.nodes.read().await.clone() {
+        Some(report) => Ok(Json(report)),
+        None => Err(retry_after_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Nodes not compared yet",
+            Duration::from_secs(state.config.nodes.poll_interval_secs.max(1)),
+        )),
+    }
+}
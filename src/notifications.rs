@@ -0,0 +1,118 @@
+//! kaspad notification listener.
+//!
+//! The gRPC client runs in `NotificationMode::Direct`, so every subscribed notification
+//! arrives on the client's single notification channel. This task subscribes once a client
+//! is available and dispatches each notification to the parts of the explorer that track
+//! live state, resubscribing if the channel closes. Addresses of webhook watches are added
+//! to a utxos-changed subscription on top of the fixed scopes (see `watch.rs`), unless the
+//! rich list already subscribes to every address (see `rich_list.rs`).
+
+use crate::chain::ChainSource;
+use crate::{
+    acceptance, home, mempool_history, miners, reorgs, rich_list, sse, virtual_state, watch,
+    whales, AppState,
+};
+use kaspa_notify::listener::ListenerId;
+use kaspa_notify::scope::{
+    BlockAddedScope, Scope, SinkBlueScoreChangedScope, UtxosChangedScope,
+    VirtualChainChangedScope, VirtualDaaScoreChangedScope,
+};
+use kaspa_rpc_core::Notification;
+use tokio::time::{sleep, Duration};
+
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+fn scopes(state: &AppState) -> Vec<Scope> {
+    let mut scopes = vec![
+        Scope::VirtualDaaScoreChanged(VirtualDaaScoreChangedScope {}),
+        Scope::SinkBlueScoreChanged(SinkBlueScoreChangedScope {}),
+        // Acceptance data feeds the index's acceptance table.
+        Scope::VirtualChainChanged(VirtualChainChangedScope::new(state.index.is_some())),
+        Scope::BlockAdded(BlockAddedScope {}),
+    ];
+    if state.config.index.rich_list {
+        // An empty address list subscribes to every address.
+        scopes.push(Scope::UtxosChanged(UtxosChangedScope::new(Vec::new())));
+    }
+    scopes
+}
+
+pub fn spawn(state: AppState) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            let receiver = {
+                let client_guard = state.client.read().await;
+                match client_guard.as_ref() {
+                    Some(client) => {
+                        let receiver = client.notification_channel_receiver();
+                        let mut subscribed = true;
+                        // Direct mode ignores the listener id; the default one is used throughout.
+                        for scope in scopes(&state) {
+                            if let Err(e) = client.start_notify(ListenerId::default(), scope).await {
+                                tracing::warn!("Failed to subscribe to kaspad notifications: {:?}", e);
+                                subscribed = false;
+                                break;
+                            }
+                        }
+                        if subscribed {
+                            watch::subscribe(&state, client, watch::watched_addresses(&state).await).await;
+                        }
+                        subscribed.then_some(receiver)
+                    }
+                    None => None,
+                }
+            };
+
+            if let Some(receiver) = receiver {
+                tracing::info!("Subscribed to kaspad notifications");
+                rich_list::on_subscribed(&state);
+                sse::on_connection_changed(&state, true).await;
+                virtual_state::seed(&state).await;
+                while let Ok(notification) = receiver.recv().await {
+                    dispatch(&state, notification).await;
+                }
+                tracing::warn!("kaspad notification channel closed, resubscribing");
+                sse::on_connection_changed(&state, false).await;
+            }
+            sleep(RESUBSCRIBE_DELAY).await;
+        }
+    });
+}
+
+async fn dispatch(state: &AppState, notification: Notification) {
+    state.debug_stats.notification(match &notification {
+        Notification::VirtualDaaScoreChanged(_) => "virtual_daa_score_changed",
+        Notification::SinkBlueScoreChanged(_) => "sink_blue_score_changed",
+        Notification::VirtualChainChanged(_) => "virtual_chain_changed",
+        Notification::UtxosChanged(_) => "utxos_changed",
+        Notification::BlockAdded(_) => "block_added",
+        _ => "other",
+    });
+    match notification {
+        Notification::VirtualDaaScoreChanged(n) => {
+            virtual_state::on_daa_score_changed(state, n.virtual_daa_score).await
+        }
+        Notification::SinkBlueScoreChanged(n) => {
+            virtual_state::on_sink_blue_score_changed(state, n.sink_blue_score).await
+        }
+        Notification::VirtualChainChanged(n) => {
+            sse::on_chain_changed(state, &n);
+            reorgs::on_chain_changed(state, &n).await;
+            acceptance::on_chain_changed(state, &n).await;
+            miners::on_chain_changed(state, &n).await;
+            watch::on_chain_changed(state, &n).await;
+            virtual_state::on_chain_changed(state, &n).await
+        }
+        Notification::UtxosChanged(n) => {
+            rich_list::on_utxos_changed(state, &n).await;
+            watch::on_utxos_changed(state, &n).await
+        }
+        Notification::BlockAdded(n) => {
+            sse::on_block_added(state, &n);
+            home::on_block_added(state, &n).await;
+            mempool_history::on_block_added(state, &n).await;
+            whales::on_block_added(state, &n).await
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,286 @@
+//! OpenAPI description of the JSON API.
+//!
+//! Handlers and their request/response types carry `utoipa` annotations; this module
+//! collects them into one document and serves a Swagger UI page that renders it.
+
+use crate::{acceptance, activity, address_data, address_mempool, address_summary, address_validation, admin, balance_history, block, block_intervals, block_size, block_transactions, blocks_range, charts, crawler, daa_time, debug_stats, decode_script, emission, epochs, faucet, fee_histogram, feed, filter, geoip, headers, home, labels, mempool_history, metrics, miners, mining, nodes, orphans, outpoint, payment_uri, pools, portfolio, proof, pruning, qr, raw, reorgs, rich_list, sampler, signing, snapshots, sse, sync, tokens, transaction, utxo_stats, validate, verify_payment, virtual_state, wallet, watch, whales};
+use crate::{
+    AddressBalance, BlockInfo, BlocksExistRequest, BlocksExistResponse, BlocksResponse, MempoolInfo,
+    NetworkInfo, PeerInfo, TransactionInfo, UtxoInfo,
+};
+use crate::errors::ErrorResponse;
+use axum::response::{Html, Json};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Kaspa Testnet 12 Explorer API"),
+    paths(
+        crate::get_status_text,
+        feed::get_feed,
+        metrics::get_metrics,
+        crate::get_network_info,
+        crate::get_blocks,
+        crate::blocks_exist,
+        block::get_block,
+        block::get_block_by_daa,
+        block_transactions::get_block_transactions,
+        blocks_range::get_blocks_range,
+        headers::get_headers,
+        crate::get_mempool,
+        orphans::get_orphans,
+        fee_histogram::get_fee_histogram,
+        mempool_history::get_mempool_history,
+        mempool_history::get_transaction_lifecycle,
+        filter::match_filter,
+        transaction::get_transaction,
+        acceptance::get_acceptance,
+        proof::get_proof,
+        raw::get_block_raw,
+        raw::get_transaction_raw,
+        outpoint::get_outpoint,
+        validate::validate_transaction,
+        crate::get_address_balance,
+        address_data::get_address_utxos,
+        address_data::get_address_transactions,
+        balance_history::get_balance_history,
+        address_mempool::get_address_mempool,
+        verify_payment::verify_payment,
+        faucet::get_faucet,
+        faucet::post_faucet,
+        portfolio::post_portfolio,
+        portfolio::get_portfolio,
+        wallet::scan_wallet,
+        snapshots::post_snapshot,
+        snapshots::get_snapshot,
+        snapshots::download_snapshot,
+        watch::post_watch,
+        watch::get_watch,
+        watch::delete_watch,
+        labels::get_labels,
+        tokens::get_tokens,
+        tokens::get_token,
+        tokens::get_address_tokens,
+        crate::get_peer_info,
+        sampler::get_network_stats,
+        sampler::get_tps,
+        block_intervals::get_block_intervals,
+        block_size::get_block_size,
+        sync::get_sync_status,
+        epochs::get_epochs,
+        daa_time::get_daa_time,
+        daa_time::get_time_daa,
+        virtual_state::get_virtual,
+        nodes::get_nodes,
+        crawler::get_network_map,
+        crawler::get_network_geo,
+        reorgs::get_reorgs,
+        home::get_home,
+        sse::get_events,
+        signing::get_signing_key,
+        debug_stats::get_debug_stats,
+        payment_uri::get_payment_uri,
+        address_validation::validate_address,
+        decode_script::get_decode_script,
+        decode_script::post_decode_script,
+        qr::get_qr_svg,
+        qr::get_qr_png,
+        charts::get_chart,
+        miners::get_miners,
+        mining::get_mining,
+        pruning::get_pruning,
+        rich_list::get_rich_list,
+        whales::get_whales,
+        utxo_stats::get_utxo_stats,
+        emission::get_emission,
+        admin::reconnect,
+        admin::switch_node,
+        admin::get_cache_stats,
+        admin::flush_caches,
+        admin::get_log_level,
+        admin::set_log_level,
+        admin::get_diagnostics,
+        admin::download_index_snapshot,
+        admin::wipe_index,
+        labels::set_label,
+        labels::delete_label,
+    ),
+    components(schemas(
+        NetworkInfo,
+        BlockInfo,
+        BlocksResponse,
+        BlocksExistRequest,
+        BlocksExistResponse,
+        block::BlockDetail,
+        block::BlockSummary,
+        block::BlockByDaa,
+        block_transactions::BlockTransactionsResponse,
+        block_transactions::BlockTransaction,
+        blocks_range::BlocksRangeResponse,
+        blocks_range::BlockSummary,
+        headers::HeadersResponse,
+        headers::CompactHeader,
+        TransactionInfo,
+        MempoolInfo,
+        filter::FilterMatchRequest,
+        filter::FilterMatchResponse,
+        transaction::TransactionDetail,
+        transaction::TransactionInput,
+        transaction::TransactionOutput,
+        acceptance::TransactionAcceptance,
+        proof::InclusionProof,
+        proof::BranchStep,
+        proof::Side,
+        raw::RawBlock,
+        raw::RawTransaction,
+        outpoint::OutpointStatus,
+        outpoint::SpentStatus,
+        validate::ValidateTransactionRequest,
+        validate::ValidateInput,
+        validate::ValidateOutpoint,
+        validate::ValidateOutput,
+        validate::ValidateScriptPublicKey,
+        validate::ValidateTransactionResponse,
+        validate::ValidationIssue,
+        verify_payment::VerifyPaymentRequest,
+        verify_payment::VerifyPaymentResponse,
+        verify_payment::Payment,
+        faucet::FaucetInfo,
+        faucet::FaucetRequest,
+        faucet::Drip,
+        AddressBalance,
+        UtxoInfo,
+        address_data::AddressUtxos,
+        address_data::AddressUtxo,
+        address_data::AddressHistory,
+        address_data::AddressHistoryEntry,
+        balance_history::BalanceHistory,
+        balance_history::BalancePoint,
+        address_mempool::AddressMempool,
+        address_mempool::PendingTransaction,
+        PeerInfo,
+        ErrorResponse,
+        activity::AddressActivity,
+        address_summary::AddressSummary,
+        portfolio::PortfolioRequest,
+        portfolio::PortfolioResponse,
+        portfolio::PortfolioAddress,
+        wallet::WalletScanRequest,
+        wallet::WalletScanResponse,
+        wallet::WalletAddress,
+        wallet::Chain,
+        snapshots::SnapshotRequest,
+        snapshots::SnapshotStatus,
+        snapshots::SnapshotState,
+        snapshots::SnapshotDownload,
+        snapshots::SnapshotBalance,
+        watch::WatchRequest,
+        watch::WatchInfo,
+        sampler::NetworkStats,
+        fee_histogram::FeeHistogramResponse,
+        fee_histogram::FeeBucket,
+        orphans::OrphansResponse,
+        orphans::OrphanTransaction,
+        orphans::OrphanInput,
+        orphans::ParentStatus,
+        mempool_history::MempoolHistoryResponse,
+        mempool_history::TransactionLifecycle,
+        sampler::TpsStats,
+        sampler::TpsWindow,
+        block_intervals::BlockIntervalsResponse,
+        block_size::BlockSizeResponse,
+        block_size::BlockSizeWindow,
+        sync::SyncStatus,
+        epochs::EpochsResponse,
+        daa_time::DaaTimeEstimate,
+        daa_time::EstimateBasis,
+        crate::index::Epoch,
+        virtual_state::VirtualState,
+        nodes::NodesReport,
+        nodes::NodeStatus,
+        crawler::NetworkMapResponse,
+        crawler::VersionCount,
+        crawler::CrawledNode,
+        crawler::NetworkGeoResponse,
+        crawler::CountryCount,
+        crawler::GeoPoint,
+        crawler::OperatorCount,
+        geoip::Location,
+        reorgs::ReorgsResponse,
+        reorgs::Reorg,
+        home::HomeResponse,
+        home::HomeBlock,
+        home::HomeStats,
+        signing::SigningKeyInfo,
+        debug_stats::DebugStats,
+        debug_stats::BalanceCacheStats,
+        debug_stats::RingStats,
+        debug_stats::TaskStats,
+        debug_stats::RpcCalls,
+        payment_uri::PaymentUri,
+        address_validation::AddressValidation,
+        decode_script::DecodeScriptRequest,
+        decode_script::Opcode,
+        decode_script::DecodedScript,
+        charts::ChartResponse,
+        charts::ChartPoint,
+        miners::MinersResponse,
+        miners::MinerStats,
+        pools::MinedBy,
+        pools::Kind,
+        mining::MiningInfo,
+        mining::BlockTemplateInfo,
+        mining::RecentBlocks,
+        pruning::PruningInfo,
+        rich_list::RichListResponse,
+        rich_list::RichListEntry,
+        whales::WhalesResponse,
+        whales::WhaleTransaction,
+        utxo_stats::UtxoStats,
+        utxo_stats::UtxoBucket,
+        emission::EmissionInfo,
+        emission::EmissionPhase,
+        admin::SwitchNodeRequest,
+        admin::NodeResponse,
+        admin::CacheStats,
+        admin::FlushResponse,
+        admin::LogLevel,
+        admin::Diagnostics,
+        admin::WipeIndexRequest,
+        admin::WipeIndexResponse,
+        labels::Label,
+        labels::LabeledAddress,
+        labels::LabelsResponse,
+        tokens::Token,
+        tokens::TokensResponse,
+        tokens::TokenHolder,
+        tokens::TokenOperation,
+        tokens::TokenDetail,
+        tokens::AddressTokenBalance,
+        tokens::AddressTokens,
+        crate::index::IndexStats,
+        crate::telemetry::LogRecord,
+    )),
+    tags(
+        (name = "network", description = "Node, sync and virtual block state"),
+        (name = "blocks", description = "Blocks"),
+        (name = "mempool", description = "Mempool"),
+        (name = "transactions", description = "Indexed transactions"),
+        (name = "addresses", description = "Address balances and UTXOs"),
+        (name = "tokens", description = "KRC-20 tokens from Kasplex inscriptions; require [index] tokens"),
+        (name = "stats", description = "Sampled network statistics and charts"),
+        (name = "tools", description = "Stateless helpers for wallets and the UI"),
+        (name = "faucet", description = "Testnet faucet; mounted with the faucet feature"),
+        (name = "admin", description = "Operator endpoints; mounted with the admin feature and require a bearer token"),
+    )
+)]
+pub struct ApiDoc;
+
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI for the document above; assets come from a CDN like the main frontend's.
+pub async fn get_docs() -> Html<&'static str> {
+    Html(include_str!("../static/docs.html"))
+}
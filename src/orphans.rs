@@ -0,0 +1,215 @@
+//! Orphan pool, for debugging transactions kaspad holds back.
+//!
+//! kaspad keeps a transaction in its orphan pool while some of its inputs spend outputs it
+//! does not know yet. Each orphan input's parent is looked up: in the transaction pool (the
+//! parent is pending and the input is fine), in the orphan pool (the parent is itself
+//! waiting), and then in the index, which tells a confirmed output from one that does not
+//! exist or that another transaction already spent; the latter orphan can never be accepted.
+//! Without the index, parents outside the mempool are `unknown`.
+
+use crate::chain::ChainSource;
+use crate::resolver::{self, Outpoint};
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParentStatus {
+    InMempool,
+    Orphan,
+    Confirmed,
+    DoubleSpent,
+    Missing,
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrphansQuery {
+    /// Orphans returned (default 100, max 500)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OrphanInput {
+    outpoint: String, // txid:index
+    status: ParentStatus,
+    // Indexed transaction that spent the output, for double_spent.
+    spent_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OrphanTransaction {
+    transaction_id: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    fee: u64,
+    mass: u64,
+    inputs: Vec<OrphanInput>,
+    // Outpoints kaspad is waiting for: of orphan parents or not found at all.
+    missing_outpoints: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OrphansResponse {
+    orphans: Vec<OrphanTransaction>,
+    truncated: bool,
+    total_available: usize,
+    indexed: bool, // Whether parents outside the mempool were looked up in the index
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/mempool/orphans",
+    tag = "mempool",
+    params(OrphansQuery),
+    responses(
+        (status = 200, description = "Orphan transactions with the status of each input's parent", body = OrphansResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_orphans(
+    State(state): State<AppState>,
+    Query(query): Query<OrphansQuery>,
+) -> Result<Json<OrphansResponse>, Response> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let fetch_failed = |e| {
+        tracing::error!("Failed to get mempool entries: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch mempool entries",
+        )
+    };
+    // (include_orphan_pool=true, filter_transaction_pool=true) => orphans only
+    let orphans = rpc::retried("get_mempool_entries", || {
+        client.get_mempool_entries(true, true)
+    })
+    .await
+    .map_err(fetch_failed)?;
+    let pool = rpc::retried("get_mempool_entries", || {
+        client.get_mempool_entries(false, false)
+    })
+    .await
+    .map_err(fetch_failed)?;
+    drop(client_guard);
+
+    let ids = |entries: &[kaspa_rpc_core::RpcMempoolEntry]| -> HashSet<String> {
+        entries
+            .iter()
+            .filter_map(|entry| entry.transaction.verbose_data.as_ref())
+            .map(|v| v.transaction_id.to_string())
+            .collect()
+    };
+    let pool_ids = ids(&pool);
+    let orphan_ids = ids(&orphans);
+    let total_available = orphans.len();
+    let orphans = &orphans[..total_available.min(limit)];
+
+    // Parents outside the mempool, looked up in the index at once.
+    let outside: Vec<Outpoint> = orphans
+        .iter()
+        .flat_map(|entry| &entry.transaction.inputs)
+        .map(|input| {
+            (
+                input.previous_outpoint.transaction_id.to_string(),
+                input.previous_outpoint.index,
+            )
+        })
+        .filter(|(parent, _)| !pool_ids.contains(parent) && !orphan_ids.contains(parent))
+        .collect();
+    let index = state
+        .index
+        .as_ref()
+        .filter(|index| index.network_mismatch().is_none());
+    let indexed = resolver::from_index(&state, &outside).await;
+
+    let mut response = Vec::with_capacity(orphans.len());
+    for entry in orphans {
+        let mut inputs = Vec::with_capacity(entry.transaction.inputs.len());
+        for input in &entry.transaction.inputs {
+            let outpoint: Outpoint = (
+                input.previous_outpoint.transaction_id.to_string(),
+                input.previous_outpoint.index,
+            );
+            let mut spent_by = None;
+            let status = if pool_ids.contains(&outpoint.0) {
+                ParentStatus::InMempool
+            } else if orphan_ids.contains(&outpoint.0) {
+                ParentStatus::Orphan
+            } else if let Some(index) = index {
+                if indexed.contains_key(&outpoint) {
+                    spent_by = index
+                        .spending_transaction(outpoint.0.clone(), outpoint.1)
+                        .await
+                        .unwrap_or_else(|e| {
+                            tracing::warn!(
+                                "Index lookup of the spender of {}:{} failed: {:?}",
+                                outpoint.0,
+                                outpoint.1,
+                                e
+                            );
+                            None
+                        });
+                    if spent_by.is_some() {
+                        ParentStatus::DoubleSpent
+                    } else {
+                        ParentStatus::Confirmed
+                    }
+                } else {
+                    ParentStatus::Missing
+                }
+            } else {
+                ParentStatus::Unknown
+            };
+            inputs.push(OrphanInput {
+                outpoint: format!("{}:{}", outpoint.0, outpoint.1),
+                status,
+                spent_by,
+            });
+        }
+        response.push(OrphanTransaction {
+            transaction_id: entry
+                .transaction
+                .verbose_data
+                .as_ref()
+                .map(|v| v.transaction_id.to_string())
+                .unwrap_or_default(),
+            fee: entry.fee,
+            mass: entry.transaction.mass,
+            missing_outpoints: inputs
+                .iter()
+                .filter(|input| {
+                    matches!(input.status, ParentStatus::Orphan | ParentStatus::Missing)
+                })
+                .map(|input| input.outpoint.clone())
+                .collect(),
+            inputs,
+        });
+    }
+
+    Ok(Json(OrphansResponse {
+        truncated: total_available > response.len(),
+        orphans: response,
+        total_available,
+        indexed: index.is_some(),
+    }))
+}
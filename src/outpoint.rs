@@ -0,0 +1,174 @@
+//! Spent status of a single outpoint, for debugging wallet double spends.
+//!
+//! The outpoint is resolved to its output first (see `resolver.rs`), since the UTXO set can
+//! only be queried by address. Whether it is unspent comes from kaspad's UTXO index; the
+//! spending transaction comes from the local index, and a pending spend from the mempool.
+
+use crate::chain::ChainSource;
+use crate::errors::ApiError;
+use crate::resolver::{self, Outpoint};
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::Address;
+use kaspa_hashes::Hash;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpentStatus {
+    Unspent,
+    Spent,
+    // The output pays a non-standard script, which the UTXO index cannot be queried by.
+    Unknown,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OutpointStatus {
+    transaction_id: String,
+    index: u32,
+    address: Option<String>,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64,
+    status: SpentStatus,
+    // Indexed transaction that spent the output; None if unspent or not indexed.
+    spent_by: Option<String>,
+    // Mempool transaction spending the output, not yet in a block.
+    spent_in_mempool_by: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/outpoint/{txid}/{index}",
+    tag = "transactions",
+    params(
+        ("txid" = String, Path, description = "Id of the transaction that created the output"),
+        ("index" = u32, Path, description = "Output index")
+    ),
+    responses(
+        (status = 200, description = "Whether the outpoint is unspent, and what spent it", body = OutpointStatus),
+        (status = 400, description = "Invalid transaction id or index", body = ErrorResponse),
+        (status = 404, description = "Output not found", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, or kaspad runs without --utxoindex", body = ErrorResponse)
+    )
+)]
+pub async fn get_outpoint(
+    State(state): State<AppState>,
+    Path((txid, index)): Path<(String, String)>,
+) -> Result<Json<OutpointStatus>, Response> {
+    let txid = txid
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid transaction id"))?;
+    let index = index
+        .parse::<u32>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid output index"))?;
+
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
+    let info = rpc::retried("get_info", || client.get_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get kaspad info before outpoint lookup: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to query kaspad info",
+            )
+        })?;
+    if !info.is_utxo_indexed {
+        return Err(ApiError::UtxoIndexRequired(
+            "Outpoint status requires kaspad to run with --utxoindex".to_string(),
+        )
+        .into_response());
+    }
+
+    let outpoint: Outpoint = (txid.to_string(), index);
+    let output = resolver::resolve(&state, &client, std::slice::from_ref(&outpoint))
+        .await
+        .remove(&outpoint)
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Output not found"))?;
+    let mut response = OutpointStatus {
+        transaction_id: outpoint.0.clone(),
+        index,
+        address: output.address.clone(),
+        amount: output.amount,
+        status: SpentStatus::Unknown,
+        spent_by: None,
+        spent_in_mempool_by: None,
+    };
+    let Some(address) = output
+        .address
+        .as_deref()
+        .and_then(|a| Address::try_from(a).ok())
+    else {
+        return Ok(Json(response));
+    };
+
+    let utxos = rpc::retried("get_utxos_by_addresses", || {
+        client.get_utxos_by_addresses(vec![address.clone()])
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch UTXOs of {}: {:?}", address, e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch UTXOs")
+    })?;
+    let unspent = utxos
+        .iter()
+        .any(|utxo| utxo.outpoint.transaction_id == txid && utxo.outpoint.index == index);
+    response.status = if unspent {
+        SpentStatus::Unspent
+    } else {
+        SpentStatus::Spent
+    };
+
+    let entries = rpc::retried("get_mempool_entries_by_addresses", || {
+        client.get_mempool_entries_by_addresses(vec![address.clone()], true, false)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!(
+            "Failed to fetch mempool entries for outpoint lookup: {:?}",
+            e
+        );
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch mempool entries",
+        )
+    })?;
+    drop(client);
+    response.spent_in_mempool_by = entries
+        .iter()
+        .flat_map(|e| &e.sending)
+        .find(|entry| {
+            entry.transaction.inputs.iter().any(|input| {
+                input.previous_outpoint.transaction_id == txid
+                    && input.previous_outpoint.index == index
+            })
+        })
+        .and_then(|entry| entry.transaction.verbose_data.as_ref())
+        .map(|v| v.transaction_id.to_string());
+
+    if !unspent {
+        if let Some(tx_index) = state
+            .index
+            .as_ref()
+            .filter(|i| i.network_mismatch().is_none())
+        {
+            response.spent_by = tx_index
+                .spending_transaction(outpoint.0, index)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "Index lookup of the spender of {}:{} failed: {:?}",
+                        txid,
+                        index,
+                        e
+                    );
+                    None
+                });
+        }
+    }
+    Ok(Json(response))
+}
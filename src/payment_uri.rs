@@ -0,0 +1,257 @@
+//! `kaspa:` payment URIs.
+//!
+//! A payment URI is the address itself (its prefix doubles as the URI scheme, `kaspatest:`
+//! on testnets) followed by optional BIP21-style parameters: `amount` in KAS with up to 8
+//! decimals, and percent-encoded `label` and `message`. As in BIP21, an unknown parameter
+//! starting with `req-` makes the URI invalid, while other unknown parameters are ignored.
+//! Addresses must belong to the network the explorer is configured for.
+
+use crate::network::Network;
+use crate::{error_response, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_addresses::Address;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const SOMPI_PER_KAS: u64 = 100_000_000;
+const MAX_TEXT_LENGTH: usize = 256;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PaymentUriQuery {
+    /// URI to parse; when given, the other parameters are ignored
+    uri: Option<String>,
+    /// Address to build a URI for
+    address: Option<String>,
+    /// Amount in KAS, up to 8 decimals
+    amount: Option<String>,
+    label: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PaymentUri {
+    pub uri: String,
+    address: String,
+    amount: Option<String>, // KAS
+    amount_sompi: Option<u64>,
+    label: Option<String>,
+    message: Option<String>,
+}
+
+/// Parses a KAS amount such as `1.5` into sompi.
+pub fn parse_kas(amount: &str) -> Option<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || fraction.len() > 8
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: u64 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<8}", fraction).parse().ok()?
+    };
+    whole.checked_mul(SOMPI_PER_KAS)?.checked_add(fraction)
+}
+
+/// Formats sompi as KAS without trailing zeros, e.g. `1.5`.
+pub fn format_kas(sompi: u64) -> String {
+    let fraction = sompi % SOMPI_PER_KAS;
+    if fraction == 0 {
+        return (sompi / SOMPI_PER_KAS).to_string();
+    }
+    let fraction = format!("{:08}", fraction);
+    format!(
+        "{}.{}",
+        sompi / SOMPI_PER_KAS,
+        fraction.trim_end_matches('0')
+    )
+}
+
+// RFC 3986 percent-encoding of everything but unreserved characters, so spaces become %20
+// rather than the form-encoded `+` some wallets read literally.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn check_text(name: &str, text: Option<String>) -> Result<Option<String>, Response> {
+    match text {
+        Some(text) if text.len() > MAX_TEXT_LENGTH => Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("{} is limited to {} bytes", name, MAX_TEXT_LENGTH),
+        )),
+        text => Ok(text.filter(|t| !t.is_empty())),
+    }
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn parse_amount(amount: Option<&str>) -> Result<Option<u64>, Response> {
+    amount
+        .map(|amount| {
+            parse_kas(amount).filter(|sompi| *sompi > 0).ok_or_else(|| {
+                error_response(
+                    StatusCode::BAD_REQUEST,
+                    "amount must be a positive KAS amount with up to 8 decimals",
+                )
+            })
+        })
+        .transpose()
+}
+
+fn build(
+    address: &Address,
+    amount_sompi: Option<u64>,
+    label: Option<String>,
+    message: Option<String>,
+) -> PaymentUri {
+    let address = address.to_string();
+    let amount = amount_sompi.map(format_kas);
+    let mut params = Vec::new();
+    if let Some(amount) = &amount {
+        params.push(format!("amount={}", amount));
+    }
+    if let Some(label) = &label {
+        params.push(format!("label={}", percent_encode(label)));
+    }
+    if let Some(message) = &message {
+        params.push(format!("message={}", percent_encode(message)));
+    }
+    let uri = if params.is_empty() {
+        address.clone()
+    } else {
+        format!("{}?{}", address, params.join("&"))
+    };
+    PaymentUri {
+        uri,
+        address,
+        amount,
+        amount_sompi,
+        label,
+        message,
+    }
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn parse(uri: &str, network: Network) -> Result<PaymentUri, Response> {
+    let invalid = |reason: &str| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid payment URI: {}", reason),
+        )
+    };
+    let (address, query) = uri.trim().split_once('?').unwrap_or((uri.trim(), ""));
+    let address = network
+        .parse_address(address)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, &e))?;
+    let (mut amount, mut label, mut message) = (None, None, None);
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value).ok_or_else(|| invalid("bad percent-encoding"))?;
+        match key {
+            "amount" => amount = Some(value),
+            "label" => label = Some(value),
+            "message" => message = Some(value),
+            key if key.starts_with("req-") => {
+                return Err(invalid(&format!("unsupported required parameter {}", key)))
+            }
+            _ => {}
+        }
+    }
+    let amount_sompi = parse_amount(amount.as_deref())?;
+    Ok(build(
+        &address,
+        amount_sompi,
+        check_text("label", label)?,
+        check_text("message", message)?,
+    ))
+}
+
+/// Validates `address` and the optional parameters, and builds their payment URI.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+pub fn build_checked(
+    address: &str,
+    network: Network,
+    amount: Option<&str>,
+    label: Option<String>,
+    message: Option<String>,
+) -> Result<PaymentUri, Response> {
+    let address = network
+        .parse_address(address.trim())
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, &e))?;
+    let amount_sompi = parse_amount(amount)?;
+    Ok(build(
+        &address,
+        amount_sompi,
+        check_text("label", label)?,
+        check_text("message", message)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tools/payment-uri",
+    tag = "tools",
+    params(PaymentUriQuery),
+    responses(
+        (status = 200, description = "The built or parsed payment URI and its fields", body = PaymentUri),
+        (status = 400, description = "Invalid URI, address, amount or text, or an address of another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_payment_uri(
+    State(state): State<AppState>,
+    Query(query): Query<PaymentUriQuery>,
+) -> Result<Json<PaymentUri>, Response> {
+    if let Some(uri) = &query.uri {
+        return Ok(Json(parse(uri, state.network)?));
+    }
+    let address = query.address.as_deref().ok_or_else(|| {
+        error_response(StatusCode::BAD_REQUEST, "Either uri or address is required")
+    })?;
+    Ok(Json(build_checked(
+        address,
+        state.network,
+        query.amount.as_deref(),
+        query.label,
+        query.message,
+    )?))
+}
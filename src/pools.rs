@@ -0,0 +1,138 @@
+//! Pool and mining-software identification of blocks.
+//!
+//! The operator can supply a signature table (`[pools] signatures_file`), a JSON array such as
+//!
+//! ```json
+//! [
+//!   {"name": "Example Pool", "kind": "pool", "addresses": ["kaspatest:qr..."], "patterns": ["examplepool"]},
+//!   {"name": "kaspa-miner", "kind": "software", "patterns": ["kaspa-miner"]}
+//! ]
+//! ```
+//!
+//! A block is matched by its coinbase payout address first, then by the patterns, which are
+//! searched case-insensitively in the coinbase extra data (the bytes after the payout script,
+//! where pools and mining software leave their tag). Signatures are tried in file order and
+//! the first match wins; the table is read once at startup.
+
+use crate::config::PoolsConfig;
+use crate::miners;
+use crate::network::Network;
+use kaspa_rpc_core::RpcBlock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Pool,
+    Software, // Mining software, for solo miners that leave its default tag
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MinedBy {
+    name: String,
+    kind: Kind,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Signature {
+    name: String,
+    #[serde(default)]
+    kind: Kind,
+    #[serde(default)]
+    addresses: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<String>, // Substrings of the coinbase extra data, case-insensitive
+}
+
+pub struct PoolSignatures {
+    by_address: HashMap<String, MinedBy>,
+    patterns: Vec<(String, MinedBy)>, // Lowercased pattern, in file order
+}
+
+impl PoolSignatures {
+    /// Reads the configured signature table; None when no file is configured. Addresses must
+    /// belong to `network`.
+    pub fn load(config: &PoolsConfig, network: Network) -> anyhow::Result<Option<Arc<Self>>> {
+        let Some(path) = &config.signatures_file else {
+            return Ok(None);
+        };
+        let signatures = Self::parse(path, network)?;
+        tracing::info!(
+            "Loaded {} pool signature address(es) and {} pattern(s) from {}",
+            signatures.by_address.len(),
+            signatures.patterns.len(),
+            path.display()
+        );
+        Ok(Some(Arc::new(signatures)))
+    }
+
+    fn parse(path: &Path, network: Network) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("Failed to read pool signatures {}: {}", path.display(), e)
+        })?;
+        let entries: Vec<Signature> = serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid pool signatures {}: {}", path.display(), e))?;
+
+        let mut by_address = HashMap::new();
+        let mut patterns = Vec::new();
+        for entry in entries {
+            let name = entry.name.trim().to_string();
+            if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+                anyhow::bail!(
+                    "Pool signature names in {} must be 1 to {} characters",
+                    path.display(),
+                    MAX_NAME_LENGTH
+                );
+            }
+            let mined_by = MinedBy {
+                name,
+                kind: entry.kind,
+            };
+            for address in entry.addresses {
+                let address = network.parse_address(address.trim()).map_err(|e| {
+                    anyhow::anyhow!("{} (for {} in {})", e, mined_by.name, path.display())
+                })?;
+                // Earlier entries win, like patterns do.
+                by_address
+                    .entry(address.to_string())
+                    .or_insert_with(|| mined_by.clone());
+            }
+            for pattern in entry.patterns {
+                if pattern.is_empty() {
+                    anyhow::bail!("Empty pattern for {} in {}", mined_by.name, path.display());
+                }
+                patterns.push((pattern.to_lowercase(), mined_by.clone()));
+            }
+        }
+        Ok(PoolSignatures {
+            by_address,
+            patterns,
+        })
+    }
+
+    /// The pool or software a payout address is known to belong to.
+    pub fn by_address(&self, address: &str) -> Option<MinedBy> {
+        self.by_address.get(address).cloned()
+    }
+
+    /// Resolves `block` by its payout address, then by its coinbase extra data.
+    pub fn identify(&self, block: &RpcBlock) -> Option<MinedBy> {
+        if let Some(mined_by) = miners::coinbase_miner(block).and_then(|a| self.by_address(&a)) {
+            return Some(mined_by);
+        }
+        let extra_data =
+            String::from_utf8_lossy(miners::coinbase_extra_data(block)?).to_lowercase();
+        self.patterns
+            .iter()
+            .find(|(pattern, _)| extra_data.contains(pattern.as_str()))
+            .map(|(_, mined_by)| mined_by.clone())
+    }
+}
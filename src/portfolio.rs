@@ -0,0 +1,280 @@
+//! Watch-only portfolios.
+//!
+//! `POST /portfolio` takes a named set of addresses and returns their combined and per-address
+//! confirmed balance and pending (mempool) amounts in one call. With `save` set, the address
+//! set is stored in the index database under a random API key returned once in the response;
+//! `GET /portfolio` with that key in `x-api-key` recomputes the same view later. Only the
+//! SHA-256 of the key is stored.
+
+use crate::chain::ChainSource;
+use crate::errors::ApiError;
+use crate::network::Network;
+use crate::{address_summary, error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::Address;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+// Both kaspad calls take the whole set at once; bounds the work one request can cause.
+const MAX_PORTFOLIO_ADDRESSES: usize = 100;
+const MAX_NAME_LENGTH: usize = 100;
+const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PortfolioRequest {
+    name: Option<String>,
+    addresses: Vec<String>,
+    // Store the portfolio and return an API key for `GET /portfolio`.
+    #[serde(default)]
+    save: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PortfolioAddress {
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_received: u64, // Mempool outputs paying this address
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_sent: u64, // Mempool outputs and fees spent from this address, change excluded
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PortfolioResponse {
+    name: Option<String>,
+    // API key of a newly saved portfolio; shown only once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_received: u64,
+    #[serde(serialize_with = "crate::units::sompi")]
+    pending_sent: u64,
+    addresses: Vec<PortfolioAddress>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/portfolio",
+    tag = "addresses",
+    request_body = PortfolioRequest,
+    responses(
+        (status = 200, description = "Combined and per-address balances and pending amounts", body = PortfolioResponse),
+        (status = 400, description = "Invalid address or too many addresses", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, UTXO index disabled, or saving without the index", body = ErrorResponse)
+    )
+)]
+pub async fn post_portfolio(
+    State(state): State<AppState>,
+    Json(request): Json<PortfolioRequest>,
+) -> Result<Json<PortfolioResponse>, Response> {
+    if request
+        .name
+        .as_ref()
+        .is_some_and(|name| name.len() > MAX_NAME_LENGTH)
+    {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Portfolio names are limited to {} bytes", MAX_NAME_LENGTH),
+        ));
+    }
+    let addresses = parse_addresses(state.network, &request.addresses)?;
+    let mut response = summarize(&state, request.name.clone(), addresses.clone()).await?;
+
+    if request.save {
+        let index = state.index.clone().ok_or_else(|| {
+            error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Saving portfolios requires the index",
+            )
+        })?;
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        let key = hex::encode(key);
+        let stored: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+        let stored = serde_json::to_string(&stored).map_err(|e| {
+            tracing::error!("Failed to encode portfolio addresses: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to save portfolio",
+            )
+        })?;
+        index
+            .save_portfolio(hash_key(&key), request.name, stored)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to save portfolio: {:?}", e);
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to save portfolio",
+                )
+            })?;
+        response.key = Some(key);
+    }
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolio",
+    tag = "addresses",
+    params(("x-api-key" = String, Header, description = "Key returned when the portfolio was saved")),
+    responses(
+        (status = 200, description = "Current balances and pending amounts of a saved portfolio", body = PortfolioResponse),
+        (status = 401, description = "Missing API key", body = ErrorResponse),
+        (status = 404, description = "No portfolio saved under this key", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad, UTXO index disabled, or index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn get_portfolio(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<PortfolioResponse>, Response> {
+    // A header rather than a query parameter keeps the key out of access logs.
+    let key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| error_response(StatusCode::UNAUTHORIZED, "Missing x-api-key header"))?;
+    let index = state.index.clone().ok_or_else(|| {
+        error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Saved portfolios require the index",
+        )
+    })?;
+    let (name, stored) = index
+        .portfolio(hash_key(key.trim()))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to read portfolio: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index query failed")
+        })?
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Portfolio not found"))?;
+    let stored: Vec<String> = serde_json::from_str(&stored).map_err(|e| {
+        tracing::error!("Stored portfolio is corrupt: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Stored portfolio is corrupt",
+        )
+    })?;
+    let addresses = parse_addresses(state.network, &stored)?;
+    Ok(Json(summarize(&state, name, addresses).await?))
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Parses and deduplicates `addresses`, keeping their order.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn parse_addresses(network: Network, addresses: &[String]) -> Result<Vec<Address>, Response> {
+    if addresses.is_empty() || addresses.len() > MAX_PORTFOLIO_ADDRESSES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "A portfolio holds between 1 and {} addresses",
+                MAX_PORTFOLIO_ADDRESSES
+            ),
+        ));
+    }
+    let mut seen = HashSet::new();
+    let mut parsed = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        let address = network
+            .parse_address(address.trim())
+            .map_err(|e| error_response(StatusCode::BAD_REQUEST, &e))?;
+        if seen.insert(address.to_string()) {
+            parsed.push(address);
+        }
+    }
+    Ok(parsed)
+}
+
+async fn summarize(
+    state: &AppState,
+    name: Option<String>,
+    addresses: Vec<Address>,
+) -> Result<PortfolioResponse, Response> {
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
+    let info = rpc::retried("get_info", || client.get_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get kaspad info before portfolio lookup: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to query kaspad info",
+            )
+        })?;
+    if !info.is_utxo_indexed {
+        return Err(ApiError::UtxoIndexRequired(
+            "Portfolios require kaspad to run with --utxoindex".to_string(),
+        )
+        .into_response());
+    }
+
+    let balances = rpc::retried("get_balances_by_addresses", || {
+        client.get_balances_by_addresses(addresses.clone())
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch portfolio balances: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch balances",
+        )
+    })?;
+    let entries = rpc::retried("get_mempool_entries_by_addresses", || {
+        client.get_mempool_entries_by_addresses(addresses.clone(), true, false)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch portfolio mempool entries: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch mempool entries",
+        )
+    })?;
+
+    let mut rows: Vec<PortfolioAddress> = addresses
+        .iter()
+        .map(|address| PortfolioAddress {
+            address: address.to_string(),
+            balance: 0,
+            pending_received: 0,
+            pending_sent: 0,
+        })
+        .collect();
+    let row = |rows: &[PortfolioAddress], address: &Address| {
+        let address = address.to_string();
+        rows.iter().position(|row| row.address == address)
+    };
+    for entry in balances {
+        if let Some(i) = row(&rows, &entry.address) {
+            rows[i].balance = entry.balance.unwrap_or(0);
+        }
+    }
+    for entry in entries {
+        if let Some(i) = row(&rows, &entry.address) {
+            let (incoming, outgoing) = address_summary::pending_amounts(&entry);
+            rows[i].pending_received += incoming;
+            rows[i].pending_sent += outgoing;
+        }
+    }
+
+    Ok(PortfolioResponse {
+        name,
+        key: None,
+        balance: rows.iter().map(|row| row.balance).sum(),
+        pending_received: rows.iter().map(|row| row.pending_received).sum(),
+        pending_sent: rows.iter().map(|row| row.pending_sent).sum(),
+        addresses: rows,
+    })
+}
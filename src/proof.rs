@@ -0,0 +1,252 @@
+//! Merkle inclusion proofs for transactions.
+//!
+//! A block header commits to its transactions through `hash_merkle_root`, the root of a
+//! Merkle tree over the transactions' hashes in block order. Unlike the transaction id, the
+//! hash covers the signature scripts (and the mass, when the transaction commits to one).
+//! Leaves are padded to a power of two; a node whose right child is padding is hashed with
+//! the zero hash, and branches are hashed with the `MerkleBranchHash` keyed BLAKE2b.
+//!
+//! To verify, start from `transaction_hash` and for each branch step hash `hash || current`
+//! when the step is on the left, `current || hash` when on the right; the result must equal
+//! the `hash_merkle_root` of the header, whose serialization is included so the client can
+//! recompute the block hash as well (see `headers::serialize_header`).
+
+use crate::chain::ChainSource;
+use crate::{error_response, headers, not_connected, require_index, rpc, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::{Hash, Hasher, HasherBase, MerkleBranchHash, ZERO_HASH};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BranchStep {
+    hash: String,
+    side: Side, // Where the sibling goes when hashing with the current node
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InclusionProof {
+    transaction_id: String,
+    transaction_hash: String, // The leaf
+    block_hash: String,
+    index: usize, // Position of the transaction in the block
+    hash_merkle_root: String,
+    branch: Vec<BranchStep>, // Leaf to root
+    header: String,          // Hex of the header serialization kaspad hashes
+}
+
+fn merkle_hash(left: Hash, right: Hash) -> Hash {
+    let mut hasher = MerkleBranchHash::new();
+    hasher.update(left).update(right);
+    hasher.finalize()
+}
+
+/// Root of the tree over `leaves`, with the branch from leaf `index` up to it.
+fn merkle_branch(leaves: &[Hash], mut index: usize) -> (Hash, Vec<(Hash, Side)>) {
+    let mut level: Vec<Option<Hash>> = leaves.iter().copied().map(Some).collect();
+    level.resize(leaves.len().next_power_of_two(), None);
+    let mut branch = Vec::new();
+    while level.len() > 1 {
+        let sibling = level[index ^ 1].unwrap_or(ZERO_HASH);
+        let side = if index % 2 == 0 {
+            Side::Right
+        } else {
+            Side::Left
+        };
+        branch.push((sibling, side));
+        level = level
+            .chunks(2)
+            .map(|pair| Some(merkle_hash(pair[0]?, pair[1].unwrap_or(ZERO_HASH))))
+            .collect();
+        index /= 2;
+    }
+    (level[0].unwrap_or(ZERO_HASH), branch)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tx/{id}/proof",
+    tag = "transactions",
+    params(("id" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "Merkle branch of the transaction within its block", body = InclusionProof),
+        (status = 400, description = "Invalid transaction id", body = ErrorResponse),
+        (status = 404, description = "Transaction not indexed, or its block is no longer available", body = ErrorResponse),
+        (status = 503, description = "Index disabled or not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_proof(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<InclusionProof>, Response> {
+    let id = id
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid transaction id"))?;
+    let index = require_index(&state)?;
+    let block_hashes = index
+        .transaction_blocks(id.to_string())
+        .await
+        .map_err(|e| {
+            tracing::error!("Index lookup of transaction {} failed: {:?}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+        })?;
+    let block_hash = block_hashes
+        .first()
+        .and_then(|hash| hash.parse::<Hash>().ok())
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Transaction not indexed"))?;
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let block = rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(block_hash, true).await },
+    )
+    .await
+    .map_err(|e| {
+        if rpc::is_not_found(&e) {
+            error_response(
+                StatusCode::NOT_FOUND,
+                "Transaction's block is no longer available",
+            )
+        } else {
+            tracing::error!("Failed to fetch block {}: {:?}", block_hash, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block")
+        }
+    })?;
+    drop(client_guard);
+    // Pruned blocks keep their header only.
+    if block.transactions.is_empty() {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "Transaction's block is no longer available",
+        ));
+    }
+
+    let mut leaves = Vec::with_capacity(block.transactions.len());
+    let mut position = None;
+    for (i, tx) in block.transactions.iter().enumerate() {
+        let verbose = tx.verbose_data.as_ref().ok_or_else(|| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Block transactions lack their hashes",
+            )
+        })?;
+        if verbose.transaction_id == id {
+            position = Some(i);
+        }
+        leaves.push(verbose.hash);
+    }
+    let position = position.ok_or_else(|| {
+        tracing::error!(
+            "Transaction {} is indexed but missing from block {}",
+            id,
+            block_hash
+        );
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch transaction",
+        )
+    })?;
+
+    let (root, branch) = merkle_branch(&leaves, position);
+    // A proof that does not reach the header's root would be worse than none.
+    if root != block.header.hash_merkle_root {
+        tracing::error!(
+            "Merkle root of block {} recomputes to {} instead of {}",
+            block_hash,
+            root,
+            block.header.hash_merkle_root
+        );
+        return Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to reproduce the block's merkle root",
+        ));
+    }
+
+    Ok(Json(InclusionProof {
+        transaction_id: id.to_string(),
+        transaction_hash: leaves[position].to_string(),
+        block_hash: block_hash.to_string(),
+        index: position,
+        hash_merkle_root: root.to_string(),
+        branch: branch
+            .into_iter()
+            .map(|(hash, side)| BranchStep {
+                hash: hash.to_string(),
+                side,
+            })
+            .collect(),
+        header: hex::encode(headers::serialize_header(&block.header)),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u64) -> Vec<Hash> {
+        (1..=count).map(Hash::from_u64_word).collect()
+    }
+
+    // Hashes `leaf` up the branch, as a client checking the proof would.
+    fn fold(leaf: Hash, branch: &[(Hash, Side)]) -> Hash {
+        branch
+            .iter()
+            .fold(leaf, |node, &(sibling, side)| match side {
+                Side::Left => merkle_hash(sibling, node),
+                Side::Right => merkle_hash(node, sibling),
+            })
+    }
+
+    #[test]
+    fn every_branch_leads_to_the_root() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let (root, _) = merkle_branch(&leaves, 0);
+            let depth = leaves.len().next_power_of_two().trailing_zeros() as usize;
+            for (index, &leaf) in leaves.iter().enumerate() {
+                let (branch_root, branch) = merkle_branch(&leaves, index);
+                assert_eq!(branch_root, root);
+                assert_eq!(branch.len(), depth);
+                assert_eq!(fold(leaf, &branch), root, "leaf {} of {}", index, count);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_leaves_hash_as_zero() {
+        let leaves = leaves(3);
+        let (root, branch) = merkle_branch(&leaves, 2);
+        assert_eq!(
+            root,
+            merkle_hash(
+                merkle_hash(leaves[0], leaves[1]),
+                merkle_hash(leaves[2], ZERO_HASH)
+            )
+        );
+        assert_eq!(branch[0].0, ZERO_HASH);
+        assert!(matches!(branch[0].1, Side::Right));
+        assert!(matches!(branch[1].1, Side::Left));
+    }
+
+    #[test]
+    fn single_leaf_is_the_root() {
+        let leaves = leaves(1);
+        let (root, branch) = merkle_branch(&leaves, 0);
+        assert_eq!(root, leaves[0]);
+        assert!(branch.is_empty());
+    }
+}
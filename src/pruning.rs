@@ -0,0 +1,87 @@
+//! Pruning point and how far back block data reaches.
+//!
+//! kaspad deletes block bodies older than its pruning point and keeps only their headers, so
+//! transactions can be looked up from the pruning point's DAA score on. Block detail answers
+//! `410 Gone` for a block whose header survived but whose body did not.
+
+use crate::chain::{ChainSource, Client};
+use crate::sampler::now_ms;
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PruningInfo {
+    pruning_point: String,
+    pruning_point_daa_score: u64,
+    pruning_point_blue_score: u64,
+    pruning_point_timestamp: u64, // Unix ms
+    virtual_daa_score: u64,
+    // Depth of the retained block data, from the pruning point to the virtual.
+    retained_daa_scores: u64,
+    retained_seconds: u64,
+    block_count: u64,  // Blocks with bodies
+    header_count: u64, // Headers, including pruned blocks still known
+}
+
+/// Pruning point and retained range, read from kaspad.
+pub async fn pruning_info(client: &Client) -> anyhow::Result<PruningInfo> {
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await?;
+    let header = rpc::retried("get_block", || {
+        client.get_block(dag_info.pruning_point_hash, false)
+    })
+    .await?
+    .header;
+    Ok(PruningInfo {
+        pruning_point: dag_info.pruning_point_hash.to_string(),
+        pruning_point_daa_score: header.daa_score,
+        pruning_point_blue_score: header.blue_score,
+        pruning_point_timestamp: header.timestamp,
+        virtual_daa_score: dag_info.virtual_daa_score,
+        retained_daa_scores: dag_info.virtual_daa_score.saturating_sub(header.daa_score),
+        retained_seconds: now_ms().saturating_sub(header.timestamp) / 1000,
+        block_count: dag_info.block_count,
+        header_count: dag_info.header_count,
+    })
+}
+
+/// `410 Gone` for a block kaspad only has the header of.
+pub async fn pruned_response(client: &Client, daa_score: u64) -> Response {
+    let message = match pruning_info(client).await {
+        Ok(info) => format!(
+            "Block pruned: its DAA score {} is below the pruning point's {}, and only headers are kept that far back",
+            daa_score, info.pruning_point_daa_score
+        ),
+        Err(e) => {
+            tracing::warn!("Failed to fetch pruning point: {:?}", e);
+            "Block pruned: only its header is kept".to_string()
+        }
+    };
+    error_response(StatusCode::GONE, &message)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/pruning",
+    tag = "network",
+    responses(
+        (status = 200, description = "Pruning point and the range of retrievable block data", body = PruningInfo),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_pruning(State(state): State<AppState>) -> Result<Json<PruningInfo>, Response> {
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    pruning_info(client).await.map(Json).map_err(|e| {
+        tracing::error!("Failed to fetch pruning point: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch pruning point",
+        )
+    })
+}
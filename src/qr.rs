@@ -0,0 +1,133 @@
+//! QR codes of addresses.
+//!
+//! Renders an address, or a payment URI when an amount, label or message is given, as an SVG
+//! or PNG QR code, so the address page can show a scannable code without a client-side
+//! library. The URI is validated and built exactly as `/api/tools/payment-uri` does it.
+
+use crate::payment_uri;
+use crate::{error_response, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use image::{ImageFormat, Luma};
+use qrcode::{render::svg, EcLevel, QrCode};
+use serde::Deserialize;
+use std::io::Cursor;
+use utoipa::IntoParams;
+
+const DEFAULT_SIZE: u32 = 256;
+const MIN_SIZE: u32 = 64;
+const MAX_SIZE: u32 = 1024;
+// Codes only depend on the request, so clients and proxies may keep them.
+const CACHE_CONTROL: &str = "public, max-age=86400";
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QrQuery {
+    /// Amount in KAS, up to 8 decimals; encodes a payment URI
+    amount: Option<String>,
+    label: Option<String>,
+    message: Option<String>,
+    /// Minimum width and height in pixels (default 256, 64 to 1024)
+    size: Option<u32>,
+}
+
+async fn encode(
+    state: &AppState,
+    address: &str,
+    query: QrQuery,
+) -> Result<(QrCode, u32), Response> {
+    let size = query.size.unwrap_or(DEFAULT_SIZE);
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("size must be between {} and {}", MIN_SIZE, MAX_SIZE),
+        ));
+    }
+    let uri = payment_uri::build_checked(
+        address,
+        state.network,
+        query.amount.as_deref(),
+        query.label,
+        query.message,
+    )?
+    .uri;
+    // Medium error correction survives some smudging while keeping modules large.
+    let code = QrCode::with_error_correction_level(uri.as_bytes(), EcLevel::M).map_err(|e| {
+        tracing::warn!("Failed to encode QR code for {}: {:?}", uri, e);
+        error_response(
+            StatusCode::BAD_REQUEST,
+            "Payment URI is too long for a QR code",
+        )
+    })?;
+    Ok((code, size))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/qr.svg",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address"), QrQuery),
+    responses(
+        (status = 200, description = "QR code of the address or payment URI", content_type = "image/svg+xml", body = String),
+        (status = 400, description = "Invalid address, amount, text or size, or an address of another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_qr_svg(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(query): Query<QrQuery>,
+) -> Result<Response, Response> {
+    let (code, size) = encode(&state, &address, query).await?;
+    let image = code
+        .render::<svg::Color>()
+        .min_dimensions(size, size)
+        .build();
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, CACHE_CONTROL),
+        ],
+        image,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/qr.png",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Kaspa address"), QrQuery),
+    responses(
+        (status = 200, description = "QR code of the address or payment URI", content_type = "image/png", body = Vec<u8>),
+        (status = 400, description = "Invalid address, amount, text or size, or an address of another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_qr_png(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(query): Query<QrQuery>,
+) -> Result<Response, Response> {
+    let (code, size) = encode(&state, &address, query).await?;
+    let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| {
+            tracing::error!("Failed to encode QR code as PNG: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to render QR code",
+            )
+        })?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, CACHE_CONTROL),
+        ],
+        png,
+    )
+        .into_response())
+}
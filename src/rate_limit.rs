@@ -0,0 +1,157 @@
+//! Per-IP rate limiting.
+//!
+//! Routes are split into classes with their own token buckets: `heavy` routes (address
+//! lookups, mempool, index scans) each cost kaspad several RPCs, everything else is `default`.
+//! A client over its budget gets 429 with `Retry-After`.
+
+use crate::config::RateLimitConfig;
+use crate::shutdown::Shutdown;
+use crate::{retry_after_response, AppState};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+
+// How often idle per-IP buckets are dropped.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct RateLimiters {
+    default: DefaultKeyedRateLimiter<IpAddr>,
+    heavy: DefaultKeyedRateLimiter<IpAddr>,
+    forwarded_hops: usize,
+}
+
+impl RateLimiters {
+    pub fn new(config: &RateLimitConfig) -> Arc<Self> {
+        let quota = |per_minute: u32| {
+            Quota::per_minute(NonZeroU32::new(per_minute).unwrap_or(NonZeroU32::MIN))
+        };
+        Arc::new(RateLimiters {
+            default: RateLimiter::keyed(quota(config.default_per_minute)),
+            heavy: RateLimiter::keyed(quota(config.heavy_per_minute)),
+            forwarded_hops: config.forwarded_hops(),
+        })
+    }
+}
+
+/// Client address. Behind `forwarded_hops` trusted proxies, each appending the address it
+/// received the request from to `X-Forwarded-For`, that is the entry `forwarded_hops` from the
+/// right; entries further left are whatever the client sent. Otherwise, or when the header has
+/// fewer entries, the peer address.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, forwarded_hops: usize) -> IpAddr {
+    if forwarded_hops > 0 {
+        let entries: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect();
+        let forwarded = entries
+            .iter()
+            .rev()
+            .nth(forwarded_hops - 1)
+            .and_then(|ip| ip.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+pub fn spawn_cleanup(limiters: Arc<RateLimiters>, shutdown: &Shutdown) {
+    shutdown.spawn(async move {
+        loop {
+            sleep(CLEANUP_INTERVAL).await;
+            limiters.default.retain_recent();
+            limiters.heavy.retain_recent();
+        }
+    });
+}
+
+async fn check(
+    state: &AppState,
+    class: fn(&RateLimiters) -> &DefaultKeyedRateLimiter<IpAddr>,
+    peer: SocketAddr,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(limiters) = state.rate_limiters.as_ref() else {
+        return next.run(request).await;
+    };
+    let ip = client_ip(request.headers(), peer, limiters.forwarded_hops);
+    match class(limiters).check_key(&ip) {
+        Ok(()) => next.run(request).await,
+        Err(not_until) => retry_after_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Rate limit exceeded",
+            not_until.wait_time_from(DefaultClock::default().now()),
+        ),
+    }
+}
+
+pub async fn limit_default(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    check(&state, |l| &l.default, peer, request, next).await
+}
+
+pub async fn limit_heavy(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    check(&state, |l| &l.heavy, peer, request, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn client_ip_skips_trusted_hops_from_the_right() {
+        let peer: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let headers = forwarded(&["1.1.1.1, 2.2.2.2", "3.3.3.3"]);
+        assert_eq!(client_ip(&headers, peer, 0), peer.ip());
+        assert_eq!(
+            client_ip(&headers, peer, 1),
+            "3.3.3.3".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip(&headers, peer, 2),
+            "2.2.2.2".parse::<IpAddr>().unwrap()
+        );
+        // Fewer entries than proxies: the header cannot be trusted.
+        assert_eq!(client_ip(&headers, peer, 4), peer.ip());
+    }
+
+    #[test]
+    fn spoofed_leftmost_entry_is_ignored() {
+        let peer: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let headers = forwarded(&["6.6.6.6, 4.4.4.4"]);
+        assert_eq!(
+            client_ip(&headers, peer, 1),
+            "4.4.4.4".parse::<IpAddr>().unwrap()
+        );
+    }
+}
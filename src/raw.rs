@@ -0,0 +1,183 @@
+//! Raw block and transaction export.
+//!
+//! Each answer carries the consensus serialization as hex next to kaspad's verbose RPC JSON,
+//! so the bytes can be fed to test harnesses and debuggers as they are. The serialization is
+//! the one kaspad hashes: a block is its header (see `headers::serialize_header`), the
+//! transaction count and each transaction in block order; a transaction is its version,
+//! inputs (outpoint, signature script, sig op count, sequence), outputs (value, script
+//! version, script), lock time, subnetwork id, gas and payload, followed by its mass when it
+//! commits to one. Integers are little-endian and lengths u64 prefixes.
+
+use crate::chain::ChainSource;
+use crate::{error_response, headers, not_connected, pruning, rpc, transaction, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::{RpcBlock, RpcTransaction};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RawBlock {
+    hash: String,
+    hex: String, // Consensus serialization
+    #[schema(value_type = Object)]
+    json: serde_json::Value, // Verbose RPC block
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RawTransaction {
+    id: String,
+    block_hash: Option<String>, // A block that included it; None while in the mempool
+    in_mempool: bool,
+    hex: String, // Consensus serialization
+    #[schema(value_type = Object)]
+    json: serde_json::Value, // Verbose RPC transaction
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn serialize_transaction(out: &mut Vec<u8>, tx: &RpcTransaction) {
+    out.extend_from_slice(&tx.version.to_le_bytes());
+    out.extend_from_slice(&(tx.inputs.len() as u64).to_le_bytes());
+    for input in &tx.inputs {
+        out.extend_from_slice(&input.previous_outpoint.transaction_id.as_bytes());
+        out.extend_from_slice(&input.previous_outpoint.index.to_le_bytes());
+        write_bytes(out, &input.signature_script);
+        out.push(input.sig_op_count);
+        out.extend_from_slice(&input.sequence.to_le_bytes());
+    }
+    out.extend_from_slice(&(tx.outputs.len() as u64).to_le_bytes());
+    for output in &tx.outputs {
+        out.extend_from_slice(&output.value.to_le_bytes());
+        out.extend_from_slice(&output.script_public_key.version().to_le_bytes());
+        write_bytes(out, output.script_public_key.script());
+    }
+    out.extend_from_slice(&tx.lock_time.to_le_bytes());
+    out.extend_from_slice(tx.subnetwork_id.as_ref());
+    out.extend_from_slice(&tx.gas.to_le_bytes());
+    write_bytes(out, &tx.payload);
+    // Only transactions that commit to a mass carry it.
+    if tx.mass > 0 {
+        out.extend_from_slice(&tx.mass.to_le_bytes());
+    }
+}
+
+pub fn serialize_block(block: &RpcBlock) -> Vec<u8> {
+    let mut out = headers::serialize_header(&block.header);
+    out.extend_from_slice(&(block.transactions.len() as u64).to_le_bytes());
+    for tx in &block.transactions {
+        serialize_transaction(&mut out, tx);
+    }
+    out
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<serde_json::Value, Response> {
+    serde_json::to_value(value).map_err(|e| {
+        tracing::error!("Failed to encode RPC JSON: {:?}", e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to encode RPC JSON",
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/block/{hash}/raw",
+    tag = "blocks",
+    params(("hash" = String, Path, description = "Block hash")),
+    responses(
+        (status = 200, description = "Consensus serialization (hex) and verbose RPC JSON of the block", body = RawBlock),
+        (status = 400, description = "Invalid hash", body = ErrorResponse),
+        (status = 404, description = "Block not found", body = ErrorResponse),
+        (status = 410, description = "Block pruned; only its header is kept", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_block_raw(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+) -> Result<Json<RawBlock>, Response> {
+    let hash = hash
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid block hash"))?;
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let block = rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(hash, true).await },
+    )
+    .await
+    .map_err(|e| {
+        if rpc::is_not_found(&e) {
+            error_response(StatusCode::NOT_FOUND, "Block not found")
+        } else {
+            tracing::error!("Failed to fetch block {}: {:?}", hash, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block")
+        }
+    })?;
+    // Without its transactions the serialization would describe a different block.
+    if block.transactions.is_empty() {
+        return Err(pruning::pruned_response(client, block.header.daa_score).await);
+    }
+    drop(client_guard);
+
+    Ok(Json(RawBlock {
+        hash: hash.to_string(),
+        hex: hex::encode(serialize_block(&block)),
+        json: to_json(&block)?,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tx/{id}/raw",
+    tag = "transactions",
+    params(("id" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "Consensus serialization (hex) and verbose RPC JSON of the transaction", body = RawTransaction),
+        (status = 400, description = "Invalid transaction id", body = ErrorResponse),
+        (status = 404, description = "Transaction neither indexed nor in the mempool", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_transaction_raw(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<RawTransaction>, Response> {
+    let id = id
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid transaction id"))?;
+    let block_hash = transaction::block_hashes(&state, id)
+        .await?
+        .into_iter()
+        .next();
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let tx = match &block_hash {
+        Some(block_hash) => transaction::confirmed(&state, client, block_hash, id).await?,
+        None => transaction::unconfirmed(client, id).await?,
+    };
+    drop(client_guard);
+
+    let mut bytes = Vec::new();
+    serialize_transaction(&mut bytes, &tx);
+    Ok(Json(RawTransaction {
+        id: id.to_string(),
+        in_mempool: block_hash.is_none(),
+        block_hash,
+        hex: hex::encode(bytes),
+        json: to_json(&tx)?,
+    }))
+}
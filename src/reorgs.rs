@@ -0,0 +1,156 @@
+//! Reorg log.
+//!
+//! A virtual-chain-changed notification that removes chain blocks is a reorganization of the
+//! selected chain: the sink moved to a branch that does not extend the previous one. Each is
+//! stored in the index with its depth (the number of chain blocks removed), the virtual DAA
+//! score at the time and both block lists, so shallow testnet reorgs can be told apart from
+//! deep ones after the fact. Like the rest of the index, the log starts over with each epoch.
+
+use crate::index::StoredReorg;
+use crate::sampler::now_ms;
+use crate::{error_response, require_index, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_rpc_core::VirtualChainChangedNotification;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReorgsQuery {
+    /// Reorgs per page (default 50, max 500)
+    limit: Option<usize>,
+    /// Only reorgs removing at least this many chain blocks (default 1)
+    min_depth: Option<u64>,
+    /// next_cursor of the previous page
+    cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Reorg {
+    id: i64,
+    detected_at: u64,       // Unix ms
+    daa_score: Option<u64>, // Virtual DAA score when detected
+    depth: usize,           // Chain blocks removed
+    added_count: usize,
+    old_sink: Option<String>,
+    new_sink: Option<String>,
+    // From the old sink down.
+    removed_chain_blocks: Vec<String>,
+    // Up to the new sink.
+    added_chain_blocks: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReorgsResponse {
+    total: u64,         // Reorgs in the current index epoch
+    max_depth: u64,     // Deepest of them
+    reorgs: Vec<Reorg>, // Newest first
+    // Pass as cursor to continue; None on the last page.
+    next_cursor: Option<i64>,
+}
+
+pub async fn on_chain_changed(state: &AppState, notification: &VirtualChainChangedNotification) {
+    if notification.removed_chain_block_hashes.is_empty() {
+        return;
+    }
+    let removed: Vec<String> = notification
+        .removed_chain_block_hashes
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let added: Vec<String> = notification
+        .added_chain_block_hashes
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let daa_score = state.virtual_state.read().await.daa_score();
+    tracing::info!(
+        "Reorg of depth {}: sink {} replaced by {}",
+        removed.len(),
+        removed[0],
+        added.last().map_or("none", String::as_str)
+    );
+
+    let Some(index) = &state.index else {
+        return;
+    };
+    if index.network_mismatch().is_some() {
+        return;
+    }
+    let reorg = StoredReorg {
+        id: 0,
+        detected_at: now_ms(),
+        daa_score,
+        removed,
+        added,
+    };
+    if let Err(e) = index.record_reorg(reorg).await {
+        tracing::warn!("Failed to record reorg: {:?}", e);
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/reorgs",
+    tag = "network",
+    params(ReorgsQuery),
+    responses(
+        (status = 200, description = "Selected chain reorganizations, newest first", body = ReorgsResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse),
+        (status = 503, description = "Index disabled or built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_reorgs(
+    State(state): State<AppState>,
+    Query(query): Query<ReorgsQuery>,
+) -> Result<Json<ReorgsResponse>, Response> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let index = require_index(&state)?;
+
+    let lookup_failed = |e: anyhow::Error| {
+        tracing::error!("Index lookup of reorgs failed: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    };
+    let (total, max_depth) = index.reorg_totals().await.map_err(lookup_failed)?;
+    // One extra reorg tells whether another page follows.
+    let mut reorgs = index
+        .reorgs(query.min_depth.unwrap_or(1), query.cursor, limit + 1)
+        .await
+        .map_err(lookup_failed)?;
+    let more = reorgs.len() > limit;
+    reorgs.truncate(limit);
+    let next_cursor = reorgs.last().filter(|_| more).map(|last| last.id);
+
+    Ok(Json(ReorgsResponse {
+        total,
+        max_depth,
+        reorgs: reorgs
+            .into_iter()
+            .map(|reorg| Reorg {
+                id: reorg.id,
+                detected_at: reorg.detected_at,
+                daa_score: reorg.daa_score,
+                depth: reorg.removed.len(),
+                added_count: reorg.added.len(),
+                old_sink: reorg.removed.first().cloned(),
+                new_sink: reorg.added.last().cloned(),
+                removed_chain_blocks: reorg.removed,
+                added_chain_blocks: reorg.added,
+            })
+            .collect(),
+        next_cursor,
+    }))
+}
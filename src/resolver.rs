@@ -0,0 +1,158 @@
+//! Resolution of transaction inputs to the outputs they spend.
+//!
+//! An input only names its previous outpoint, so its address and amount (and with them the
+//! transaction's fee) have to be looked up. The index answers most lookups directly. For
+//! the rest, the source transaction is fetched: from its block, when the index knows which
+//! block included it, or from the mempool for chained unconfirmed transactions.
+
+use crate::chain::{ChainSource, Client};
+use crate::{rpc, AppState};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcTransaction;
+use std::collections::{BTreeSet, HashMap};
+
+// Bounds the RPCs one resolution can cost when the index has gaps.
+const MAX_SOURCE_FETCHES: usize = 16;
+
+pub type Outpoint = (String, u32);
+
+#[derive(Debug, Clone)]
+pub struct ResolvedOutput {
+    pub address: Option<String>,
+    pub amount: u64,
+}
+
+/// Outputs found in the index; missing outpoints are simply absent.
+pub async fn from_index(
+    state: &AppState,
+    outpoints: &[Outpoint],
+) -> HashMap<Outpoint, ResolvedOutput> {
+    let Some(index) = state
+        .index
+        .as_ref()
+        .filter(|index| index.network_mismatch().is_none())
+    else {
+        return HashMap::new();
+    };
+    if outpoints.is_empty() {
+        return HashMap::new();
+    }
+    match index.outputs(outpoints.to_vec()).await {
+        Ok(outputs) => outputs
+            .into_iter()
+            .map(|(outpoint, (address, amount))| (outpoint, ResolvedOutput { address, amount }))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Index lookup of spent outputs failed: {:?}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Like `from_index`, then fetches the source transactions of what the index is missing.
+pub async fn resolve(
+    state: &AppState,
+    client: &Client,
+    outpoints: &[Outpoint],
+) -> HashMap<Outpoint, ResolvedOutput> {
+    let mut resolved = from_index(state, outpoints).await;
+    let missing: BTreeSet<&str> = outpoints
+        .iter()
+        .filter(|outpoint| !resolved.contains_key(*outpoint))
+        .map(|(tx_id, _)| tx_id.as_str())
+        .collect();
+    if missing.len() > MAX_SOURCE_FETCHES {
+        tracing::debug!(
+            "Resolving only {} of {} unindexed source transactions",
+            MAX_SOURCE_FETCHES,
+            missing.len()
+        );
+    }
+
+    for tx_id in missing.into_iter().take(MAX_SOURCE_FETCHES) {
+        let Some(tx) = source_transaction(state, client, tx_id).await else {
+            continue;
+        };
+        for (i, output) in tx.outputs.iter().enumerate() {
+            let outpoint = (tx_id.to_string(), i as u32);
+            if outpoints.contains(&outpoint) {
+                let address = output
+                    .verbose_data
+                    .as_ref()
+                    .map(|v| v.script_public_key_address.to_string());
+                resolved.insert(
+                    outpoint,
+                    ResolvedOutput {
+                        address,
+                        amount: output.value,
+                    },
+                );
+            }
+        }
+    }
+    resolved
+}
+
+/// Sum of the spent amounts, if every outpoint was resolved.
+pub fn input_value(
+    outpoints: &[Outpoint],
+    resolved: &HashMap<Outpoint, ResolvedOutput>,
+) -> Option<u64> {
+    outpoints
+        .iter()
+        .map(|outpoint| resolved.get(outpoint).map(|output| output.amount))
+        .sum()
+}
+
+async fn source_transaction(
+    state: &AppState,
+    client: &Client,
+    tx_id: &str,
+) -> Option<RpcTransaction> {
+    let id: Hash = tx_id.parse().ok()?;
+    let block_hash = match &state.index {
+        Some(index) if index.network_mismatch().is_none() => {
+            match index.transaction_blocks(tx_id.to_string()).await {
+                Ok(blocks) => blocks.into_iter().next(),
+                Err(e) => {
+                    tracing::warn!("Index lookup of transaction {} failed: {:?}", tx_id, e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    if let Some(block_hash) = block_hash.and_then(|hash| hash.parse::<Hash>().ok()) {
+        match rpc::hedged(
+            state.hedging.as_deref(),
+            client,
+            "get_block",
+            |c| async move { c.get_block(block_hash, true).await },
+        )
+        .await
+        {
+            Ok(block) => {
+                return block.transactions.into_iter().find(|tx| {
+                    tx.verbose_data
+                        .as_ref()
+                        .is_some_and(|v| v.transaction_id == id)
+                });
+            }
+            Err(e) => tracing::warn!(
+                "Failed to fetch block {} of transaction {}: {:?}",
+                block_hash,
+                tx_id,
+                e
+            ),
+        }
+    }
+
+    // Chained transactions spend outputs that are not in any block yet.
+    rpc::retried("get_mempool_entry", || {
+        client.get_mempool_entry(id, true, false)
+    })
+    .await
+    .ok()
+    .map(|entry| entry.transaction)
+}
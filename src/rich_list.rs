@@ -0,0 +1,255 @@
+//! Rich list: the addresses holding the most KAS.
+//!
+//! With `[index] rich_list` set, the `balances` table of the index holds the balance and UTXO
+//! count of every funded address. A background scan reads every address the index has seen
+//! in the current epoch from kaspad's UTXO index and stores the result. Between scans the
+//! explorer subscribes to utxos-changed notifications for all addresses and adds each
+//! notification's differences. The scan runs after every (re)subscription, since
+//! notifications may have been missed while disconnected, and every `RESCAN_INTERVAL`.
+//!
+//! Addresses whose UTXOs all predate the start of the index are only counted in full from the
+//! first scan after they transact. A change that arrives while the scan is reading the same
+//! address can be overwritten by the older value until the next scan.
+
+use crate::chain::ChainSource;
+use crate::index::{AddressBalance, IndexHandle};
+use crate::sampler::now_ms;
+use crate::{error_response, require_index, rpc, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_addresses::Address;
+use kaspa_rpc_core::UtxosChangedNotification;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Notify, RwLock};
+use tokio::time::{sleep, timeout, Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
+
+// Addresses per get_utxos_by_addresses call; kept small since mining addresses hold
+// thousands of UTXOs each.
+const BATCH_SIZE: usize = 100;
+const RESCAN_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+// Delay before retrying a failed scan (kaspad down, index not started yet).
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1_000;
+
+pub type RichListHandle = Arc<RichList>;
+
+#[derive(Default)]
+pub struct RichList {
+    rescan: Notify,
+    scanned_at: RwLock<Option<u64>>, // Unix ms of the last completed scan
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RichListQuery {
+    /// Addresses returned (default 100, max 1000)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RichListEntry {
+    rank: usize,
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    utxo_count: u64,
+    share: Option<f64>, // Of the circulating supply, 0.0 to 1.0
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RichListResponse {
+    total_addresses: u64, // Addresses with a non-zero balance
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    circulating_supply: Option<u64>, // None while kaspad is unreachable
+    scanned_at: Option<u64>, // None until the first scan completes
+    addresses: Vec<RichListEntry>,
+}
+
+pub fn spawn(state: AppState, index: IndexHandle) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            // Elapsing is fine; the periodic scan corrects drift.
+            let _ = timeout(RESCAN_INTERVAL, state.rich_list.rescan.notified()).await;
+            while let Err(e) = scan(&state, &index).await {
+                tracing::warn!("Rich list scan failed: {:?}", e);
+                sleep(RETRY_INTERVAL).await;
+            }
+        }
+    });
+}
+
+/// Schedules a full scan; called once notifications are (re)subscribed.
+pub fn on_subscribed(state: &AppState) {
+    if state.config.index.rich_list {
+        state.rich_list.rescan.notify_one();
+    }
+}
+
+async fn scan(state: &AppState, index: &IndexHandle) -> anyhow::Result<()> {
+    if index.current_epoch_id().is_none() {
+        anyhow::bail!("No index epoch started");
+    }
+    if let Some(mismatch) = index.network_mismatch() {
+        anyhow::bail!(mismatch);
+    }
+    let started = Instant::now();
+    let mut after = String::new();
+    let mut scanned = 0;
+    loop {
+        let addresses = index.active_addresses(after, BATCH_SIZE).await?;
+        let Some(last) = addresses.last() else {
+            break;
+        };
+        after = last.clone();
+        let parsed = addresses
+            .iter()
+            .filter_map(|address| Address::try_from(address.as_str()).ok())
+            .collect();
+        let entries = {
+            let client_guard = state.client.read().await;
+            let client = client_guard
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Not connected to kaspad"))?;
+            rpc::retried("get_utxos_by_addresses", || {
+                client.get_utxos_by_addresses(parsed.clone())
+            })
+            .await?
+        };
+        // Addresses without entries are stored as empty, which drops them from the list.
+        let mut balances: HashMap<String, (u64, u64)> = addresses
+            .into_iter()
+            .map(|address| (address, (0, 0)))
+            .collect();
+        for entry in &entries {
+            let Some(address) = &entry.address else {
+                continue;
+            };
+            if let Some((balance, utxo_count)) = balances.get_mut(&address.to_string()) {
+                *balance += entry.utxo_entry.amount;
+                *utxo_count += 1;
+            }
+        }
+        scanned += balances.len();
+        index
+            .set_balances(
+                balances
+                    .into_iter()
+                    .map(|(address, (balance, utxo_count))| AddressBalance {
+                        address,
+                        balance,
+                        utxo_count,
+                    })
+                    .collect(),
+            )
+            .await?;
+    }
+    *state.rich_list.scanned_at.write().await = Some(now_ms());
+    tracing::info!(
+        "Rich list scan read {} addresses in {:?}",
+        scanned,
+        started.elapsed()
+    );
+    Ok(())
+}
+
+pub async fn on_utxos_changed(state: &AppState, notification: &UtxosChangedNotification) {
+    if !state.config.index.rich_list {
+        return;
+    }
+    let Some(index) = &state.index else {
+        return;
+    };
+    if index.network_mismatch().is_some() {
+        return;
+    }
+    // Per address: (balance difference, UTXO count difference).
+    let mut changes: HashMap<String, (i64, i64)> = HashMap::new();
+    for (entry, sign) in notification
+        .added
+        .iter()
+        .map(|entry| (entry, 1))
+        .chain(notification.removed.iter().map(|entry| (entry, -1)))
+    {
+        let Some(address) = &entry.address else {
+            continue;
+        };
+        let change = changes.entry(address.to_string()).or_default();
+        change.0 += sign * entry.utxo_entry.amount as i64;
+        change.1 += sign;
+    }
+    if changes.is_empty() {
+        return;
+    }
+    let changes = changes
+        .into_iter()
+        .map(|(address, (balance, utxo_count))| (address, balance, utxo_count))
+        .collect();
+    if let Err(e) = index.apply_balance_changes(changes).await {
+        tracing::warn!("Failed to update rich list balances: {:?}", e);
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/rich-list",
+    tag = "stats",
+    params(RichListQuery),
+    responses(
+        (status = 200, description = "Addresses by balance, largest first", body = RichListResponse),
+        (status = 503, description = "Rich list or index disabled, or index built from another network", body = ErrorResponse)
+    )
+)]
+pub async fn get_rich_list(
+    State(state): State<AppState>,
+    Query(query): Query<RichListQuery>,
+) -> Result<Json<RichListResponse>, Response> {
+    let index = require_index(&state)?;
+    if !state.config.index.rich_list {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The rich list is disabled (set rich_list in [index])",
+        ));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let (balances, total_addresses) = index.rich_list(limit).await.map_err(|e| {
+        tracing::error!("Index lookup of the rich list failed: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    })?;
+
+    let circulating_supply = match state.client.read().await.as_ref() {
+        Some(client) => match rpc::retried("get_coin_supply", || client.get_coin_supply()).await {
+            Ok(supply) => Some(supply.circulating_sompi),
+            Err(e) => {
+                tracing::warn!("Failed to get the coin supply: {:?}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    Ok(Json(RichListResponse {
+        total_addresses,
+        circulating_supply,
+        scanned_at: *state.rich_list.scanned_at.read().await,
+        addresses: balances
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| RichListEntry {
+                rank: i + 1,
+                share: circulating_supply
+                    .filter(|supply| *supply > 0)
+                    .map(|supply| entry.balance as f64 / supply as f64),
+                address: entry.address,
+                balance: entry.balance,
+                utxo_count: entry.utxo_count,
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,563 @@
+//! Helpers around kaspad RPC calls.
+//!
+//! Every call made on behalf of a request goes through `traced`, which runs it in a
+//! `kaspad_rpc` child span of the current (request) span and records its latency, both in the
+//! span and in the `/metrics` histogram, and counts it (and whether it failed) for its
+//! network's `/admin/debug/stats`.
+//!
+//! Reads go through `retried` instead, which wraps `traced` in the retry policy set from the
+//! `[retry]` config: transient failures (timeouts, dropped connections) are retried with
+//! exponential backoff and jitter, while answers such as "block not found" are returned at
+//! once. Calls with side effects, like submitting a transaction, stay single-shot.
+//!
+//! Idempotent reads on hot request paths can instead go through `hedged`: when backup nodes
+//! are configured and kaspad takes longer than its recent `percentile` latency, the same read
+//! is also sent to a backup node and the first successful answer is used. Hedged reads are
+//! retried under the same policy.
+//!
+//! Heavy handlers (UTXO enumeration) take their client from `checkout` rather than the
+//! shared primary: a pool of `[rpc] pool_size` extra connections to the same kaspad is handed
+//! out round-robin, so one large enumeration does not hold up the others on one connection.
+//!
+//! Clients are `chain::Client`s: a `mock://<network>` URL (what `--mock` connects to) gives
+//! the generated chain of `mock.rs` instead of kaspad.
+
+use crate::chain::Client;
+use crate::config::{HedgingConfig, RetryConfig};
+use crate::{debug_stats, metrics, mock, AppState};
+use kaspa_grpc_client::GrpcClient;
+use kaspa_rpc_core::notify::mode::NotificationMode;
+use kaspa_rpc_core::{RpcError, RpcResult};
+use rand::Rng;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration, Instant};
+use tracing::Instrument;
+
+// Recent primary latencies the hedging threshold is computed from.
+const LATENCY_WINDOW: usize = 512;
+// Below this many samples the threshold is meaningless, so nothing is hedged.
+const MIN_LATENCY_SAMPLES: usize = 20;
+
+// Error messages of failures worth retrying; anything else is kaspad's answer to the request.
+const TRANSIENT_ERRORS: &[&str] = &[
+    "timeout",
+    "timed out",
+    "connection",
+    "transport",
+    "unavailable",
+    "broken pipe",
+    "reset by peer",
+    "disconnected",
+    "channel closed",
+];
+
+static RETRY_POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// How failed kaspad reads are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    attempts: u32, // Including the first one
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64, // Fraction of each backoff that is randomized away, 0.0 to 1.0
+}
+
+impl From<&RetryConfig> for RetryPolicy {
+    fn from(config: &RetryConfig) -> Self {
+        RetryPolicy {
+            attempts: config.attempts.max(1),
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(
+                config.max_backoff_ms.max(config.initial_backoff_ms),
+            ),
+            jitter: config.jitter.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (from 1): doubled each time up to the maximum, then
+    /// shortened by a random part of `jitter` so clients recovering together spread out.
+    fn backoff(&self, retry: u32) -> Duration {
+        let doubled = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_backoff);
+        let cut = rand::thread_rng().gen_range(0.0..=self.jitter);
+        doubled.mul_f64(1.0 - cut)
+    }
+}
+
+/// Sets the policy used by `retried` and `hedged`; calls made before it use the defaults.
+pub fn set_retry_policy(config: &RetryConfig) {
+    let _ = RETRY_POLICY.set(RetryPolicy::from(config));
+}
+
+fn retry_policy() -> &'static RetryPolicy {
+    RETRY_POLICY.get_or_init(|| RetryPolicy::from(&RetryConfig::default()))
+}
+
+/// Whether `error` looks like a network or node hiccup rather than an answer.
+pub fn is_retryable(error: &impl std::fmt::Display) -> bool {
+    let message = error.to_string().to_lowercase();
+    TRANSIENT_ERRORS
+        .iter()
+        .any(|transient| message.contains(transient))
+}
+
+/// Whether `error` is kaspad answering that the block, transaction or entry asked for does not
+/// exist, as opposed to the call failing. kaspad's answers reach the client as their message
+/// only, so the message is checked on those variants alone.
+pub fn is_not_found(error: &RpcError) -> bool {
+    match error {
+        RpcError::General(message) | RpcError::RpcSubsystem(message) => {
+            message.to_lowercase().contains("not found")
+        }
+        _ => false,
+    }
+}
+
+/// Runs `call` until it succeeds, fails with a non-retryable error, or `policy` runs out of
+/// attempts.
+pub async fn with_retry<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    method: &'static str,
+    call: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(e) if attempt < policy.attempts && is_retryable(&e) => {
+                let backoff = policy.backoff(attempt);
+                tracing::warn!(
+                    rpc.method = method,
+                    "kaspad RPC failed (attempt {}/{}), retrying in {}ms: {}",
+                    attempt,
+                    policy.attempts,
+                    backoff.as_millis(),
+                    e
+                );
+                sleep(backoff).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Like `traced`, retrying transient failures under the configured policy. `call` is invoked
+/// once per attempt, so it must be safe to repeat.
+pub async fn retried<T, F, Fut>(method: &'static str, call: F) -> RpcResult<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = RpcResult<T>>,
+{
+    with_retry(retry_policy(), method, || traced(method, call())).await
+}
+
+/// Awaits `call` inside a span named after the RPC `method`.
+pub async fn traced<T, E, F>(method: &'static str, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let span = tracing::info_span!("kaspad_rpc", rpc.method = method);
+    async move {
+        let started = Instant::now();
+        let result = call.await;
+        let elapsed = started.elapsed();
+        metrics::observe_rpc(method, elapsed);
+        debug_stats::rpc_call(method, result.is_err());
+        tracing::debug!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            "kaspad RPC finished"
+        );
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+/// Opens a gRPC client to `url` (with or without a `grpc://` prefix), or the mock chain of a
+/// `mock://` URL.
+pub async fn connect(url: &str) -> anyhow::Result<Client> {
+    if let Some(network) = url.strip_prefix(mock::SCHEME) {
+        return mock::connect(network);
+    }
+    tracing::info!("Connecting to kaspad at: {}", url);
+
+    // Always use grpc:// for gRPC connections
+    let grpc_url = if url.starts_with("grpc://") {
+        url.to_string()
+    } else {
+        format!(
+            "grpc://{}",
+            url.replace("http://", "").replace("https://", "")
+        )
+    };
+
+    tracing::info!("Using gRPC URL: {}", grpc_url);
+
+    // Prefer the more robust connection used by the Stratum bridge:
+    // - explicit grpc:// prefix
+    // - extended request timeout
+    // - client start()
+    match GrpcClient::connect_with_args(
+        NotificationMode::Direct,
+        grpc_url.clone(),
+        None,
+        true,
+        None,
+        false,
+        Some(500_000),
+        Default::default(),
+    )
+    .await
+    {
+        Ok(c) => {
+            c.start(None).await;
+            Ok(Arc::new(c))
+        }
+        Err(e) => {
+            tracing::warn!(
+                "connect_with_args failed, falling back to connect(): {:?}",
+                e
+            );
+            Ok(Arc::new(GrpcClient::connect(grpc_url).await?))
+        }
+    }
+}
+
+/// Extra connections to the primary kaspad, refilled whenever the primary (re)connects.
+#[derive(Default)]
+pub struct ClientPool {
+    clients: RwLock<Vec<Client>>,
+    next: AtomicUsize,
+}
+
+impl ClientPool {
+    /// Replaces the pooled connections with `size` new ones to `url`. Connections that fail
+    /// are left out; the pool then runs smaller.
+    pub async fn fill(&self, url: &str, size: usize) {
+        let mut clients = Vec::with_capacity(size);
+        for _ in 0..size {
+            match connect(url).await {
+                Ok(client) => clients.push(client),
+                Err(e) => tracing::warn!("Failed to open a pooled kaspad connection: {}", e),
+            }
+        }
+        if size > 0 {
+            tracing::info!(
+                "Opened {}/{} pooled kaspad connection(s)",
+                clients.len(),
+                size
+            );
+        }
+        metrics::set_pool_connections(clients.len());
+        let previous = std::mem::replace(&mut *self.clients.write().await, clients);
+        close(previous).await;
+    }
+
+    pub async fn disconnect(&self) {
+        metrics::set_pool_connections(0);
+        close(std::mem::take(&mut *self.clients.write().await)).await;
+    }
+}
+
+async fn close(clients: Vec<Client>) {
+    for client in clients {
+        if let Err(e) = client.disconnect().await {
+            tracing::warn!("Failed to disconnect a pooled kaspad connection: {}", e);
+        }
+    }
+}
+
+/// A client checked out for one request; derefs to the `Client`.
+pub struct PooledClient {
+    client: Client,
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        metrics::pool_checkin();
+    }
+}
+
+/// The next pooled client round-robin, or the primary one while the pool is empty. None when
+/// not connected to kaspad.
+pub async fn checkout(state: &AppState) -> Option<PooledClient> {
+    let primary = state.client.read().await.clone()?;
+    let clients = state.client_pool.clients.read().await;
+    let (connection, client) = if clients.is_empty() {
+        ("primary".to_string(), primary)
+    } else {
+        let i = state.client_pool.next.fetch_add(1, Ordering::Relaxed) % clients.len();
+        (i.to_string(), clients[i].clone())
+    };
+    metrics::pool_checkout(connection);
+    Some(PooledClient { client })
+}
+
+/// Backup nodes for hedged reads, with the primary's recent latencies.
+pub struct Hedging {
+    backups: Vec<Client>,
+    next_backup: AtomicUsize,
+    latencies: Mutex<VecDeque<Duration>>,
+    percentile: f64,
+    min_delay: Duration,
+}
+
+impl Hedging {
+    /// Connects the configured backup nodes. `None` when none are configured or reachable.
+    pub async fn connect(config: &HedgingConfig) -> Option<Arc<Self>> {
+        let mut backups = Vec::new();
+        for url in &config.backup_nodes {
+            match connect(url).await {
+                Ok(client) => backups.push(client),
+                Err(e) => tracing::warn!("Skipping hedging backup node {}: {}", url, e),
+            }
+        }
+        if backups.is_empty() {
+            return None;
+        }
+        tracing::info!(
+            "Hedging reads across {} backup node(s) at p{} latency",
+            backups.len(),
+            config.percentile
+        );
+        Some(Arc::new(Hedging {
+            backups,
+            next_backup: AtomicUsize::new(0),
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            percentile: config.percentile.clamp(0.0, 100.0),
+            min_delay: Duration::from_millis(config.min_delay_ms),
+        }))
+    }
+
+    /// How long to wait on the primary before hedging, once enough latencies are known.
+    fn delay(&self) -> Option<Duration> {
+        let latencies = self
+            .latencies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if latencies.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (self.percentile / 100.0 * (sorted.len() - 1) as f64).round() as usize;
+        Some(sorted[rank].max(self.min_delay))
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self
+            .latencies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    pub async fn disconnect(&self) {
+        for client in &self.backups {
+            if let Err(e) = client.disconnect().await {
+                tracing::warn!("Failed to disconnect hedging backup node: {}", e);
+            }
+        }
+    }
+
+    // Round-robin, so a single slow backup does not take every hedge.
+    fn backup(&self) -> Client {
+        let i = self.next_backup.fetch_add(1, Ordering::Relaxed) % self.backups.len();
+        self.backups[i].clone()
+    }
+}
+
+/// Runs the idempotent read `call` against `primary`, hedging to a backup node if the primary
+/// is slower than usual. A failed backup answer never beats a pending primary one. Transient
+/// failures of the whole attempt are retried like `retried` does.
+pub async fn hedged<T, F, Fut>(
+    hedging: Option<&Hedging>,
+    primary: &Client,
+    method: &'static str,
+    call: F,
+) -> RpcResult<T>
+where
+    F: Fn(Client) -> Fut,
+    Fut: Future<Output = RpcResult<T>>,
+{
+    with_retry(retry_policy(), method, || {
+        hedged_once(hedging, primary, method, &call)
+    })
+    .await
+}
+
+async fn hedged_once<T, F, Fut>(
+    hedging: Option<&Hedging>,
+    primary: &Client,
+    method: &'static str,
+    call: &F,
+) -> RpcResult<T>
+where
+    F: Fn(Client) -> Fut,
+    Fut: Future<Output = RpcResult<T>>,
+{
+    let Some(hedging) = hedging else {
+        return traced(method, call(primary.clone())).await;
+    };
+    let started = Instant::now();
+    let first = traced(method, call(primary.clone()));
+    tokio::pin!(first);
+
+    if let Some(delay) = hedging.delay() {
+        tokio::select! {
+            result = &mut first => {
+                hedging.record(started.elapsed());
+                return result;
+            }
+            _ = sleep(delay) => {}
+        }
+        tracing::debug!(
+            rpc.method = method,
+            delay_ms = delay.as_millis() as u64,
+            "Hedging kaspad RPC"
+        );
+        let second = traced(method, call(hedging.backup()));
+        tokio::pin!(second);
+        tokio::select! {
+            result = &mut first => {
+                hedging.record(started.elapsed());
+                return result;
+            }
+            result = &mut second => {
+                if result.is_ok() {
+                    // The primary's latency is at least this long; record it as such.
+                    hedging.record(started.elapsed());
+                    return result;
+                }
+            }
+        }
+    }
+
+    let result = first.await;
+    hedging.record(started.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    fn policy(
+        attempts: u32,
+        initial_backoff_ms: u64,
+        max_backoff_ms: u64,
+        jitter: f64,
+    ) -> RetryPolicy {
+        RetryPolicy::from(&RetryConfig {
+            attempts,
+            initial_backoff_ms,
+            max_backoff_ms,
+            jitter,
+        })
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = policy(10, 100, 1_000, 0.0);
+        let backoffs: Vec<u128> = (1..=6)
+            .map(|retry| policy.backoff(retry).as_millis())
+            .collect();
+        assert_eq!(backoffs, [100, 200, 400, 800, 1_000, 1_000]);
+        // Far past the cap the doubling saturates instead of overflowing.
+        assert_eq!(policy.backoff(100).as_millis(), 1_000);
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_backoff() {
+        let policy = policy(10, 1_000, 1_000, 0.25);
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(
+                backoff >= Duration::from_millis(750) && backoff <= Duration::from_millis(1_000)
+            );
+        }
+    }
+
+    #[test]
+    fn config_is_clamped() {
+        let policy = policy(0, 500, 100, 3.0);
+        assert_eq!(policy.attempts, 1);
+        assert_eq!(policy.max_backoff, Duration::from_millis(500));
+        assert_eq!(policy.jitter, 1.0);
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_until_attempts_run_out() {
+        let policy = policy(3, 1, 1, 0.0);
+        let calls = &AtomicU32::new(0);
+        let result: Result<(), String> = with_retry(&policy, "test", move || async move {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err("Connection reset by peer".to_string())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        let calls = &AtomicU32::new(0);
+        let result = with_retry(&policy, "test", move || async move {
+            match calls.fetch_add(1, Ordering::Relaxed) {
+                0 => Err("request timed out".to_string()),
+                _ => Ok(7),
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(7));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn answers_are_not_retried() {
+        let policy = policy(3, 1, 1, 0.0);
+        let calls = &AtomicU32::new(0);
+        let result: Result<(), String> = with_retry(&policy, "test", move || async move {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err("Block not found".to_string())
+        })
+        .await;
+        assert_eq!(result, Err("Block not found".to_string()));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn only_kaspad_answers_are_not_found() {
+        assert!(is_not_found(&RpcError::General(
+            "Block abc not found".to_string()
+        )));
+        assert!(is_not_found(&RpcError::RpcSubsystem(
+            "transaction not found in the mempool".to_string()
+        )));
+        assert!(!is_not_found(&RpcError::General("timed out".to_string())));
+        assert!(!is_not_found(&RpcError::NotImplemented));
+    }
+}
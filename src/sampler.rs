@@ -0,0 +1,364 @@
+//! Background network sampler.
+//!
+//! Polls kaspad on a fixed interval and keeps a ring buffer of samples, so stats
+//! endpoints can answer instantly instead of issuing several RPCs per request.
+//!
+//! Every block has exactly one coinbase transaction, so transactions excluding coinbase are
+//! the counted transactions minus the counted blocks.
+
+use crate::chain::ChainSource;
+use crate::network::Network;
+use crate::{retry_after_response, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration};
+use utoipa::ToSchema;
+
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+// One hour of history at the sample interval above.
+pub const MAX_SAMPLES: usize = 720;
+// Per-minute rollups kept for longer chart windows: seven days.
+pub const ROLLUP_INTERVAL_MS: u64 = 60_000;
+const MAX_ROLLUPS: usize = 7 * 24 * 60;
+// Rolling window used for averaged values (block interval, TPS).
+const STATS_WINDOW_MS: u64 = 60_000;
+// Number of blocks kaspad uses to estimate the network hashrate.
+const HASHRATE_WINDOW: u32 = 1000;
+// Windows reported by /api/stats/tps; the short ones come from the raw samples, the rest from
+// the per-minute rollups.
+const TPS_WINDOWS: [(&str, u64); 5] = [("1m", 60_000), ("5m", 300_000), ("1h", 3_600_000), ("24h", 86_400_000), ("7d", 604_800_000)];
+const TPS_MAX_SAMPLE_WINDOW_MS: u64 = 300_000;
+
+pub type SamplerHandle = Arc<RwLock<Sampler>>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkSample {
+    pub timestamp: u64, // Unix time in milliseconds
+    pub daa_score: u64,
+    pub blue_score: u64,
+    pub block_count: u64,
+    pub header_count: u64,
+    pub difficulty: f64,
+    pub hashrate: u64,
+    pub mempool_size: u64,
+    pub fee_rate: f64, // Normal-priority fee rate estimate in sompi/gram
+    // Blocks and transactions (coinbase included) merged since the previous sample.
+    pub new_blocks: u64,
+    pub new_transactions: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Sampler {
+    samples: VecDeque<NetworkSample>,
+    // One entry per minute: gauges hold the last value seen, counters are summed.
+    rollups: VecDeque<NetworkSample>,
+    last_sink: Option<Hash>,
+    // Cumulative counters since the first sample.
+    counting_since: Option<u64>,
+    total_blocks: u64,
+    total_transactions: u64,
+}
+
+impl Sampler {
+    pub fn latest(&self) -> Option<&NetworkSample> {
+        self.samples.back()
+    }
+
+    pub fn samples(&self) -> &VecDeque<NetworkSample> {
+        &self.samples
+    }
+
+    pub fn rollups(&self) -> &VecDeque<NetworkSample> {
+        &self.rollups
+    }
+
+    /// Samples taken within the last `window_ms` milliseconds of the latest sample, oldest first.
+    pub fn window(&self, window_ms: u64) -> Vec<&NetworkSample> {
+        let Some(latest) = self.latest() else {
+            return Vec::new();
+        };
+        let since = latest.timestamp.saturating_sub(window_ms);
+        self.samples.iter().filter(|s| s.timestamp >= since).collect()
+    }
+
+    /// Observed DAA score growth per second over the raw sample buffer; `network`'s target
+    /// rate until enough samples exist to measure it.
+    pub fn daa_per_second(&self, network: Network) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if last.timestamp > first.timestamp && last.daa_score > first.daa_score => {
+                (last.daa_score - first.daa_score) as f64 * 1000.0 / (last.timestamp - first.timestamp) as f64
+            }
+            _ => network.blocks_per_second() as f64,
+        }
+    }
+
+    fn push(&mut self, sample: NetworkSample) {
+        self.counting_since.get_or_insert(sample.timestamp);
+        self.total_blocks += sample.new_blocks;
+        self.total_transactions += sample.new_transactions;
+
+        let minute = sample.timestamp - sample.timestamp % ROLLUP_INTERVAL_MS;
+        match self.rollups.back_mut() {
+            Some(rollup) if rollup.timestamp == minute => {
+                *rollup = NetworkSample {
+                    timestamp: minute,
+                    new_blocks: rollup.new_blocks + sample.new_blocks,
+                    new_transactions: rollup.new_transactions + sample.new_transactions,
+                    ..sample.clone()
+                };
+            }
+            _ => {
+                self.rollups.push_back(NetworkSample { timestamp: minute, ..sample.clone() });
+                while self.rollups.len() > MAX_ROLLUPS {
+                    self.rollups.pop_front();
+                }
+            }
+        }
+
+        self.samples.push_back(sample);
+        while self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NetworkStats {
+    sampled_at: u64,
+    daa_score: u64,
+    virtual_blue_score: u64,
+    block_count: u64,
+    header_count: u64,
+    difficulty: f64,
+    hashrate: u64,
+    mempool_size: u64,
+    window_seconds: f64,
+    average_block_interval_ms: Option<f64>,
+    tps: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TpsWindow {
+    window: String,
+    window_seconds: f64, // Covered by samples; shorter than the window right after startup
+    blocks: u64,
+    transactions: u64, // Coinbase included
+    tps: f64,
+    tps_excluding_coinbase: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TpsStats {
+    sampled_at: u64,
+    counting_since: u64, // Unix ms of the first sample; totals start there
+    total_blocks: u64,
+    total_transactions: u64,
+    total_transactions_excluding_coinbase: u64,
+    windows: Vec<TpsWindow>,
+}
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn spawn(state: AppState) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            if let Err(e) = sample_once(&state).await {
+                tracing::warn!("Network sampler failed: {:?}", e);
+            }
+            sleep(SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+async fn sample_once(state: &AppState) -> anyhow::Result<()> {
+    let client_guard = state.client.read().await;
+    let Some(client) = client_guard.as_ref() else {
+        return Ok(());
+    };
+
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await?;
+    let blue_score = rpc::retried("get_sink_blue_score", || client.get_sink_blue_score()).await?;
+    let mempool_size = rpc::retried("get_info", || client.get_info()).await?.mempool_size;
+
+    let fee_rate = rpc::retried("get_fee_estimate", || client.get_fee_estimate())
+        .await
+        .map(|estimate| estimate.normal_buckets.first().unwrap_or(&estimate.priority_bucket).feerate)
+        .unwrap_or_else(|e| {
+            tracing::debug!("Failed to get fee estimate: {:?}", e);
+            0.0
+        });
+
+    // Hashrate estimation can fail right after startup (not enough blocks in the window);
+    // that should not prevent the rest of the sample from being recorded.
+    let hashrate = rpc::retried(
+        "estimate_network_hashes_per_second",
+        || client.estimate_network_hashes_per_second(HASHRATE_WINDOW, None),
+    )
+        .await
+        .unwrap_or_else(|e| {
+            tracing::debug!("Failed to estimate network hashrate: {:?}", e);
+            0
+        });
+
+    // Count blocks and transactions merged since the previous sink. The low hash itself is
+    // returned by get_blocks and was already counted in the previous sample.
+    let last_sink = state.sampler.read().await.last_sink;
+    let (new_blocks, new_transactions) = match last_sink {
+        Some(low) if low != dag_info.sink => match rpc::retried("get_blocks", || client.get_blocks(Some(low), true, false)).await {
+            Ok(response) => response
+                .blocks
+                .iter()
+                .filter(|b| b.header.hash != low)
+                .fold((0u64, 0u64), |(blocks, txs), b| {
+                    let tx_count = b
+                        .verbose_data
+                        .as_ref()
+                        .map(|v| v.transaction_ids.len())
+                        .unwrap_or_else(|| b.transactions.len());
+                    (blocks + 1, txs + tx_count as u64)
+                }),
+            Err(e) => {
+                tracing::warn!("Failed to fetch blocks since {} for sampling: {:?}", low, e);
+                (0, 0)
+            }
+        },
+        _ => (0, 0),
+    };
+
+    let sample = NetworkSample {
+        timestamp: now_ms(),
+        daa_score: dag_info.virtual_daa_score,
+        blue_score,
+        block_count: dag_info.block_count,
+        header_count: dag_info.header_count,
+        difficulty: dag_info.difficulty,
+        hashrate,
+        mempool_size,
+        fee_rate,
+        new_blocks,
+        new_transactions,
+    };
+
+    let mut sampler = state.sampler.write().await;
+    sampler.push(sample);
+    sampler.last_sink = Some(dag_info.sink);
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/network",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Latest network sample with averaged block interval and TPS", body = NetworkStats),
+        (status = 503, description = "No samples collected yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_network_stats(State(state): State<AppState>) -> Result<Json<NetworkStats>, Response> {
+    let sampler = state.sampler.read().await;
+    // Nothing sampled yet (startup or kaspad down): the next sample is at most one interval away.
+    let latest = sampler.latest().ok_or_else(|| {
+        retry_after_response(StatusCode::SERVICE_UNAVAILABLE, "No network samples collected yet", SAMPLE_INTERVAL)
+    })?;
+
+    // The first sample in the window only marks its start; its counters cover the interval before it.
+    let window = sampler.window(STATS_WINDOW_MS);
+    let elapsed_ms = window
+        .first()
+        .map(|first| latest.timestamp.saturating_sub(first.timestamp))
+        .unwrap_or(0);
+    let (blocks, transactions) = window
+        .iter()
+        .skip(1)
+        .fold((0u64, 0u64), |(b, t), s| (b + s.new_blocks, t + s.new_transactions));
+
+    let window_seconds = elapsed_ms as f64 / 1000.0;
+    let average_block_interval_ms = (blocks > 0).then(|| elapsed_ms as f64 / blocks as f64);
+    let tps = if elapsed_ms > 0 { transactions as f64 / window_seconds } else { 0.0 };
+
+    Ok(Json(NetworkStats {
+        sampled_at: latest.timestamp,
+        daa_score: latest.daa_score,
+        virtual_blue_score: latest.blue_score,
+        block_count: latest.block_count,
+        header_count: latest.header_count,
+        difficulty: latest.difficulty,
+        hashrate: latest.hashrate,
+        mempool_size: latest.mempool_size,
+        window_seconds,
+        average_block_interval_ms,
+        tps,
+    }))
+}
+
+/// Blocks, transactions and covered ms over the last `window_ms` before the latest sample.
+fn window_counts(sampler: &Sampler, latest: &NetworkSample, window_ms: u64) -> (u64, u64, u64) {
+    // The first sample in a raw window only marks its start; rollups are whole minutes whose
+    // counters accrued within them.
+    let (first, counted): (Option<u64>, Vec<&NetworkSample>) = if window_ms <= TPS_MAX_SAMPLE_WINDOW_MS {
+        let window = sampler.window(window_ms);
+        (window.first().map(|s| s.timestamp), window.into_iter().skip(1).collect())
+    } else {
+        let since = latest.timestamp.saturating_sub(window_ms);
+        let window: Vec<&NetworkSample> = sampler.rollups.iter().filter(|r| r.timestamp >= since).collect();
+        (window.first().map(|r| r.timestamp), window)
+    };
+    let elapsed_ms = first.map(|first| latest.timestamp.saturating_sub(first)).unwrap_or(0);
+    let (blocks, transactions) = counted.iter().fold((0u64, 0u64), |(b, t), s| (b + s.new_blocks, t + s.new_transactions));
+    (blocks, transactions, elapsed_ms)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/tps",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Transactions per second over rolling windows and totals since sampling started", body = TpsStats),
+        (status = 503, description = "No samples collected yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_tps(State(state): State<AppState>) -> Result<Json<TpsStats>, Response> {
+    let sampler = state.sampler.read().await;
+    let (Some(latest), Some(counting_since)) = (sampler.latest(), sampler.counting_since) else {
+        return Err(retry_after_response(StatusCode::SERVICE_UNAVAILABLE, "No network samples collected yet", SAMPLE_INTERVAL));
+    };
+
+    let windows = TPS_WINDOWS
+        .iter()
+        .map(|(name, window_ms)| {
+            let (blocks, transactions, elapsed_ms) = window_counts(&sampler, latest, *window_ms);
+            let per_second = |count: u64| if elapsed_ms > 0 { count as f64 * 1000.0 / elapsed_ms as f64 } else { 0.0 };
+            TpsWindow {
+                window: name.to_string(),
+                window_seconds: elapsed_ms as f64 / 1000.0,
+                blocks,
+                transactions,
+                tps: per_second(transactions),
+                tps_excluding_coinbase: per_second(transactions.saturating_sub(blocks)),
+            }
+        })
+        .collect();
+
+    Ok(Json(TpsStats {
+        sampled_at: latest.timestamp,
+        counting_since,
+        total_blocks: sampler.total_blocks,
+        total_transactions: sampler.total_transactions,
+        total_transactions_excluding_coinbase: sampler.total_transactions.saturating_sub(sampler.total_blocks),
+        windows,
+    }))
+}
@@ -0,0 +1,121 @@
+//! Graceful shutdown.
+//!
+//! On SIGINT or SIGTERM the server stops accepting connections and lets in-flight requests
+//! finish. Background tasks are spawned through `Shutdown::spawn`, which cancels them at their
+//! next await point; `Shutdown::stop` then waits for them before kaspad is disconnected and the
+//! index is checkpointed. Tasks that end in a panic are counted for `/admin/debug/stats`.
+//!
+//! Each network's `AppState` holds its own handle, sharing the cancellation and task tracking
+//! of the others but spawning its tasks with the network's `debug_stats` counters.
+
+use crate::debug_stats::{self, Counters};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+// Upper bound on waiting for background tasks; an RPC stuck in kaspad should not block exit.
+const TASK_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+    panicked: Arc<AtomicU64>,
+    counters: Arc<Counters>, // Where kaspad calls made by spawned tasks are counted
+}
+
+impl Shutdown {
+    /// This handle, spawning tasks that count their kaspad calls in `counters`.
+    pub fn with_counters(&self, counters: Arc<Counters>) -> Shutdown {
+        Shutdown {
+            counters,
+            ..self.clone()
+        }
+    }
+
+    /// Spawns a background task that is dropped when shutdown starts.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let token = self.token.clone();
+        let guard = PanicGuard(self.panicked.clone());
+        let task = debug_stats::counting(self.counters.clone(), task);
+        self.tasks.spawn(async move {
+            let _guard = guard;
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = task => {}
+            }
+        });
+    }
+
+    /// Background tasks still running.
+    pub fn running(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Background tasks that ended in a panic.
+    pub fn panicked(&self) -> u64 {
+        self.panicked.load(Ordering::Relaxed)
+    }
+
+    /// Cancels all background tasks and waits (bounded) for them to finish.
+    pub async fn stop(&self) {
+        self.token.cancel();
+        self.tasks.close();
+        if timeout(TASK_DRAIN_TIMEOUT, self.tasks.wait())
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                "{} background task(s) still running after {:?}",
+                self.tasks.len(),
+                TASK_DRAIN_TIMEOUT
+            );
+        }
+    }
+}
+
+// Dropped with its task; a panic unwinding through the task drops it while panicking.
+struct PanicGuard(Arc<AtomicU64>);
+
+impl Drop for PanicGuard {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Resolves on SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received Ctrl-C, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
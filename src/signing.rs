@@ -0,0 +1,140 @@
+//! Optional response signing.
+//!
+//! When a key is configured, every JSON response body is rewritten in canonical form
+//! (object keys sorted, no insignificant whitespace) and signed with Ed25519, so mirrors and
+//! bots can check the data was not altered by intermediate proxies. Verification: SHA-256 the
+//! body bytes, compare with `X-Content-Digest`, then check `X-Signature` over the digest with
+//! the public key from `/api/signing-key`.
+
+use crate::{error_response, format, AppState};
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Json, Response},
+};
+use ed25519_dalek::{Signer as _, SigningKey};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use utoipa::ToSchema;
+
+pub struct Signer {
+    key: SigningKey,
+}
+
+impl Signer {
+    /// Reads a 32-byte Ed25519 seed stored as hex (e.g. `openssl rand -hex 32 > signing.key`).
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read signing key {}: {}", path.display(), e))?;
+        let seed: [u8; 32] = hex::decode(contents.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Signing key {} must be 64 hex characters", path.display())
+            })?;
+        Ok(Signer {
+            key: SigningKey::from_bytes(&seed),
+        })
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SigningKeyInfo {
+    algorithm: &'static str,
+    digest: &'static str,
+    public_key: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/signing-key",
+    tag = "network",
+    responses(
+        (status = 200, description = "Public key used to sign responses", body = SigningKeyInfo),
+        (status = 404, description = "Response signing is disabled", body = ErrorResponse)
+    )
+)]
+pub async fn get_signing_key(
+    State(state): State<AppState>,
+) -> Result<Json<SigningKeyInfo>, Response> {
+    let signer = state
+        .signer
+        .as_ref()
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Response signing is not enabled"))?;
+    Ok(Json(SigningKeyInfo {
+        algorithm: "ed25519",
+        digest: "sha-256",
+        public_key: signer.public_key_hex(),
+    }))
+}
+
+/// Canonicalizes and signs JSON responses. Must run outside every layer that rewrites bodies.
+pub async fn sign_responses(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let Some(signer) = state.signer.as_ref() else {
+        return response;
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(format::is_json);
+    // Streaming bodies have no upper bound and are left unsigned.
+    let max = state.config.limits.max_response_bytes;
+    let bounded = body
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len as usize <= max);
+    if !is_json || !bounded {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match axum::body::to_bytes(body, max).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for signing: {:?}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to buffer the response",
+            );
+        }
+    };
+    // serde_json keeps object keys in a sorted map, so a parse/serialize round trip is canonical.
+    let canonical = match serde_json::from_slice::<serde_json::Value>(&bytes)
+        .and_then(|v| serde_json::to_vec(&v))
+    {
+        Ok(canonical) => canonical,
+        Err(e) => {
+            tracing::warn!("Not signing malformed JSON response: {:?}", e);
+            return Response::from_parts(parts, Body::from(bytes));
+        }
+    };
+
+    let digest = Sha256::digest(&canonical);
+    let signature = signer.key.sign(&digest);
+    let headers = [
+        ("x-content-digest", hex::encode(digest)),
+        ("x-signature", hex::encode(signature.to_bytes())),
+        ("x-signature-key", signer.public_key_hex()),
+    ];
+    for (name, value) in headers {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            parts.headers.insert(name, value);
+        }
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(canonical))
+}
@@ -0,0 +1,442 @@
+//! Bulk balance snapshots of uploaded address lists.
+//!
+//! `POST /snapshots` takes up to `MAX_ADDRESSES` addresses (a JSON list, or text with one
+//! address per line where only the first CSV column is read) and starts a background job;
+//! the snapshot is then polled at `GET /snapshots/:id` and downloaded as CSV or JSON.
+//!
+//! All balances are taken at one DAA score `D`, the virtual DAA score when the job starts.
+//! The UTXO set is read address batch by address batch while the DAG moves on, so UTXOs
+//! created after `D` are left out, and outputs that existed at `D` but were spent while the
+//! job ran are added back from the index. That needs the index to have caught up with the
+//! end of the job; without it, the snapshot is marked `consistent: false`.
+
+use crate::chain::ChainSource;
+use crate::errors::ApiError;
+use crate::network::Network;
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::Address;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
+
+const MAX_ADDRESSES: usize = 10_000;
+// Addresses per get_utxos_by_addresses call.
+const BATCH_SIZE: usize = 500;
+// Finished snapshots kept for download; the oldest is dropped first.
+const MAX_SNAPSHOTS: usize = 16;
+// How long a job waits for the index to reach the DAA score the job ended at.
+const INDEX_WAIT: Duration = Duration::from_secs(60);
+
+/// Snapshot jobs, oldest first.
+pub type Snapshots = Arc<RwLock<VecDeque<Snapshot>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotState {
+    Running,
+    Done,
+    Failed,
+}
+
+pub struct Snapshot {
+    id: String,
+    state: SnapshotState,
+    created_at: u64, // Unix ms
+    addresses: Vec<Address>,
+    daa_score: Option<u64>,
+    consistent: bool,
+    error: Option<String>,
+    balances: Vec<u64>, // Parallel to `addresses` once done
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SnapshotRequest {
+    addresses: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnapshotStatus {
+    id: String,
+    state: SnapshotState,
+    created_at: u64,
+    address_count: usize,
+    daa_score: Option<u64>,
+    // False when spends during the job could not be checked against the index.
+    consistent: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnapshotBalance {
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnapshotDownload {
+    id: String,
+    daa_score: u64,
+    consistent: bool,
+    #[serde(serialize_with = "crate::units::sompi")]
+    total: u64,
+    balances: Vec<SnapshotBalance>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownloadQuery {
+    /// `csv` (default) or `json`
+    format: Option<String>,
+}
+
+impl Snapshot {
+    fn status(&self) -> SnapshotStatus {
+        SnapshotStatus {
+            id: self.id.clone(),
+            state: self.state,
+            created_at: self.created_at,
+            address_count: self.addresses.len(),
+            daa_score: self.daa_score,
+            consistent: self.consistent,
+            error: self.error.clone(),
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/snapshots",
+    tag = "addresses",
+    request_body(content = SnapshotRequest, description = "JSON address list, or text/csv with one address per line"),
+    responses(
+        (status = 202, description = "Snapshot job started", body = SnapshotStatus),
+        (status = 400, description = "Invalid address, empty list or too many addresses", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad or UTXO index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn post_snapshot(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<SnapshotStatus>), Response> {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let raw: Vec<String> = if is_json {
+        serde_json::from_slice::<SnapshotRequest>(&body)
+            .map_err(|e| error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {}", e)))?
+            .addresses
+    } else {
+        let text = std::str::from_utf8(&body)
+            .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Body is not UTF-8"))?;
+        text.lines()
+            .filter_map(|line| line.split(',').next())
+            .map(|field| field.trim().trim_matches('"').to_string())
+            // Skips blank lines and a header row.
+            .filter(|field| !field.is_empty() && field != "address")
+            .collect()
+    };
+    let addresses = parse_addresses(state.network, &raw)?;
+
+    {
+        let client_guard = state.client.read().await;
+        let client = client_guard.as_ref().ok_or_else(not_connected)?;
+        let info = rpc::retried("get_info", || client.get_info())
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get kaspad info before snapshot: {:?}", e);
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to query kaspad info",
+                )
+            })?;
+        if !info.is_utxo_indexed {
+            return Err(ApiError::UtxoIndexRequired(
+                "Snapshots require kaspad to run with --utxoindex".to_string(),
+            )
+            .into_response());
+        }
+    }
+
+    let mut id = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut id);
+    let snapshot = Snapshot {
+        id: hex::encode(id),
+        state: SnapshotState::Running,
+        created_at: crate::sampler::now_ms(),
+        addresses,
+        daa_score: None,
+        consistent: false,
+        error: None,
+        balances: Vec::new(),
+    };
+    let status = snapshot.status();
+    {
+        let mut snapshots = state.snapshots.write().await;
+        if snapshots.len() >= MAX_SNAPSHOTS {
+            match snapshots
+                .iter()
+                .position(|s| s.state != SnapshotState::Running)
+            {
+                Some(oldest) => {
+                    snapshots.remove(oldest);
+                }
+                None => {
+                    return Err(error_response(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "Too many snapshots are running; try again later",
+                    ))
+                }
+            }
+        }
+        snapshots.push_back(snapshot);
+    }
+
+    let job_state = state.clone();
+    let id = status.id.clone();
+    state.shutdown.clone().spawn(async move {
+        let result = run(&job_state, &id).await;
+        let mut snapshots = job_state.snapshots.write().await;
+        if let Some(snapshot) = snapshots.iter_mut().find(|s| s.id == id) {
+            match result {
+                Ok((daa_score, consistent, balances)) => {
+                    tracing::info!(
+                        "Snapshot {} of {} addresses done at DAA score {}",
+                        id,
+                        balances.len(),
+                        daa_score
+                    );
+                    snapshot.state = SnapshotState::Done;
+                    snapshot.daa_score = Some(daa_score);
+                    snapshot.consistent = consistent;
+                    snapshot.balances = balances;
+                }
+                Err(e) => {
+                    tracing::warn!("Snapshot {} failed: {:?}", id, e);
+                    snapshot.state = SnapshotState::Failed;
+                    snapshot.error = Some(e.to_string());
+                }
+            }
+        }
+    });
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/snapshots/{id}",
+    tag = "addresses",
+    params(("id" = String, Path, description = "Snapshot id")),
+    responses(
+        (status = 200, description = "Snapshot job state", body = SnapshotStatus),
+        (status = 404, description = "Unknown or expired snapshot", body = ErrorResponse)
+    )
+)]
+pub async fn get_snapshot(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SnapshotStatus>, Response> {
+    state
+        .snapshots
+        .read()
+        .await
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| Json(s.status()))
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Snapshot not found"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/snapshots/{id}/download",
+    tag = "addresses",
+    params(("id" = String, Path, description = "Snapshot id"), DownloadQuery),
+    responses(
+        (status = 200, description = "Balances as CSV (address,balance) or JSON", body = SnapshotDownload),
+        (status = 400, description = "Unknown format", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired snapshot", body = ErrorResponse),
+        (status = 409, description = "Snapshot still running or failed", body = ErrorResponse)
+    )
+)]
+pub async fn download_snapshot(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<Response, Response> {
+    let format = query.format.as_deref().unwrap_or("csv");
+    if format != "csv" && format != "json" {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "format must be csv or json",
+        ));
+    }
+    let snapshots = state.snapshots.read().await;
+    let snapshot = snapshots
+        .iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Snapshot not found"))?;
+    let Some(daa_score) = snapshot
+        .daa_score
+        .filter(|_| snapshot.state == SnapshotState::Done)
+    else {
+        return Err(error_response(StatusCode::CONFLICT, "Snapshot is not done"));
+    };
+
+    if format == "json" {
+        let balances: Vec<SnapshotBalance> = snapshot
+            .addresses
+            .iter()
+            .zip(&snapshot.balances)
+            .map(|(address, &balance)| SnapshotBalance {
+                address: address.to_string(),
+                balance,
+            })
+            .collect();
+        return Ok(Json(SnapshotDownload {
+            id: snapshot.id.clone(),
+            daa_score,
+            consistent: snapshot.consistent,
+            total: snapshot.balances.iter().sum(),
+            balances,
+        })
+        .into_response());
+    }
+
+    let mut csv = String::from("address,balance\n");
+    for (address, balance) in snapshot.addresses.iter().zip(&snapshot.balances) {
+        csv.push_str(&format!("{},{}\n", address, balance));
+    }
+    let disposition = format!(
+        "attachment; filename=\"snapshot-{}-{}.csv\"",
+        daa_score, snapshot.id
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn parse_addresses(network: Network, raw: &[String]) -> Result<Vec<Address>, Response> {
+    if raw.is_empty() {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "No addresses given",
+        ));
+    }
+    if raw.len() > MAX_ADDRESSES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Snapshots are limited to {} addresses", MAX_ADDRESSES),
+        ));
+    }
+    let mut seen = HashSet::new();
+    let mut addresses = Vec::with_capacity(raw.len());
+    for (i, address) in raw.iter().enumerate() {
+        let parsed = network.parse_address(address).map_err(|e| {
+            error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Address at position {}: {}", i, e),
+            )
+        })?;
+        if seen.insert(parsed.to_string()) {
+            addresses.push(parsed);
+        }
+    }
+    Ok(addresses)
+}
+
+/// Takes the snapshot; returns its DAA score, whether it is consistent, and the balances.
+async fn run(state: &AppState, id: &str) -> anyhow::Result<(u64, bool, Vec<u64>)> {
+    let addresses = state
+        .snapshots
+        .read()
+        .await
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| s.addresses.clone())
+        .ok_or_else(|| anyhow::anyhow!("Snapshot was dropped"))?;
+    let position: HashMap<String, usize> = addresses
+        .iter()
+        .enumerate()
+        .map(|(i, address)| (address.to_string(), i))
+        .collect();
+    let mut balances = vec![0u64; addresses.len()];
+
+    let (daa_score, end_daa_score) = {
+        let client = rpc::checkout(state)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Not connected to kaspad"))?;
+        let daa_score = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+            .await?
+            .virtual_daa_score;
+        for batch in addresses.chunks(BATCH_SIZE) {
+            let utxos = rpc::retried("get_utxos_by_addresses", || {
+                client.get_utxos_by_addresses(batch.to_vec())
+            })
+            .await?;
+            for utxo in utxos {
+                // Created after the snapshot point.
+                if utxo.utxo_entry.block_daa_score > daa_score {
+                    continue;
+                }
+                if let Some(&i) = utxo
+                    .address
+                    .as_ref()
+                    .and_then(|address| position.get(&address.to_string()))
+                {
+                    balances[i] += utxo.utxo_entry.amount;
+                }
+            }
+        }
+        let end_daa_score = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+            .await?
+            .virtual_daa_score;
+        (daa_score, end_daa_score)
+    };
+
+    let Some(index) = state
+        .index
+        .clone()
+        .filter(|index| index.network_mismatch().is_none())
+    else {
+        return Ok((daa_score, false, balances));
+    };
+    let deadline = Instant::now() + INDEX_WAIT;
+    while index.indexed_daa_score().await?.unwrap_or(0) < end_daa_score {
+        if Instant::now() >= deadline {
+            tracing::warn!(
+                "Snapshot {}: index did not reach DAA score {} in time",
+                id,
+                end_daa_score
+            );
+            return Ok((daa_score, false, balances));
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    // Outputs that existed at the snapshot point but were spent while the job ran.
+    for (address, amount, created) in index.spent_between(daa_score, end_daa_score).await? {
+        if created <= daa_score {
+            if let Some(&i) = position.get(&address) {
+                balances[i] += amount;
+            }
+        }
+    }
+    Ok((daa_score, true, balances))
+}
@@ -0,0 +1,225 @@
+//! Server-Sent Events stream of live chain activity.
+//!
+//! `GET /events` streams typed events: `block` (block-added notifications), `chain-changed`
+//! (selected chain updates), `mempool` (transactions added to and removed from the mempool),
+//! `connection` (kaspad subscription up or down) and `whale` (a transaction above the
+//! `[whales]` threshold, see `whales.rs`). `?filter=block,mempool` limits the stream to the
+//! listed types. Events fan out through a broadcast channel; a client that falls too far
+//! behind skips the missed events and gets a `lagged` comment instead.
+//!
+//! kaspad has no mempool notifications, so while anyone is subscribed the mempool is re-read
+//! once a second through the homepage feed (see `home.rs`), which publishes the differences.
+
+use crate::events::BlockEvent;
+use crate::whales::WhaleTransaction;
+use crate::{error_response, home, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+};
+use kaspa_rpc_core::{BlockAddedNotification, VirtualChainChangedNotification};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+use tokio::time::{sleep, Duration};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use utoipa::IntoParams;
+
+// Events buffered per subscriber before it is considered lagging.
+const CHANNEL_CAPACITY: usize = 1_024;
+const MAX_SUBSCRIBERS: usize = 1_000;
+const MEMPOOL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const KINDS: [&str; 5] = ["block", "chain-changed", "mempool", "connection", "whale"];
+
+pub type EventBus = broadcast::Sender<StreamEvent>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum StreamEvent {
+    Block(BlockEvent),
+    ChainChanged(ChainChangedEvent),
+    Mempool(MempoolEvent),
+    Connection(ConnectionEvent),
+    Whale(WhaleTransaction),
+}
+
+impl StreamEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            StreamEvent::Block(_) => "block",
+            StreamEvent::ChainChanged(_) => "chain-changed",
+            StreamEvent::Mempool(_) => "mempool",
+            StreamEvent::Connection(_) => "connection",
+            StreamEvent::Whale(_) => "whale",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainChangedEvent {
+    pub added_chain_block_hashes: Vec<String>,
+    pub removed_chain_block_hashes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MempoolEvent {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    pub connected: bool,
+    pub server_url: String,
+}
+
+pub fn bus() -> EventBus {
+    broadcast::channel(CHANNEL_CAPACITY).0
+}
+
+/// Sends the event built by `make` to the current subscribers; skipped when there are none.
+pub fn publish(state: &AppState, make: impl FnOnce() -> StreamEvent) {
+    if state.stream.receiver_count() > 0 {
+        // Fails only when the last subscriber left in the meantime.
+        let _ = state.stream.send(make());
+    }
+}
+
+pub fn on_block_added(state: &AppState, notification: &BlockAddedNotification) {
+    publish(state, || {
+        let block = &notification.block;
+        StreamEvent::Block(BlockEvent {
+            hash: block.header.hash.to_string(),
+            daa_score: block.header.daa_score,
+            blue_score: block.header.blue_score,
+            timestamp: block.header.timestamp,
+            transaction_ids: block
+                .transactions
+                .iter()
+                .filter_map(|tx| tx.verbose_data.as_ref())
+                .map(|v| v.transaction_id.to_string())
+                .collect(),
+            replayed: false,
+        })
+    });
+}
+
+pub fn on_chain_changed(state: &AppState, notification: &VirtualChainChangedNotification) {
+    publish(state, || {
+        StreamEvent::ChainChanged(ChainChangedEvent {
+            added_chain_block_hashes: notification
+                .added_chain_block_hashes
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            removed_chain_block_hashes: notification
+                .removed_chain_block_hashes
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        })
+    });
+}
+
+pub async fn on_connection_changed(state: &AppState, connected: bool) {
+    let server_url = state.network_info.read().await.server_url.clone();
+    publish(state, || {
+        StreamEvent::Connection(ConnectionEvent {
+            connected,
+            server_url,
+        })
+    });
+}
+
+/// Polls the mempool while there are subscribers.
+pub fn spawn(state: AppState) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            if state.stream.receiver_count() > 0 {
+                home::refresh_mempool(&state).await;
+            }
+            sleep(MEMPOOL_POLL_INTERVAL).await;
+        }
+    });
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// Comma-separated event types: block, chain-changed, mempool, connection, whale (default all)
+    filter: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    tag = "network",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "text/event-stream of block, chain-changed, mempool, connection and whale events", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Unknown event type in filter", body = ErrorResponse),
+        (status = 503, description = "Too many subscribers", body = ErrorResponse)
+    )
+)]
+pub async fn get_events(
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
+    let kinds: HashSet<&'static str> = match query.filter.as_deref() {
+        Some(filter) => {
+            let mut kinds = HashSet::new();
+            for kind in filter.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+                let known = KINDS.iter().copied().find(|k| *k == kind).ok_or_else(|| {
+                    error_response(
+                        StatusCode::BAD_REQUEST,
+                        &format!(
+                            "Unknown event type {} (expected {})",
+                            kind,
+                            KINDS.join(", ")
+                        ),
+                    )
+                })?;
+                kinds.insert(known);
+            }
+            kinds
+        }
+        None => KINDS.into_iter().collect(),
+    };
+    if state.stream.receiver_count() >= MAX_SUBSCRIBERS {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many event stream subscribers",
+        ));
+    }
+
+    // The stream opens with the current connection state.
+    let (connected, server_url) = {
+        let info = state.network_info.read().await;
+        (info.is_connected, info.server_url.clone())
+    };
+    let initial = StreamEvent::Connection(ConnectionEvent {
+        connected,
+        server_url,
+    });
+    let events =
+        tokio_stream::once(Ok(initial)).chain(BroadcastStream::new(state.stream.subscribe()));
+    let stream = events.filter_map(move |event| match event {
+        Ok(event) if kinds.contains(event.kind()) => Some(Ok(Event::default()
+            .event(event.kind())
+            .json_data(&event)
+            .unwrap_or_else(|e| Event::default().comment(format!("unserializable event: {}", e))))),
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(missed)) => {
+            Some(Ok(Event::default().comment(format!("lagged {}", missed))))
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
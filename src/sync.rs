@@ -0,0 +1,144 @@
+//! Node sync status.
+//!
+//! `/api/sync` reports how far behind the backing kaspad is, and `flag_syncing` marks every
+//! JSON object response with `"syncing": true` while the node is not synced, so clients
+//! know the data they are looking at may be stale.
+
+use crate::chain::ChainSource;
+use crate::sampler::now_ms;
+use crate::{error_response, network, not_connected, rpc, AppState};
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// Responses larger than this are passed through without the flag rather than buffered.
+const MAX_FLAGGED_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncStatus {
+    is_synced: bool,
+    header_count: u64,
+    block_count: u64,
+    sink: String,
+    sink_timestamp: u64,
+    sink_lag_seconds: u64,
+    progress_percent: f64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sync",
+    tag = "network",
+    responses(
+        (status = 200, description = "Sync status and estimated progress", body = SyncStatus),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_sync_status(State(state): State<AppState>) -> Result<Json<SyncStatus>, Response> {
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let internal_error =
+        |_| error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to query kaspad");
+
+    let is_synced = rpc::retried("get_info", || client.get_info())
+        .await
+        .map_err(internal_error)?
+        .is_synced;
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+        .await
+        .map_err(internal_error)?;
+    let sink = rpc::retried("get_block", || client.get_block(dag_info.sink, false))
+        .await
+        .map_err(internal_error)?;
+
+    let now = now_ms();
+    let sink_timestamp = sink.header.timestamp;
+    let sink_lag_seconds = now.saturating_sub(sink_timestamp) / 1000;
+
+    // Progress is measured in time: how much of the span from the pruning point to now the
+    // sink already covers. Falls back to blocks-vs-headers when the pruning point is unavailable.
+    let progress_percent = if is_synced {
+        100.0
+    } else {
+        match rpc::retried("get_block", || client.get_block(dag_info.pruning_point_hash, false)).await {
+            Ok(pruning_point) if now > pruning_point.header.timestamp => {
+                let covered = sink_timestamp.saturating_sub(pruning_point.header.timestamp) as f64;
+                let total = (now - pruning_point.header.timestamp) as f64;
+                (covered / total * 100.0).clamp(0.0, 100.0)
+            }
+            _ if dag_info.header_count > 0 => {
+                (dag_info.block_count as f64 / dag_info.header_count as f64 * 100.0)
+                    .clamp(0.0, 100.0)
+            }
+            _ => 0.0,
+        }
+    };
+
+    Ok(Json(SyncStatus {
+        is_synced,
+        header_count: dag_info.header_count,
+        block_count: dag_info.block_count,
+        sink: dag_info.sink.to_string(),
+        sink_timestamp,
+        sink_lag_seconds,
+        progress_percent,
+    }))
+}
+
+/// Adds `"syncing": true` to JSON object responses (and an `X-Kaspad-Syncing` header to all
+/// responses) while the background refresher reports kaspad as not synced.
+pub async fn flag_syncing(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    // Routes of an additional network report on that network's node.
+    let state = match response.extensions().get::<network::ServedBy>() {
+        Some(network::ServedBy(served_by)) => served_by.clone(),
+        None => state,
+    };
+    if state.network_info.read().await.is_synced != Some(false) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .insert("x-kaspad-syncing", HeaderValue::from_static("true"));
+
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    // Streaming or oversized bodies pass through unflagged rather than being buffered.
+    let small_enough = body
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len as usize <= MAX_FLAGGED_BODY_BYTES);
+    if !is_json || !small_enough {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match axum::body::to_bytes(body, MAX_FLAGGED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer response to add syncing flag: {:?}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to buffer the response");
+        }
+    };
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert("syncing".to_string(), serde_json::Value::Bool(true));
+            serde_json::to_vec(&object)
+                .map(Body::from)
+                .unwrap_or_else(|_| Body::from(bytes))
+        }
+        _ => Body::from(bytes),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, body)
+}
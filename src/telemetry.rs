@@ -0,0 +1,187 @@
+//! Logging and request tracing.
+//!
+//! Log output goes through `tracing` (events from crates still using `log` are bridged in),
+//! as human-readable text or one JSON object per line. Each HTTP request gets an
+//! `x-request-id` (taken from the client or generated) that is recorded on its span, echoed
+//! back in the response, and inherited by the kaspad RPC spans issued while serving it.
+//! Optionally, spans are exported over OTLP (gRPC) to a collector such as Jaeger or Tempo.
+//! The level filter can be replaced at runtime through the returned `LogFilter` handle.
+//! The latest warnings and errors are also kept in memory for `/admin/diagnostics`.
+
+use crate::config::TelemetryConfig;
+use crate::sampler::now_ms;
+use axum::{extract::Request, http::HeaderName};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Sampler, TracerProvider};
+use opentelemetry_sdk::Resource;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+use utoipa::ToSchema;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_RECENT_ERRORS: usize = 50;
+
+/// Handle to the active `EnvFilter`, used by the admin API to change log levels.
+pub type LogFilter = reload::Handle<EnvFilter, Registry>;
+
+/// The latest warning and error events, oldest first.
+pub type RecentErrors = Arc<Mutex<VecDeque<LogRecord>>>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LogRecord {
+    timestamp: u64, // Unix ms
+    level: String,
+    target: String,
+    message: String, // Includes the event's other fields as `key=value`
+}
+
+/// Copies warnings and errors that pass the level filter into `RecentErrors`.
+struct RecentErrorsLayer(RecentErrors);
+
+impl<S: Subscriber> Layer<S> for RecentErrorsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // More verbose levels compare greater.
+        if *metadata.level() > Level::WARN {
+            return;
+        }
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        let mut recent = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        recent.push_back(LogRecord {
+            timestamp: now_ms(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: message.0,
+        });
+        if recent.len() > MAX_RECENT_ERRORS {
+            recent.pop_front();
+        }
+    }
+}
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Installs the global subscriber. The level filter comes from `RUST_LOG` (default `info`).
+/// Logs go to stdout, or to stderr for commands whose output is on stdout.
+/// When an OTLP endpoint is configured, spans are also exported there; the returned provider
+/// must be shut down on exit to flush pending spans.
+pub fn init(
+    format: LogFormat,
+    config: &TelemetryConfig,
+    to_stderr: bool,
+) -> anyhow::Result<(Option<TracerProvider>, LogFilter, RecentErrors)> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, log_filter) = reload::Layer::new(filter);
+    let writer = move || {
+        if to_stderr {
+            BoxMakeWriter::new(std::io::stderr)
+        } else {
+            BoxMakeWriter::new(std::io::stdout)
+        }
+    };
+    let fmt_layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_writer(writer())
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .with_writer(writer())
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .boxed(),
+    };
+
+    let provider = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()?;
+            Some(
+                TracerProvider::builder()
+                    .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+                    .with_sampler(Sampler::TraceIdRatioBased(config.sample_ratio))
+                    .with_resource(Resource::new([KeyValue::new(
+                        "service.name",
+                        config.service_name.clone(),
+                    )]))
+                    .build(),
+            )
+        }
+        None => None,
+    };
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("kaspa-testnet12-explorer"))
+    });
+
+    let recent_errors = RecentErrors::default();
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(otel_layer)
+        .with(RecentErrorsLayer(recent_errors.clone()))
+        .init();
+    if let Some(endpoint) = &config.otlp_endpoint {
+        tracing::info!("Exporting traces to OTLP endpoint {}", endpoint);
+    }
+    Ok((provider, log_filter, recent_errors))
+}
+
+/// Root span for an HTTP request, used by the `TraceLayer`.
+pub fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+    tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        client = tracing::field::Empty, // API token name, recorded by auth.rs
+    )
+}
+
+/// Trace id of the current span, if it is exported (OTLP configured and the trace sampled).
+pub fn sampled_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span_context = context.span().span_context().clone();
+    (span_context.is_valid() && span_context.is_sampled())
+        .then(|| span_context.trace_id().to_string())
+}
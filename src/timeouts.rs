@@ -0,0 +1,95 @@
+//! Per-route request timeouts.
+//!
+//! Every API route gets a time budget for producing its response: `heavy_ms` for the routes
+//! that share the heavy rate limit class, `default_ms` for the rest, and an entry in `routes`
+//! for any route that needs its own (keyed by its path pattern under `/api`, like
+//! `/address/:address`, on every network). A handler still waiting on kaspad or the index when its budget runs
+//! out is dropped and the client gets `504 Gateway Timeout`. Streamed bodies (event streams,
+//! downloads) are only timed until their headers are sent. A budget of 0 disables the timeout.
+
+use crate::errors::ApiError;
+use crate::{AppState, API_VERSION};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::time::{timeout, Duration};
+
+/// Route pattern as configured: the matched path without its `/api/v1` or `/api` prefix, and
+/// without the `/<prefix>` an additional network's API is nested under.
+pub fn route_key(request: &Request) -> Option<&str> {
+    api_route(request.extensions().get::<MatchedPath>()?.as_str())
+}
+
+fn api_route(matched: &str) -> Option<&str> {
+    // Network prefixes are single path segments and never `api`.
+    let route = under_api(matched).or_else(|| {
+        let segment_end = matched.get(1..)?.find('/')? + 1;
+        under_api(&matched[segment_end..])
+    })?;
+    Some(
+        route
+            .strip_prefix(format!("/{}", API_VERSION).as_str())
+            .unwrap_or(route),
+    )
+}
+
+// `path` without its leading `/api` segment.
+fn under_api(path: &str) -> Option<&str> {
+    path.strip_prefix("/api")
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+async fn enforce(state: &AppState, class_ms: u64, request: Request, next: Next) -> Response {
+    let config = &state.config.timeouts;
+    let budget_ms = route_key(&request)
+        .and_then(|route| config.routes.get(route).copied())
+        .unwrap_or(class_ms);
+    if budget_ms == 0 {
+        return next.run(request).await;
+    }
+    let path = request.uri().path().to_string();
+    match timeout(Duration::from_millis(budget_ms), next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request for {} timed out after {}ms", path, budget_ms);
+            ApiError::UpstreamTimeout(format!(
+                "The request did not complete within {}ms",
+                budget_ms
+            ))
+            .into_response()
+        }
+    }
+}
+
+pub async fn limit_default(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let class_ms = state.config.timeouts.default_ms;
+    enforce(&state, class_ms, request, next).await
+}
+
+pub async fn limit_heavy(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let class_ms = state.config.timeouts.heavy_ms;
+    enforce(&state, class_ms, request, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_drop_the_api_and_network_prefixes() {
+        assert_eq!(api_route("/api/v1/watch/:id"), Some("/watch/:id"));
+        assert_eq!(api_route("/api/watch/:id"), Some("/watch/:id"));
+        assert_eq!(api_route("/tn10/api/v1/watch/:id"), Some("/watch/:id"));
+        assert_eq!(api_route("/tn10/api/watch"), Some("/watch"));
+        assert_eq!(api_route("/tn10"), None);
+        assert_eq!(api_route("/admin/caches"), None);
+        assert_eq!(api_route("/tn10/admin/debug/stats"), None);
+        assert_eq!(api_route("/apis/api/v1/blocks"), Some("/blocks"));
+    }
+}
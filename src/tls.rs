@@ -0,0 +1,70 @@
+//! HTTPS without a reverse proxy.
+//!
+//! With `--tls-cert` and `--tls-key` (PEM files, the certificate file holding the full chain)
+//! every TCP listener serves HTTPS through rustls; Unix sockets stay plain, since only local
+//! processes reach them. `--http-redirect` adds a plain HTTP listener that answers every
+//! request with a permanent redirect to the same path on HTTPS, on the port of the first TCP
+//! listener. The certificate is read once at startup, so renewing it takes a restart.
+
+use crate::error_response;
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use std::net::SocketAddr;
+use std::path::Path;
+
+pub struct Tls {
+    pub config: RustlsConfig,
+    pub redirect: Option<SocketAddr>, // Plain HTTP listener redirecting to HTTPS
+}
+
+pub async fn load(cert: &Path, key: &Path) -> anyhow::Result<RustlsConfig> {
+    // rustls needs a process-wide crypto provider; ring is the one already linked for reqwest.
+    // An error only means one is installed.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert, key).await.map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load TLS certificate {} and key {}: {}",
+            cert.display(),
+            key.display(),
+            e
+        )
+    })
+}
+
+/// Routes for the `--http-redirect` listener: everything moves to HTTPS on `https_port`.
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(move |request: Request| async move { redirect(&request, https_port) })
+}
+
+fn redirect(request: &Request, https_port: u16) -> Response {
+    let Some(host) = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "HTTPS required; the request has no Host",
+        );
+    };
+    // Drop the plain port, keeping IPv6 brackets: "[::1]:80" -> "[::1]".
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let authority = if https_port == 443 {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, https_port)
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
@@ -0,0 +1,787 @@
+//! KRC-20 tokens from Kasplex inscriptions.
+//!
+//! With `[index] tokens` set, the indexer looks for Kasplex envelopes in transaction inputs:
+//! a P2SH redeem script `<pubkey> OpCheckSig OpFalse OpIf "kasplex" ... OpFalse <json>
+//! OpEndIf`, whose JSON is a KRC-20 operation (`{"p":"krc-20","op":"deploy",...}`). The
+//! operation's sender is the address of the redeem script's public key. Inscriptions are
+//! stored as their transactions are indexed and applied once a chain block accepts the
+//! transaction, in the order of the virtual-chain-changed notifications (`acceptance.rs`),
+//! once per transaction id and epoch:
+//!
+//! - `deploy` creates a token with a 4 to 6 letter `tick`, a `max` supply, a per-mint `lim`
+//!   and `dec` decimals (default 8), unless the tick is already deployed;
+//! - `mint` credits `lim` (or what is left of `max`) to `to`, or to the sender;
+//! - `transfer` moves `amt` from the sender to `to`, if the sender holds it.
+//!
+//! Amounts are integers in the token's smallest unit and are returned as strings, since they
+//! can exceed 64 bits. Rejected operations are kept with the reason. When a reorg removes a
+//! chain block, the operations of the transactions it accepted are undone, newest first,
+//! before the new chain blocks apply theirs. This is meant for testnet experiments:
+//! transactions accepted while the explorer was not listening are not applied, and the
+//! Kasplex fee rules are not enforced, so balances can differ from the Kasplex indexer's.
+
+use crate::{decode_script, error_response, network, require_index, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_rpc_core::RpcTransaction;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const MARKER: &[u8] = b"kasplex";
+const OP_FALSE: u8 = 0x00;
+const OP_DATA_32: u8 = 0x20;
+const OP_DATA_33: u8 = 0x21;
+const OP_ENDIF: u8 = 0x68;
+const OP_CHECKSIG_ECDSA: u8 = 0xab;
+const OP_CHECKSIG: u8 = 0xac;
+const DEFAULT_DECIMALS: u8 = 8;
+const MAX_DECIMALS: u8 = 18;
+const TOP_HOLDERS: usize = 100;
+const RECENT_OPERATIONS: usize = 50;
+
+/// A KRC-20 operation as inscribed, before it is checked against the token state.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Inscription {
+    #[serde(skip)]
+    from: String,
+    p: String,
+    op: String,
+    tick: String,
+    max: Option<String>,
+    lim: Option<String>,
+    dec: Option<String>,
+    amt: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Token {
+    tick: String,
+    max_supply: String,
+    mint_limit: String, // Minted per mint operation
+    decimals: u8,
+    minted: String,
+    holders: u64,
+    deployer: String,
+    deploy_transaction_id: String,
+    deploy_daa_score: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokensResponse {
+    tokens: Vec<Token>, // Oldest deployment first
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenHolder {
+    address: String,
+    balance: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenOperation {
+    transaction_id: String,
+    op: String,
+    from: String,
+    to: Option<String>,
+    amount: Option<String>, // Supply for deploys, credited amount for mints
+    daa_score: u64,
+    accepted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>, // Why the operation was rejected
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenDetail {
+    token: Token,
+    top_holders: Vec<TokenHolder>, // Up to 100, largest balance first
+    recent_operations: Vec<TokenOperation>, // Up to 50, newest first
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressTokenBalance {
+    tick: String,
+    balance: String,
+    decimals: u8,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddressTokens {
+    address: String,
+    tokens: Vec<AddressTokenBalance>,
+}
+
+/// The KRC-20 operation inscribed by one of `tx`'s inputs, if any.
+pub fn parse_inscription(tx: &RpcTransaction) -> Option<Inscription> {
+    // Outputs carry the network's prefix; an inscription without one cannot name its sender.
+    let prefix = tx
+        .outputs
+        .iter()
+        .find_map(|output| output.verbose_data.as_ref())
+        .map(|v| v.script_public_key_address.prefix)?;
+    tx.inputs
+        .iter()
+        .find_map(|input| parse_signature_script(&input.signature_script, prefix))
+}
+
+fn parse_signature_script(signature_script: &[u8], prefix: Prefix) -> Option<Inscription> {
+    // Cheap check first; almost no signature script carries an envelope.
+    if !signature_script
+        .windows(MARKER.len())
+        .any(|window| window == MARKER)
+    {
+        return None;
+    }
+    let (pushes, _) = decode_script::instructions(signature_script);
+    let redeem_script = pushes.last()?.1.as_deref()?;
+    let (instructions, _) = decode_script::instructions(redeem_script);
+    let from = match instructions.as_slice() {
+        [(OP_DATA_32, Some(key)), (OP_CHECKSIG, None), ..] => {
+            Address::new(prefix, Version::PubKey, key)
+        }
+        [(OP_DATA_33, Some(key)), (OP_CHECKSIG_ECDSA, None), ..] => {
+            Address::new(prefix, Version::PubKeyECDSA, key)
+        }
+        _ => return None,
+    };
+    let marker = instructions
+        .iter()
+        .position(|(_, data)| data.as_deref() == Some(MARKER))?;
+    let body = marker
+        + 1
+        + instructions[marker + 1..]
+            .iter()
+            .position(|(code, _)| *code == OP_FALSE)?
+        + 1;
+    // Content longer than one push is split across several.
+    let mut content = Vec::new();
+    for (code, data) in &instructions[body..] {
+        match data {
+            Some(data) => content.extend_from_slice(data),
+            None if *code == OP_ENDIF => break,
+            None => return None,
+        }
+    }
+    let mut inscription: Inscription = serde_json::from_slice(&content).ok()?;
+    if !inscription.p.eq_ignore_ascii_case("krc-20") {
+        return None;
+    }
+    inscription.op = inscription.op.to_lowercase();
+    inscription.tick = inscription.tick.to_uppercase();
+    inscription.from = from.to_string();
+    Some(inscription)
+}
+
+fn parse_amount(amount: Option<&str>) -> Option<u128> {
+    amount
+        .filter(|amount| !amount.is_empty() && amount.bytes().all(|b| b.is_ascii_digit()))?
+        .parse()
+        .ok()
+}
+
+// Balances are stored zero-padded so that they sort numerically as text.
+fn padded(amount: u128) -> String {
+    format!("{:039}", amount)
+}
+
+fn amount_column(row: &rusqlite::Row, i: usize) -> rusqlite::Result<u128> {
+    row.get::<_, String>(i)?.parse().map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn balance(conn: &Connection, epoch: i64, tick: &str, address: &str) -> rusqlite::Result<u128> {
+    conn.prepare_cached(
+        "SELECT balance FROM token_balances WHERE tick = ?1 AND address = ?2 AND epoch = ?3",
+    )?
+    .query_row(params![tick, address, epoch], |row| amount_column(row, 0))
+    .optional()
+    .map(Option::unwrap_or_default)
+}
+
+fn set_balance(
+    conn: &Connection,
+    epoch: i64,
+    tick: &str,
+    address: &str,
+    balance: u128,
+) -> rusqlite::Result<()> {
+    if balance == 0 {
+        conn.prepare_cached(
+            "DELETE FROM token_balances WHERE tick = ?1 AND address = ?2 AND epoch = ?3",
+        )?
+        .execute(params![tick, address, epoch])?;
+    } else {
+        conn.prepare_cached(
+            "INSERT OR REPLACE INTO token_balances (tick, address, epoch, balance) VALUES (?1, ?2, ?3, ?4)",
+        )?
+        .execute(params![tick, address, epoch, padded(balance)])?;
+    }
+    Ok(())
+}
+
+// (max supply, mint limit, minted) of a deployed token.
+fn supply(
+    conn: &Connection,
+    epoch: i64,
+    tick: &str,
+) -> rusqlite::Result<Option<(u128, u128, u128)>> {
+    conn.prepare_cached(
+        "SELECT max_supply, mint_limit, minted FROM tokens WHERE tick = ?1 AND epoch = ?2",
+    )?
+    .query_row(params![tick, epoch], |row| {
+        Ok((
+            amount_column(row, 0)?,
+            amount_column(row, 1)?,
+            amount_column(row, 2)?,
+        ))
+    })
+    .optional()
+}
+
+fn valid_address(address: &str) -> bool {
+    Address::try_from(address).is_ok()
+}
+
+/// Checks `inscription` against the token state and applies it. The outer error is a
+/// database failure; the inner one the reason the operation is rejected.
+fn execute(
+    conn: &Connection,
+    epoch: i64,
+    tx_id: &str,
+    daa_score: u64,
+    inscription: &Inscription,
+) -> rusqlite::Result<Result<(Option<String>, u128), String>> {
+    let tick = inscription.tick.as_str();
+    let from = inscription.from.as_str();
+    match inscription.op.as_str() {
+        "deploy" => {
+            if !(4..=6).contains(&tick.len()) || !tick.bytes().all(|b| b.is_ascii_alphabetic()) {
+                return Ok(Err("tick must be 4 to 6 letters".to_string()));
+            }
+            let Some(max) = parse_amount(inscription.max.as_deref()).filter(|&max| max > 0) else {
+                return Ok(Err("max must be a positive integer".to_string()));
+            };
+            let Some(lim) = parse_amount(inscription.lim.as_deref()).filter(|&lim| lim > 0) else {
+                return Ok(Err("lim must be a positive integer".to_string()));
+            };
+            let decimals = match inscription.dec.as_deref() {
+                None => DEFAULT_DECIMALS,
+                Some(dec) => match dec.parse::<u8>() {
+                    Ok(dec) if dec <= MAX_DECIMALS => dec,
+                    _ => return Ok(Err(format!("dec must be 0 to {}", MAX_DECIMALS))),
+                },
+            };
+            if supply(conn, epoch, tick)?.is_some() {
+                return Ok(Err(format!("{} is already deployed", tick)));
+            }
+            conn.prepare_cached(
+                "INSERT INTO tokens (tick, epoch, max_supply, mint_limit, decimals, minted, deployer, deploy_tx_id, deploy_daa_score)
+                 VALUES (?1, ?2, ?3, ?4, ?5, '0', ?6, ?7, ?8)",
+            )?
+            .execute(params![
+                tick,
+                epoch,
+                max.to_string(),
+                lim.to_string(),
+                decimals,
+                from,
+                tx_id,
+                daa_score as i64
+            ])?;
+            Ok(Ok((None, max)))
+        }
+        "mint" => {
+            let Some((max, lim, minted)) = supply(conn, epoch, tick)? else {
+                return Ok(Err(format!("{} is not deployed", tick)));
+            };
+            let amount = lim.min(max - minted);
+            if amount == 0 {
+                return Ok(Err(format!("{} is fully minted", tick)));
+            }
+            let to = inscription.to.as_deref().unwrap_or(from);
+            if !valid_address(to) {
+                return Ok(Err("to is not a valid address".to_string()));
+            }
+            conn.prepare_cached("UPDATE tokens SET minted = ?1 WHERE tick = ?2 AND epoch = ?3")?
+                .execute(params![(minted + amount).to_string(), tick, epoch])?;
+            let balance = balance(conn, epoch, tick, to)?;
+            set_balance(conn, epoch, tick, to, balance + amount)?;
+            Ok(Ok((Some(to.to_string()), amount)))
+        }
+        "transfer" => {
+            if supply(conn, epoch, tick)?.is_none() {
+                return Ok(Err(format!("{} is not deployed", tick)));
+            }
+            let Some(amount) = parse_amount(inscription.amt.as_deref()).filter(|&amt| amt > 0)
+            else {
+                return Ok(Err("amt must be a positive integer".to_string()));
+            };
+            let Some(to) = inscription.to.as_deref().filter(|to| valid_address(to)) else {
+                return Ok(Err("to must be a valid address".to_string()));
+            };
+            let from_balance = balance(conn, epoch, tick, from)?;
+            if from_balance < amount {
+                return Ok(Err("Insufficient balance".to_string()));
+            }
+            set_balance(conn, epoch, tick, from, from_balance - amount)?;
+            let to_balance = balance(conn, epoch, tick, to)?;
+            set_balance(conn, epoch, tick, to, to_balance + amount)?;
+            Ok(Ok((Some(to.to_string()), amount)))
+        }
+        op => Ok(Err(format!("Unknown operation {:?}", op))),
+    }
+}
+
+/// Stores `inscription` of transaction `tx_id` until a chain block accepts the transaction.
+/// If the acceptance is already recorded (the indexer trailing the virtual chain), it is
+/// applied right away.
+pub fn record(
+    conn: &Connection,
+    epoch: i64,
+    tx_id: &str,
+    daa_score: u64,
+    inscription: &Inscription,
+) -> rusqlite::Result<()> {
+    let body = serde_json::to_string(inscription)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.prepare_cached(
+        "INSERT OR IGNORE INTO token_inscriptions (tx_id, epoch, daa_score, from_address, body) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![tx_id, epoch, daa_score as i64, inscription.from, body])?;
+    let accepting_block: Option<String> = conn
+        .prepare_cached(
+            "SELECT accepting_block FROM accepted_transactions WHERE tx_id = ?1 AND epoch = ?2",
+        )?
+        .query_row(params![tx_id, epoch], |row| row.get(0))
+        .optional()?;
+    match accepting_block {
+        Some(accepting_block) => apply(conn, epoch, tx_id, &accepting_block),
+        None => Ok(()),
+    }
+}
+
+// DAA score of the including block and the inscription of transaction `tx_id`, if recorded.
+fn inscription(
+    conn: &Connection,
+    epoch: i64,
+    tx_id: &str,
+) -> rusqlite::Result<Option<(u64, Inscription)>> {
+    conn.prepare_cached(
+        "SELECT daa_score, from_address, body FROM token_inscriptions WHERE tx_id = ?1 AND epoch = ?2",
+    )?
+    .query_row(params![tx_id, epoch], |row| {
+        let body: String = row.get(2)?;
+        let mut inscription: Inscription = serde_json::from_str(&body).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        inscription.from = row.get(1)?;
+        Ok((row.get::<_, i64>(0)? as u64, inscription))
+    })
+    .optional()
+}
+
+/// Applies the inscription of transaction `tx_id`, accepted by chain block
+/// `accepting_block`, to the token tables and records the operation. Transactions without
+/// an inscription, and those already applied in `epoch`, are skipped.
+pub fn apply(
+    conn: &Connection,
+    epoch: i64,
+    tx_id: &str,
+    accepting_block: &str,
+) -> rusqlite::Result<()> {
+    let recorded = conn
+        .prepare_cached("SELECT 1 FROM token_operations WHERE tx_id = ?1 AND epoch = ?2")?
+        .exists(params![tx_id, epoch])?;
+    if recorded {
+        return Ok(());
+    }
+    let Some((daa_score, inscription)) = inscription(conn, epoch, tx_id)? else {
+        return Ok(());
+    };
+    let (to, amount, error) = match execute(conn, epoch, tx_id, daa_score, &inscription)? {
+        Ok((to, amount)) => (to, Some(amount.to_string()), None),
+        Err(error) => (inscription.to.clone(), inscription.amt.clone(), Some(error)),
+    };
+    conn.prepare_cached(
+        "INSERT INTO token_operations (tx_id, epoch, tick, op, from_address, to_address, amount, daa_score, error, accepting_block)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?
+    .execute(params![
+        tx_id,
+        epoch,
+        inscription.tick,
+        inscription.op,
+        inscription.from,
+        to,
+        amount,
+        daa_score as i64,
+        error,
+        accepting_block
+    ])?;
+    Ok(())
+}
+
+/// Undoes the operations of the transactions chain block `accepting_block` accepted, newest
+/// first, when a reorg removes it from the selected chain. Callers revert later chain blocks
+/// before earlier ones, so every state an operation changed is restored exactly.
+pub fn revert(conn: &Connection, epoch: i64, accepting_block: &str) -> rusqlite::Result<()> {
+    let operations = conn
+        .prepare_cached(
+            "SELECT tick, op, from_address, to_address, amount FROM token_operations
+             WHERE accepting_block = ?1 AND epoch = ?2 AND error IS NULL ORDER BY rowid DESC",
+        )?
+        .query_map(params![accepting_block, epoch], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                amount_column(row, 4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (tick, op, from, to, amount) in operations {
+        match (op.as_str(), to) {
+            ("deploy", _) => {
+                conn.prepare_cached("DELETE FROM tokens WHERE tick = ?1 AND epoch = ?2")?
+                    .execute(params![tick, epoch])?;
+            }
+            ("mint", Some(to)) => {
+                if let Some((_, _, minted)) = supply(conn, epoch, &tick)? {
+                    conn.prepare_cached(
+                        "UPDATE tokens SET minted = ?1 WHERE tick = ?2 AND epoch = ?3",
+                    )?
+                    .execute(params![
+                        minted.saturating_sub(amount).to_string(),
+                        tick,
+                        epoch
+                    ])?;
+                }
+                let balance = balance(conn, epoch, &tick, &to)?;
+                set_balance(conn, epoch, &tick, &to, balance.saturating_sub(amount))?;
+            }
+            ("transfer", Some(to)) => {
+                let to_balance = balance(conn, epoch, &tick, &to)?;
+                set_balance(conn, epoch, &tick, &to, to_balance.saturating_sub(amount))?;
+                let from_balance = balance(conn, epoch, &tick, &from)?;
+                set_balance(conn, epoch, &tick, &from, from_balance + amount)?;
+            }
+            _ => {}
+        }
+    }
+    conn.prepare_cached("DELETE FROM token_operations WHERE accepting_block = ?1 AND epoch = ?2")?
+        .execute(params![accepting_block, epoch])?;
+    Ok(())
+}
+
+fn token_row(row: &rusqlite::Row) -> rusqlite::Result<Token> {
+    Ok(Token {
+        tick: row.get(0)?,
+        max_supply: row.get(1)?,
+        mint_limit: row.get(2)?,
+        decimals: row.get(3)?,
+        minted: row.get(4)?,
+        deployer: row.get(5)?,
+        deploy_transaction_id: row.get(6)?,
+        deploy_daa_score: row.get::<_, i64>(7)? as u64,
+        holders: row.get::<_, i64>(8)? as u64,
+    })
+}
+
+const TOKEN_COLUMNS: &str = "t.tick, t.max_supply, t.mint_limit, t.decimals, t.minted, t.deployer, t.deploy_tx_id, t.deploy_daa_score,
+     (SELECT COUNT(*) FROM token_balances b WHERE b.tick = t.tick AND b.epoch = t.epoch)";
+
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn require_tokens(state: &AppState) -> Result<crate::index::IndexHandle, Response> {
+    let index = require_index(state)?;
+    if !state.config.index.tokens {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Token indexing is disabled (set tokens in [index])",
+        ));
+    }
+    Ok(index)
+}
+
+fn lookup_failed(e: anyhow::Error) -> Response {
+    tracing::error!("Index lookup of tokens failed: {:?}", e);
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tokens",
+    tag = "tokens",
+    responses(
+        (status = 200, description = "Deployed KRC-20 tokens", body = TokensResponse),
+        (status = 503, description = "Index or token indexing disabled", body = ErrorResponse)
+    )
+)]
+pub async fn get_tokens(State(state): State<AppState>) -> Result<Json<TokensResponse>, Response> {
+    let index = require_tokens(&state)?;
+    let epoch = index.current_epoch_id().unwrap_or(0);
+    let tokens = index
+        .run(move |conn| {
+            conn.prepare_cached(&format!(
+                "SELECT {} FROM tokens t WHERE t.epoch = ?1 ORDER BY t.deploy_daa_score, t.tick",
+                TOKEN_COLUMNS
+            ))?
+            .query_map([epoch], token_row)?
+            .collect()
+        })
+        .await
+        .map_err(lookup_failed)?;
+    Ok(Json(TokensResponse { tokens }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/token/{tick}",
+    tag = "tokens",
+    params(("tick" = String, Path, description = "Token ticker, case-insensitive")),
+    responses(
+        (status = 200, description = "Token state, largest holders and latest operations", body = TokenDetail),
+        (status = 404, description = "Token not deployed", body = ErrorResponse),
+        (status = 503, description = "Index or token indexing disabled", body = ErrorResponse)
+    )
+)]
+pub async fn get_token(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> Result<Json<TokenDetail>, Response> {
+    let index = require_tokens(&state)?;
+    let epoch = index.current_epoch_id().unwrap_or(0);
+    let tick = tick.to_uppercase();
+    let detail = index
+        .run(move |conn| {
+            let Some(token) = conn
+                .prepare_cached(&format!(
+                    "SELECT {} FROM tokens t WHERE t.tick = ?1 AND t.epoch = ?2",
+                    TOKEN_COLUMNS
+                ))?
+                .query_row(params![tick, epoch], token_row)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            let top_holders = conn
+                .prepare_cached(
+                    "SELECT address, balance FROM token_balances WHERE tick = ?1 AND epoch = ?2
+                     ORDER BY balance DESC, address LIMIT ?3",
+                )?
+                .query_map(params![tick, epoch, TOP_HOLDERS as i64], |row| {
+                    Ok(TokenHolder {
+                        address: row.get(0)?,
+                        balance: amount_column(row, 1)?.to_string(),
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let recent_operations = conn
+                .prepare_cached(
+                    "SELECT tx_id, op, from_address, to_address, amount, daa_score, error FROM token_operations
+                     WHERE tick = ?1 AND epoch = ?2 ORDER BY rowid DESC LIMIT ?3",
+                )?
+                .query_map(params![tick, epoch, RECENT_OPERATIONS as i64], |row| {
+                    let error: Option<String> = row.get(6)?;
+                    Ok(TokenOperation {
+                        transaction_id: row.get(0)?,
+                        op: row.get(1)?,
+                        from: row.get(2)?,
+                        to: row.get(3)?,
+                        amount: row.get(4)?,
+                        daa_score: row.get::<_, i64>(5)? as u64,
+                        accepted: error.is_none(),
+                        error,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(Some(TokenDetail {
+                token,
+                top_holders,
+                recent_operations,
+            }))
+        })
+        .await
+        .map_err(lookup_failed)?;
+    detail
+        .map(Json)
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Token not deployed"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/tokens",
+    tag = "tokens",
+    params(("address" = String, Path, description = "Kaspa address")),
+    responses(
+        (status = 200, description = "KRC-20 balances of the address", body = AddressTokens),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 503, description = "Index or token indexing disabled", body = ErrorResponse)
+    )
+)]
+pub async fn get_address_tokens(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<Json<AddressTokens>, Response> {
+    let address = network::parse_address(&state, &address)?.to_string();
+    let index = require_tokens(&state)?;
+    let epoch = index.current_epoch_id().unwrap_or(0);
+    let owner = address.clone();
+    let tokens = index
+        .run(move |conn| {
+            conn.prepare_cached(
+                "SELECT b.tick, b.balance, t.decimals FROM token_balances b
+                 JOIN tokens t ON t.tick = b.tick AND t.epoch = b.epoch
+                 WHERE b.address = ?1 AND b.epoch = ?2 ORDER BY b.tick",
+            )?
+            .query_map(params![owner, epoch], |row| {
+                Ok(AddressTokenBalance {
+                    tick: row.get(0)?,
+                    balance: amount_column(row, 1)?.to_string(),
+                    decimals: row.get(2)?,
+                })
+            })?
+            .collect()
+        })
+        .await
+        .map_err(lookup_failed)?;
+    Ok(Json(AddressTokens { address, tokens }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn).unwrap();
+        conn
+    }
+
+    fn address(byte: u8) -> String {
+        Address::new(Prefix::Testnet, Version::PubKey, &[byte; 32]).to_string()
+    }
+
+    fn operation(op: &str, tick: &str, from: &str) -> Inscription {
+        Inscription {
+            from: from.to_string(),
+            p: "krc-20".to_string(),
+            op: op.to_string(),
+            tick: tick.to_string(),
+            max: None,
+            lim: None,
+            dec: None,
+            amt: None,
+            to: None,
+        }
+    }
+
+    fn deploy(max: &str, lim: &str) -> Inscription {
+        Inscription {
+            max: Some(max.to_string()),
+            lim: Some(lim.to_string()),
+            ..operation("deploy", "TEST", &address(1))
+        }
+    }
+
+    fn run(conn: &Connection, inscription: &Inscription) -> Result<(Option<String>, u128), String> {
+        execute(conn, 1, "tx", 0, inscription).unwrap()
+    }
+
+    #[test]
+    fn deploy_checks_its_fields() {
+        let conn = database();
+        let bad_tick = Inscription {
+            tick: "T1".to_string(),
+            ..deploy("100", "10")
+        };
+        assert_eq!(
+            run(&conn, &bad_tick),
+            Err("tick must be 4 to 6 letters".to_string())
+        );
+        assert_eq!(
+            run(&conn, &deploy("0", "10")),
+            Err("max must be a positive integer".to_string())
+        );
+        assert_eq!(
+            run(&conn, &deploy("100", "-1")),
+            Err("lim must be a positive integer".to_string())
+        );
+        let bad_dec = Inscription {
+            dec: Some("19".to_string()),
+            ..deploy("100", "10")
+        };
+        assert_eq!(run(&conn, &bad_dec), Err("dec must be 0 to 18".to_string()));
+        assert_eq!(run(&conn, &deploy("100", "10")), Ok((None, 100)));
+        assert_eq!(
+            run(&conn, &deploy("100", "10")),
+            Err("TEST is already deployed".to_string())
+        );
+    }
+
+    #[test]
+    fn mint_stops_at_the_max_supply() {
+        let conn = database();
+        let minter = address(2);
+        assert_eq!(
+            run(&conn, &operation("mint", "TEST", &minter)),
+            Err("TEST is not deployed".to_string())
+        );
+        run(&conn, &deploy("25", "10")).unwrap();
+        let mint = operation("mint", "TEST", &minter);
+        assert_eq!(run(&conn, &mint), Ok((Some(minter.clone()), 10)));
+        assert_eq!(run(&conn, &mint), Ok((Some(minter.clone()), 10)));
+        assert_eq!(run(&conn, &mint), Ok((Some(minter.clone()), 5)));
+        assert_eq!(run(&conn, &mint), Err("TEST is fully minted".to_string()));
+        assert_eq!(balance(&conn, 1, "TEST", &minter).unwrap(), 25);
+        assert_eq!(supply(&conn, 1, "TEST").unwrap(), Some((25, 10, 25)));
+    }
+
+    #[test]
+    fn transfer_moves_held_amounts() {
+        let conn = database();
+        let (sender, receiver) = (address(2), address(3));
+        run(&conn, &deploy("100", "10")).unwrap();
+        run(&conn, &operation("mint", "TEST", &sender)).unwrap();
+        let transfer = |amt: &str, to: &str| Inscription {
+            amt: Some(amt.to_string()),
+            to: Some(to.to_string()),
+            ..operation("transfer", "TEST", &sender)
+        };
+        assert_eq!(
+            run(&conn, &transfer("11", &receiver)),
+            Err("Insufficient balance".to_string())
+        );
+        assert_eq!(
+            run(&conn, &transfer("1", "kaspatest:nope")),
+            Err("to must be a valid address".to_string())
+        );
+        assert_eq!(
+            run(&conn, &transfer("4", &receiver)),
+            Ok((Some(receiver.clone()), 4))
+        );
+        assert_eq!(balance(&conn, 1, "TEST", &sender).unwrap(), 6);
+        assert_eq!(balance(&conn, 1, "TEST", &receiver).unwrap(), 4);
+        // An emptied balance is removed rather than kept at zero.
+        run(&conn, &transfer("6", &receiver)).unwrap();
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM token_balances", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn unknown_operations_are_rejected() {
+        let conn = database();
+        assert_eq!(
+            run(&conn, &operation("burn", "TEST", &address(1))),
+            Err("Unknown operation \"burn\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Transaction detail view.
+//!
+//! Confirmed transactions are found through the index, which records the blocks that
+//! included them; unconfirmed ones come from the mempool. Each input is resolved to the
+//! address and amount of the output it spends, which is what makes the fee computable. The
+//! accepting chain block comes from the index's acceptance table (see `acceptance.rs`), so
+//! it costs no chain walk; it is None until the acceptance has been recorded.
+
+use crate::chain::{ChainSource, Client};
+use crate::labels::Label;
+use crate::resolver::{self, Outpoint};
+use crate::{acceptance, error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcTransaction;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionDetail {
+    id: String,
+    // Blocks that included the transaction; empty while it is in the mempool.
+    block_hashes: Vec<String>,
+    in_mempool: bool,
+    // The selected-chain block that accepted it, once recorded.
+    accepting_block_hash: Option<String>,
+    accepting_daa_score: Option<u64>,
+    mass: u64,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    // None unless every input was resolved.
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    input_value: Option<u64>,
+    #[serde(serialize_with = "crate::units::sompi")]
+    output_value: u64,
+    // input_value - output_value; None for coinbase or unresolved inputs.
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionInput {
+    previous_transaction_id: String,
+    previous_index: u32,
+    // Address and amount of the spent output, when it could be resolved.
+    address: Option<String>,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    amount: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<Label>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionOutput {
+    index: u32,
+    address: Option<String>,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<Label>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/transaction/{id}",
+    tag = "transactions",
+    params(("id" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "Transaction with resolved inputs and fee", body = TransactionDetail),
+        (status = 400, description = "Invalid transaction id", body = ErrorResponse),
+        (status = 404, description = "Transaction neither indexed nor in the mempool", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn get_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionDetail>, Response> {
+    let id = id
+        .parse::<Hash>()
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid transaction id"))?;
+
+    let block_hashes = block_hashes(&state, id).await?;
+    let acceptance = if block_hashes.is_empty() {
+        None
+    } else {
+        acceptance::recorded(&state, id).await?
+    };
+
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref().ok_or_else(not_connected)?;
+    let (tx, in_mempool) = match block_hashes.first() {
+        Some(block_hash) => (confirmed(&state, client, block_hash, id).await?, false),
+        None => (unconfirmed(client, id).await?, true),
+    };
+
+    let outpoints: Vec<Outpoint> = tx
+        .inputs
+        .iter()
+        .map(|input| {
+            (
+                input.previous_outpoint.transaction_id.to_string(),
+                input.previous_outpoint.index,
+            )
+        })
+        .collect();
+    let resolved = resolver::resolve(&state, client, &outpoints).await;
+    drop(client_guard);
+
+    let labels = state.labels.read().await;
+    let label = |address: &Option<String>| address.as_ref().and_then(|a| labels.get(a)).cloned();
+    let inputs = outpoints
+        .iter()
+        .map(|outpoint| {
+            let output = resolved.get(outpoint);
+            let address = output.and_then(|o| o.address.clone());
+            TransactionInput {
+                previous_transaction_id: outpoint.0.clone(),
+                previous_index: outpoint.1,
+                label: label(&address),
+                address,
+                amount: output.map(|o| o.amount),
+            }
+        })
+        .collect();
+    let outputs = tx
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(i, output)| {
+            let address = output
+                .verbose_data
+                .as_ref()
+                .map(|v| v.script_public_key_address.to_string());
+            TransactionOutput {
+                index: i as u32,
+                label: label(&address),
+                address,
+                amount: output.value,
+            }
+        })
+        .collect();
+    let output_value = tx.outputs.iter().map(|o| o.value).sum();
+    let input_value = if outpoints.is_empty() {
+        None
+    } else {
+        resolver::input_value(&outpoints, &resolved)
+    };
+    let mass = tx.verbose_data.as_ref().map_or(tx.mass, |v| v.compute_mass);
+
+    Ok(Json(TransactionDetail {
+        id: id.to_string(),
+        block_hashes,
+        in_mempool,
+        accepting_daa_score: acceptance.as_ref().and_then(|a| a.daa_score),
+        accepting_block_hash: acceptance.map(|a| a.block_hash),
+        mass,
+        inputs,
+        outputs,
+        input_value,
+        output_value,
+        fee: input_value.and_then(|input| input.checked_sub(output_value)),
+    }))
+}
+
+/// Indexed blocks that included transaction `id`; empty without a usable index.
+pub async fn block_hashes(state: &AppState, id: Hash) -> Result<Vec<String>, Response> {
+    match &state.index {
+        Some(index) if index.network_mismatch().is_none() => {
+            index.transaction_blocks(id.to_string()).await.map_err(|e| {
+                tracing::error!("Index lookup of transaction {} failed: {:?}", id, e);
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+            })
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+pub async fn confirmed(
+    state: &AppState,
+    client: &Client,
+    block_hash: &str,
+    id: Hash,
+) -> Result<RpcTransaction, Response> {
+    let block_hash = block_hash.parse::<Hash>().map_err(|_| {
+        tracing::error!("Index holds an invalid block hash {:?}", block_hash);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+    })?;
+    let block = rpc::hedged(
+        state.hedging.as_deref(),
+        client,
+        "get_block",
+        |c| async move { c.get_block(block_hash, true).await },
+    )
+    .await
+    .map_err(|e| {
+        if rpc::is_not_found(&e) {
+            // Pruned by the node since it was indexed.
+            error_response(
+                StatusCode::NOT_FOUND,
+                "Transaction's block is no longer available",
+            )
+        } else {
+            tracing::error!(
+                "Failed to fetch block {} with transactions: {:?}",
+                block_hash,
+                e
+            );
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch block")
+        }
+    })?;
+    block
+        .transactions
+        .into_iter()
+        .find(|tx| {
+            tx.verbose_data
+                .as_ref()
+                .is_some_and(|v| v.transaction_id == id)
+        })
+        .ok_or_else(|| {
+            tracing::error!(
+                "Transaction {} is indexed but missing from block {}",
+                id,
+                block_hash
+            );
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch transaction",
+            )
+        })
+}
+
+pub async fn unconfirmed(client: &Client, id: Hash) -> Result<RpcTransaction, Response> {
+    rpc::retried("get_mempool_entry", || {
+        client.get_mempool_entry(id, true, false)
+    })
+    .await
+    .map(|entry| entry.transaction)
+    .map_err(|e| {
+        if rpc::is_not_found(&e) {
+            error_response(StatusCode::NOT_FOUND, "Transaction not found")
+        } else {
+            tracing::error!("Failed to fetch mempool entry {}: {:?}", id, e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch mempool entry",
+            )
+        }
+    })
+}
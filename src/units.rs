@@ -0,0 +1,111 @@
+//! Money field units.
+//!
+//! Amounts are sompi and serialize as JSON integers by default. JavaScript numbers lose
+//! precision above 2^53 sompi (about 90 million KAS), so with `?units=both`, or `units =
+//! "both"` in `[format]`, every money field becomes a pair of strings instead:
+//! `{"sompi": "12345", "kas": "0.00012345"}`. Money fields opt in with `serialize_with` one
+//! of the functions below. The layer puts the request's units in a task-local that they read,
+//! so anything serialized outside a request (SSE events, webhooks) keeps integers; streamed
+//! exports carry the units over with `scoped`.
+
+use crate::config::Units;
+use crate::{error_response, AppState};
+use axum::{
+    extract::{Query, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize, Serializer};
+
+const SOMPI_PER_KAS: u64 = 100_000_000;
+
+tokio::task_local! {
+    static UNITS: Units;
+}
+
+#[derive(Debug, Deserialize)]
+struct UnitsQuery {
+    units: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Amount {
+    sompi: String,
+    kas: String,
+}
+
+pub async fn apply_units(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let requested = Query::<UnitsQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(query)| query.units);
+    let units = match requested.as_deref() {
+        None => state.config.format.units,
+        Some("sompi") => Units::Sompi,
+        Some("both") => Units::Both,
+        Some(_) => {
+            return error_response(StatusCode::BAD_REQUEST, "units must be sompi or both");
+        }
+    };
+    UNITS.scope(units, next.run(request)).await
+}
+
+fn serialize_amount<S: Serializer>(
+    negative: bool,
+    sompi: u64,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sign = if negative { "-" } else { "" };
+    Amount {
+        sompi: format!("{}{}", sign, sompi),
+        kas: format!(
+            "{}{}.{:08}",
+            sign,
+            sompi / SOMPI_PER_KAS,
+            sompi % SOMPI_PER_KAS
+        ),
+    }
+    .serialize(serializer)
+}
+
+/// Units of the current request; integers outside one.
+pub fn current() -> Units {
+    UNITS.try_with(|units| *units).unwrap_or_default()
+}
+
+/// Runs `f` with `units`, for serializing outside the request task (streamed bodies).
+pub fn scoped<R>(units: Units, f: impl FnOnce() -> R) -> R {
+    UNITS.sync_scope(units, f)
+}
+
+fn both() -> bool {
+    UNITS
+        .try_with(|units| *units == Units::Both)
+        .unwrap_or(false)
+}
+
+/// For `u64` sompi fields.
+pub fn sompi<S: Serializer>(amount: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    if both() {
+        serialize_amount(false, *amount, serializer)
+    } else {
+        serializer.serialize_u64(*amount)
+    }
+}
+
+/// For `Option<u64>` sompi fields; None stays null.
+pub fn sompi_opt<S: Serializer>(amount: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match amount {
+        Some(amount) => sompi(amount, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// For `i64` sompi differences.
+pub fn sompi_signed<S: Serializer>(amount: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+    if both() {
+        serialize_amount(*amount < 0, amount.unsigned_abs(), serializer)
+    } else {
+        serializer.serialize_i64(*amount)
+    }
+}
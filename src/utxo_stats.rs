@@ -0,0 +1,200 @@
+//! UTXO set statistics.
+//!
+//! kaspad cannot enumerate its UTXO set over RPC, so a background job estimates it: every
+//! `REFRESH_INTERVAL` it reads the UTXOs of a random sample of the addresses the index has seen
+//! in the current epoch, and scales the counts by all addresses / sampled addresses. The total
+//! value is kaspad's circulating supply, which is exact. A UTXO's age is the virtual DAA score
+//! minus the DAA score of the block that created it, converted to time at the DAA score rate
+//! measured by the sampler.
+//!
+//! Addresses funded only before the index started are never sampled, and a few addresses
+//! holding many UTXOs (miners) can swing a sample; `sampled_addresses` and `sampled_utxos`
+//! tell how much the estimate rests on.
+
+use crate::chain::ChainSource;
+use crate::index::IndexHandle;
+use crate::sampler::now_ms;
+use crate::{retry_after_response, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_addresses::Address;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration};
+use utoipa::ToSchema;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+// Delay before retrying a failed pass (kaspad down, index not started yet).
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+const SAMPLE_ADDRESSES: usize = 2_000;
+// Addresses per get_utxos_by_addresses call.
+const BATCH_SIZE: usize = 100;
+// Lower bounds of the value buckets after the first, in sompi: 0.001 KAS up to 1000 KAS.
+const BUCKET_BOUNDS: [u64; 7] = [
+    100_000,
+    1_000_000,
+    10_000_000,
+    100_000_000,
+    1_000_000_000,
+    10_000_000_000,
+    100_000_000_000,
+];
+
+/// The last completed estimate; None until the first pass finishes.
+pub type UtxoStatsHandle = Arc<RwLock<Option<UtxoStats>>>;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UtxoBucket {
+    #[serde(serialize_with = "crate::units::sompi")]
+    min_sompi: u64,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    max_sompi: Option<u64>, // Exclusive; None for the last bucket
+    estimated_utxos: u64,
+    share: f64, // Of all UTXOs, 0.0 to 1.0
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UtxoStats {
+    computed_at: u64, // Unix ms
+    daa_score: u64,   // Virtual DAA score the ages are measured from
+    #[serde(serialize_with = "crate::units::sompi")]
+    total_value: u64, // Circulating supply in sompi
+    estimated_utxo_count: u64,
+    average_age_daa: Option<u64>,
+    average_age_seconds: Option<u64>,
+    total_addresses: u64, // Addresses seen by the index in the current epoch
+    sampled_addresses: usize,
+    sampled_utxos: u64,
+    buckets: Vec<UtxoBucket>,
+}
+
+pub fn spawn(state: AppState, index: IndexHandle) {
+    state.shutdown.clone().spawn(async move {
+        loop {
+            match compute(&state, &index).await {
+                Ok(stats) => {
+                    tracing::info!(
+                        "Estimated {} UTXOs from {} sampled addresses",
+                        stats.estimated_utxo_count,
+                        stats.sampled_addresses
+                    );
+                    *state.utxo_stats.write().await = Some(stats);
+                    sleep(REFRESH_INTERVAL).await;
+                }
+                Err(e) => {
+                    tracing::warn!("UTXO statistics pass failed: {:?}", e);
+                    sleep(RETRY_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+fn bucket_of(amount: u64) -> usize {
+    BUCKET_BOUNDS
+        .iter()
+        .take_while(|bound| amount >= **bound)
+        .count()
+}
+
+async fn compute(state: &AppState, index: &IndexHandle) -> anyhow::Result<UtxoStats> {
+    if index.current_epoch_id().is_none() {
+        anyhow::bail!("No index epoch started");
+    }
+    if let Some(mismatch) = index.network_mismatch() {
+        anyhow::bail!(mismatch);
+    }
+    let (sample, total_addresses) = index.sample_addresses(SAMPLE_ADDRESSES).await?;
+    let sample: Vec<Address> = sample
+        .iter()
+        .filter_map(|address| Address::try_from(address.as_str()).ok())
+        .collect();
+
+    let client_guard = state.client.read().await;
+    let client = client_guard
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Not connected to kaspad"))?;
+    let supply = rpc::retried("get_coin_supply", || client.get_coin_supply()).await?;
+    let daa_score = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+        .await?
+        .virtual_daa_score;
+    let mut counts = [0u64; BUCKET_BOUNDS.len() + 1];
+    let mut age_sum: u128 = 0;
+    for batch in sample.chunks(BATCH_SIZE) {
+        let entries = rpc::retried("get_utxos_by_addresses", || {
+            client.get_utxos_by_addresses(batch.to_vec())
+        })
+        .await?;
+        for entry in &entries {
+            counts[bucket_of(entry.utxo_entry.amount)] += 1;
+            age_sum += daa_score.saturating_sub(entry.utxo_entry.block_daa_score) as u128;
+        }
+    }
+    drop(client_guard);
+
+    let sampled_utxos: u64 = counts.iter().sum();
+    // Each sampled address stands for this many addresses.
+    let scale = if sample.is_empty() {
+        0.0
+    } else {
+        total_addresses as f64 / sample.len() as f64
+    };
+    let average_age_daa = (sampled_utxos > 0).then(|| (age_sum / sampled_utxos as u128) as u64);
+    let daa_per_second = state.sampler.read().await.daa_per_second(state.network);
+    let buckets = counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| UtxoBucket {
+            min_sompi: if i == 0 { 0 } else { BUCKET_BOUNDS[i - 1] },
+            max_sompi: BUCKET_BOUNDS.get(i).copied(),
+            estimated_utxos: (*count as f64 * scale).round() as u64,
+            share: if sampled_utxos == 0 {
+                0.0
+            } else {
+                *count as f64 / sampled_utxos as f64
+            },
+        })
+        .collect();
+    Ok(UtxoStats {
+        computed_at: now_ms(),
+        daa_score,
+        total_value: supply.circulating_sompi,
+        estimated_utxo_count: (sampled_utxos as f64 * scale).round() as u64,
+        average_age_daa,
+        average_age_seconds: average_age_daa.map(|age| (age as f64 / daa_per_second) as u64),
+        total_addresses,
+        sampled_addresses: sample.len(),
+        sampled_utxos,
+        buckets,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/utxos",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Estimated UTXO count, value distribution and average age", body = UtxoStats),
+        (status = 503, description = "Index disabled or no estimate computed yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_utxo_stats(State(state): State<AppState>) -> Result<Json<UtxoStats>, Response> {
+    state
+        .utxo_stats
+        .read()
+        .await
+        .clone()
+        .map(Json)
+        .ok_or_else(|| {
+            let message = if state.index.is_some() {
+                "No UTXO statistics computed yet"
+            } else {
+                "UTXO statistics need the transaction index, which is disabled"
+            };
+            retry_after_response(StatusCode::SERVICE_UNAVAILABLE, message, RETRY_INTERVAL)
+        })
+}
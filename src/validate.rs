@@ -0,0 +1,666 @@
+//! Transaction dry runs.
+//!
+//! `POST /transaction/validate` applies the checks kaspad's mempool runs before accepting a
+//! transaction (version, standard scripts, dust, mass, fee, and that every input is unspent
+//! and not already spent by a mempool transaction) without broadcasting anything. Input UTXOs
+//! are located through the local index or the request's `input_addresses`, then confirmed
+//! against kaspad's UTXO index and mempool. Signatures are not verified.
+//!
+//! Compute and storage mass come from kaspa-consensus-core's mass calculator with the served
+//! network's parameters, so they follow consensus; the standardness limits and relay fee are
+//! kaspad's mempool policy. Amounts that overflow when summed are rejected with a 400.
+
+use crate::chain::ChainSource;
+use crate::errors::ApiError;
+use crate::{error_response, network, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::Address;
+use kaspa_consensus_core::mass::{self, MassCalculator};
+use kaspa_consensus_core::subnets::SubnetworkId;
+use kaspa_consensus_core::tx::{
+    PopulatedTransaction, ScriptPublicKey, ScriptVec, Transaction, TransactionInput,
+    TransactionOutpoint, TransactionOutput, UtxoEntry,
+};
+use kaspa_hashes::Hash;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+
+// Standardness and mass parameters, as in kaspad's mempool and consensus configuration.
+const MAX_STANDARD_TRANSACTION_VERSION: u16 = 0;
+const MAX_SCRIPT_PUBLIC_KEY_VERSION: u16 = 0;
+const MAXIMUM_STANDARD_TRANSACTION_MASS: u64 = 100_000;
+const MAX_STANDARD_SIGNATURE_SCRIPT_SIZE: usize = 1650;
+const MINIMUM_RELAY_TRANSACTION_FEE: u64 = 1000; // Sompi per 1000 grams of mass
+const SUBNETWORK_ID_SIZE: usize = 20;
+// Lock times below this are DAA scores, above it Unix timestamps in milliseconds.
+const LOCK_TIME_THRESHOLD: u64 = 500_000_000_000;
+// Each input can cost an index lookup; bounds the work one request can cause.
+const MAX_INPUTS: usize = 1000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateOutpoint {
+    transaction_id: String,
+    index: u32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateInput {
+    previous_outpoint: ValidateOutpoint,
+    #[serde(default)]
+    signature_script: String, // Hex
+    #[serde(default)]
+    sequence: u64,
+    #[serde(default = "default_sig_op_count")]
+    sig_op_count: u8,
+}
+
+fn default_sig_op_count() -> u8 {
+    1
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateScriptPublicKey {
+    #[serde(default)]
+    version: u16,
+    script: String, // Hex
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateOutput {
+    amount: u64,
+    script_public_key: ValidateScriptPublicKey,
+}
+
+/// A transaction in kaspad's RPC layout, with hex-encoded byte fields.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateTransactionRequest {
+    #[serde(default)]
+    version: u16,
+    inputs: Vec<ValidateInput>,
+    outputs: Vec<ValidateOutput>,
+    #[serde(default)]
+    lock_time: u64,
+    subnetwork_id: Option<String>, // Hex; native when omitted
+    #[serde(default)]
+    gas: u64,
+    #[serde(default)]
+    payload: String, // Hex
+    // Owners of the inputs, for outpoints the local index does not cover.
+    #[serde(default)]
+    input_addresses: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationIssue {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidateTransactionResponse {
+    valid: bool, // No issues found; signatures are not checked
+    compute_mass: u64,
+    storage_mass: Option<u64>, // None while any input is unresolved
+    mass_limit: u64,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    input_amount: Option<u64>,
+    #[serde(serialize_with = "crate::units::sompi")]
+    output_amount: u64,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    fee: Option<u64>,
+    #[serde(serialize_with = "crate::units::sompi_opt")]
+    minimum_fee: Option<u64>,
+    issues: Vec<ValidationIssue>,
+}
+
+struct Issues(Vec<ValidationIssue>);
+
+impl Issues {
+    fn transaction(&mut self, code: &'static str, message: String) {
+        self.0.push(ValidationIssue {
+            code,
+            message,
+            input: None,
+            output: None,
+        });
+    }
+
+    fn input(&mut self, i: usize, code: &'static str, message: String) {
+        self.0.push(ValidationIssue {
+            code,
+            message,
+            input: Some(i),
+            output: None,
+        });
+    }
+
+    fn output(&mut self, i: usize, code: &'static str, message: String) {
+        self.0.push(ValidationIssue {
+            code,
+            message,
+            input: None,
+            output: Some(i),
+        });
+    }
+}
+
+/// P2PK (Schnorr), P2PK (ECDSA) and P2SH, the script classes kaspad relays.
+fn is_standard_script(script: &[u8]) -> bool {
+    match script.len() {
+        34 => script[0] == 0x20 && script[33] == 0xac,
+        35 => {
+            (script[0] == 0x21 && script[34] == 0xab)
+                || (script[0] == 0xaa && script[1] == 0x20 && script[34] == 0x87)
+        }
+        _ => false,
+    }
+}
+
+/// An output is dust when relaying it would cost more than a third of its value (kaspad's
+/// mempool rule, on consensus' serialized size of the output).
+fn is_dust(output: &TransactionOutput) -> bool {
+    let total_serialized_size = mass::transaction_output_estimated_serialized_size(output) + 148;
+    (output.value as u128 * 1000 / (3 * total_serialized_size as u128))
+        < MINIMUM_RELAY_TRANSACTION_FEE as u128
+}
+
+/// Minimum relay fee for `mass`, or None if it overflows.
+fn minimum_fee(mass: u64) -> Option<u64> {
+    Some(
+        (mass.checked_mul(MINIMUM_RELAY_TRANSACTION_FEE)? / 1000)
+            .max(MINIMUM_RELAY_TRANSACTION_FEE),
+    )
+}
+
+/// Sum of `amounts`, or None if it overflows.
+fn checked_sum(amounts: impl IntoIterator<Item = u64>) -> Option<u64> {
+    amounts
+        .into_iter()
+        .try_fold(0u64, |total, amount| total.checked_add(amount))
+}
+
+#[allow(clippy::result_large_err)] // Returned from the handler as-is
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, Response> {
+    hex::decode(value).map_err(|_| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("{} must be hex-encoded", field),
+        )
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/transaction/validate",
+    tag = "transactions",
+    request_body = ValidateTransactionRequest,
+    responses(
+        (status = 200, description = "Validation result; the transaction is not broadcast", body = ValidateTransactionResponse),
+        (status = 400, description = "Malformed transaction", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad or UTXO index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn validate_transaction(
+    State(state): State<AppState>,
+    Json(request): Json<ValidateTransactionRequest>,
+) -> Result<Json<ValidateTransactionResponse>, Response> {
+    if request.inputs.len() > MAX_INPUTS {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("At most {} inputs can be validated", MAX_INPUTS),
+        ));
+    }
+    let mut outpoints = Vec::with_capacity(request.inputs.len());
+    let mut signature_scripts = Vec::with_capacity(request.inputs.len());
+    let mut inputs = Vec::with_capacity(request.inputs.len());
+    for input in &request.inputs {
+        let transaction_id = input
+            .previous_outpoint
+            .transaction_id
+            .parse::<Hash>()
+            .map_err(|_| {
+                error_response(StatusCode::BAD_REQUEST, "Invalid outpoint transaction id")
+            })?;
+        outpoints.push((transaction_id.to_string(), input.previous_outpoint.index));
+        let signature_script = decode_hex("signature_script", &input.signature_script)?;
+        inputs.push(TransactionInput::new(
+            TransactionOutpoint::new(transaction_id, input.previous_outpoint.index),
+            signature_script.clone(),
+            input.sequence,
+            input.sig_op_count,
+        ));
+        signature_scripts.push(signature_script);
+    }
+    let mut outputs = Vec::with_capacity(request.outputs.len());
+    for output in &request.outputs {
+        let script = decode_hex("script", &output.script_public_key.script)?;
+        outputs.push(TransactionOutput::new(
+            output.amount,
+            ScriptPublicKey::new(
+                output.script_public_key.version,
+                ScriptVec::from_slice(&script),
+            ),
+        ));
+    }
+    let output_amount = checked_sum(request.outputs.iter().map(|o| o.amount))
+        .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Output amounts overflow"))?;
+    let payload = decode_hex("payload", &request.payload)?;
+    let subnetwork_id: [u8; SUBNETWORK_ID_SIZE] = match &request.subnetwork_id {
+        Some(id) => decode_hex("subnetwork_id", id)?
+            .as_slice()
+            .try_into()
+            .map_err(|_| {
+                error_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("subnetwork_id must be {} bytes", SUBNETWORK_ID_SIZE),
+                )
+            })?,
+        None => [0; SUBNETWORK_ID_SIZE],
+    };
+    let mut input_addresses = Vec::with_capacity(request.input_addresses.len());
+    for address in &request.input_addresses {
+        input_addresses.push(network::parse_address(&state, address)?);
+    }
+
+    let mut issues = Issues(Vec::new());
+
+    // Context-free checks.
+    if request.version > MAX_STANDARD_TRANSACTION_VERSION {
+        issues.transaction(
+            "non_standard_version",
+            format!(
+                "Version {} is above the maximum standard version {}",
+                request.version, MAX_STANDARD_TRANSACTION_VERSION
+            ),
+        );
+    }
+    if request.inputs.is_empty() {
+        issues.transaction("no_inputs", "Transaction has no inputs".to_string());
+    }
+    if request.outputs.is_empty() {
+        issues.transaction("no_outputs", "Transaction has no outputs".to_string());
+    }
+    if subnetwork_id.iter().any(|&b| b != 0) {
+        issues.transaction(
+            "non_native_subnetwork",
+            "Only native-subnetwork transactions are relayed".to_string(),
+        );
+    } else if request.gas != 0 {
+        issues.transaction(
+            "gas_not_allowed",
+            "Native-subnetwork transactions must have zero gas".to_string(),
+        );
+    }
+    let mut seen = HashSet::new();
+    for (i, (outpoint, signature_script)) in outpoints.iter().zip(&signature_scripts).enumerate() {
+        if !seen.insert(outpoint) {
+            issues.input(
+                i,
+                "duplicate_input",
+                format!("Outpoint {}:{} is spent twice", outpoint.0, outpoint.1),
+            );
+        }
+        if signature_script.is_empty() {
+            issues.input(
+                i,
+                "missing_signature",
+                "Signature script is empty".to_string(),
+            );
+        } else if signature_script.len() > MAX_STANDARD_SIGNATURE_SCRIPT_SIZE {
+            issues.input(
+                i,
+                "signature_script_too_large",
+                format!(
+                    "Signature script is {} bytes, above the standard {}",
+                    signature_script.len(),
+                    MAX_STANDARD_SIGNATURE_SCRIPT_SIZE
+                ),
+            );
+        }
+    }
+    for (i, output) in outputs.iter().enumerate() {
+        if output.script_public_key.version() > MAX_SCRIPT_PUBLIC_KEY_VERSION
+            || !is_standard_script(output.script_public_key.script())
+        {
+            issues.output(
+                i,
+                "non_standard_script",
+                "Script is not a standard pay-to-pubkey or pay-to-script-hash script".to_string(),
+            );
+        }
+        if is_dust(output) {
+            issues.output(
+                i,
+                "dust_output",
+                format!("Amount {} sompi is below the dust threshold", output.value),
+            );
+        }
+    }
+
+    let transaction = Transaction::new(
+        request.version,
+        inputs,
+        outputs,
+        request.lock_time,
+        SubnetworkId::from_bytes(subnetwork_id),
+        request.gas,
+        payload,
+    );
+    let calculator = MassCalculator::new_with_consensus_params(&state.network.params());
+    let compute_mass = calculator
+        .calc_non_contextual_masses(&transaction)
+        .compute_mass;
+    if compute_mass > MAXIMUM_STANDARD_TRANSACTION_MASS {
+        issues.transaction(
+            "mass_too_high",
+            format!(
+                "Compute mass {} is above the standard limit {}",
+                compute_mass, MAXIMUM_STANDARD_TRANSACTION_MASS
+            ),
+        );
+    }
+
+    // Inputs against the UTXO set and mempool.
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
+    let info = rpc::retried("get_info", || client.get_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get kaspad info before validation: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to query kaspad info",
+            )
+        })?;
+    if !info.is_utxo_indexed {
+        return Err(ApiError::UtxoIndexRequired(
+            "Transaction validation requires kaspad to run with --utxoindex".to_string(),
+        )
+        .into_response());
+    }
+
+    // A lock time only binds while some input has a non-final sequence number.
+    if request.lock_time != 0
+        && request
+            .inputs
+            .iter()
+            .any(|input| input.sequence != u64::MAX)
+    {
+        let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get DAG info for validation: {:?}", e);
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to query kaspad DAG info",
+                )
+            })?;
+        let (current, unit) = if request.lock_time < LOCK_TIME_THRESHOLD {
+            (dag_info.virtual_daa_score, "DAA score")
+        } else {
+            (dag_info.past_median_time, "past median time")
+        };
+        if request.lock_time >= current {
+            issues.transaction(
+                "not_final",
+                format!(
+                    "Lock time {} is not reached yet (current {} {})",
+                    request.lock_time, unit, current
+                ),
+            );
+        }
+    }
+
+    if let Some(index) = state
+        .index
+        .as_ref()
+        .filter(|i| i.network_mismatch().is_none())
+    {
+        match index.outputs(outpoints.clone()).await {
+            Ok(outputs) => {
+                for (address, _) in outputs.into_values() {
+                    if let Some(address) = address.and_then(|a| Address::try_from(a.as_str()).ok())
+                    {
+                        input_addresses.push(address);
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Index lookup for validation failed: {:?}", e),
+        }
+    }
+    input_addresses.sort_by_key(|address| address.to_string());
+    input_addresses.dedup();
+
+    let wanted: HashSet<&(String, u32)> = outpoints.iter().collect();
+    let mut available: HashMap<(String, u32), u64> = HashMap::new();
+    let mut spent_in_mempool: HashMap<(String, u32), String> = HashMap::new();
+    if !input_addresses.is_empty() {
+        let utxos = rpc::retried("get_utxos_by_addresses", || {
+            client.get_utxos_by_addresses(input_addresses.clone())
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch UTXOs for validation: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch UTXOs")
+        })?;
+        for utxo in utxos {
+            let outpoint = (
+                utxo.outpoint.transaction_id.to_string(),
+                utxo.outpoint.index,
+            );
+            if wanted.contains(&outpoint) {
+                available.insert(outpoint, utxo.utxo_entry.amount);
+            }
+        }
+
+        let entries = rpc::retried("get_mempool_entries_by_addresses", || {
+            client.get_mempool_entries_by_addresses(input_addresses.clone(), true, false)
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch mempool entries for validation: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch mempool entries",
+            )
+        })?;
+        for entry in entries
+            .iter()
+            .flat_map(|e| e.sending.iter().chain(&e.receiving))
+        {
+            let Some(id) = entry
+                .transaction
+                .verbose_data
+                .as_ref()
+                .map(|v| v.transaction_id.to_string())
+            else {
+                continue;
+            };
+            for input in &entry.transaction.inputs {
+                let outpoint = (
+                    input.previous_outpoint.transaction_id.to_string(),
+                    input.previous_outpoint.index,
+                );
+                if wanted.contains(&outpoint) {
+                    spent_in_mempool.insert(outpoint, id.clone());
+                }
+            }
+            // Unconfirmed outputs can be spent by a chained transaction.
+            for (i, output) in entry.transaction.outputs.iter().enumerate() {
+                let outpoint = (id.clone(), i as u32);
+                if wanted.contains(&outpoint) {
+                    available.entry(outpoint).or_insert(output.value);
+                }
+            }
+        }
+    }
+
+    let mut input_amounts = Vec::with_capacity(outpoints.len());
+    for (i, outpoint) in outpoints.iter().enumerate() {
+        if let Some(spender) = spent_in_mempool.get(outpoint) {
+            issues.input(
+                i,
+                "input_spent_in_mempool",
+                format!(
+                    "Outpoint {}:{} is already spent by mempool transaction {}",
+                    outpoint.0, outpoint.1, spender
+                ),
+            );
+        }
+        match available.get(outpoint) {
+            Some(&amount) => input_amounts.push(amount),
+            None => issues.input(
+                i,
+                "missing_input",
+                format!(
+                    "Outpoint {}:{} is not an unspent output (pass input_addresses if the index does not cover it)",
+                    outpoint.0, outpoint.1
+                ),
+            ),
+        }
+    }
+
+    let resolved = !outpoints.is_empty() && input_amounts.len() == outpoints.len();
+    let input_amount = if resolved {
+        Some(
+            checked_sum(input_amounts.iter().copied())
+                .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Input amounts overflow"))?,
+        )
+    } else {
+        None
+    };
+    let storage_mass = if resolved {
+        // Only the amounts of the spent outputs matter to storage mass.
+        let entries = input_amounts
+            .iter()
+            .map(|&amount| UtxoEntry::new(amount, ScriptPublicKey::from_vec(0, vec![]), 0, false))
+            .collect();
+        calculator
+            .calc_contextual_masses(&PopulatedTransaction::new(&transaction, entries))
+            .map(|masses| masses.storage_mass)
+    } else {
+        None
+    };
+    if let Some(storage_mass) = storage_mass.filter(|&m| m > MAXIMUM_STANDARD_TRANSACTION_MASS) {
+        issues.transaction(
+            "storage_mass_too_high",
+            format!(
+                "Storage mass {} is above the standard limit {}; avoid many small outputs",
+                storage_mass, MAXIMUM_STANDARD_TRANSACTION_MASS
+            ),
+        );
+    }
+
+    let mass = compute_mass.max(storage_mass.unwrap_or(0));
+    let minimum_fee = minimum_fee(mass)
+        .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Transaction mass overflows"))?;
+    let fee = match input_amount {
+        Some(input_amount) if input_amount < output_amount => {
+            issues.transaction(
+                "outputs_exceed_inputs",
+                format!(
+                    "Outputs spend {} sompi but inputs only provide {}",
+                    output_amount, input_amount
+                ),
+            );
+            None
+        }
+        Some(input_amount) => Some(input_amount - output_amount),
+        None => None,
+    };
+    if let Some(fee) = fee.filter(|&fee| fee < minimum_fee) {
+        issues.transaction(
+            "insufficient_fee",
+            format!(
+                "Fee {} sompi is below the minimum relay fee {}",
+                fee, minimum_fee
+            ),
+        );
+    }
+
+    Ok(Json(ValidateTransactionResponse {
+        valid: issues.0.is_empty(),
+        compute_mass,
+        storage_mass,
+        mass_limit: MAXIMUM_STANDARD_TRANSACTION_MASS,
+        input_amount,
+        output_amount,
+        fee,
+        minimum_fee: resolved.then_some(minimum_fee),
+        issues: issues.0,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(value: u64, script: Vec<u8>) -> TransactionOutput {
+        TransactionOutput::new(
+            value,
+            ScriptPublicKey::new(0, ScriptVec::from_slice(&script)),
+        )
+    }
+
+    fn p2pk() -> Vec<u8> {
+        let mut script = vec![0x20];
+        script.extend([1; 32]);
+        script.push(0xac);
+        script
+    }
+
+    #[test]
+    fn minimum_fee_scales_with_mass() {
+        assert_eq!(minimum_fee(0), Some(MINIMUM_RELAY_TRANSACTION_FEE));
+        assert_eq!(minimum_fee(999), Some(MINIMUM_RELAY_TRANSACTION_FEE));
+        assert_eq!(minimum_fee(2_500), Some(2_500));
+        assert_eq!(
+            minimum_fee(MAXIMUM_STANDARD_TRANSACTION_MASS),
+            Some(100_000)
+        );
+        assert_eq!(minimum_fee(u64::MAX), None);
+    }
+
+    #[test]
+    fn checked_sum_detects_overflow() {
+        assert_eq!(checked_sum([]), Some(0));
+        assert_eq!(checked_sum([1, 2, 3]), Some(6));
+        assert_eq!(checked_sum([u64::MAX, 0]), Some(u64::MAX));
+        assert_eq!(checked_sum([u64::MAX, 1]), None);
+    }
+
+    #[test]
+    fn dust_threshold_of_pay_to_pubkey() {
+        // 52 bytes of output plus the 148 of the input spending it: 600 sompi at the
+        // minimum relay fee.
+        assert!(is_dust(&output(599, p2pk())));
+        assert!(!is_dust(&output(600, p2pk())));
+        assert!(!is_dust(&output(u64::MAX, p2pk())));
+    }
+
+    #[test]
+    fn standard_script_classes() {
+        assert!(is_standard_script(&p2pk()));
+        let mut ecdsa = vec![0x21];
+        ecdsa.extend([2; 33]);
+        ecdsa.push(0xab);
+        assert!(is_standard_script(&ecdsa));
+        let mut p2sh = vec![0xaa, 0x20];
+        p2sh.extend([3; 32]);
+        p2sh.push(0x87);
+        assert!(is_standard_script(&p2sh));
+        assert!(!is_standard_script(&[]));
+        assert!(!is_standard_script(&ecdsa[..34]));
+        let mut wrong_opcode = p2pk();
+        wrong_opcode[33] = 0xab;
+        assert!(!is_standard_script(&wrong_opcode));
+    }
+}
@@ -0,0 +1,204 @@
+//! Payment verification for merchants.
+//!
+//! Answers "has this address been paid at least X since I showed the customer an invoice?".
+//! The invoice is identified by the address and the virtual DAA score when it was issued;
+//! outputs paying the address that kaspad's UTXO index records at a later DAA score count as
+//! payments. A UTXO's DAA score is that of the chain block that accepted its transaction, so
+//! confirmations are the DAA scores the virtual has advanced since (about 10 per second).
+//! Each transaction is a separate payment; several smaller ones do not add up to a paid
+//! invoice, but their total is reported. Mempool transactions paying the address are listed
+//! without confirmations. Outputs spent since are no longer in the UTXO set, so verify before
+//! sweeping the address.
+
+use crate::chain::ChainSource;
+use crate::{error_response, network, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VerifyPaymentRequest {
+    address: String,
+    amount: u64, // Sompi
+    // Virtual DAA score when the invoice was issued; only later payments count.
+    after_daa_score: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Payment {
+    transaction_id: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64, // Paid to the address by this transaction
+    daa_score: Option<u64>, // Of the accepting block; None while in the mempool
+    confirmations: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VerifyPaymentResponse {
+    address: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64,
+    after_daa_score: u64,
+    virtual_daa_score: u64,
+    // A single accepted transaction paid at least amount.
+    paid: bool,
+    // Not paid yet, but a mempool transaction paying at least amount is waiting.
+    pending: bool,
+    // The paying transaction with the most confirmations, when paid.
+    transaction_id: Option<String>,
+    confirmations: Option<u64>,
+    #[serde(serialize_with = "crate::units::sompi")]
+    received: u64, // By all accepted payments after after_daa_score
+    payments: Vec<Payment>, // Accepted ones oldest first, then mempool ones
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/verify-payment",
+    tag = "addresses",
+    request_body = VerifyPaymentRequest,
+    responses(
+        (status = 200, description = "Whether the address was paid at least amount after after_daa_score, with confirmations", body = VerifyPaymentResponse),
+        (status = 400, description = "Invalid address or zero amount", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad", body = ErrorResponse)
+    )
+)]
+pub async fn verify_payment(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyPaymentRequest>,
+) -> Result<Json<VerifyPaymentResponse>, Response> {
+    let address = network::parse_address(&state, request.address.trim())?;
+    if request.amount == 0 {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "amount must be positive",
+        ));
+    }
+
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
+    let dag_info = rpc::retried("get_block_dag_info", || client.get_block_dag_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get block DAG info: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get block DAG info",
+            )
+        })?;
+    let entries = rpc::retried("get_utxos_by_addresses", || {
+        client.get_utxos_by_addresses(vec![address.clone()])
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get UTXOs for address {}: {:?}", address, e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch UTXOs (is --utxoindex enabled?)",
+        )
+    })?;
+    let mempool = rpc::retried("get_mempool_entries_by_addresses", || {
+        client.get_mempool_entries_by_addresses(vec![address.clone()], true, false)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch mempool entries of {}: {:?}", address, e);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch mempool entries",
+        )
+    })?;
+    drop(client);
+
+    let virtual_daa_score = dag_info.virtual_daa_score;
+    // Outputs of one transaction to the address make up one payment.
+    let mut accepted: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.utxo_entry.block_daa_score > request.after_daa_score)
+    {
+        let payment = accepted
+            .entry(entry.outpoint.transaction_id.to_string())
+            .or_insert((entry.utxo_entry.block_daa_score, 0));
+        payment.1 += entry.utxo_entry.amount;
+    }
+    let mut payments: Vec<Payment> = accepted
+        .into_iter()
+        .map(|(transaction_id, (daa_score, amount))| Payment {
+            transaction_id,
+            amount,
+            daa_score: Some(daa_score),
+            confirmations: Some(virtual_daa_score.saturating_sub(daa_score)),
+        })
+        .collect();
+    payments.sort_by_key(|payment| payment.daa_score);
+
+    for entry in mempool.iter().filter(|entry| entry.address == address) {
+        // Change of the address's own sends is not a payment.
+        let sending: HashSet<_> = entry
+            .sending
+            .iter()
+            .filter_map(|sent| sent.transaction.verbose_data.as_ref())
+            .map(|v| v.transaction_id)
+            .collect();
+        for received in &entry.receiving {
+            let Some(transaction_id) = received
+                .transaction
+                .verbose_data
+                .as_ref()
+                .map(|v| v.transaction_id)
+                .filter(|id| !sending.contains(id))
+            else {
+                continue;
+            };
+            let amount = received
+                .transaction
+                .outputs
+                .iter()
+                .filter(|output| {
+                    output
+                        .verbose_data
+                        .as_ref()
+                        .is_some_and(|v| v.script_public_key_address == address)
+                })
+                .map(|output| output.value)
+                .sum();
+            payments.push(Payment {
+                transaction_id: transaction_id.to_string(),
+                amount,
+                daa_score: None,
+                confirmations: None,
+            });
+        }
+    }
+
+    let best = payments
+        .iter()
+        .filter(|payment| payment.daa_score.is_some() && payment.amount >= request.amount)
+        .max_by_key(|payment| payment.confirmations);
+    let pending = best.is_none()
+        && payments
+            .iter()
+            .any(|payment| payment.daa_score.is_none() && payment.amount >= request.amount);
+
+    Ok(Json(VerifyPaymentResponse {
+        address: address.to_string(),
+        amount: request.amount,
+        after_daa_score: request.after_daa_score,
+        virtual_daa_score,
+        paid: best.is_some(),
+        pending,
+        transaction_id: best.map(|payment| payment.transaction_id.clone()),
+        confirmations: best.and_then(|payment| payment.confirmations),
+        received: payments
+            .iter()
+            .filter(|payment| payment.daa_score.is_some())
+            .map(|payment| payment.amount)
+            .sum(),
+        payments,
+    }))
+}
@@ -0,0 +1,126 @@
+//! Current virtual block state, kept up to date from kaspad notifications.
+//!
+//! The virtual block is the closest thing a DAG has to a "current height": its DAA score,
+//! the blue score of its selected parent (the sink), and the tips it merges.
+
+use crate::chain::ChainSource;
+use crate::sampler::now_ms;
+use crate::errors::KASPAD_RETRY_AFTER;
+use crate::{retry_after_response, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_rpc_core::VirtualChainChangedNotification;
+use serde::Serialize;
+use std::time::Instant;
+use tokio::time::Duration;
+use utoipa::ToSchema;
+
+// Merged tips are not part of any notification; re-read them at most this often.
+const TIPS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct VirtualState {
+    daa_score: Option<u64>,
+    blue_score: Option<u64>,
+    selected_parent: Option<String>,
+    merged_tips: Vec<String>,
+    updated_at: u64, // Unix time in milliseconds
+    #[serde(skip)]
+    tips_refreshed_at: Option<Instant>,
+}
+
+impl VirtualState {
+    pub fn daa_score(&self) -> Option<u64> {
+        self.daa_score
+    }
+
+    pub fn blue_score(&self) -> Option<u64> {
+        self.blue_score
+    }
+}
+
+/// Initializes the state from RPC, so it is complete before the first notifications arrive.
+pub async fn seed(state: &AppState) {
+    refresh_tips(state).await;
+    let client_guard = state.client.read().await;
+    if let Some(client) = client_guard.as_ref() {
+        if let Ok(blue_score) = rpc::retried("get_sink_blue_score", || client.get_sink_blue_score()).await {
+            on_sink_blue_score_changed(state, blue_score).await;
+        }
+    }
+}
+
+pub async fn on_daa_score_changed(state: &AppState, daa_score: u64) {
+    let mut virtual_state = state.virtual_state.write().await;
+    virtual_state.daa_score = Some(daa_score);
+    virtual_state.updated_at = now_ms();
+}
+
+pub async fn on_sink_blue_score_changed(state: &AppState, blue_score: u64) {
+    let mut virtual_state = state.virtual_state.write().await;
+    virtual_state.blue_score = Some(blue_score);
+    virtual_state.updated_at = now_ms();
+}
+
+pub async fn on_chain_changed(state: &AppState, notification: &VirtualChainChangedNotification) {
+    {
+        let mut virtual_state = state.virtual_state.write().await;
+        if let Some(sink) = notification.added_chain_block_hashes.last() {
+            virtual_state.selected_parent = Some(sink.to_string());
+            virtual_state.updated_at = now_ms();
+        }
+        if virtual_state
+            .tips_refreshed_at
+            .is_some_and(|at| at.elapsed() < TIPS_REFRESH_INTERVAL)
+        {
+            return;
+        }
+    }
+    refresh_tips(state).await;
+}
+
+async fn refresh_tips(state: &AppState) {
+    let client_guard = state.client.read().await;
+    let Some(client) = client_guard.as_ref() else {
+        return;
+    };
+    match rpc::retried("get_block_dag_info", || client.get_block_dag_info()).await {
+        Ok(dag_info) => {
+            let mut virtual_state = state.virtual_state.write().await;
+            virtual_state.daa_score = Some(dag_info.virtual_daa_score);
+            virtual_state.selected_parent = Some(dag_info.sink.to_string());
+            virtual_state.merged_tips = dag_info
+                .virtual_parent_hashes
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            virtual_state.tips_refreshed_at = Some(Instant::now());
+            virtual_state.updated_at = now_ms();
+        }
+        Err(e) => tracing::warn!("Failed to refresh virtual parents: {:?}", e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/virtual",
+    tag = "network",
+    responses(
+        (status = 200, description = "Current virtual block state", body = VirtualState),
+        (status = 503, description = "Virtual state not available yet", body = ErrorResponse)
+    )
+)]
+pub async fn get_virtual(State(state): State<AppState>) -> Result<Json<VirtualState>, Response> {
+    let virtual_state = state.virtual_state.read().await.clone();
+    if virtual_state.daa_score.is_none() {
+        return Err(retry_after_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Virtual state not available yet",
+            KASPAD_RETRY_AFTER,
+        ));
+    }
+    Ok(Json(virtual_state))
+}
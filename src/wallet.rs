@@ -0,0 +1,344 @@
+//! Watch-only HD wallet scans.
+//!
+//! `POST /wallet/scan` takes an account-level extended public key (`kpub`/`ktub` as Kaspa
+//! wallets export them, or `xpub`/`tpub`), i.e. the key at `m/44'/111111'/<account>'`, and
+//! derives its receive (`0/i`) and change (`1/i`) addresses with kaspa-bip32's public
+//! derivation. The key must belong to the served network: `kpub`/`xpub` on mainnet,
+//! `ktub`/`tpub` on the testnets, devnet and simnet. Addresses are Schnorr (`PubKey`, the x-only key) unless `ecdsa` is set. Each chain is
+//! scanned in batches of `gap_limit` addresses until `gap_limit` consecutive addresses are
+//! unused; an address is used when it holds UTXOs or, with the local index, has any indexed
+//! activity. Balances and UTXOs come from kaspad's UTXO index. Extended private keys are
+//! refused rather than parsed.
+
+use crate::address_data::AddressUtxo;
+use crate::chain::{ChainSource, Client};
+use crate::errors::ApiError;
+use crate::network::Network;
+use crate::{error_response, not_connected, rpc, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::{Address, Version};
+use kaspa_bip32::{ChildNumber, ExtendedKey, Prefix as KeyPrefix};
+use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+const DEFAULT_GAP_LIMIT: u32 = 20;
+const MAX_GAP_LIMIT: u32 = 100;
+// Addresses derived per chain at most; bounds the kaspad and index work of one scan.
+const MAX_ADDRESSES_PER_CHAIN: u32 = 1_000;
+
+type ExtendedPublicKey = kaspa_bip32::ExtendedPublicKey<PublicKey>;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WalletScanRequest {
+    xpub: String, // Account-level extended public key
+    #[serde(default = "default_gap_limit")]
+    gap_limit: u32, // Consecutive unused addresses that end a chain (default 20, max 100)
+    #[serde(default)]
+    ecdsa: bool, // Derive PubKeyECDSA addresses instead of Schnorr ones
+    #[serde(default = "default_change")]
+    change: bool, // Scan the change chain as well
+    #[serde(default)]
+    include_utxos: bool,
+}
+
+fn default_gap_limit() -> u32 {
+    DEFAULT_GAP_LIMIT
+}
+
+fn default_change() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Chain {
+    Receive,
+    Change,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WalletAddress {
+    address: String,
+    chain: Chain,
+    index: u32,
+    path: String, // Relative to the extended key, e.g. "0/3"
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    utxo_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    utxos: Option<Vec<AddressUtxo>>, // With include_utxos
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WalletScanResponse {
+    #[serde(serialize_with = "crate::units::sompi")]
+    balance: u64,
+    utxo_count: usize,
+    receive_addresses_scanned: u32,
+    change_addresses_scanned: u32,
+    next_receive_address: Option<String>, // First receive address after the last used one
+    next_change_address: Option<String>,
+    truncated: bool, // A chain reached the derivation limit before its gap
+    addresses: Vec<WalletAddress>, // Used addresses only, receive chain first
+}
+
+/// Extended public key prefixes accepted on `network`.
+fn key_prefixes(network: Network) -> [KeyPrefix; 2] {
+    match network {
+        Network::Mainnet => [KeyPrefix::KPUB, KeyPrefix::XPUB],
+        _ => [KeyPrefix::KTUB, KeyPrefix::TPUB],
+    }
+}
+
+/// Parses an account-level extended public key of `network`.
+fn parse_key(encoded: &str, network: Network) -> Result<ExtendedPublicKey, String> {
+    let key: ExtendedKey = encoded
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid extended key: {}", e))?;
+    if key.prefix.is_private() {
+        return Err("Extended private keys are refused; send the public key".to_string());
+    }
+    let prefixes = key_prefixes(network);
+    if !prefixes.contains(&key.prefix) {
+        return Err(format!(
+            "{} keys are not {} keys; send a {} or {} key",
+            key.prefix.as_str(),
+            network.name(),
+            prefixes[0].as_str(),
+            prefixes[1].as_str()
+        ));
+    }
+    ExtendedPublicKey::try_from(key).map_err(|e| format!("Invalid extended key: {}", e))
+}
+
+/// Non-hardened child `index` of `key`.
+fn child(key: &ExtendedPublicKey, index: u32) -> Result<ExtendedPublicKey, String> {
+    // Invalid children occur with probability below 2^-127.
+    ChildNumber::new(index, false)
+        .and_then(|number| key.derive_child(number))
+        .map_err(|_| format!("Child {} of the extended key is invalid", index))
+}
+
+fn address(key: &ExtendedPublicKey, network: Network, ecdsa: bool) -> Address {
+    let public_key = key.public_key();
+    if ecdsa {
+        Address::new(
+            network.prefix(),
+            Version::PubKeyECDSA,
+            &public_key.serialize(),
+        )
+    } else {
+        let (x_only, _) = public_key.x_only_public_key();
+        Address::new(network.prefix(), Version::PubKey, &x_only.serialize())
+    }
+}
+
+struct ChainScan {
+    used: Vec<WalletAddress>,
+    scanned: u32,
+    next_address: Option<String>,
+    truncated: bool,
+}
+
+async fn scan_chain(
+    state: &AppState,
+    client: &Client,
+    account: &ExtendedPublicKey,
+    chain: Chain,
+    request: &WalletScanRequest,
+) -> Result<ChainScan, Response> {
+    let chain_number = match chain {
+        Chain::Receive => 0,
+        Chain::Change => 1,
+    };
+    let bad_key = |e: String| error_response(StatusCode::BAD_REQUEST, &e);
+    let chain_key = child(account, chain_number).map_err(bad_key)?;
+
+    let mut used = Vec::new();
+    let mut next_index = 0;
+    let mut unused_run = 0;
+    let mut next_address = None;
+    while unused_run < request.gap_limit && next_index < MAX_ADDRESSES_PER_CHAIN {
+        let batch_end = (next_index + request.gap_limit).min(MAX_ADDRESSES_PER_CHAIN);
+        let mut batch = Vec::with_capacity((batch_end - next_index) as usize);
+        for index in next_index..batch_end {
+            let key = child(&chain_key, index).map_err(bad_key)?;
+            batch.push((index, address(&key, state.network, request.ecdsa)));
+        }
+        next_index = batch_end;
+
+        let addresses: Vec<Address> = batch.iter().map(|(_, address)| address.clone()).collect();
+        let entries = rpc::retried("get_utxos_by_addresses", || {
+            client.get_utxos_by_addresses(addresses.clone())
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch wallet UTXOs: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch UTXOs")
+        })?;
+        let mut utxos: HashMap<String, Vec<AddressUtxo>> = HashMap::new();
+        for entry in entries {
+            if let Some(address) = &entry.address {
+                utxos
+                    .entry(address.to_string())
+                    .or_default()
+                    .push(AddressUtxo::from(entry));
+            }
+        }
+
+        for (index, address) in batch {
+            if unused_run >= request.gap_limit {
+                break;
+            }
+            let address = address.to_string();
+            let address_utxos = utxos.remove(&address).unwrap_or_default();
+            let has_history = match (&state.index, address_utxos.is_empty()) {
+                (Some(index), true) => index
+                    .address_activity(address.clone())
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Index lookup of address {} failed: {:?}", address, e);
+                        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Index lookup failed")
+                    })?
+                    .is_some(),
+                _ => false,
+            };
+            if address_utxos.is_empty() && !has_history {
+                if unused_run == 0 {
+                    next_address = Some(address);
+                }
+                unused_run += 1;
+                continue;
+            }
+            unused_run = 0;
+            next_address = None;
+            used.push(WalletAddress {
+                balance: address_utxos.iter().map(|utxo| utxo.amount()).sum(),
+                utxo_count: address_utxos.len(),
+                utxos: request.include_utxos.then_some(address_utxos),
+                path: format!("{}/{}", chain_number, index),
+                address,
+                chain,
+                index,
+            });
+        }
+    }
+
+    let scanned = used.last().map_or(0, |last| last.index + 1) + unused_run;
+    Ok(ChainScan {
+        used,
+        scanned,
+        next_address,
+        truncated: unused_run < request.gap_limit,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/wallet/scan",
+    tag = "addresses",
+    request_body = WalletScanRequest,
+    responses(
+        (status = 200, description = "Balance of the wallet and its used addresses", body = WalletScanResponse),
+        (status = 400, description = "Invalid or private extended key, key of another network, or gap limit out of range", body = ErrorResponse),
+        (status = 503, description = "Not connected to kaspad or UTXO index disabled", body = ErrorResponse)
+    )
+)]
+pub async fn scan_wallet(
+    State(state): State<AppState>,
+    Json(request): Json<WalletScanRequest>,
+) -> Result<Json<WalletScanResponse>, Response> {
+    if request.gap_limit == 0 || request.gap_limit > MAX_GAP_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("gap_limit must be between 1 and {}", MAX_GAP_LIMIT),
+        ));
+    }
+    let account = parse_key(&request.xpub, state.network)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, &e))?;
+
+    let client = rpc::checkout(&state).await.ok_or_else(not_connected)?;
+    let info = rpc::retried("get_info", || client.get_info())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get kaspad info before wallet scan: {:?}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to query kaspad info",
+            )
+        })?;
+    if !info.is_utxo_indexed {
+        return Err(ApiError::UtxoIndexRequired(
+            "Wallet scans require kaspad to run with --utxoindex".to_string(),
+        )
+        .into_response());
+    }
+
+    let receive = scan_chain(&state, &client, &account, Chain::Receive, &request).await?;
+    let change = if request.change {
+        Some(scan_chain(&state, &client, &account, Chain::Change, &request).await?)
+    } else {
+        None
+    };
+
+    let mut addresses = receive.used;
+    let mut truncated = receive.truncated;
+    let mut change_addresses_scanned = 0;
+    let mut next_change_address = None;
+    if let Some(change) = change {
+        addresses.extend(change.used);
+        truncated |= change.truncated;
+        change_addresses_scanned = change.scanned;
+        next_change_address = change.next_address;
+    }
+    Ok(Json(WalletScanResponse {
+        balance: addresses.iter().map(|address| address.balance).sum(),
+        utxo_count: addresses.iter().map(|address| address.utxo_count).sum(),
+        receive_addresses_scanned: receive.scanned,
+        change_addresses_scanned,
+        next_receive_address: receive.next_address,
+        next_change_address,
+        truncated,
+        addresses,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP32 test vector 1's m/0H key, re-encoded with the kpub version bytes.
+    const KPUB: &str = "kpub2EHcK5Be8WCqCwMydYJgg99v6TxXRPn66GbtAAoArLo6ZyUQycFz3vVS5pCuCfoKRL5nsxJXxLx3FETEyKyEb8isTgM3NbL15KsprxXRXYP";
+
+    #[test]
+    fn derives_addresses_from_kpub() {
+        let account = parse_key(KPUB, Network::Mainnet).unwrap();
+        let change = child(&account, 1).unwrap();
+        // m/0H/1 of the test vector.
+        assert_eq!(
+            hex::encode(change.public_key().serialize()),
+            "03501e454bf00751f24b1b489aa925215d66af2234e3891c3b21a52bedb3cd711c"
+        );
+        let first = child(&change, 0).unwrap();
+        assert_eq!(
+            address(&first, Network::Mainnet, false).to_string(),
+            "kaspa:qrss7ncq8vmwslq8plx62gqmkhel3f9f2dlc20364jjn5383v6mrql87zd595"
+        );
+        assert_eq!(
+            address(&first, Network::Mainnet, true).to_string(),
+            "kaspa:qyp7zr60qqand6ruqu8umffqrw6l879y49fhlpf782k22wjy79ntvvq65rwwtsd"
+        );
+    }
+
+    #[test]
+    fn refuses_keys_of_another_network() {
+        assert!(parse_key(KPUB, Network::Testnet12).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+//! Address and transaction watches with webhook delivery.
+//!
+//! `POST /watch` registers addresses and/or transaction ids with a callback URL and returns
+//! the watch id and a secret, shown once. Watched addresses are added to the kaspad
+//! utxos-changed subscription, and every notification touching one is POSTed to the callback
+//! as a `balance_changed` event. A watched transaction is reported once, as
+//! `transaction_confirmed`, when a virtual-chain-changed notification first lists it among
+//! the transactions a chain block accepted (being in a block is not enough, see
+//! `acceptance.rs`), and is then dropped from the watch.
+//!
+//! Deliveries go through an `events::Sink` webhook, so they are retried with backoff and
+//! signed with the secret (see `events.rs`). Callback URLs must resolve to public addresses,
+//! both when the watch is registered and when an event is sent. Watches are stored in the
+//! index database and reloaded on startup; the secret also authenticates `GET` and `DELETE`
+//! in `x-api-key`.
+
+use crate::chain::{ChainSource, Client};
+use crate::events::{self, BalanceChangedEvent, Event, Sink, TransactionConfirmedEvent};
+use crate::index::StoredWatch;
+use crate::{error_response, network, rpc, AppState};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use kaspa_addresses::Address;
+use kaspa_hashes::Hash;
+use kaspa_notify::listener::ListenerId;
+use kaspa_notify::scope::{Scope, UtxosChangedScope};
+use kaspa_rpc_core::{UtxosChangedNotification, VirtualChainChangedNotification};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+const MAX_WATCHES: usize = 1_000;
+const MAX_WATCH_ADDRESSES: usize = 100;
+const MAX_WATCH_TRANSACTIONS: usize = 100;
+const API_KEY_HEADER: &str = "x-api-key";
+
+pub type Watches = Arc<RwLock<HashMap<String, Arc<Watch>>>>;
+
+pub struct Watch {
+    id: String,
+    secret: String,
+    callback_url: String,
+    addresses: HashSet<String>,
+    // Not yet accepted; confirmed ids are removed.
+    transaction_ids: Mutex<HashSet<String>>,
+    sink: Sink,
+}
+
+impl Watch {
+    fn new(
+        id: String,
+        secret: String,
+        callback_url: String,
+        addresses: HashSet<String>,
+        transaction_ids: HashSet<String>,
+    ) -> anyhow::Result<Self> {
+        let sink = Sink::signed_webhook(callback_url.clone(), secret.as_bytes().to_vec())?;
+        Ok(Watch {
+            id,
+            secret,
+            callback_url,
+            addresses,
+            transaction_ids: Mutex::new(transaction_ids),
+            sink,
+        })
+    }
+
+    fn pending_transactions(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.transaction_ids
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn info(&self) -> WatchInfo {
+        let mut addresses: Vec<String> = self.addresses.iter().cloned().collect();
+        addresses.sort();
+        let mut transaction_ids: Vec<String> =
+            self.pending_transactions().iter().cloned().collect();
+        transaction_ids.sort();
+        WatchInfo {
+            id: self.id.clone(),
+            callback_url: self.callback_url.clone(),
+            addresses,
+            transaction_ids,
+            secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WatchRequest {
+    callback_url: String, // http(s) URL the events are POSTed to
+    #[serde(default)]
+    addresses: Vec<String>,
+    #[serde(default)]
+    transaction_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WatchInfo {
+    id: String,
+    callback_url: String,
+    addresses: Vec<String>,
+    transaction_ids: Vec<String>, // Not yet confirmed
+    // Webhook signing key and API key of a new watch; shown only once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+}
+
+/// Loads the stored watches into `state`. Watches that no longer parse are skipped.
+pub async fn load(state: &AppState) {
+    let Some(index) = &state.index else {
+        return;
+    };
+    let stored = match index.watches().await {
+        Ok(stored) => stored,
+        Err(e) => {
+            tracing::warn!("Failed to load watches: {:?}", e);
+            return;
+        }
+    };
+    let mut watches = state.watches.write().await;
+    for stored in stored {
+        let lists =
+            serde_json::from_str::<HashSet<String>>(&stored.addresses).and_then(|addresses| {
+                serde_json::from_str::<HashSet<String>>(&stored.transaction_ids)
+                    .map(|transaction_ids| (addresses, transaction_ids))
+            });
+        let watch = lists
+            .map_err(anyhow::Error::from)
+            .and_then(|(addresses, transaction_ids)| {
+                Watch::new(
+                    stored.id.clone(),
+                    stored.secret,
+                    stored.callback_url,
+                    addresses,
+                    transaction_ids,
+                )
+            });
+        match watch {
+            Ok(watch) => {
+                watches.insert(stored.id, Arc::new(watch));
+            }
+            Err(e) => tracing::warn!("Skipping stored watch {}: {:?}", stored.id, e),
+        }
+    }
+    if !watches.is_empty() {
+        tracing::info!("Loaded {} watch(es)", watches.len());
+    }
+}
+
+/// Addresses of all watches, for the utxos-changed subscription.
+pub async fn watched_addresses(state: &AppState) -> Vec<Address> {
+    let watches = state.watches.read().await;
+    let addresses: HashSet<&String> = watches.values().flat_map(|w| &w.addresses).collect();
+    addresses
+        .into_iter()
+        .filter_map(|address| Address::try_from(address.as_str()).ok())
+        .collect()
+}
+
+/// Adds `addresses` to the utxos-changed subscription. Needs kaspad's `--utxoindex`; a
+/// failure is logged and leaves the other subscriptions alone.
+pub async fn subscribe(state: &AppState, client: &Client, addresses: Vec<Address>) {
+    // An empty list would subscribe to every address; the rich list already does.
+    if addresses.is_empty() || state.config.index.rich_list {
+        return;
+    }
+    let scope = Scope::UtxosChanged(UtxosChangedScope::new(addresses));
+    if let Err(e) = client.start_notify(ListenerId::default(), scope).await {
+        tracing::warn!(
+            "Failed to subscribe to utxos-changed notifications: {:?}",
+            e
+        );
+    }
+}
+
+fn deliver(state: &AppState, watch: Arc<Watch>, event: Event) {
+    state.shutdown.clone().spawn(async move {
+        if let Err(e) = watch.sink.emit(&event).await {
+            tracing::warn!("Watch {}: {}", watch.id, e);
+        }
+    });
+}
+
+pub async fn on_utxos_changed(state: &AppState, notification: &UtxosChangedNotification) {
+    let watches = state.watches.read().await;
+    if watches.is_empty() {
+        return;
+    }
+    // Per address: (received, spent, UTXOs added, UTXOs removed, creating transactions).
+    let mut changes: BTreeMap<String, (u64, u64, usize, usize, Vec<String>)> = BTreeMap::new();
+    for (entry, added) in notification
+        .added
+        .iter()
+        .map(|entry| (entry, true))
+        .chain(notification.removed.iter().map(|entry| (entry, false)))
+    {
+        let Some(address) = &entry.address else {
+            continue;
+        };
+        let change = changes.entry(address.to_string()).or_default();
+        if added {
+            change.0 += entry.utxo_entry.amount;
+            change.2 += 1;
+            let id = entry.outpoint.transaction_id.to_string();
+            if !change.4.contains(&id) {
+                change.4.push(id);
+            }
+        } else {
+            change.1 += entry.utxo_entry.amount;
+            change.3 += 1;
+        }
+    }
+    for watch in watches.values() {
+        for (address, change) in &changes {
+            if !watch.addresses.contains(address) {
+                continue;
+            }
+            let event = Event::BalanceChanged(BalanceChangedEvent {
+                watch_id: watch.id.clone(),
+                address: address.clone(),
+                received: change.0,
+                spent: change.1,
+                utxos_added: change.2,
+                utxos_removed: change.3,
+                transaction_ids: change.4.clone(),
+            });
+            deliver(state, watch.clone(), event);
+        }
+    }
+}
+
+/// Daa score and timestamp of the accepting block `hash`, for the confirmation event.
+async fn accepting_header(state: &AppState, hash: Hash) -> Option<(u64, u64)> {
+    let client_guard = state.client.read().await;
+    let client = client_guard.as_ref()?;
+    match rpc::retried("get_block", || client.get_block(hash, false)).await {
+        Ok(block) => Some((block.header.daa_score, block.header.timestamp)),
+        Err(e) => {
+            tracing::warn!("Failed to fetch accepting block {}: {:?}", hash, e);
+            None
+        }
+    }
+}
+
+pub async fn on_chain_changed(state: &AppState, notification: &VirtualChainChangedNotification) {
+    let watches = state.watches.read().await;
+    if watches.is_empty() {
+        return;
+    }
+    let accepted: HashMap<String, Hash> = notification
+        .accepted_transaction_ids
+        .iter()
+        .flat_map(|accepted| {
+            accepted
+                .accepted_transaction_ids
+                .iter()
+                .map(|id| (id.to_string(), accepted.accepting_block_hash))
+        })
+        .collect();
+    if accepted.is_empty() {
+        return;
+    }
+    let mut headers: HashMap<Hash, Option<(u64, u64)>> = HashMap::new();
+    for watch in watches.values() {
+        let (confirmed, remaining) = {
+            let mut pending = watch.pending_transactions();
+            let confirmed: Vec<(String, Hash)> = pending
+                .iter()
+                .filter_map(|id| Some((id.clone(), *accepted.get(id)?)))
+                .collect();
+            for (id, _) in &confirmed {
+                pending.remove(id);
+            }
+            (confirmed, pending.clone())
+        };
+        if confirmed.is_empty() {
+            continue;
+        }
+        for (transaction_id, block_hash) in confirmed {
+            let header = match headers.get(&block_hash) {
+                Some(header) => *header,
+                None => {
+                    let header = accepting_header(state, block_hash).await;
+                    headers.insert(block_hash, header);
+                    header
+                }
+            };
+            let event = Event::TransactionConfirmed(TransactionConfirmedEvent {
+                watch_id: watch.id.clone(),
+                transaction_id,
+                block_hash: block_hash.to_string(),
+                daa_score: header.map(|(daa_score, _)| daa_score),
+                timestamp: header.map(|(_, timestamp)| timestamp),
+            });
+            deliver(state, watch.clone(), event);
+        }
+        if let Some(index) = &state.index {
+            let remaining = serde_json::to_string(&remaining).unwrap_or_else(|_| "[]".to_string());
+            if let Err(e) = index
+                .update_watch_transactions(watch.id.clone(), remaining)
+                .await
+            {
+                tracing::warn!("Failed to update watch {}: {:?}", watch.id, e);
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/watch",
+    tag = "addresses",
+    request_body = WatchRequest,
+    responses(
+        (status = 201, description = "Watch created; the secret is shown only once", body = WatchInfo),
+        (status = 400, description = "Invalid or non-public callback URL, invalid address or transaction id, or nothing to watch", body = ErrorResponse),
+        (status = 503, description = "Index disabled, or too many watches", body = ErrorResponse)
+    )
+)]
+pub async fn post_watch(
+    State(state): State<AppState>,
+    Json(request): Json<WatchRequest>,
+) -> Result<Response, Response> {
+    let url = reqwest::Url::parse(&request.callback_url)
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Invalid callback_url"))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "callback_url must be an http or https URL",
+        ));
+    }
+    if let Err(e) = events::ensure_public(&url).await {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("callback_url must be a public address: {}", e),
+        ));
+    }
+    if request.addresses.is_empty() && request.transaction_ids.is_empty() {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "A watch needs at least one address or transaction id",
+        ));
+    }
+    if request.addresses.len() > MAX_WATCH_ADDRESSES
+        || request.transaction_ids.len() > MAX_WATCH_TRANSACTIONS
+    {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "A watch holds up to {} addresses and {} transaction ids",
+                MAX_WATCH_ADDRESSES, MAX_WATCH_TRANSACTIONS
+            ),
+        ));
+    }
+    let mut addresses = Vec::new();
+    let mut address_set = HashSet::new();
+    for address in &request.addresses {
+        let parsed = network::parse_address(&state, address.trim())?;
+        if address_set.insert(parsed.to_string()) {
+            addresses.push(parsed);
+        }
+    }
+    let mut transaction_ids = HashSet::new();
+    for id in &request.transaction_ids {
+        let parsed = id.trim().parse::<Hash>().map_err(|_| {
+            error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid transaction id: {}", id),
+            )
+        })?;
+        transaction_ids.insert(parsed.to_string());
+    }
+
+    let index = state.index.clone().ok_or_else(|| {
+        error_response(StatusCode::SERVICE_UNAVAILABLE, "Watches require the index")
+    })?;
+    if state.watches.read().await.len() >= MAX_WATCHES {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many watches registered",
+        ));
+    }
+
+    let mut id = [0u8; 16];
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut id);
+    rand::thread_rng().fill_bytes(&mut secret);
+    let (id, secret) = (hex::encode(id), hex::encode(secret));
+    let failed = |e: anyhow::Error| {
+        tracing::error!("Failed to save watch: {:?}", e);
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save watch")
+    };
+    let watch = Watch::new(
+        id.clone(),
+        secret.clone(),
+        url.to_string(),
+        address_set,
+        transaction_ids,
+    )
+    .map_err(failed)?;
+    let mut info = watch.info();
+    index
+        .save_watch(StoredWatch {
+            id: id.clone(),
+            callback_url: watch.callback_url.clone(),
+            secret: secret.clone(),
+            addresses: serde_json::to_string(&info.addresses).map_err(|e| failed(e.into()))?,
+            transaction_ids: serde_json::to_string(&info.transaction_ids)
+                .map_err(|e| failed(e.into()))?,
+        })
+        .await
+        .map_err(failed)?;
+    state.watches.write().await.insert(id, Arc::new(watch));
+
+    if let Some(client) = state.client.read().await.as_ref() {
+        subscribe(&state, client, addresses).await;
+    }
+    info.secret = Some(secret);
+    Ok((StatusCode::CREATED, Json(info)).into_response())
+}
+
+/// The watch `id`, if `x-api-key` holds its secret.
+#[allow(clippy::result_large_err)] // Handlers return the error response as-is
+fn authorize(
+    watches: &HashMap<String, Arc<Watch>>,
+    id: &str,
+    headers: &HeaderMap,
+) -> Result<Arc<Watch>, Response> {
+    let key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| error_response(StatusCode::UNAUTHORIZED, "Missing x-api-key header"))?;
+    let watch = watches
+        .get(id)
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Watch not found"))?;
+    // Digests are compared so the comparison time does not depend on the secret.
+    if Sha256::digest(key.trim().as_bytes()) != Sha256::digest(watch.secret.as_bytes()) {
+        return Err(error_response(StatusCode::UNAUTHORIZED, "Invalid API key"));
+    }
+    Ok(watch.clone())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/watch/{id}",
+    tag = "addresses",
+    params(
+        ("id" = String, Path, description = "Watch id"),
+        ("x-api-key" = String, Header, description = "Secret returned when the watch was created")
+    ),
+    responses(
+        (status = 200, description = "The watch and its unconfirmed transaction ids", body = WatchInfo),
+        (status = 401, description = "Missing or wrong API key", body = ErrorResponse),
+        (status = 404, description = "Watch not found", body = ErrorResponse)
+    )
+)]
+pub async fn get_watch(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<WatchInfo>, Response> {
+    let watches = state.watches.read().await;
+    Ok(Json(authorize(&watches, &id, &headers)?.info()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/watch/{id}",
+    tag = "addresses",
+    params(
+        ("id" = String, Path, description = "Watch id"),
+        ("x-api-key" = String, Header, description = "Secret returned when the watch was created")
+    ),
+    responses(
+        (status = 204, description = "Watch removed"),
+        (status = 401, description = "Missing or wrong API key", body = ErrorResponse),
+        (status = 404, description = "Watch not found", body = ErrorResponse)
+    )
+)]
+pub async fn delete_watch(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, Response> {
+    let mut watches = state.watches.write().await;
+    authorize(&watches, &id, &headers)?;
+    if let Some(index) = &state.index {
+        index.delete_watch(id.clone()).await.map_err(|e| {
+            tracing::error!("Failed to delete watch {}: {:?}", id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete watch")
+        })?;
+    }
+    watches.remove(&id);
+    // The addresses stay in kaspad's subscription until the next resubscribe; their
+    // notifications no longer match a watch.
+    Ok(StatusCode::NO_CONTENT)
+}
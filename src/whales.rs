@@ -0,0 +1,217 @@
+//! Largest recent transactions, for spotting big transfers during a test.
+//!
+//! Every transaction whose outputs add up to at least `[whales] threshold` is kept for
+//! `window_secs` after it was first seen, whether that was in a block-added notification or
+//! in a mempool poll (see `mempool_history.rs`). A mempool transaction gets its block hash
+//! once a block including it arrives. Beyond `max_entries` the smallest are dropped. Each
+//! transaction is announced once, when first seen, as a `whale` event on the event stream;
+//! the last `MAX_ANNOUNCED` announced ids are remembered, so a dropped transaction still in
+//! the mempool is neither kept again nor announced again. Coinbase transactions are not
+//! counted.
+
+use crate::sampler::now_ms;
+use crate::sse::{self, StreamEvent};
+use crate::{error_response, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Response},
+};
+use kaspa_rpc_core::{BlockAddedNotification, RpcMempoolEntry, RpcTransaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1_000;
+// Announced transaction ids remembered, oldest forgotten first.
+const MAX_ANNOUNCED: usize = 10_000;
+
+pub type WhalesHandle = Arc<RwLock<Whales>>;
+
+#[derive(Debug, Default)]
+pub struct Whales {
+    transactions: HashMap<String, WhaleTransaction>,
+    announced: HashSet<String>,
+    announced_order: VecDeque<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WhaleTransaction {
+    transaction_id: String,
+    #[serde(serialize_with = "crate::units::sompi")]
+    amount: u64, // Sum of the outputs
+    output_count: usize,
+    // Receiver of the largest output, if it pays a standard script.
+    largest_output_address: Option<String>,
+    first_seen_at: u64,         // Unix ms
+    block_hash: Option<String>, // None while only seen in the mempool
+}
+
+impl Whales {
+    /// Records `tx` if it is large enough; returns it when it was not announced before.
+    fn record(
+        &mut self,
+        state: &AppState,
+        tx: &RpcTransaction,
+        block_hash: Option<String>,
+        now: u64,
+    ) -> Option<WhaleTransaction> {
+        let amount: u64 = tx.outputs.iter().map(|output| output.value).sum();
+        if amount < state.config.whales.threshold {
+            return None;
+        }
+        let transaction_id = tx.verbose_data.as_ref()?.transaction_id.to_string();
+        if let Some(known) = self.transactions.get_mut(&transaction_id) {
+            if known.block_hash.is_none() {
+                known.block_hash = block_hash;
+            }
+            return None;
+        }
+        if !self.announced.insert(transaction_id.clone()) {
+            return None;
+        }
+        self.announced_order.push_back(transaction_id.clone());
+        if self.announced_order.len() > MAX_ANNOUNCED {
+            if let Some(oldest) = self.announced_order.pop_front() {
+                self.announced.remove(&oldest);
+            }
+        }
+        let whale = WhaleTransaction {
+            transaction_id: transaction_id.clone(),
+            amount,
+            output_count: tx.outputs.len(),
+            largest_output_address: tx
+                .outputs
+                .iter()
+                .max_by_key(|output| output.value)
+                .and_then(|output| output.verbose_data.as_ref())
+                .map(|v| v.script_public_key_address.to_string()),
+            first_seen_at: now,
+            block_hash,
+        };
+        self.transactions.insert(transaction_id, whale.clone());
+        Some(whale)
+    }
+
+    /// Drops transactions older than the window, then the smallest beyond `max_entries`.
+    fn trim(&mut self, state: &AppState, now: u64) {
+        let config = &state.config.whales;
+        let since = now.saturating_sub(config.window_secs * 1000);
+        self.transactions
+            .retain(|_, whale| whale.first_seen_at >= since);
+        let max_entries = config.max_entries.max(1);
+        if self.transactions.len() > max_entries {
+            let mut amounts: Vec<u64> = self.transactions.values().map(|w| w.amount).collect();
+            amounts.sort_unstable_by(|a, b| b.cmp(a));
+            let smallest_kept = amounts[max_entries - 1];
+            self.transactions
+                .retain(|_, whale| whale.amount >= smallest_kept);
+        }
+    }
+}
+
+async fn record_all<'a>(
+    state: &AppState,
+    transactions: impl Iterator<Item = &'a RpcTransaction>,
+    block_hash: Option<String>,
+) {
+    let now = now_ms();
+    let found: Vec<WhaleTransaction> = {
+        let mut whales = state.whales.write().await;
+        let found = transactions
+            .filter_map(|tx| whales.record(state, tx, block_hash.clone(), now))
+            .collect();
+        whales.trim(state, now);
+        found
+    };
+    for whale in found {
+        tracing::info!(
+            "Large transaction {} moves {} sompi",
+            whale.transaction_id,
+            whale.amount
+        );
+        sse::publish(state, || StreamEvent::Whale(whale));
+    }
+}
+
+pub async fn on_block_added(state: &AppState, notification: &BlockAddedNotification) {
+    if !state.config.features.analytics {
+        return;
+    }
+    let block = &notification.block;
+    // The first transaction is the coinbase.
+    record_all(
+        state,
+        block.transactions.iter().skip(1),
+        Some(block.header.hash.to_string()),
+    )
+    .await;
+}
+
+/// Called with every mempool snapshot.
+pub async fn on_mempool(state: &AppState, entries: &[RpcMempoolEntry]) {
+    record_all(state, entries.iter().map(|entry| &entry.transaction), None).await;
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WhalesQuery {
+    /// Transactions returned (default 100, max 1000)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WhalesResponse {
+    #[serde(serialize_with = "crate::units::sompi")]
+    threshold: u64,
+    window_secs: u64,
+    transactions: Vec<WhaleTransaction>, // Largest first
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/whales",
+    tag = "stats",
+    params(WhalesQuery),
+    responses(
+        (status = 200, description = "Largest transactions seen in recent blocks and the mempool", body = WhalesResponse),
+        (status = 400, description = "Invalid limit", body = ErrorResponse)
+    )
+)]
+pub async fn get_whales(
+    State(state): State<AppState>,
+    Query(query): Query<WhalesQuery>,
+) -> Result<Json<WhalesResponse>, Response> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let config = &state.config.whales;
+    let since = now_ms().saturating_sub(config.window_secs * 1000);
+    let mut transactions: Vec<WhaleTransaction> = state
+        .whales
+        .read()
+        .await
+        .transactions
+        .values()
+        .filter(|whale| whale.first_seen_at >= since)
+        .cloned()
+        .collect();
+    transactions.sort_by(|a, b| {
+        b.amount
+            .cmp(&a.amount)
+            .then(b.first_seen_at.cmp(&a.first_seen_at))
+    });
+    transactions.truncate(limit);
+    Ok(Json(WhalesResponse {
+        threshold: config.threshold,
+        window_secs: config.window_secs,
+        transactions,
+    }))
+}
@@ -0,0 +1,108 @@
+        const API_BASE = '/api/v1';
+
+        function formatKas(sompi) {
+            return `${(sompi / 100000000).toFixed(8)} KAS`;
+        }
+
+        function formatInterval(seconds) {
+            return seconds % 3600 === 0 ? `${seconds / 3600} h` : `${Math.ceil(seconds / 60)} min`;
+        }
+
+        function escapeHtml(text) {
+            const div = document.createElement('div');
+            div.textContent = text;
+            return div.innerHTML;
+        }
+
+        // The captcha widget is rendered once, from the script the operator configured.
+        let captchaLoaded = false;
+
+        function loadCaptcha(info) {
+            if (captchaLoaded || !info.captcha_site_key || !info.captcha_script_url) {
+                return;
+            }
+            captchaLoaded = true;
+            const widget = document.getElementById('faucet-captcha');
+            widget.dataset.sitekey = info.captcha_site_key;
+            widget.classList.remove('hidden');
+            const script = document.createElement('script');
+            script.src = info.captcha_script_url;
+            script.async = true;
+            document.head.appendChild(script);
+        }
+
+        function captchaToken() {
+            const field = document.querySelector(
+                '[name="h-captcha-response"], [name="cf-turnstile-response"], [name="g-recaptcha-response"]'
+            );
+            return field && field.value ? field.value : null;
+        }
+
+        async function refreshFaucet() {
+            try {
+                const response = await axios.get(`${API_BASE}/faucet`);
+                const info = response.data;
+                document.getElementById('faucet-balance').textContent = formatKas(info.balance);
+                document.getElementById('faucet-amount').textContent = formatKas(info.amount);
+                document.getElementById('faucet-interval').textContent =
+                    `Once every ${formatInterval(info.ip_interval_secs)} per IP and ${formatInterval(info.address_interval_secs)} per address`;
+                document.getElementById('faucet-address').textContent = info.address;
+                loadCaptcha(info);
+
+                const container = document.getElementById('drips-container');
+                if (info.drips.length === 0) {
+                    container.innerHTML = '<div class="text-sm text-gray-400">No drips yet</div>';
+                    return;
+                }
+                container.innerHTML = info.drips.map(drip => `
+                    <div class="flex items-center justify-between bg-surface-2 rounded-lg p-3 border border-card">
+                        <div>
+                            <p class="text-sm font-mono break-all">${escapeHtml(drip.address)}</p>
+                            <p class="text-xs text-gray-400 font-mono">${escapeHtml(drip.transaction_id)}</p>
+                        </div>
+                        <div class="text-right">
+                            <p class="text-sm text-green-400">${formatKas(drip.amount)}</p>
+                            <p class="text-xs text-gray-400">${new Date(drip.timestamp).toLocaleString()}</p>
+                        </div>
+                    </div>
+                `).join('');
+            } catch (error) {
+                console.error('Failed to load faucet info:', error);
+                const message = error.response && error.response.data && error.response.data.error;
+                document.getElementById('drips-container').innerHTML =
+                    `<div class="text-sm text-red-400">${escapeHtml(message || 'Failed to load faucet info')}</div>`;
+            }
+        }
+
+        async function requestDrip() {
+            const address = document.getElementById('faucet-input').value.trim();
+            const result = document.getElementById('faucet-result');
+            const button = document.getElementById('faucet-button');
+            if (!address) {
+                result.innerHTML = '<span class="text-red-400">Enter an address first</span>';
+                return;
+            }
+            button.disabled = true;
+            result.innerHTML = '<span class="text-gray-400">Sending...</span>';
+            try {
+                const response = await axios.post(`${API_BASE}/faucet`, { address, captcha: captchaToken() });
+                result.innerHTML = `<span class="text-green-400">Sent ${formatKas(response.data.amount)} in transaction <span class="font-mono">${escapeHtml(response.data.transaction_id)}</span></span>`;
+                refreshFaucet();
+            } catch (error) {
+                const message = error.response && error.response.data && error.response.data.error;
+                result.innerHTML = `<span class="text-red-400">${escapeHtml(message || 'Request failed')}</span>`;
+            } finally {
+                button.disabled = false;
+                // Captcha tokens are single use.
+                ['hcaptcha', 'turnstile', 'grecaptcha'].forEach(name => {
+                    if (window[name] && typeof window[name].reset === 'function') {
+                        window[name].reset();
+                    }
+                });
+            }
+        }
+
+        window.addEventListener('load', () => {
+            refreshFaucet();
+            setInterval(refreshFaucet, 30000);
+        });
@@ -1,4 +1,4 @@
-        const API_BASE = '/api';
+        const API_BASE = (window.API_PREFIX || '') + '/api/v1';
         let currentTab = 'blocks';
         let refreshInterval;
         let autoRefreshInterval = null;
@@ -19,6 +19,35 @@
             }, 5000); // Refresh mempool every 5 seconds
         });
         
+        // Server-requested backoff: 429/503 responses carry a Retry-After header and a
+        // retry_after_ms field. While it is in effect, polling loops skip their requests.
+        let backoffUntil = 0;
+
+        function retryAfterMs(response) {
+            if (response.data && typeof response.data.retry_after_ms === 'number') {
+                return response.data.retry_after_ms;
+            }
+            const header = response.headers && response.headers['retry-after'];
+            const seconds = parseInt(header, 10);
+            return Number.isFinite(seconds) ? seconds * 1000 : 0;
+        }
+
+        function isBackingOff() {
+            return Date.now() < backoffUntil;
+        }
+
+        axios.interceptors.response.use(response => response, error => {
+            const response = error.response;
+            if (response && (response.status === 429 || response.status === 503)) {
+                const delay = retryAfterMs(response);
+                if (delay > 0) {
+                    backoffUntil = Math.max(backoffUntil, Date.now() + delay);
+                    console.warn(`Server asked to back off for ${delay}ms`);
+                }
+            }
+            return Promise.reject(error);
+        });
+
         // Performance optimizations
         let isLoading = {
             blocks: false,
@@ -60,6 +89,7 @@
         
         // Throttled auto-refresh to reduce server load
         const throttledRefresh = throttle(async function() {
+            if (isBackingOff()) return;
             const isConnected = await fetchNetworkInfo();
             if (isConnected) {
                 if (currentTab === 'blocks') {
@@ -368,6 +398,7 @@
                 <div class="bg-surface-1 rounded-lg p-6 mb-4 border border-card">
                     <h3 class="text-lg font-semibold mb-2">Mempool Size</h3>
                     <p class="text-2xl font-bold kaspa-primary">${mempool.size} transactions</p>
+                    ${mempool.truncated ? `<p class="text-xs text-gray-400 mt-1">Showing ${mempool.transactions.length} of ${mempool.total_available}</p>` : ''}
                 </div>
             `;
 
@@ -431,6 +462,7 @@
 
         async function refreshBlocks() {
             if (window.isLoading && window.isLoading.blocks) return;
+            if (isBackingOff()) return;
             window.isLoading = window.isLoading || {};
             window.isLoading.blocks = true;
 
@@ -475,7 +507,9 @@
                     if (!autoRefreshInterval) return;
                     await refreshBlocks();
                     if (!autoRefreshInterval) return;
-                    autoRefreshInterval = setTimeout(run, 2000);
+                    // Honour any server backoff before the next poll.
+                    const delay = Math.max(2000, backoffUntil - Date.now());
+                    autoRefreshInterval = setTimeout(run, delay);
                 };
                 autoRefreshInterval = setTimeout(run, 0);
                 
@@ -499,6 +533,7 @@
 
         function refreshMempool() {
             if (window.isLoading && window.isLoading.mempool) return;
+            if (isBackingOff()) return;
             window.isLoading = window.isLoading || {};
             window.isLoading.mempool = true;
 
@@ -527,7 +562,13 @@
             const address = addressInput.value.trim();
             
             if (!address) {
-                alert('Please enter a Kaspa address');
+                alert('Please enter a Kaspa address or DAA score');
+                return;
+            }
+            
+            // Kaspa has no block heights; numbers are looked up as DAA scores.
+            if (/^\d+$/.test(address)) {
+                await performDaaSearch(address);
                 return;
             }
             
@@ -598,6 +639,51 @@
             }
         }
 
+        async function performDaaSearch(score) {
+            const addressResult = document.getElementById('address-result');
+            if (window.isLoading && window.isLoading.address) return;
+            window.isLoading = window.isLoading || {};
+            window.isLoading.address = true;
+            
+            addressResult.innerHTML = `
+                <div class="text-center py-4">
+                    <div class="loader mx-auto mb-2"></div>
+                    <p>Looking up DAA score...</p>
+                </div>
+            `;
+            addressResult.classList.remove('hidden');
+            
+            try {
+                const response = await axios.get(`${API_BASE}/block/by-daa/${score}`);
+                const { exact, block } = response.data;
+                addressResult.innerHTML = `
+                    <div class="bg-surface-2 rounded-lg p-4 border border-card">
+                        <div class="grid grid-cols-1 md:grid-cols-2 gap-4">
+                            <div>
+                                <span class="text-gray-400 text-sm">Chain block ${exact ? 'at' : 'nearest to'} DAA score ${score}:</span>
+                                <p class="font-mono text-xs break-all">${block.hash}</p>
+                            </div>
+                            <div class="text-right">
+                                <span class="text-gray-400 text-sm">DAA score / blue score:</span>
+                                <p class="text-lg font-bold">${block.daa_score} / ${block.blue_score}</p>
+                                <p class="text-xs text-gray-400">${new Date(block.timestamp).toLocaleString()} · ${block.tx_count} transactions</p>
+                            </div>
+                        </div>
+                    </div>
+                `;
+            } catch (error) {
+                console.error('Failed to look up DAA score:', error);
+                const detail = error.response && error.response.data && error.response.data.detail;
+                addressResult.innerHTML = `
+                    <div class="bg-red-900 bg-opacity-50 rounded-lg p-4">
+                        <p class="text-red-400">Failed to look up DAA score: ${detail || error.message}</p>
+                    </div>
+                `;
+            } finally {
+                if (window.isLoading) window.isLoading.address = false;
+            }
+        }
+
         // Initialize
         async function init() {
             await fetchNetworkInfo();
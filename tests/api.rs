@@ -0,0 +1,221 @@
+//! End-to-end checks of the HTTP API, run against the chain picked in `common`.
+
+mod common;
+
+use common::Explorer;
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_consensus_core::config::params::SIMNET_PARAMS;
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+// DAA scores after which the faucet spends a coinbase output on simnet, plus a margin for the
+// block whose coinbase pays the first reward.
+fn faucet_funding_blocks() -> u64 {
+    SIMNET_PARAMS.coinbase_maturity().after() + 20
+}
+
+#[tokio::test]
+async fn info_reports_the_connected_node() {
+    let explorer = Explorer::start().await;
+    let info = explorer.get_ok("/api/v1/info").await;
+    assert_eq!(info["network"], "simnet");
+    assert_eq!(info["is_connected"], true);
+    assert_eq!(info["api_version"], "v1");
+    assert!(info.get("deprecation").is_none());
+}
+
+#[tokio::test]
+async fn unversioned_paths_are_deprecated() {
+    let explorer = Explorer::start().await;
+    let info = explorer.get_ok("/api/info").await;
+    assert!(info["deprecation"].is_string());
+}
+
+#[tokio::test]
+async fn new_blocks_reach_the_api() {
+    let explorer = Explorer::start().await;
+    let before = explorer.daa_score().await;
+    explorer.advance(2).await;
+    assert!(explorer.daa_score().await >= before + 2);
+
+    let blocks = explorer.get_ok("/api/v1/blocks").await;
+    let newest = &blocks["blocks"][0];
+    assert!(newest["hash"].is_string());
+    assert!(blocks["total_count"].as_u64().unwrap() >= 2);
+}
+
+#[tokio::test]
+async fn block_detail_matches_the_block_list() {
+    let explorer = Explorer::start().await;
+    explorer.advance(2).await;
+    let blocks = explorer.get_ok("/api/v1/blocks").await;
+    let hash = blocks["blocks"][0]["hash"].as_str().unwrap().to_string();
+
+    let block = explorer.get_ok(&format!("/api/v1/block/{}", hash)).await;
+    assert_eq!(block["hash"], hash.as_str());
+    assert!(block["tx_count"].as_u64().unwrap() >= 1); // The coinbase
+    assert!(block["miner"].is_string());
+}
+
+#[tokio::test]
+async fn unknown_block_is_not_found() {
+    let explorer = Explorer::start().await;
+    let (status, _) = explorer
+        .get(&format!("/api/v1/block/{}", "ab".repeat(32)))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn malformed_address_is_rejected() {
+    let explorer = Explorer::start().await;
+    let (status, _) = explorer.get("/api/v1/address/not-an-address").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn miner_address_has_a_balance() {
+    let explorer = Explorer::start().await;
+    explorer.advance(3).await;
+    let blocks = explorer.get_ok("/api/v1/blocks").await;
+    let hash = blocks["blocks"][0]["hash"].as_str().unwrap().to_string();
+    let block = explorer.get_ok(&format!("/api/v1/block/{}", hash)).await;
+    let miner = block["miner"].as_str().unwrap().to_string();
+
+    let address = explorer.get_ok(&format!("/api/v1/address/{}", miner)).await;
+    assert_eq!(address["address"], miner.as_str());
+    assert!(address["balance"].as_u64().unwrap() > 0);
+    assert!(!address["utxos"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn mempool_lists_pending_transactions() {
+    let explorer = Explorer::start().await;
+    let mempool = explorer.get_ok("/api/v1/mempool").await;
+    let listed = mempool["transactions"].as_array().unwrap().len();
+    assert!(listed as u64 <= mempool["size"].as_u64().unwrap());
+    if explorer.is_mock() {
+        // The mock always holds the next block's transfer.
+        assert!(listed >= 1);
+    }
+}
+
+#[tokio::test]
+async fn openapi_document_lists_the_routes() {
+    let explorer = Explorer::start().await;
+    let openapi = explorer.get_ok("/api/v1/openapi.json").await;
+    assert!(openapi["paths"]["/api/v1/blocks"].is_object());
+}
+
+#[tokio::test]
+async fn debug_stats_count_rpc_calls() {
+    let explorer = Explorer::start().await;
+    explorer.get_ok("/api/v1/blocks").await;
+    let stats = explorer.get_admin("/admin/debug/stats").await;
+    assert!(stats["rpc"]["get_info"]["calls"].as_u64().unwrap() >= 1);
+    assert_eq!(stats["tasks"]["panicked"], 0);
+    assert!(stats["tasks"]["running"].as_u64().unwrap() >= 1);
+
+    let (status, _) = explorer.get("/admin/debug/stats").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn block_detail_reports_size_and_mass() {
+    let explorer = Explorer::start().await;
+    explorer.advance(1).await;
+    let blocks = explorer.get_ok("/api/v1/blocks").await;
+    let hash = blocks["blocks"][0]["hash"].as_str().unwrap().to_string();
+
+    let block = explorer.get_ok(&format!("/api/v1/block/{}", hash)).await;
+    assert!(block["size"].as_u64().unwrap() > 0);
+    let utilization = block["mass_utilization"].as_f64().unwrap();
+    assert!((0.0..=1.0).contains(&utilization));
+}
+
+#[tokio::test]
+async fn faucet_submits_a_transaction() {
+    let explorer = Explorer::start_with_faucet().await;
+    let faucet = explorer.get_ok("/api/v1/faucet").await;
+    let faucet_address = Address::try_from(faucet["address"].as_str().unwrap()).unwrap();
+    let recipient = Address::new(Prefix::Simnet, Version::PubKey, &[2; 32]).to_string();
+    if explorer.is_mock() {
+        // The generated chain pays nothing to the faucet key.
+        let (status, _) = explorer
+            .post("/api/v1/faucet", &json!({ "address": recipient }))
+            .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        return;
+    }
+
+    explorer
+        .advance_paying(faucet_funding_blocks(), &faucet_address)
+        .await;
+    let (status, drip) = explorer
+        .post("/api/v1/faucet", &json!({ "address": recipient }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "the faucet answered {}", drip);
+    let transaction_id = drip["transaction_id"].as_str().unwrap().to_string();
+    let amount = drip["amount"].as_u64().unwrap();
+
+    // Mined into the next block and accepted by the chain block after it.
+    explorer.advance(2).await;
+    explorer
+        .wait_for("the drip to reach the recipient", || async {
+            let address = explorer
+                .get_ok(&format!("/api/v1/address/{}", recipient))
+                .await;
+            (address["balance"].as_u64()? == amount).then_some(())
+        })
+        .await;
+    let transaction = explorer
+        .wait_for("the drip to be indexed", || async {
+            let transaction = explorer
+                .get_ok(&format!("/api/v1/transaction/{}", transaction_id))
+                .await;
+            let included = !transaction["block_hashes"].as_array()?.is_empty();
+            included.then_some(transaction)
+        })
+        .await;
+    assert_eq!(transaction["id"], transaction_id.as_str());
+    assert_eq!(transaction["in_mempool"], false);
+    assert!(transaction["fee"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn labels_are_removed_once() {
+    let explorer = Explorer::start().await;
+    let address = Address::new(Prefix::Simnet, Version::PubKey, &[3; 32]).to_string();
+    let path = format!("/admin/labels/{}", address);
+    let label = json!({ "name": "Test wallet", "tag": "test" });
+    let (status, labeled) = explorer.admin(Method::PUT, &path, Some(&label)).await;
+    assert_eq!(status, StatusCode::OK, "labeling answered {}", labeled);
+
+    // The address is normalized before it is looked up, like when it was labeled.
+    let padded = format!("/admin/labels/%20{}%20", address);
+    let (status, _) = explorer.admin(Method::DELETE, &padded, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = explorer.admin(Method::DELETE, &path, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = explorer
+        .admin(Method::DELETE, "/admin/labels/not-an-address", None)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn auth_covers_additional_networks() {
+    let explorer = Explorer::start_with_config(
+        "[auth]\nenabled = true\n\n[[networks]]\nprefix = 'dev'\nnetwork = 'devnet'\n",
+    )
+    .await;
+    for path in ["/api/v1/watch", "/dev/api/v1/watch"] {
+        let (status, _) = explorer.post(path, &json!({})).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "POST {}", path);
+    }
+    let (status, _) = explorer
+        .get(&format!("/dev/api/v1/watch/{}", "ab".repeat(16)))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,333 @@
+//! Test harness: runs the explorer binary against a chain and talks to its API over HTTP.
+//!
+//! The chain is picked from the environment:
+//!
+//! - `EXPLORER_TEST_KASPAD_BIN`: a kaspad binary, started per test as a simnet node with
+//!   `--utxoindex` and unsynced mining enabled, in its own temporary directory
+//! - `EXPLORER_TEST_KASPAD_URL`: an already running simnet node with `--utxoindex`
+//! - neither: the explorer's generated chain (`--mock`), which needs nothing installed
+//!
+//! On a kaspad backend `advance` mines blocks by submitting block templates (simnet skips
+//! proof of work); on the mock it waits for the chain to grow by itself. Each test gets its
+//! own explorer, port and index, so tests run in parallel. The admin API is enabled with
+//! `ADMIN_TOKEN`; `start_with_faucet` also enables the faucet with a fixed key, for tests that
+//! submit a transaction through it, and `start_with_config` adds config sections of its own.
+
+#![allow(dead_code)] // Each test binary uses a different part of the harness
+
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_grpc_client::GrpcClient;
+use kaspa_rpc_core::api::rpc::RpcApi;
+use kaspa_rpc_core::SubmitBlockReport;
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+const EXPLORER_BIN: &str = env!("CARGO_BIN_EXE_kaspa-testnet12-explorer");
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const ADMIN_TOKEN: &str = "explorer-test-admin-token";
+// Hex private key of the faucet `start_with_faucet` enables.
+const FAUCET_KEY: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+/// Where the explorer under test reads the chain from.
+enum Backend {
+    Mock,
+    Kaspad {
+        url: String,
+        node: Option<Child>, // Started by the harness, killed with it
+    },
+}
+
+pub struct Explorer {
+    process: Child,
+    backend: Backend,
+    base_url: String,
+    http: reqwest::Client,
+    dir: PathBuf,
+}
+
+impl Explorer {
+    /// Starts an explorer on a fresh port and index, and waits until it is connected.
+    pub async fn start() -> Explorer {
+        Self::launch(false, "").await
+    }
+
+    /// Like `start`, with the faucet feature enabled and paying from `FAUCET_KEY`.
+    pub async fn start_with_faucet() -> Explorer {
+        Self::launch(true, "").await
+    }
+
+    /// Like `start`, with the TOML `sections` appended to the config file.
+    pub async fn start_with_config(sections: &str) -> Explorer {
+        Self::launch(false, sections).await
+    }
+
+    async fn launch(faucet: bool, sections: &str) -> Explorer {
+        let dir = std::env::temp_dir().join(format!(
+            "explorer-test-{}-{}",
+            std::process::id(),
+            INSTANCES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).expect("create the test directory");
+
+        let backend = if let Ok(bin) = std::env::var("EXPLORER_TEST_KASPAD_BIN") {
+            let rpc_port = free_port();
+            let node = Command::new(bin)
+                .arg("--simnet")
+                .arg("--utxoindex")
+                .arg("--enable-unsynced-mining")
+                .arg("--nologfiles")
+                .arg("--disable-upnp")
+                .arg("--nodnsseed")
+                .arg(format!("--appdir={}", dir.join("kaspad").display()))
+                .arg(format!("--rpclisten=127.0.0.1:{}", rpc_port))
+                .arg(format!("--listen=127.0.0.1:{}", free_port()))
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("start kaspad");
+            Backend::Kaspad {
+                url: format!("127.0.0.1:{}", rpc_port),
+                node: Some(node),
+            }
+        } else if let Ok(url) = std::env::var("EXPLORER_TEST_KASPAD_URL") {
+            Backend::Kaspad { url, node: None }
+        } else {
+            Backend::Mock
+        };
+
+        // Rate limits would throttle the polling below; the index stays out of the repo.
+        let config = dir.join("explorer.toml");
+        let mut contents = format!(
+            "[index]\npath = '{}'\n\n[rate_limit]\nenabled = false\n\n[features]\nadmin = true\nfaucet = {}\n",
+            dir.join("index.sqlite").display(),
+            faucet
+        );
+        if faucet {
+            let key_file = dir.join("faucet.key");
+            std::fs::write(&key_file, FAUCET_KEY).expect("write the faucet key");
+            contents.push_str(&format!(
+                "\n[faucet]\nkey_file = '{}'\n",
+                key_file.display()
+            ));
+        }
+        contents.push('\n');
+        contents.push_str(sections);
+        std::fs::write(&config, contents).expect("write the test config");
+
+        let port = free_port();
+        let mut command = Command::new(EXPLORER_BIN);
+        command
+            .arg("--network")
+            .arg("simnet")
+            .arg("--listen")
+            .arg(format!("127.0.0.1:{}", port))
+            .arg("--config")
+            .arg(&config)
+            .arg("--admin-token")
+            .arg(ADMIN_TOKEN)
+            .env("RUST_LOG", "warn")
+            .stdout(Stdio::null());
+        match &backend {
+            Backend::Mock => command.arg("--mock"),
+            Backend::Kaspad { url, .. } => command.arg("--kaspad-url").arg(url),
+        };
+        let process = command.spawn().expect("start the explorer");
+
+        let explorer = Explorer {
+            process,
+            backend,
+            base_url: format!("http://127.0.0.1:{}", port),
+            http: reqwest::Client::new(),
+            dir,
+        };
+        explorer.wait_until_connected().await;
+        explorer
+    }
+
+    pub fn is_mock(&self) -> bool {
+        matches!(self.backend, Backend::Mock)
+    }
+
+    /// `path` (e.g. `/api/v1/info`) with its status and JSON body; Null for other bodies.
+    pub async fn get(&self, path: &str) -> (StatusCode, Value) {
+        let response = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .unwrap_or_else(|e| panic!("GET {} failed: {}", path, e));
+        let status = response.status();
+        let body = response.json().await.unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// POSTs `body` as JSON to `path`, with the status and JSON body of the answer.
+    pub async fn post(&self, path: &str, body: &Value) -> (StatusCode, Value) {
+        let response = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .json(body)
+            .send()
+            .await
+            .unwrap_or_else(|e| panic!("POST {} failed: {}", path, e));
+        let status = response.status();
+        let body = response.json().await.unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// Like `get`, failing the test unless the answer is 200.
+    pub async fn get_ok(&self, path: &str) -> Value {
+        let (status, body) = self.get(path).await;
+        assert_eq!(status, StatusCode::OK, "GET {} answered {}", path, body);
+        body
+    }
+
+    /// `method` on the admin route `path` with the admin token and an optional JSON body,
+    /// with the status and JSON body of the answer.
+    pub async fn admin(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = self
+            .http
+            .request(method.clone(), format!("{}{}", self.base_url, path))
+            .bearer_auth(ADMIN_TOKEN);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request
+            .send()
+            .await
+            .unwrap_or_else(|e| panic!("{} {} failed: {}", method, path, e));
+        let status = response.status();
+        let body = response.json().await.unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// Like `get_ok`, with the admin token.
+    pub async fn get_admin(&self, path: &str) -> Value {
+        let (status, body) = self.admin(Method::GET, path, None).await;
+        assert_eq!(status, StatusCode::OK, "GET {} answered {}", path, body);
+        body
+    }
+
+    /// The virtual DAA score, once the explorer has heard of one.
+    pub async fn daa_score(&self) -> u64 {
+        self.wait_for("a virtual DAA score", || async {
+            self.get_ok("/api/v1/virtual").await["daa_score"].as_u64()
+        })
+        .await
+    }
+
+    /// Grows the chain by at least `blocks` blocks and waits until the explorer has seen
+    /// them.
+    pub async fn advance(&self, blocks: u64) {
+        let miner = Address::new(Prefix::Simnet, Version::PubKey, &[1; 32]);
+        self.advance_paying(blocks, &miner).await
+    }
+
+    /// Like `advance`, with the mined blocks paying their reward to `address`. The mock
+    /// chain pays its own addresses.
+    pub async fn advance_paying(&self, blocks: u64, address: &Address) {
+        let target = self.daa_score().await + blocks;
+        if let Backend::Kaspad { url, .. } = &self.backend {
+            mine(url, blocks, address).await;
+        }
+        self.wait_for("the chain to advance", || async {
+            let score = self.get_ok("/api/v1/virtual").await["daa_score"].as_u64()?;
+            (score >= target).then_some(())
+        })
+        .await
+    }
+
+    /// Polls `check` until it returns Some, failing the test after `STARTUP_TIMEOUT`.
+    pub async fn wait_for<T, F, Fut>(&self, what: &str, check: F) -> T
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Option<T>>,
+    {
+        let started = Instant::now();
+        loop {
+            if let Some(value) = check().await {
+                return value;
+            }
+            assert!(
+                started.elapsed() < STARTUP_TIMEOUT,
+                "Timed out waiting for {}",
+                what
+            );
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn wait_until_connected(&self) {
+        self.wait_for("the explorer to connect", || async {
+            let response = self
+                .http
+                .get(format!("{}/api/v1/info", self.base_url))
+                .send()
+                .await
+                .ok()?;
+            let info: Value = response.json().await.ok()?;
+            info["is_connected"].as_bool()?.then_some(())
+        })
+        .await
+    }
+}
+
+impl Drop for Explorer {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        if let Backend::Kaspad {
+            node: Some(node), ..
+        } = &mut self.backend
+        {
+            let _ = node.kill();
+            let _ = node.wait();
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A port nothing listens on right now.
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("find a free port")
+        .port()
+}
+
+/// Mines `blocks` blocks paying to `pay_address` on the simnet node at `url`.
+async fn mine(url: &str, blocks: u64, pay_address: &Address) {
+    let client = GrpcClient::connect(format!("grpc://{}", url))
+        .await
+        .expect("connect to kaspad");
+    for _ in 0..blocks {
+        let template = client
+            .get_block_template(pay_address.clone(), vec![])
+            .await
+            .expect("get a block template");
+        let report = client
+            .submit_block(template.block, false)
+            .await
+            .expect("submit a block");
+        assert!(
+            matches!(report, SubmitBlockReport::Success),
+            "kaspad rejected a mined block: {:?}",
+            report
+        );
+    }
+    let _ = client.disconnect().await;
+}